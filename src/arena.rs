@@ -0,0 +1,249 @@
+//! Parsing into a bump arena (enabled with the `bumpalo` feature), for embedders that need to own every allocation a parse makes
+//!
+//! `parse_in` allocates the whole value in a `bumpalo::Bump`, including the text of strings, keys and numbers, so nothing is left
+//! on the global heap and the value is freed at once by resetting or dropping the arena. `JSON` itself keeps using the global allocator.
+
+/* (IMPORTS) */
+use bumpalo::collections::{String as BumpString, Vec as BumpVec};
+use bumpalo::Bump;
+
+use crate::lexer::{lex, match_escape, Lexeme, LexemeKind, Lexer};
+use crate::{Number, ParseError, ParseErrorKind, MAX_DEPTH, JSON};
+
+
+/// A json value allocated in a bump arena, which borrows from the arena
+///
+/// Objects keep their members in the order of the document, duplicate keys included (`get` finds the last, as `try_parse` keeps).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ArenaJson<'bump> {
+    Null,
+    Bool(bool),
+    /// A number, as its literal
+    Number(&'bump str),
+    /// A string, with its escapes replaced
+    String(&'bump str),
+    Array(&'bump [ArenaJson<'bump>]),
+    Object(&'bump [(&'bump str, ArenaJson<'bump>)]),
+}
+
+impl<'bump> ArenaJson<'bump> {
+
+    /// Returns the member of an object with a key (the last one if the key is there more than once), or None for any other value
+    pub fn get(&self, key: &str) -> Option<&ArenaJson<'bump>>
+    {
+        match self {
+            ArenaJson::Object(members) => members.iter().rev().find(|(name, _)| *name == key).map(|(_, member)| member),
+            _ => None
+        }
+    }
+
+    /// Returns the value of a number, or None for any other value
+    pub fn as_number(&self) -> Option<Number>
+    {
+        match self {
+            ArenaJson::Number(literal) => Number::from_literal(literal),
+            _ => None
+        }
+    }
+
+    /// Returns a copy of the value that uses the global allocator, as `try_parse` would have returned it
+    pub fn to_json(&self) -> JSON
+    {
+        match *self {
+            ArenaJson::Null => JSON::JsNull,
+            ArenaJson::Bool(b) => JSON::JsBool(b),
+            ArenaJson::Number(literal) => Number::from_literal(literal).map_or(JSON::JsNull, JSON::JsNumber),
+            ArenaJson::String(s) => JSON::JsString(s.to_string()),
+            ArenaJson::Array(elements) => JSON::JsArray(elements.iter().map(ArenaJson::to_json).collect()),
+            ArenaJson::Object(members) => JSON::JsObject(members.iter().map(|(key, member)| (key.to_string(), member.to_json())).collect()),
+        }
+    }
+}
+
+/// Parses a string containing a single json value into a bump arena, see `try_parse`
+///
+/// # Examples
+/// ```
+/// use bumpalo::Bump;
+/// use Parser::{parse_in, try_parse, ArenaJson};
+///
+/// let bump = Bump::new();
+/// let value = parse_in(&bump, r#"{ "name" : "Ada", "langs" : ["en", "fr"] }"#).unwrap();
+///
+/// assert_eq!( Some(&ArenaJson::String("Ada")), value.get("name") );
+/// assert_eq!( try_parse(r#"{ "name" : "Ada", "langs" : ["en", "fr"] }"#).unwrap(), value.to_json() );
+/// ```
+///
+/// # Errors
+/// Returns a `ParseError` if the input is not valid json, has anything after the value, or is nested more than 128 deep
+pub fn parse_in<'bump>(bump: &'bump Bump, input: &str) -> Result<ArenaJson<'bump>, ParseError>
+{
+    let mut reader = ArenaReader { bump, input, lexer: lex(input) };
+
+    let first = reader.next()?;
+    let value = reader.value(first, 0)?;
+    match reader.next()? {
+        Some(lexeme) => Err(reader.error(ParseErrorKind::TrailingCharacters, lexeme.start)),
+        None => Ok(value),
+    }
+}
+
+// Recursive descent over the lexemes of the input, allocating the value in the arena
+struct ArenaReader<'a, 'bump> {
+    bump: &'bump Bump,
+    input: &'a str,
+    lexer: Lexer<'a>,
+}
+
+impl<'a, 'bump> ArenaReader<'a, 'bump> {
+
+    fn error(&self, kind: ParseErrorKind, offset: usize) -> ParseError
+    {
+        ParseError::new(kind, self.input, offset)
+    }
+
+    // Returns an error at a lexeme that is out of place, or at the end of the input if there is none
+    fn unexpected(&self, lexeme: Option<Lexeme>) -> ParseError
+    {
+        self.error(ParseErrorKind::Syntax, lexeme.map_or(self.input.len(), |lexeme| lexeme.start))
+    }
+
+    // Returns the next lexeme that is not whitespace, or None at the end of the input
+    fn next(&mut self) -> Result<Option<Lexeme<'a>>, ParseError>
+    {
+        loop {
+            match self.lexer.next().transpose()? {
+                Some(lexeme) if lexeme.kind == LexemeKind::Whitespace => continue,
+                lexeme => return Ok(lexeme),
+            }
+        }
+    }
+
+    // Reads the value that starts with a lexeme, with depth containers around it
+    fn value(&mut self, lexeme: Option<Lexeme<'a>>, depth: usize) -> Result<ArenaJson<'bump>, ParseError>
+    {
+        let Some(lexeme) = lexeme else { return Err(self.unexpected(None)); };
+
+        match lexeme.kind {
+            LexemeKind::Null => Ok(ArenaJson::Null),
+            LexemeKind::True => Ok(ArenaJson::Bool(true)),
+            LexemeKind::False => Ok(ArenaJson::Bool(false)),
+            LexemeKind::Number => {
+                //Without arbitrary_precision numbers that overflow a float are rejected as try_parse does, checking them allocates nothing
+                #[cfg(not(feature = "arbitrary_precision"))]
+                if Number::from_literal(lexeme.text).is_none() { return Err(self.unexpected(Some(lexeme))); }
+
+                Ok(ArenaJson::Number(self.bump.alloc_str(lexeme.text)))
+            }
+            LexemeKind::String => self.string(lexeme).map(ArenaJson::String),
+            LexemeKind::BeginArray | LexemeKind::BeginObject if depth >= MAX_DEPTH => Err(self.error(ParseErrorKind::TooDeep, lexeme.start)),
+            LexemeKind::BeginArray => {
+                let mut elements = BumpVec::new_in(self.bump);
+                let mut next = self.next()?;
+
+                if !next.is_some_and(|lexeme| lexeme.kind == LexemeKind::EndArray) {
+                    loop {
+                        elements.push(self.value(next, depth + 1)?);
+                        match self.next()? {
+                            Some(lexeme) if lexeme.kind == LexemeKind::Comma => next = self.next()?,
+                            Some(lexeme) if lexeme.kind == LexemeKind::EndArray => break,
+                            other => return Err(self.unexpected(other)),
+                        }
+                    }
+                }
+                Ok(ArenaJson::Array(elements.into_bump_slice()))
+            }
+            LexemeKind::BeginObject => {
+                let mut members = BumpVec::new_in(self.bump);
+                let mut next = self.next()?;
+
+                if !next.is_some_and(|lexeme| lexeme.kind == LexemeKind::EndObject) {
+                    loop {
+                        let key = match next {
+                            Some(lexeme) if lexeme.kind == LexemeKind::String => self.string(lexeme)?,
+                            other => return Err(self.unexpected(other)),
+                        };
+                        match self.next()? {
+                            Some(lexeme) if lexeme.kind == LexemeKind::Colon => {}
+                            other => return Err(self.unexpected(other)),
+                        }
+
+                        let value = self.next()?;
+                        members.push((key, self.value(value, depth + 1)?));
+                        match self.next()? {
+                            Some(lexeme) if lexeme.kind == LexemeKind::Comma => next = self.next()?,
+                            Some(lexeme) if lexeme.kind == LexemeKind::EndObject => break,
+                            other => return Err(self.unexpected(other)),
+                        }
+                    }
+                }
+                Ok(ArenaJson::Object(members.into_bump_slice()))
+            }
+            _ => Err(self.unexpected(Some(lexeme))),
+        }
+    }
+
+    // Copies the contents of a string literal into the arena, replacing its escapes
+    fn string(&self, lexeme: Lexeme) -> Result<&'bump str, ParseError>
+    {
+        let mut rest = &lexeme.text[1..lexeme.text.len() - 1];
+        if !rest.contains('\\') { return Ok(self.bump.alloc_str(rest)); }
+
+        let mut unescaped = BumpString::with_capacity_in(rest.len(), self.bump);
+        while let Some(n) = rest.find('\\') {
+            unescaped.push_str(&rest[..n]);
+            let (after, ch) = match_escape(&rest[n + 1..]).map_err(|_| self.error(ParseErrorKind::Syntax, lexeme.start))?;
+            unescaped.push(ch);
+            rest = after;
+        }
+        unescaped.push_str(rest);
+        Ok(unescaped.into_bump_str())
+    }
+}
+
+
+/* (TESTS) */
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::try_parse;
+
+    #[test]
+    fn test_parse_in()
+    {
+        let bump = Bump::new();
+        let input = r#" { "a" : [1, -2.5e3, null, true, {}], "b\"c" : "é\n🦀", "d" : { "e" : [] }, "a" : 0 } "#;
+        let value = parse_in(&bump, input).unwrap();
+
+        assert_eq!( try_parse(input).unwrap(), value.to_json() );
+        assert_eq!( Some(&ArenaJson::Number("0")), value.get("a") );
+        assert_eq!( Some(&ArenaJson::String("é\n🦀")), value.get("b\"c") );
+        assert_eq!( Some(Number::from(0u64)), value.get("a").and_then(ArenaJson::as_number) );
+        assert_eq!( None, ArenaJson::Null.get("a") );
+
+        //Every string and container of the value is in the arena
+        let ArenaJson::Object(members) = value else { panic!("not an object") };
+        assert!( bump.allocated_bytes() > 0 );
+        assert_eq!( 4, members.len() );
+    }
+
+    #[test]
+    fn test_parse_in_errors()
+    {
+        let bump = Bump::new();
+        let error = |input: &str| parse_in(&bump, input).map_err(|e| (e.kind(), e.offset())).unwrap_err();
+
+        assert_eq!( (ParseErrorKind::Syntax, 3), error("[1 2]") );
+        assert_eq!( (ParseErrorKind::Syntax, 4), error("[1, ]") );
+        assert_eq!( (ParseErrorKind::Syntax, 6), error(r#"{ "a" 1 }"#) );
+        assert_eq!( (ParseErrorKind::Syntax, 1), error(r#"["\q"]"#) );
+        assert_eq!( (ParseErrorKind::Syntax, 2), error("{ 1 : 2 }") );
+        assert_eq!( (ParseErrorKind::Syntax, 4), error("[[1]") );
+        assert_eq!( (ParseErrorKind::TrailingCharacters, 3), error("[] []") );
+        assert_eq!( (ParseErrorKind::Syntax, 0), error("") );
+
+        let deep = "[".repeat(MAX_DEPTH + 1) + &"]".repeat(MAX_DEPTH + 1);
+        assert_eq!( (ParseErrorKind::TooDeep, MAX_DEPTH), error(&deep) );
+        assert!( parse_in(&bump, &deep[1..deep.len() - 1]).is_ok() );
+    }
+}
@@ -0,0 +1,62 @@
+//! Base64 decoding of json strings (enabled with the `base64` feature)
+//!
+//! Json can not hold raw bytes, so binary blobs are embedded as base64 strings.
+//! Both the standard alphabet (+ and /) and the URL-safe alphabet (- and _) are accepted, with or without padding.
+
+// Returns the 6-bit value of a base64 character from either alphabet
+fn sextet(b: u8) -> Option<u32>
+{
+    match b {
+        b'A'..=b'Z' => Some((b - b'A') as u32),
+        b'a'..=b'z' => Some((b - b'a') as u32 + 26),
+        b'0'..=b'9' => Some((b - b'0') as u32 + 52),
+        b'+' | b'-' => Some(62),
+        b'/' | b'_' => Some(63),
+        _ => None
+    }
+}
+
+// Decodes a base64 string, returns None if it contains characters outside of the alphabets or has an invalid length
+pub(crate) fn decode(input: &str) -> Option<Vec<u8>>
+{
+    //Padding is optional, but if present it must bring the length to a multiple of 4
+    let trimmed = input.trim_end_matches('=');
+    let padding = input.len() - trimmed.len();
+    if padding > 2 || (padding > 0 && !input.len().is_multiple_of(4)) || trimmed.len() % 4 == 1 { return None; }
+
+    let mut bytes = Vec::with_capacity(trimmed.len() * 3 / 4);
+
+    for chunk in trimmed.as_bytes().chunks(4) {
+        //Pack up to 4 characters into 24 bits, then take out the bytes that were actually encoded
+        let mut bits = 0;
+        for (i, b) in chunk.iter().enumerate() {
+            bits |= sextet(*b)? << (18 - 6 * i);
+        }
+
+        let n = chunk.len() - 1;
+        bytes.extend_from_slice( &bits.to_be_bytes()[1..1 + n] );
+    }
+
+    Some(bytes)
+}
+
+
+/* (TESTS) */
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode()
+    {
+        assert_eq!( Some(b"".to_vec()), decode("") );
+        assert_eq!( Some(b"Hello".to_vec()), decode("SGVsbG8=") );
+        assert_eq!( Some(b"Hello".to_vec()), decode("SGVsbG8") );       //Padding is optional
+        assert_eq!( Some(vec![0xfb, 0xff]), decode("+/8=") );           //Standard alphabet
+        assert_eq!( Some(vec![0xfb, 0xff]), decode("-_8") );            //URL-safe alphabet
+
+        assert_eq!( None, decode("SGVsbG8*") );
+        assert_eq!( None, decode("SGVsb") );                            //Single trailing character can not encode a byte
+        assert_eq!( None, decode("SGVsbG8==") );
+    }
+}
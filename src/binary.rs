@@ -0,0 +1,530 @@
+//! Reading and writing the binary formats MessagePack and CBOR (RFC 8949)
+//!
+//! Integers are written in the fewest bytes that hold them and other numbers as 64-bit floats. Object members are written in order of key,
+//! so equal values are always written the same. Values json can not hold fail to read: byte strings, extension types, map keys that are
+//! not strings, and floats that are infinite or NaN. CBOR tags are skipped, keeping the value they tag (ie. a timestamp's number).
+
+/* (IMPORTS) */
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::{Number, JSON, MAX_DEPTH};
+
+
+/// Error returned when a MessagePack or CBOR value can not be read as json, with the offset of the problem in the bytes
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BinaryError {
+    message: String,
+    offset: usize,
+}
+
+impl BinaryError {
+
+    /// Returns what is wrong with the bytes (ie. "unexpected end of input")
+    pub fn message(&self) -> &str
+    {
+        &self.message
+    }
+
+    /// Returns the offset of the problem in the bytes
+    pub fn offset(&self) -> usize
+    {
+        self.offset
+    }
+}
+
+impl fmt::Display for BinaryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+    {
+        write!(f, "{} at byte {}", self.message, self.offset)
+    }
+}
+
+impl std::error::Error for BinaryError {}
+
+impl JSON {
+
+    /// Returns the value as MessagePack
+    ///
+    /// # Panics
+    /// If a string, array or object is too long for MessagePack, which holds lengths in 32 bits
+    pub fn to_msgpack(&self) -> Vec<u8>
+    {
+        let mut out = vec![];
+        write_msgpack(&mut out, self);
+        out
+    }
+
+    /// Reads a value from MessagePack bytes holding exactly one value
+    ///
+    /// # Examples
+    /// ```
+    /// use Parser::{try_parse, JSON};
+    ///
+    /// let value = try_parse(r#"{ "id" : 7, "tags" : ["a"] }"#).unwrap();
+    /// assert_eq!( vec![0x82, 0xa2, b'i', b'd', 0x07, 0xa4, b't', b'a', b'g', b's', 0x91, 0xa1, b'a'], value.to_msgpack() );
+    /// assert_eq!( Ok(value.clone()), JSON::from_msgpack(&value.to_msgpack()) );
+    /// ```
+    ///
+    /// # Errors
+    /// Returns a `BinaryError` if the bytes are not one MessagePack value or it has no json equivalent
+    pub fn from_msgpack(bytes: &[u8]) -> Result<JSON, BinaryError>
+    {
+        let mut reader = BinaryReader { bytes, pos: 0 };
+        let value = reader.msgpack(0)?;
+        reader.finish(value)
+    }
+
+    /// Returns the value as CBOR
+    pub fn to_cbor(&self) -> Vec<u8>
+    {
+        let mut out = vec![];
+        write_cbor(&mut out, self);
+        out
+    }
+
+    /// Reads a value from CBOR bytes holding exactly one data item
+    ///
+    /// # Errors
+    /// Returns a `BinaryError` if the bytes are not one CBOR data item or it has no json equivalent
+    pub fn from_cbor(bytes: &[u8]) -> Result<JSON, BinaryError>
+    {
+        let mut reader = BinaryReader { bytes, pos: 0 };
+        let value = reader.cbor(0)?;
+        reader.finish(value)
+    }
+}
+
+// Returns the members of an object in order of key
+fn sorted_members(map: &HashMap<String, JSON>) -> Vec<(&String, &JSON)>
+{
+    let mut members: Vec<(&String, &JSON)> = map.iter().collect();
+    members.sort_by(|a, b| a.0.cmp(b.0));
+    members
+}
+
+
+/* (MESSAGEPACK) */
+
+// Appends the marker of a string, array or map of a length, given the marker of its short form and of its 8 (strings only), 16 & 32 bit forms
+fn write_msgpack_len(out: &mut Vec<u8>, len: usize, short: (u8, usize), long: [Option<u8>; 3])
+{
+    let len = u32::try_from(len).expect("MessagePack lengths fit in 32 bits");
+
+    match (long, len) {
+        (_, len) if (len as usize) < short.1 => out.push(short.0 | len as u8),
+        ([Some(marker), _, _], len) if len <= u8::MAX as u32 => out.extend([marker, len as u8]),
+        ([_, Some(marker), _], len) if len <= u16::MAX as u32 => { out.push(marker); out.extend((len as u16).to_be_bytes()); }
+        ([_, _, Some(marker)], len) => { out.push(marker); out.extend(len.to_be_bytes()); }
+        _ => unreachable!("every length has a 32 bit form"),
+    }
+}
+
+fn write_msgpack(out: &mut Vec<u8>, value: &JSON)
+{
+    match value {
+        JSON::JsNull => out.push(0xc0),
+        JSON::JsBool(b) => out.push(if *b { 0xc3 } else { 0xc2 }),
+        JSON::JsNumber(n) => match (n.as_u64(), n.as_i64()) {
+            (Some(u), _) if u < 0x80 => out.push(u as u8),
+            (Some(u), _) if u <= u8::MAX as u64 => out.extend([0xcc, u as u8]),
+            (Some(u), _) if u <= u16::MAX as u64 => { out.push(0xcd); out.extend((u as u16).to_be_bytes()); }
+            (Some(u), _) if u <= u32::MAX as u64 => { out.push(0xce); out.extend((u as u32).to_be_bytes()); }
+            (Some(u), _) => { out.push(0xcf); out.extend(u.to_be_bytes()); }
+            (_, Some(i)) if i >= -32 => out.push(i as u8),
+            (_, Some(i)) if i >= i8::MIN as i64 => out.extend([0xd0, i as u8]),
+            (_, Some(i)) if i >= i16::MIN as i64 => { out.push(0xd1); out.extend((i as i16).to_be_bytes()); }
+            (_, Some(i)) if i >= i32::MIN as i64 => { out.push(0xd2); out.extend((i as i32).to_be_bytes()); }
+            (_, Some(i)) => { out.push(0xd3); out.extend(i.to_be_bytes()); }
+            _ => { out.push(0xcb); out.extend(n.as_f64().to_be_bytes()); }
+        },
+        JSON::JsString(s) => {
+            write_msgpack_len(out, s.len(), (0xa0, 32), [Some(0xd9), Some(0xda), Some(0xdb)]);
+            out.extend(s.as_bytes());
+        }
+        JSON::JsArray(vec) => {
+            write_msgpack_len(out, vec.len(), (0x90, 16), [None, Some(0xdc), Some(0xdd)]);
+            for element in vec {
+                write_msgpack(out, element);
+            }
+        }
+        JSON::JsObject(map) => {
+            write_msgpack_len(out, map.len(), (0x80, 16), [None, Some(0xde), Some(0xdf)]);
+            for (key, member) in sorted_members(map) {
+                write_msgpack(out, &JSON::JsString(key.clone()));
+                write_msgpack(out, member);
+            }
+        }
+    }
+}
+
+
+/* (CBOR) */
+
+// Appends the head of a data item, its major type and argument (a value, or a length) in the fewest bytes that hold it
+fn write_cbor_head(out: &mut Vec<u8>, major: u8, argument: u64)
+{
+    let major = major << 5;
+    match argument {
+        n if n < 24 => out.push(major | n as u8),
+        n if n <= u8::MAX as u64 => out.extend([major | 24, n as u8]),
+        n if n <= u16::MAX as u64 => { out.push(major | 25); out.extend((n as u16).to_be_bytes()); }
+        n if n <= u32::MAX as u64 => { out.push(major | 26); out.extend((n as u32).to_be_bytes()); }
+        n => { out.push(major | 27); out.extend(n.to_be_bytes()); }
+    }
+}
+
+fn write_cbor(out: &mut Vec<u8>, value: &JSON)
+{
+    match value {
+        JSON::JsNull => out.push(0xf6),
+        JSON::JsBool(b) => out.push(if *b { 0xf5 } else { 0xf4 }),
+        JSON::JsNumber(n) => match (n.as_u64(), n.as_i64()) {
+            (Some(u), _) => write_cbor_head(out, 0, u),
+            (_, Some(i)) => write_cbor_head(out, 1, !i as u64),     //-1 - i, which is positive for a negative i
+            _ => { out.push(0xfb); out.extend(n.as_f64().to_be_bytes()); }
+        },
+        JSON::JsString(s) => {
+            write_cbor_head(out, 3, s.len() as u64);
+            out.extend(s.as_bytes());
+        }
+        JSON::JsArray(vec) => {
+            write_cbor_head(out, 4, vec.len() as u64);
+            for element in vec {
+                write_cbor(out, element);
+            }
+        }
+        JSON::JsObject(map) => {
+            write_cbor_head(out, 5, map.len() as u64);
+            for (key, member) in sorted_members(map) {
+                write_cbor_head(out, 3, key.len() as u64);
+                out.extend(key.as_bytes());
+                write_cbor(out, member);
+            }
+        }
+    }
+}
+
+// Returns the value of an IEEE 754 half precision float
+fn f16_to_f64(bits: u16) -> f64
+{
+    let sign = if bits & 0x8000 != 0 { -1.0 } else { 1.0 };
+    let exponent = (bits >> 10) & 0x1f;
+    let fraction = (bits & 0x3ff) as f64;
+
+    sign * match exponent {
+        0 => fraction * 2f64.powi(-24),
+        0x1f if fraction == 0.0 => f64::INFINITY,
+        0x1f => f64::NAN,
+        e => (1.0 + fraction / 1024.0) * 2f64.powi(e as i32 - 15),
+    }
+}
+
+
+/* (READING) */
+
+// The bytes of a MessagePack or CBOR value and how far into them has been read
+struct BinaryReader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> BinaryReader<'a> {
+
+    fn error<T>(&self, offset: usize, message: &str) -> Result<T, BinaryError>
+    {
+        Err(BinaryError { message: message.to_string(), offset })
+    }
+
+    fn take(&mut self, n: usize) -> Result<&'a [u8], BinaryError>
+    {
+        match self.bytes.get(self.pos..).and_then(|rest| rest.get(..n)) {
+            Some(taken) => { self.pos += n; Ok(taken) }
+            None => self.error(self.bytes.len(), "unexpected end of input"),
+        }
+    }
+
+    fn byte(&mut self) -> Result<u8, BinaryError>
+    {
+        self.take(1).map(|bytes| bytes[0])
+    }
+
+    // Reads a big endian unsigned integer of n bytes
+    fn uint(&mut self, n: usize) -> Result<u64, BinaryError>
+    {
+        Ok(self.take(n)?.iter().fold(0, |value, &b| value << 8 | b as u64))
+    }
+
+    // Reads a length, which must fit in memory
+    fn len(&mut self, n: usize) -> Result<usize, BinaryError>
+    {
+        let at = self.pos;
+        let len = self.uint(n)?;
+        match usize::try_from(len) {
+            Ok(len) if len <= self.bytes.len() - self.pos => Ok(len),   //Every element takes at least a byte, so no valid length is longer than the rest
+            _ => self.error(at, "length is longer than the input"),
+        }
+    }
+
+    fn string(&mut self, len: usize) -> Result<String, BinaryError>
+    {
+        let at = self.pos;
+        match std::str::from_utf8(self.take(len)?) {
+            Ok(s) => Ok(s.to_string()),
+            Err(_) => self.error(at, "string is not valid utf-8"),
+        }
+    }
+
+    fn float(&self, at: usize, f: f64) -> Result<JSON, BinaryError>
+    {
+        match Number::from_f64(f) {
+            Some(n) => Ok(JSON::JsNumber(n)),
+            None => self.error(at, "float is not finite"),
+        }
+    }
+
+    fn finish(self, value: JSON) -> Result<JSON, BinaryError>
+    {
+        match self.pos == self.bytes.len() {
+            true => Ok(value),
+            false => self.error(self.pos, "unexpected bytes after the value"),
+        }
+    }
+
+    // Fails if an array, object or tagged value starting at an offset would be nested too deeply
+    fn enter(&self, at: usize, depth: usize) -> Result<(), BinaryError>
+    {
+        match depth == MAX_DEPTH {
+            true => self.error(at, "arrays and objects are nested too deeply"),
+            false => Ok(()),
+        }
+    }
+
+    fn msgpack(&mut self, depth: usize) -> Result<JSON, BinaryError>
+    {
+        let at = self.pos;
+        let marker = self.byte()?;
+
+        let (len, is_map) = match marker {
+            0x00..=0x7f => return Ok(JSON::JsNumber(Number::from(marker as u64))),
+            0xe0..=0xff => return Ok(JSON::JsNumber(Number::from(marker as i8 as i64))),
+            0xc0 => return Ok(JSON::JsNull),
+            0xc2 => return Ok(JSON::JsBool(false)),
+            0xc3 => return Ok(JSON::JsBool(true)),
+            0xcc => return Ok(JSON::JsNumber(Number::from(self.uint(1)?))),
+            0xcd => return Ok(JSON::JsNumber(Number::from(self.uint(2)?))),
+            0xce => return Ok(JSON::JsNumber(Number::from(self.uint(4)?))),
+            0xcf => return Ok(JSON::JsNumber(Number::from(self.uint(8)?))),
+            0xd0 => return Ok(JSON::JsNumber(Number::from(self.uint(1)? as u8 as i8 as i64))),
+            0xd1 => return Ok(JSON::JsNumber(Number::from(self.uint(2)? as u16 as i16 as i64))),
+            0xd2 => return Ok(JSON::JsNumber(Number::from(self.uint(4)? as u32 as i32 as i64))),
+            0xd3 => return Ok(JSON::JsNumber(Number::from(self.uint(8)? as i64))),
+            0xca => { let bits = self.uint(4)? as u32; return self.float(at, f32::from_bits(bits) as f64); }
+            0xcb => { let bits = self.uint(8)?; return self.float(at, f64::from_bits(bits)); }
+            0xa0..=0xbf => return self.string((marker & 0x1f) as usize).map(JSON::JsString),
+            0xd9 => { let len = self.len(1)?; return self.string(len).map(JSON::JsString); }
+            0xda => { let len = self.len(2)?; return self.string(len).map(JSON::JsString); }
+            0xdb => { let len = self.len(4)?; return self.string(len).map(JSON::JsString); }
+            0x90..=0x9f => ((marker & 0x0f) as usize, false),
+            0xdc => (self.len(2)?, false),
+            0xdd => (self.len(4)?, false),
+            0x80..=0x8f => ((marker & 0x0f) as usize, true),
+            0xde => (self.len(2)?, true),
+            0xdf => (self.len(4)?, true),
+            0xc4..=0xc6 => return self.error(at, "binary data has no json equivalent"),
+            0xc7..=0xc9 | 0xd4..=0xd8 => return self.error(at, "extension types have no json equivalent"),
+            0xc1 => return self.error(at, "invalid marker"),
+        };
+
+        self.enter(at, depth)?;
+        if !is_map {
+            return (0..len).map(|_| self.msgpack(depth + 1)).collect::<Result<Vec<JSON>, BinaryError>>().map(JSON::JsArray);
+        }
+
+        let mut map = HashMap::with_capacity(len);
+        for _ in 0..len {
+            let at = self.pos;
+            let JSON::JsString(key) = self.msgpack(depth + 1)? else { return self.error(at, "map key is not a string"); };
+            map.insert(key, self.msgpack(depth + 1)?);
+        }
+        Ok(JSON::JsObject(map))
+    }
+
+    // Reads the argument of a data item after its first byte, None if its length is indefinite
+    fn cbor_argument(&mut self, at: usize, info: u8) -> Result<Option<u64>, BinaryError>
+    {
+        match info {
+            0..=23 => Ok(Some(info as u64)),
+            24 => self.uint(1).map(Some),
+            25 => self.uint(2).map(Some),
+            26 => self.uint(4).map(Some),
+            27 => self.uint(8).map(Some),
+            31 => Ok(None),
+            _ => self.error(at, "invalid additional information"),
+        }
+    }
+
+    // Reads a length argument, which must fit in memory
+    fn cbor_len(&self, at: usize, argument: u64) -> Result<usize, BinaryError>
+    {
+        match usize::try_from(argument) {
+            Ok(len) if len <= self.bytes.len() - self.pos => Ok(len),
+            _ => self.error(at, "length is longer than the input"),
+        }
+    }
+
+    // Consumes the break that ends an item of indefinite length, if it is next
+    fn cbor_break(&mut self) -> bool
+    {
+        let found = self.bytes.get(self.pos) == Some(&0xff);
+        if found { self.pos += 1; }
+        found
+    }
+
+    fn cbor(&mut self, depth: usize) -> Result<JSON, BinaryError>
+    {
+        let at = self.pos;
+        let first = self.byte()?;
+        let (major, info) = (first >> 5, first & 0x1f);
+        let argument = self.cbor_argument(at, info)?;
+
+        match (major, argument) {
+            (0, Some(n)) => Ok(JSON::JsNumber(Number::from(n))),
+            (1, Some(n)) => match i64::try_from(n) {
+                Ok(n) => Ok(JSON::JsNumber(Number::from(-1 - n))),
+                Err(_) => self.float(at, -1.0 - n as f64),     //Past i64::MIN, as the json parser also holds them
+            },
+            (2, _) => self.error(at, "byte strings have no json equivalent"),
+            (3, Some(n)) => {
+                let len = self.cbor_len(at, n)?;
+                self.string(len).map(JSON::JsString)
+            }
+            (3, None) => {
+                //An indefinite length string is a series of definite length strings
+                let mut s = String::new();
+                while !self.cbor_break() {
+                    let chunk_at = self.pos;
+                    let head = self.byte()?;
+                    if head >> 5 != 3 || head & 0x1f == 31 { return self.error(chunk_at, "chunk of a text string is not a text string"); }
+
+                    let Some(n) = self.cbor_argument(chunk_at, head & 0x1f)? else { unreachable!("indefinite chunks were rejected") };
+                    let len = self.cbor_len(chunk_at, n)?;
+                    s.push_str(&self.string(len)?);
+                }
+                Ok(JSON::JsString(s))
+            }
+            (4, len) => {
+                self.enter(at, depth)?;
+                let mut vec = vec![];
+                match len {
+                    Some(n) => for _ in 0..self.cbor_len(at, n)? { vec.push(self.cbor(depth + 1)?); },
+                    None => while !self.cbor_break() { vec.push(self.cbor(depth + 1)?); },
+                }
+                Ok(JSON::JsArray(vec))
+            }
+            (5, len) => {
+                self.enter(at, depth)?;
+                let mut map = HashMap::new();
+                let mut member = |reader: &mut BinaryReader<'a>| {
+                    let key_at = reader.pos;
+                    let JSON::JsString(key) = reader.cbor(depth + 1)? else { return reader.error(key_at, "map key is not a string"); };
+                    map.insert(key, reader.cbor(depth + 1)?);
+                    Ok(())
+                };
+                match len {
+                    Some(n) => for _ in 0..self.cbor_len(at, n)? { member(self)?; },
+                    None => while !self.cbor_break() { member(self)?; },
+                }
+                Ok(JSON::JsObject(map))
+            }
+            (6, Some(_)) => {
+                self.enter(at, depth)?;
+                self.cbor(depth + 1)
+            }
+            (7, _) => match (info, argument) {
+                (20, _) => Ok(JSON::JsBool(false)),
+                (21, _) => Ok(JSON::JsBool(true)),
+                (22 | 23, _) => Ok(JSON::JsNull),                  //Undefined is the closest json has to null
+                (25, Some(bits)) => self.float(at, f16_to_f64(bits as u16)),
+                (26, Some(bits)) => self.float(at, f32::from_bits(bits as u32) as f64),
+                (27, Some(bits)) => self.float(at, f64::from_bits(bits)),
+                (31, _) => self.error(at, "unexpected break"),
+                _ => self.error(at, "simple values have no json equivalent"),
+            },
+            _ => self.error(at, "invalid indefinite length"),
+        }
+    }
+}
+
+
+/* (TESTS) */
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::json;
+
+    #[test]
+    fn test_msgpack()
+    {
+        let doc = json(r#"{ "b" : [0, -1, -33, 200, -200, 70000, -2147483649, 18446744073709551615, 1.5, true, null], "a" : "é" }"#);
+        let expected = [
+            0x82, 0xa1, b'a', 0xa2, 0xc3, 0xa9, 0xa1, b'b', 0x9b,
+            0x00, 0xff, 0xd0, 0xdf, 0xcc, 0xc8, 0xd1, 0xff, 0x38, 0xce, 0x00, 0x01, 0x11, 0x70,
+            0xd3, 0xff, 0xff, 0xff, 0xff, 0x7f, 0xff, 0xff, 0xff, 0xcf, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+            0xcb, 0x3f, 0xf8, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xc3, 0xc0,
+        ];
+        assert_eq!( expected.to_vec(), doc.to_msgpack() );
+        assert_eq!( Ok(doc), JSON::from_msgpack(&expected) );
+
+        let long = JSON::JsString("x".repeat(300));
+        assert_eq!( [0xda, 0x01, 0x2c], long.to_msgpack()[..3] );
+        assert_eq!( Ok(long.clone()), JSON::from_msgpack(&long.to_msgpack()) );
+        assert_eq!( Ok(json("1.5")), JSON::from_msgpack(&[0xca, 0x3f, 0xc0, 0x00, 0x00]) );
+    }
+
+    #[test]
+    fn test_msgpack_errors()
+    {
+        let error = |bytes: &[u8]| JSON::from_msgpack(bytes).map_err(|e| (e.message().to_string(), e.offset()));
+
+        assert_eq!( Err(("unexpected end of input".to_string(), 2)), error(&[0x92, 0x01]) );
+        assert_eq!( Err(("map key is not a string".to_string(), 1)), error(&[0x81, 0x01, 0x02]) );
+        assert_eq!( Err(("binary data has no json equivalent".to_string(), 1)), error(&[0x91, 0xc4, 0x00]) );
+        assert_eq!( Err(("unexpected bytes after the value".to_string(), 1)), error(&[0xc0, 0xc0]) );
+        assert_eq!( Err(("length is longer than the input".to_string(), 1)), error(&[0xdd, 0xff, 0xff, 0xff, 0xff]) );
+        assert_eq!( Err(("string is not valid utf-8".to_string(), 1)), error(&[0xa1, 0xff]) );
+        assert_eq!( Err(("float is not finite".to_string(), 0)), error(&[0xcb, 0x7f, 0xf8, 0, 0, 0, 0, 0, 0]) );
+    }
+
+    #[test]
+    fn test_cbor()
+    {
+        let doc = json(r#"{ "b" : [0, 23, 24, -1, -500, 100000, 1.5, false, null], "a" : "é" }"#);
+        let expected = [
+            0xa2, 0x61, b'a', 0x62, 0xc3, 0xa9, 0x61, b'b', 0x89,
+            0x00, 0x17, 0x18, 0x18, 0x20, 0x39, 0x01, 0xf3, 0x1a, 0x00, 0x01, 0x86, 0xa0,
+            0xfb, 0x3f, 0xf8, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xf4, 0xf6,
+        ];
+        assert_eq!( expected.to_vec(), doc.to_cbor() );
+        assert_eq!( Ok(doc), JSON::from_cbor(&expected) );
+
+        //Indefinite lengths, half floats, tags and the most negative integer
+        let bytes = [0x9f, 0x7f, 0x61, b'a', 0x61, b'b', 0xff, 0xbf, 0x61, b'k', 0xf9, 0x3e, 0x00, 0xff, 0xc1, 0x1a, 0x65, 0x00, 0x00, 0x00,
+                     0x3b, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff];
+        assert_eq!( Ok(json(r#"["ab", { "k" : 1.5 }, 1694498816, -18446744073709551616]"#)), JSON::from_cbor(&bytes) );
+    }
+
+    #[test]
+    fn test_cbor_errors()
+    {
+        let error = |bytes: &[u8]| JSON::from_cbor(bytes).map_err(|e| (e.message().to_string(), e.offset()));
+
+        assert_eq!( Err(("byte strings have no json equivalent".to_string(), 0)), error(&[0x41, 0x00]) );
+        assert_eq!( Err(("map key is not a string".to_string(), 1)), error(&[0xa1, 0x01, 0x02]) );
+        assert_eq!( Err(("unexpected break".to_string(), 1)), error(&[0x81, 0xff]) );
+        assert_eq!( Err(("invalid indefinite length".to_string(), 0)), error(&[0x1f]) );
+        assert_eq!( Err(("chunk of a text string is not a text string".to_string(), 1)), error(&[0x7f, 0x01, 0xff]) );
+        assert_eq!( Err(("arrays and objects are nested too deeply".to_string(), MAX_DEPTH)), error(&[0x81; MAX_DEPTH + 1]) );
+        assert_eq!( Err(("arrays and objects are nested too deeply".to_string(), MAX_DEPTH)), error(&[0xc1; MAX_DEPTH + 1]) );
+    }
+}
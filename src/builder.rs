@@ -0,0 +1,203 @@
+//! Building json values in code, with conversions from Rust values and chainable builders
+//!
+//! ```
+//! use Parser::{ArrayBuilder, ObjectBuilder};
+//!
+//! let key = format!("user_{}", 7);
+//! let value = ObjectBuilder::new().field("name", "x")
+//!                                 .field("age", 33)
+//!                                 .field(key, ArrayBuilder::new().push(true).push(None::<i64>))
+//!                                 .build();
+//!
+//! assert_eq!( r#"{"age":33,"name":"x","user_7":[true,null]}"#, value.to_canonical_string() );
+//! ```
+
+/* (IMPORTS) */
+use std::collections::HashMap;
+
+use crate::{Number, JSON};
+
+
+/* (CONVERSIONS) */
+
+impl From<bool> for JSON {
+    fn from(b: bool) -> JSON
+    {
+        JSON::JsBool(b)
+    }
+}
+
+impl From<&str> for JSON {
+    fn from(s: &str) -> JSON
+    {
+        JSON::JsString(s.to_string())
+    }
+}
+
+impl From<String> for JSON {
+    fn from(s: String) -> JSON
+    {
+        JSON::JsString(s)
+    }
+}
+
+impl From<Number> for JSON {
+    fn from(n: Number) -> JSON
+    {
+        JSON::JsNumber(n)
+    }
+}
+
+// Integers convert exactly through the widest integer of their signedness
+macro_rules! from_integer {
+    ($wide:ty: $($t:ty),*) => {
+        $(
+            impl From<$t> for JSON {
+                fn from(n: $t) -> JSON
+                {
+                    JSON::JsNumber(Number::from(n as $wide))
+                }
+            }
+        )*
+    };
+}
+
+from_integer!(i64: i8, i16, i32, i64, isize);
+from_integer!(u64: u8, u16, u32, u64, usize);
+
+/// NaN and infinite floats have no json representation and become `JsNull`
+impl From<f64> for JSON {
+    fn from(f: f64) -> JSON
+    {
+        Number::from_f64(f).map_or(JSON::JsNull, JSON::JsNumber)
+    }
+}
+
+/// None becomes `JsNull`
+impl<T: Into<JSON>> From<Option<T>> for JSON {
+    fn from(value: Option<T>) -> JSON
+    {
+        value.map_or(JSON::JsNull, Into::into)
+    }
+}
+
+impl<T: Into<JSON>> From<Vec<T>> for JSON {
+    fn from(values: Vec<T>) -> JSON
+    {
+        JSON::JsArray(values.into_iter().map(Into::into).collect())
+    }
+}
+
+
+/* (BUILDERS) */
+
+/// Chainable builder for a json object, see the module documentation
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ObjectBuilder {
+    members: HashMap<String, JSON>,
+}
+
+impl ObjectBuilder {
+
+    /// Creates a builder for an empty object
+    pub fn new() -> ObjectBuilder
+    {
+        ObjectBuilder::default()
+    }
+
+    /// Adds a member, replacing any earlier member with the same key
+    pub fn field(mut self, key: impl Into<String>, value: impl Into<JSON>) -> ObjectBuilder
+    {
+        self.members.insert(key.into(), value.into());
+        self
+    }
+
+    /// Returns the object
+    pub fn build(self) -> JSON
+    {
+        JSON::JsObject(self.members)
+    }
+}
+
+impl From<ObjectBuilder> for JSON {
+    fn from(builder: ObjectBuilder) -> JSON
+    {
+        builder.build()
+    }
+}
+
+/// Chainable builder for a json array, see the module documentation
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ArrayBuilder {
+    elements: Vec<JSON>,
+}
+
+impl ArrayBuilder {
+
+    /// Creates a builder for an empty array
+    pub fn new() -> ArrayBuilder
+    {
+        ArrayBuilder::default()
+    }
+
+    /// Adds an element to the end of the array
+    pub fn push(mut self, value: impl Into<JSON>) -> ArrayBuilder
+    {
+        self.elements.push(value.into());
+        self
+    }
+
+    /// Returns the array
+    pub fn build(self) -> JSON
+    {
+        JSON::JsArray(self.elements)
+    }
+}
+
+impl From<ArrayBuilder> for JSON {
+    fn from(builder: ArrayBuilder) -> JSON
+    {
+        builder.build()
+    }
+}
+
+
+/* (TESTS) */
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::json;
+
+    #[test]
+    fn test_conversions()
+    {
+        assert_eq!( json("true"), JSON::from(true) );
+        assert_eq!( json(r#""a""#), JSON::from("a".to_string()) );
+        assert_eq!( json("-5"), JSON::from(-5i8) );
+        assert_eq!( json("18446744073709551615"), JSON::from(u64::MAX) );
+        assert_eq!( json("1.5"), JSON::from(1.5) );
+        assert_eq!( JSON::JsNull, JSON::from(f64::NAN) );
+        assert_eq!( JSON::JsNull, JSON::from(None::<bool>) );
+        assert_eq!( json("[1, 2]"), JSON::from(vec![1, 2]) );
+    }
+
+    #[test]
+    fn test_builders()
+    {
+        let mut tags = ArrayBuilder::new();
+        for tag in ["a", "b"] {
+            tags = tags.push(tag);
+        }
+
+        let built = ObjectBuilder::new().field("name", "x")
+                                        .field("age", 33)
+                                        .field("name", "y")
+                                        .field("tags", tags)
+                                        .field("address", ObjectBuilder::new().field("city", Some("London")))
+                                        .build();
+
+        assert_eq!( json(r#"{ "name" : "y", "age" : 33, "tags" : ["a", "b"], "address" : { "city" : "London" } }"#), built );
+        assert_eq!( json("{}"), ObjectBuilder::new().build() );
+        assert_eq!( json("[]"), ArrayBuilder::new().build() );
+    }
+}
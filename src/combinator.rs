@@ -0,0 +1,1228 @@
+//! The parser combinators the json grammar is built from, for building other grammars with
+//!
+//! A parser is any function from the input to either the rest of the input and what was matched, or the input at which it failed.
+//! Parsers are combined by passing them to the functions here, which return new parsers.
+//! They work on any `Input`, so a grammar built from them parses a `&[u8]` as well as a `&str`.
+//!
+//! # Examples
+//! ```
+//! use Parser::combinator::{digits, permutation, separated_pair, str_parser, terminated, token, Parser as _};
+//!
+//! //The settings of a config line in any order, each exactly once (ie. "depth=2; width=80;")
+//! let setting = |name| terminated(separated_pair(token(str_parser(name)), str_parser("="), digits), str_parser(";"));
+//! let line = permutation(( setting("width"), setting("depth") ));
+//!
+//! assert_eq!( Ok(("", (("width", "80"), ("depth", "2")))), line.parse("depth=2; width=80;") );
+//! assert!( line.parse("depth=2;").is_err() );
+//! ```
+
+/* (IMPORTS) */
+use std::borrow::Cow;
+
+#[cfg(feature = "regex")]
+use crate::regex::Regex;
+#[cfg(feature = "regex")]
+pub use crate::regex::RegexError;
+
+
+/* (INPUT) */
+
+/// The input a parser reads from, implemented for `&str`, for `&[u8]` (ie. a file read into memory that may not be utf-8) and for `Located`
+///
+/// Positions are offsets in bytes from the start of the input, the combinators only split it where a parser stopped matching.
+pub trait Input: Copy {
+    /// Returns the length of the input in bytes
+    fn input_len(&self) -> usize;
+
+    /// Splits the input at a byte offset, returning the part before it and the part from it
+    fn split_at_offset(&self, offset: usize) -> (Self, Self);
+
+    /// Tells whether the input starts with the bytes of a str
+    fn starts_with_str(&self, s: &str) -> bool;
+
+    /// Returns the bytes of the input
+    fn iter_bytes(&self) -> impl Iterator<Item = u8> + '_;
+
+    /// Returns the characters of the input with their byte offsets, a byte slice gives each byte that is not ascii as U+FFFD
+    fn iter_chars(&self) -> impl Iterator<Item = (usize, char)> + '_;
+}
+
+impl Input for &str {
+    fn input_len(&self) -> usize
+    {
+        self.len()
+    }
+
+    fn split_at_offset(&self, offset: usize) -> (Self, Self)
+    {
+        self.split_at(offset)
+    }
+
+    fn starts_with_str(&self, s: &str) -> bool
+    {
+        self.starts_with(s)
+    }
+
+    fn iter_bytes(&self) -> impl Iterator<Item = u8> + '_
+    {
+        self.bytes()
+    }
+
+    fn iter_chars(&self) -> impl Iterator<Item = (usize, char)> + '_
+    {
+        self.char_indices()
+    }
+}
+
+impl Input for &[u8] {
+    fn input_len(&self) -> usize
+    {
+        self.len()
+    }
+
+    fn split_at_offset(&self, offset: usize) -> (Self, Self)
+    {
+        self.split_at(offset)
+    }
+
+    fn starts_with_str(&self, s: &str) -> bool
+    {
+        self.starts_with(s.as_bytes())
+    }
+
+    fn iter_bytes(&self) -> impl Iterator<Item = u8> + '_
+    {
+        self.iter().copied()
+    }
+
+    fn iter_chars(&self) -> impl Iterator<Item = (usize, char)> + '_
+    {
+        self.iter().map(|&b| if b.is_ascii() { b as char } else { char::REPLACEMENT_CHARACTER }).enumerate()
+    }
+}
+
+
+/// A str along with where it starts in the text it is part of, an `Input` whose errors say where in the text parsing failed
+///
+/// # Examples
+/// ```
+/// use Parser::combinator::{digits, token, Located, Parser as _};
+///
+/// let error = token(digits).parse(Located::new("\n  x")).unwrap_err();
+/// assert_eq!( (3, 2, 3), (error.offset(), error.line(), error.column()) );
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Located<'a> {
+    fragment: &'a str,
+    offset: usize,
+    line: usize,
+    column: usize,
+}
+
+impl<'a> Located<'a> {
+
+    /// Creates the input for the whole of a text
+    pub fn new(text: &'a str) -> Located<'a>
+    {
+        Located { fragment: text, offset: 0, line: 1, column: 1 }
+    }
+
+    /// Returns the part of the text that is left
+    pub fn fragment(&self) -> &'a str
+    {
+        self.fragment
+    }
+
+    /// Returns the byte offset into the text of where the fragment starts
+    pub fn offset(&self) -> usize
+    {
+        self.offset
+    }
+
+    /// Returns the line (starting at 1) of where the fragment starts
+    pub fn line(&self) -> usize
+    {
+        self.line
+    }
+
+    /// Returns the column (in characters, starting at 1) of where the fragment starts
+    pub fn column(&self) -> usize
+    {
+        self.column
+    }
+}
+
+impl Input for Located<'_> {
+    fn input_len(&self) -> usize
+    {
+        self.fragment.len()
+    }
+
+    fn split_at_offset(&self, offset: usize) -> (Self, Self)
+    {
+        let (before, after) = self.fragment.split_at(offset);
+
+        //The position of the rest follows from the lines & characters before it
+        let (line, column) = match before.rfind('\n') {
+            Some(i) => (self.line + before.matches('\n').count(), 1 + before[i + 1..].chars().count()),
+            None => (self.line, self.column + before.chars().count()),
+        };
+        let rest = Located { fragment: after, offset: self.offset + offset, line, column };
+        (Located { fragment: before, ..*self }, rest)
+    }
+
+    fn starts_with_str(&self, s: &str) -> bool
+    {
+        self.fragment.starts_with(s)
+    }
+
+    fn iter_bytes(&self) -> impl Iterator<Item = u8> + '_
+    {
+        self.fragment.bytes()
+    }
+
+    fn iter_chars(&self) -> impl Iterator<Item = (usize, char)> + '_
+    {
+        self.fragment.char_indices()
+    }
+}
+
+/// A parser of the start of its input (a str unless said otherwise, see `Input`)
+///
+/// Left: (remaining unparsed input, what was matched) -- Right: Input on which parser failed, or a `StreamError` (see `Failure`).
+/// The error can be replaced by a richer one with `map_err`, every parser of the json grammar keeps the default.
+pub trait Parser<I, T, E = I> { 
+    /// Runs the parser at the start of the input
+    fn parse(&self, input: I) -> Result<(I, T), E>; 
+}                                                                     
+
+// Implement parser trait for some generic function F
+impl<I, F, T, E> Parser<I, T, E> for F
+where
+    F: Fn(I) -> Result<(I, T), E>, 
+{
+    fn parse(&self, input: I) -> Result<(I, T), E> { 
+        self(input)
+    }
+}
+
+
+/// How much more input a parser needs before it can tell whether the input matches
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Needed {
+    /// At least this many more bytes
+    Size(usize),
+    /// More input, of a length the parser can not tell
+    Unknown,
+}
+
+/// The error of a parser of input that arrives in chunks (ie. a buffer filled from a socket), telling input that is
+/// definitely wrong apart from input that ran out before the parser could decide
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StreamError<I> {
+    /// The input can not match, whatever follows it, at the given point of it
+    Error(I),
+    /// The input ended part way through a match, so parsing must be retried once more of it is available
+    Incomplete(Needed),
+}
+
+/// An error the combinators can fail with, either the input at which parsing failed (the default) or a `StreamError`
+///
+/// The combinators that try something else when a parser fails (ie. `alt` & `opt`) only do so if the error is definitely wrong input,
+/// an `Incomplete` error is passed on, since the parser may match once the rest of the input arrives.
+pub trait Failure<I>: Sized {
+    /// Returns the error for input that can not match, at the given point of it
+    fn error(input: I) -> Self;
+
+    /// Returns the input at which parsing failed, or None if it ran out of input
+    fn failed_at(&self) -> Option<I>;
+}
+
+impl<I: Input> Failure<I> for I {
+    fn error(input: I) -> Self
+    {
+        input
+    }
+
+    fn failed_at(&self) -> Option<I>
+    {
+        Some(*self)
+    }
+}
+
+impl<I: Input> Failure<I> for StreamError<I> {
+    fn error(input: I) -> Self
+    {
+        StreamError::Error(input)
+    }
+
+    fn failed_at(&self) -> Option<I>
+    {
+        match self {
+            StreamError::Error(input) => Some(*input),
+            StreamError::Incomplete(_) => None,
+        }
+    }
+}
+
+/* (PRIMITIVE COMBINATORS) */
+
+/// Function that returns a parser that attempts to match its str against the start of the given input, returning the part of the input matched
+pub fn str_parser<'s, I: Input>(s: &'s str) -> impl Parser<I, I> + use<'s, I>
+{
+    move |input: I|  {    match input.starts_with_str(s) { 
+                                    true => { let (matched, rest) = input.split_at_offset(s.len()); Ok( (rest, matched) ) }, //If match return shifted input & matched input
+                                    false => Err(input)             //Else return unshifted input
+                                } 
+                            }
+}
+
+/// Function that returns a parser like `str_parser` that ignores the case of ascii letters (ie. "null" also matches "Null" & "NULL"),
+/// returning the part of the input matched as it was written
+pub fn tag_no_case<'s, I: Input>(s: &'s str) -> impl Parser<I, I> + use<'s, I>
+{
+    move |input: I| {
+        let n = s.len();
+        match input.input_len() >= n && input.iter_bytes().zip(s.bytes()).all(|(a, b)| a.eq_ignore_ascii_case(&b)) {
+            true => { let (matched, rest) = input.split_at_offset(n); Ok((rest, matched)) }
+            false => Err(input)
+        }
+    }
+}
+
+/// Function that returns a parser matching a regular expression at the start of the input (so `^` matches there), returning the part of the input matched.
+/// Supports the syntax of the patterns of json schemas (ie. `[a-z]+`, `\d{2,4}`, `(?:ab|cd)*`) and takes the match a backtracking matcher would,
+/// so the first alternative to match is taken rather than the longest. The input is only read as far as a match could reach.
+///
+/// # Errors
+/// Returns a `RegexError` if the pattern is malformed, uses unsupported syntax or is too large
+#[cfg(feature = "regex")]
+pub fn regex_parser<I: Input>(pattern: &str) -> Result<impl Parser<I, I>, RegexError>
+{
+    let regex = Regex::new(pattern)?;
+
+    Ok(move |input: I| {
+        match regex.match_start(input.iter_chars(), input.input_len()) {
+            Some(end) => { let (matched, rest) = input.split_at_offset(end); Ok((rest, matched)) }
+            None => Err(input)
+        }
+    })
+}
+
+/// Parser that only matches at the end of the input, matching the empty input
+pub fn eof<I: Input>(input: I) -> Result<(I, I), I>
+{
+    match input.input_len() {
+        0 => Ok((input, input)),
+        _ => Err(input)
+    }
+}
+
+/// Parser that matches one or more ascii digits
+pub fn digits<I: Input>(input: I) -> Result<(I, I), I>
+{
+    let n = input.iter_bytes().take_while(u8::is_ascii_digit).count();
+    match n {
+        0 => Err(input),
+        _ => { let (matched, rest) = input.split_at_offset(n); Ok((rest, matched)) }
+    }
+}
+
+/// Parser that matches one or more ascii digits as a u64, failing with the input if there are none or the number does not fit
+pub fn unsigned<I: Input>(input: I) -> Result<(I, u64), I>
+{
+    let (rest, matched) = digits(input)?;
+    let n = matched.iter_bytes().try_fold(0u64, |n, b| n.checked_mul(10)?.checked_add(u64::from(b - b'0')));
+    n.map(|n| (rest, n)).ok_or(input)
+}
+
+/// Parser that matches an integer with an optional sign (ie. "-12" or "+12") as an i64, failing with the input if there are no digits
+/// or the number does not fit
+pub fn integer<I: Input>(input: I) -> Result<(I, i64), I>
+{
+    let (rest, (sign, n)) = product(opt(or(str_parser("-"), str_parser("+"))), unsigned).parse(input).map_err(|_| input)?;
+
+    let negative = sign.is_some_and(|sign: I| sign.starts_with_str("-"));
+    let n = if negative { 0i64.checked_sub_unsigned(n) } else { i64::try_from(n).ok() };
+    n.map(|n| (rest, n)).ok_or(input)
+}
+
+/// Parser that matches a number with an optional sign, fraction & exponent (ie. "-1.5e3") as an f64, failing with the input if there are no digits
+/// before the fraction or the number is too large for an f64 (ie. 1e999). The exponent is only matched if it has digits.
+pub fn float<I: Input>(input: I) -> Result<(I, f64), I>
+{
+    let sign = || opt(or(str_parser("-"), str_parser("+")));
+    let fraction = opt(product(str_parser("."), opt(digits)));
+    let exponent = opt(seq(( or(str_parser("e"), str_parser("E")), sign(), digits )));
+    let (rest, literal) = recognize(seq(( sign(), digits, fraction, exponent ))).parse(input)?;
+
+    let literal: String = literal.iter_chars().map(|(_, c)| c).collect(); //Only ascii was matched
+    match literal.parse::<f64>() {
+        Ok(f) if f.is_finite() => Ok((rest, f)),
+        _ => Err(input)
+    }
+}
+
+/// Parser that matches 1 to 8 hex digits (of either case) as a u32 (ie. the code unit of a \uXXXX escape), the digits after the 8th are left
+pub fn hex_u32<I: Input>(input: I) -> Result<(I, u32), I>
+{
+    let n = input.iter_bytes().take(8).take_while(u8::is_ascii_hexdigit).count();
+    if n == 0 { return Err(input); }
+
+    let (matched, rest) = input.split_at_offset(n);
+    let unit = matched.iter_bytes().fold(0, |unit, b| unit << 4 | char::from(b).to_digit(16).unwrap_or(0));
+    Ok((rest, unit))
+}
+
+/// Matches the whitespace at the start of the input (which may be none), returning the rest of the input and the whitespace.
+/// Always succeeds.
+pub fn ws0<I: Input>(input: I) -> Result<(I, I), I> 
+{
+    let n = input.iter_chars().find(|(_, c)| !c.is_whitespace()).map_or(input.input_len(), |(i, _)| i); //Offset in bytes, so multibyte whitespace is split correctly
+    let (whitespace, rest) = input.split_at_offset(n);
+    Ok( (rest, whitespace) )
+}
+
+/// Matches at least one whitespace character at the start of the input, returning the rest of the input and the whitespace,
+/// or the input if it does not start with whitespace
+pub fn ws1<I: Input>(input: I) -> Result<(I, I), I> 
+{
+    match ws0(input) {
+        Ok((_, whitespace)) if whitespace.input_len() == 0 => Err(input),
+        matched => matched,
+    }
+}
+
+
+/// Version of `str_parser` for input that arrives in chunks, which fails with `Incomplete` if the input ends part way through its str
+pub fn streaming_str_parser<'s, I: Input>(s: &'s str) -> impl Parser<I, I, StreamError<I>> + use<'s, I>
+{
+    move |input: I| {
+        let n = input.input_len();
+        match input.starts_with_str(s) {
+            true => { let (matched, rest) = input.split_at_offset(s.len()); Ok((rest, matched)) }
+            false if n < s.len() && input.iter_bytes().eq(s.bytes().take(n)) => Err(StreamError::Incomplete(Needed::Size(s.len() - n))),
+            false => Err(StreamError::Error(input))
+        }
+    }
+}
+
+/// Version of `digits` for input that arrives in chunks, which fails with `Incomplete` if the input ends before something that is not a digit,
+/// as more digits may follow
+pub fn streaming_digits<I: Input>(input: I) -> Result<(I, I), StreamError<I>>
+{
+    let n = input.iter_bytes().take_while(u8::is_ascii_digit).count();
+    match n {
+        _ if n == input.input_len() => Err(StreamError::Incomplete(Needed::Size(1))),
+        0 => Err(StreamError::Error(input)),
+        _ => { let (matched, rest) = input.split_at_offset(n); Ok((rest, matched)) }
+    }
+}
+
+/* (DERIVED COMBINATORS) */
+
+/// Sequences 2 parsers, trys the first parser if passes returns that result, otherwise trys the second
+pub fn or<I: Input, E: Failure<I>, P1, P2, A>(p1: P1, p2: P2) -> impl Parser<I, A, E>
+where 
+    P1: Parser<I, A, E>,
+    P2: Parser<I, A, E>
+{   
+    move |input: I| match p1.parse(input) {
+        Err(e) if e.failed_at().is_some() => p2.parse(input),
+        result => result //Including running out of input, which the second parser would not be tried on
+    }
+}
+
+/// Tries each of a tuple of parsers (of up to 8, which may all be of different types) in turn, returning the result of the first to succeed.
+/// If they all fail, fails with the input at which the parser that got furthest into it failed, which is where the input stops making sense.
+/// If one of them runs out of input, fails with its `Incomplete` error without trying the rest.
+pub fn alt<I: Input, E, L, A>(parsers: L) -> impl Parser<I, A, E>
+where
+    L: Alt<I, A, E>
+{
+    move |input: I| parsers.parse_alt(input)
+}
+
+/// A tuple of parsers with the same result that `alt` can choose between
+pub trait Alt<I, A, E> {
+    /// Tries each parser of the tuple in turn
+    fn parse_alt(&self, input: I) -> Result<(I, A), E>;
+}
+
+// Implements Alt for a tuple of parsers, given the type parameter of each parser and its index
+macro_rules! alt_tuple {
+    ($($P:ident $i:tt),+) => {
+        impl<I: Input, E: Failure<I>, A, $($P),+> Alt<I, A, E> for ($($P,)+)
+        where
+            $($P: Parser<I, A, E>),+
+        {
+            fn parse_alt(&self, input: I) -> Result<(I, A), E>
+            {
+                let mut furthest = (input.input_len(), E::error(input));
+                $(
+                    match self.$i.parse(input) {
+                        Ok(matched) => return Ok(matched),
+                        Err(e) => match e.failed_at() {
+                            Some(at) if at.input_len() < furthest.0 => furthest = (at.input_len(), e),
+                            Some(_) => {}
+                            None => return Err(e)
+                        }
+                    }
+                )+
+                Err(furthest.1)
+            }
+        }
+    };
+}
+
+alt_tuple!(P1 0, P2 1);
+alt_tuple!(P1 0, P2 1, P3 2);
+alt_tuple!(P1 0, P2 1, P3 2, P4 3);
+alt_tuple!(P1 0, P2 1, P3 2, P4 3, P5 4);
+alt_tuple!(P1 0, P2 1, P3 2, P4 3, P5 4, P6 5);
+alt_tuple!(P1 0, P2 1, P3 2, P4 3, P5 4, P6 5, P7 6);
+alt_tuple!(P1 0, P2 1, P3 2, P4 3, P5 4, P6 5, P7 6, P8 7);
+
+/// Sequences 2 parsers, running p1 then p2 and returns the pair of their results only if both succeed
+pub fn product<I: Input, E, P1, P2, R1, R2>(p1: P1, p2: P2) -> impl Parser<I, (R1, R2), E>
+where
+    P1: Parser<I, R1, E>,
+    P2: Parser<I, R2, E>,
+{
+    move |input| {
+        p1.parse(input).and_then(|(next_input, r1)| { //Note: and_then is flatMap
+            p2.parse(next_input)
+                .map(|(last_input, r2)| (last_input, (r1, r2)))
+        })
+    }
+}
+
+/// Runs each of a tuple of parsers (of up to 8) in turn, each on the input the one before left, returning the tuple of their results
+/// if all of them succeed (ie. the sign, digits & exponent of a number). Fails with the input at which the first failing parser failed.
+pub fn seq<I: Input, E, L, T>(parsers: L) -> impl Parser<I, T, E>
+where
+    L: Sequence<I, T, E>
+{
+    move |input: I| parsers.parse_sequence(input)
+}
+
+/// A tuple of parsers that `seq` can run one after another, with the tuple of their results
+pub trait Sequence<I, T, E> {
+    /// Runs each parser of the tuple in turn
+    fn parse_sequence(&self, input: I) -> Result<(I, T), E>;
+}
+
+// Implements Sequence for a tuple of parsers, given the type parameters of each parser & its result, its index and a name for its result
+macro_rules! sequence_tuple {
+    ($($P:ident $R:ident $i:tt $r:ident),+) => {
+        impl<I: Input, E, $($P, $R),+> Sequence<I, ($($R,)+), E> for ($($P,)+)
+        where
+            $($P: Parser<I, $R, E>),+
+        {
+            fn parse_sequence(&self, input: I) -> Result<(I, ($($R,)+)), E>
+            {
+                let inputted = input;
+                $( let (inputted, $r) = self.$i.parse(inputted)?; )+
+                Ok((inputted, ($($r,)+)))
+            }
+        }
+    };
+}
+
+sequence_tuple!(P1 R1 0 r1, P2 R2 1 r2);
+sequence_tuple!(P1 R1 0 r1, P2 R2 1 r2, P3 R3 2 r3);
+sequence_tuple!(P1 R1 0 r1, P2 R2 1 r2, P3 R3 2 r3, P4 R4 3 r4);
+sequence_tuple!(P1 R1 0 r1, P2 R2 1 r2, P3 R3 2 r3, P4 R4 3 r4, P5 R5 4 r5);
+sequence_tuple!(P1 R1 0 r1, P2 R2 1 r2, P3 R3 2 r3, P4 R4 3 r4, P5 R5 4 r5, P6 R6 5 r6);
+sequence_tuple!(P1 R1 0 r1, P2 R2 1 r2, P3 R3 2 r3, P4 R4 3 r4, P5 R5 4 r5, P6 R6 5 r6, P7 R7 6 r7);
+sequence_tuple!(P1 R1 0 r1, P2 R2 1 r2, P3 R3 2 r3, P4 R4 3 r4, P5 R5 4 r5, P6 R6 5 r6, P7 R7 6 r7, P8 R8 7 r8);
+
+/// Parser adapter that transforms the error of p (ie. to say what was expected, or to fail at an earlier point of the input)
+pub fn map_err<I: Input, P, A, E1, E2, F>(p: P, f: F) -> impl Parser<I, A, E2>
+where 
+    P: Parser<I, A, E1>,
+    F: Fn(E1) -> E2
+{
+    move |input: I| p.parse(input).map_err(&f)
+}
+
+/// Parser adapter that fails if the result of p does not satisfy the predicate (ie. a number out of range),
+/// with the input at the start of what p matched. The json grammar checks each value as it converts it instead.
+pub fn verify<I: Input, E: Failure<I>, P, A, F>(p: P, predicate: F) -> impl Parser<I, A, E>
+where 
+    P: Parser<I, A, E>,
+    F: Fn(&A) -> bool
+{
+    move |input: I| match p.parse(input)? {
+        (next, matched) if predicate(&matched) => Ok((next, matched)),
+        _ => Err(E::error(input))
+    }
+}
+
+/// Parser adapter that replaces the result of p with a constant (ie. the value a keyword stands for)
+pub fn value<I: Input, E, P, A, B>(konst: B, p: P) -> impl Parser<I, B, E>
+where 
+    P: Parser<I, A, E>,
+    B: Clone
+{
+    move |input: I| p.parse(input).map(|(next, _)| (next, konst.clone()))
+}
+
+/// Parser adapter that makes p optional, matches nothing if p fails on input that is definitely wrong (but not if it runs out of input)
+pub fn opt<I: Input, E: Failure<I>, P, A>(p: P) -> impl Parser<I, Option<A>, E>
+where 
+    P: Parser<I, A, E>
+{
+    move |input: I| match p.parse(input) {
+        Ok((next, matched)) => Ok((next, Some(matched))),
+        Err(e) if e.failed_at().is_some() => Ok((input, None)),
+        Err(e) => Err(e)
+    }
+}
+
+/// Parser adapter that returns the slice of the input p matched along with its result (ie. the text of a value and the value)
+pub fn consumed<I: Input, E, P, A>(p: P) -> impl Parser<I, (I, A), E>
+where 
+    P: Parser<I, A, E>
+{
+    move |input: I| {
+        let (next, matched) = p.parse(input)?;
+        let (consumed, _) = input.split_at_offset(input.input_len() - next.input_len());
+        Ok((next, (consumed, matched)))
+    }
+}
+
+/// Parser adapter that discards the result of p and instead returns the slice of the input it matched
+pub fn recognize<I: Input, E, P, A>(p: P) -> impl Parser<I, I, E>
+where 
+    P: Parser<I, A, E>
+{
+    left( consumed(p) )
+}
+
+/// Parser adapter that matches a quoted string literal 
+pub fn quoted_string_literal<I: Input, P>(p: P) -> impl Parser<I, I> 
+where 
+    P: Parser<I, I>
+{
+    delimited(str_parser("\""), p, str_parser("\""))
+}
+
+/// Parser adapter that matches text made of runs of normal characters (matched by `normal`) and escape sequences (an escape character,
+/// then what `transform` matches), returning the text with each escape sequence replaced by what `transform` returned (ie. `\n` by a newline).
+/// The text is only copied if it has an escape sequence in it. Stops at the first character that is neither normal nor the escape character,
+/// and fails with the input at which `transform` failed if an escape sequence is invalid.
+pub fn escaped_transform<'a, N, T, A>(normal: N, escape: char, transform: T) -> impl Parser<&'a str, Cow<'a, str>>
+where
+    N: Parser<&'a str, &'a str>,
+    T: Parser<&'a str, A>,
+    String: Extend<A>
+{
+    move |input: &'a str| {
+        let mut owned: Option<String> = None; //Only allocated once an escape sequence is found
+        let mut inputted = input;
+
+        loop {
+            if let Ok((next, chars)) = normal.parse(inputted) {
+                if let Some(s) = owned.as_mut() { s.push_str(chars); }
+                inputted = next;
+            }
+
+            match inputted.strip_prefix(escape) {
+                Some(sequence) => {
+                    let (next, unescaped) = transform.parse(sequence)?;
+                    let s = owned.get_or_insert_with(|| input[..input.len() - inputted.len()].to_string());
+                    s.extend(std::iter::once(unescaped));
+                    inputted = next;
+                }
+                None => break
+            }
+        }
+
+        let text = match owned {
+            Some(s) => Cow::Owned(s),
+            None => Cow::Borrowed(&input[..input.len() - inputted.len()])
+        };
+        Ok((inputted, text))
+    }
+}
+
+/// Parser adapter that matches zero or more instance of a str against a given input (the json grammar uses comma_separated instead)
+pub fn zero_or_more<I: Input, E: Failure<I>, P, A>(p: P) -> impl Parser<I, Vec<A>, E>  
+where 
+    P: Parser<I, A, E>
+{
+    move |input: I| {
+
+        let mut v = vec![];
+        let mut inputted = input; //Is the input that gets fed to parser
+
+        loop {
+            match p.parse(inputted) {
+                Ok((next, matches)) => {
+                    inputted = next;    //"Shift" forward str to be fed to parser if parser correctly parsed str
+                    v.push(matches);
+                }
+                Err(e) if e.failed_at().is_some() => break,
+                Err(e) => return Err(e) //Ran out of input, more instances may follow
+            }
+        }
+
+        Ok((inputted, v)) //Return all unparsed input and the input on the original str that got parsed
+    }
+}
+
+/// Function that returns the left value from a parser with a pair result
+pub fn left<I: Input, E, P, A, B>(p: P) -> impl Parser<I, A, E> 
+where 
+    P: Parser<I, (A, B), E>,
+{   
+    move |input: I| {
+        match p.parse(input) {
+            Ok((s, (a, _b))) => Ok((s, a)), 
+            Err(e)  =>  Err(e)
+        }
+    }
+}
+
+/// Function that returns the right value from a parser with a pair result
+pub fn right<I: Input, E, P, A, B>(p: P) -> impl Parser<I, B, E> 
+where 
+    P: Parser<I, (A, B), E>,
+{   
+    move |input: I| {
+        match p.parse(input) {
+            Ok((s, (_a,b))) => Ok((s, b)), 
+            Err(e)  =>  Err(e)
+        }
+    }
+}
+
+/// Sequences 2 parsers and only keeps the result of the second (ie. the value after a separator)
+pub fn preceded<I: Input, E, P1, P2, R1, R2>(p1: P1, p2: P2) -> impl Parser<I, R2, E>
+where
+    P1: Parser<I, R1, E>,
+    P2: Parser<I, R2, E>,
+{
+    right( product(p1, p2) )
+}
+
+/// Sequences 2 parsers and only keeps the result of the first (ie. the value before a terminator)
+pub fn terminated<I: Input, E, P1, P2, R1, R2>(p1: P1, p2: P2) -> impl Parser<I, R1, E>
+where
+    P1: Parser<I, R1, E>,
+    P2: Parser<I, R2, E>,
+{
+    left( product(p1, p2) )
+}
+
+/// Sequences 3 parsers and only keeps the result of the middle one (ie. the contents between quotes)
+pub fn delimited<I: Input, E, P1, P2, P3, R1, R2, R3>(open: P1, p: P2, close: P3) -> impl Parser<I, R2, E>
+where
+    P1: Parser<I, R1, E>,
+    P2: Parser<I, R2, E>,
+    P3: Parser<I, R3, E>,
+{
+    preceded(open, terminated(p, close))
+}
+
+/// Sequences 3 parsers and keeps the results of the first and last, dropping the separator between them (ie. key : value)
+pub fn separated_pair<I: Input, E, P1, S, P2, R1, RS, R2>(p1: P1, separator: S, p2: P2) -> impl Parser<I, (R1, R2), E>
+where
+    P1: Parser<I, R1, E>,
+    S: Parser<I, RS, E>,
+    P2: Parser<I, R2, E>,
+{
+    product(terminated(p1, separator), p2)
+}
+
+
+/// Parser adapter that skips the whitespace before a token, which is the one place the grammar allows whitespace between tokens
+pub fn token<I: Input, E, P, A>(p: P) -> impl Parser<I, A, E>
+where
+    P: Parser<I, A, E>
+{
+    move |input: I| match ws0(input) {
+        Ok((rest, _)) | Err(rest) => p.parse(rest) //ws0 always succeeds, whatever the error type of p
+    }
+}
+
+/// Parser adapter that only succeeds if nothing but whitespace follows what p matched (ie. a whole document), consuming it
+/// Otherwise fails with the input following the whitespace, which is where the unexpected input starts
+pub fn complete<I: Input, P, A>(p: P) -> impl Parser<I, A>
+where
+    P: Parser<I, A>
+{
+    terminated(p, token(eof))
+}
+
+/// Parser adapter that matches each of a tuple of parsers exactly once, in any order, returning their results in the order of the tuple
+/// (ie. the members of a config section). Fails with the input at which none of the parsers left to match succeeds.
+///
+/// At each point of the input the parsers left to match are tried in the order of the tuple, the first to succeed is taken.
+pub fn permutation<I: Input, E, L, T>(parsers: L) -> impl Parser<I, T, E>
+where
+    L: Permutation<I, T, E>
+{
+    move |input: I| parsers.parse_permutation(input)
+}
+
+/// A tuple of parsers (of up to 8) that `permutation` can match in any order, with the tuple of their results
+pub trait Permutation<I, T, E> {
+    /// Matches every parser of the tuple exactly once, in any order
+    fn parse_permutation(&self, input: I) -> Result<(I, T), E>;
+}
+
+// Implements Permutation for a tuple of parsers, given the type parameters of each parser & its result, its index and a name for its result
+macro_rules! permutation_tuple {
+    ($($P:ident $R:ident $i:tt $r:ident),+) => {
+        impl<I: Input, E: Failure<I>, $($P, $R),+> Permutation<I, ($($R,)+), E> for ($($P,)+)
+        where
+            $($P: Parser<I, $R, E>),+
+        {
+            fn parse_permutation(&self, input: I) -> Result<(I, ($($R,)+)), E>
+            {
+                let mut inputted = input;
+                let mut results: ($(Option<$R>,)+) = ($(Option::<$R>::None,)+);
+
+                //Go round the parsers left to match until none of them matches
+                'round: loop {
+                    $(
+                        if results.$i.is_none() {
+                            match self.$i.parse(inputted) {
+                                Ok((next, matched)) => {
+                                    results.$i = Some(matched);
+                                    inputted = next;
+                                    continue 'round;
+                                }
+                                Err(e) if e.failed_at().is_none() => return Err(e),
+                                Err(_) => {}
+                            }
+                        }
+                    )+
+                    break;
+                }
+
+                match results {
+                    ($(Some($r),)+) => Ok((inputted, ($($r,)+))),
+                    _ => Err(E::error(inputted))
+                }
+            }
+        }
+    };
+}
+
+permutation_tuple!(P1 R1 0 r1, P2 R2 1 r2);
+permutation_tuple!(P1 R1 0 r1, P2 R2 1 r2, P3 R3 2 r3);
+permutation_tuple!(P1 R1 0 r1, P2 R2 1 r2, P3 R3 2 r3, P4 R4 3 r4);
+permutation_tuple!(P1 R1 0 r1, P2 R2 1 r2, P3 R3 2 r3, P4 R4 3 r4, P5 R5 4 r5);
+permutation_tuple!(P1 R1 0 r1, P2 R2 1 r2, P3 R3 2 r3, P4 R4 3 r4, P5 R5 4 r5, P6 R6 5 r6);
+permutation_tuple!(P1 R1 0 r1, P2 R2 1 r2, P3 R3 2 r3, P4 R4 3 r4, P5 R5 4 r5, P6 R6 5 r6, P7 R7 6 r7);
+permutation_tuple!(P1 R1 0 r1, P2 R2 1 r2, P3 R3 2 r3, P4 R4 3 r4, P5 R5 4 r5, P6 R6 5 r6, P7 R7 6 r7, P8 R8 7 r8);
+
+/// Parser adapter that matches zero or more elements separated by commas, followed by a closing str (ie. "]")
+/// Whitespace is allowed before each comma and before the closing str, trailing commas are not allowed
+pub fn comma_separated<'s, I: Input, P, A>(p: P, closing: &'s str) -> impl Parser<I, Vec<A>> + use<'s, I, P, A>
+where 
+    P: Parser<I, A>
+{
+    let comma = token(str_parser(","));
+    let close = token(str_parser(closing));
+
+    move |input: I| {
+
+        //Empty list (ie. only whitespace before the closing str)
+        if let Ok((next, _)) = close.parse(input) {
+            return Ok((next, vec![]));
+        }
+
+        let mut v = vec![];
+        let mut inputted = input;
+
+        loop {
+            let (next, matches) = p.parse(inputted)?;
+            v.push(matches);
+
+            //Either the list ends here or there must be a comma before the next element
+            if let Ok((last, _)) = close.parse(next) {
+                return Ok((last, v));
+            }
+            inputted = comma.parse(next)?.0;
+        }
+    }
+}
+
+
+/* (TESTS) */
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test] 
+    fn test_str_parser() 
+    {
+        let parse_hello = str_parser("Hello");
+
+        assert_eq!( Ok(("", "Hello")), parse_hello.parse("Hello") );
+        assert_eq!( Err("Yello"), parse_hello.parse("Yello") );
+        assert_eq!( Ok((" Jello", "Hello")), parse_hello.parse("Hello Jello"));
+    }
+
+    #[test]
+    fn test_tag_no_case()
+    {
+        let p = tag_no_case("null");
+
+        assert_eq!( Ok((",", "null")), p.parse("null,") );
+        assert_eq!( Ok(("", "NuLL")), p.parse("NuLL") );                //Matched as written
+        assert_eq!( Err("nul"), p.parse("nul") );
+        assert_eq!( Err("nulx"), p.parse("nulx") );
+        assert_eq!( Ok(("", "ÄB")), tag_no_case("Äb").parse("ÄB") );    //Other letters must match exactly
+        assert_eq!( Err("äb"), tag_no_case("Äb").parse("äb") );
+        assert_eq!( Ok((&b"!"[..], &b"TRUE"[..])), tag_no_case("true").parse(&b"TRUE!"[..]) );
+    }
+
+    #[test]
+    #[cfg(feature = "regex")]
+    fn test_regex_parser()
+    {
+        let input = "7".repeat(100_000);
+        let regex = |pattern| regex_parser(pattern).unwrap();
+        let ident = regex(r"[A-Za-z_]\w*");
+
+        assert_eq!( Ok((" = 1", "max_depth2")), ident.parse("max_depth2 = 1") );
+        assert_eq!( Err("2max"), ident.parse("2max") );                                  //Only matches at the start
+        assert_eq!( Ok(("b", "a")), regex("a|ab").parse("ab") );                         //The first alternative to match is taken
+        assert_eq!( Ok(("", "ñé")), regex("^[^a-z]+$").parse("ñé") );
+        assert_eq!( Ok(("", ("12", "ms"))), separated_pair(regex(r"\d+"), ws1, regex("m?s")).parse("12 ms") );
+        assert_eq!( Ok((&b" ms"[..], &b"12"[..])), regex_parser(r"\d+").unwrap().parse(&b"12 ms"[..]) );
+
+        //Each match only reads the chars it needs, so repeating a parser over a long input takes time linear in it
+        assert_eq!( Ok(("", vec!["7"; 100_000])), zero_or_more(regex(r"\d")).parse(input.as_str()) );
+    }
+
+    #[test]
+    #[cfg(feature = "regex")]
+    fn test_invalid_regex()
+    {
+        assert_eq!( "missing ')'", regex_parser::<&str>("(ab").err().unwrap().to_string() );
+        assert!( regex_parser::<&str>("a{1000000}").is_err() );
+    }
+
+    #[test]
+    fn test_unsigned()
+    {
+        assert_eq!( Ok((",", 120)), unsigned("0120,") );
+        assert_eq!( Ok(("", u64::MAX)), unsigned("18446744073709551615") );
+        assert_eq!( Err("18446744073709551616"), unsigned("18446744073709551616") );  //Does not fit
+        assert_eq!( Err("-1"), unsigned("-1") );
+        assert_eq!( Err(""), unsigned("") );
+    }
+
+    #[test]
+    fn test_integer()
+    {
+        assert_eq!( Ok((".5", -12)), integer("-12.5") );
+        assert_eq!( Ok(("", 7)), integer("+7") );
+        assert_eq!( Ok(("", i64::MIN)), integer("-9223372036854775808") );
+        assert_eq!( Err("9223372036854775808"), integer("9223372036854775808") );
+        assert_eq!( Err("-x"), integer("-x") );                                            //Fails at the sign
+        assert_eq!( Ok((&b"]"[..], 42)), integer(&b"42]"[..]) );
+    }
+
+    #[test]
+    fn test_float()
+    {
+        assert_eq!( Ok((",", -1500.0)), float("-1.5e3,") );
+        assert_eq!( Ok(("", 2.0)), float("+2.") );
+        assert_eq!( Ok(("e", 0.25)), float("0.25e") );                                       //The exponent needs digits
+        assert_eq!( Ok(("", 1e-7)), float("1E-7") );
+        assert_eq!( Err(".5"), float(".5") );
+        assert_eq!( Err("1e999"), float("1e999") );                                          //Too large for an f64
+    }
+
+    #[test]
+    fn test_hex_u32()
+    {
+        assert_eq!( Ok(("", 0xe9)), hex_u32("00e9") );
+        assert_eq!( Ok(("x", 0xD83E)), hex_u32("D83Ex") );
+        assert_eq!( Ok(("9", u32::MAX)), hex_u32("ffffffff9") );                             //At most 8 digits
+        assert_eq!( Err("g1"), hex_u32("g1") );
+        assert_eq!( Ok(("", 0x12)), terminated(hex_u32, eof).parse("12") );
+    }
+
+    #[test]
+    fn test_parser_or()
+    {
+        let parse_hello = str_parser("Hello"); 
+        let parse_goodbye = str_parser("Goodbye");
+        let parse_or = or(parse_hello, parse_goodbye);
+
+        assert_eq!( Err(""), parse_or.parse("") );
+        assert_eq!( Ok(("", "Hello")), parse_or.parse("Hello"));                    //P1 succeeds
+        assert_eq!( Ok(("", "Goodbye")), parse_or.parse("Goodbye"));                //P2 succeeds
+        assert_eq!( Ok((" Goodbye", "Hello")), parse_or.parse("Hello Goodbye"));    //Both succeed
+    }
+
+    #[test]
+    fn test_alt()
+    {
+        let p = alt(( str_parser("null"), digits, recognize(product(str_parser("\""), str_parser("ab\""))) ));
+
+        assert_eq!( Ok((",", "null")), p.parse("null,") );
+        assert_eq!( Ok(("", "12")), p.parse("12") );
+        assert_eq!( Ok(("", "\"ab\"")), p.parse("\"ab\"") );
+        assert_eq!( Err("cd\""), p.parse("\"cd\"") );          //Where the furthest parser failed
+        assert_eq!( Err("x"), p.parse("x") );
+    }
+
+    #[test]
+    fn test_parser_product()
+    {
+        let p1 = product( str_parser("Goodbye"), str_parser(" Adieu"));
+        let p2 = product( str_parser("Hello"), str_parser(" Adieu"));
+        let p3 = product( str_parser("Hello"), str_parser(" Goodbye"));
+
+        assert_eq!( Err(""), p1.parse("") );
+        assert_eq!( Err(""), p2.parse("") );
+        assert_eq!( Err(""), p3.parse("") );
+
+        assert_eq!( Err("Hello Adieu"), p1.parse("Hello Adieu"));                   //P1 fails
+        assert_eq!( Err(" Goodbye"), p2.parse("Hello Goodbye"));                    //P2 fails
+        assert_eq!( Ok( ("", ("Hello", " Goodbye"))), p3.parse("Hello Goodbye"));   //Both succeed
+    }
+
+    #[test]
+    fn test_parser_quoted_str_literal()
+    {
+        let parse_quoted_hello = quoted_string_literal(str_parser("Hello")); 
+
+        assert_eq!( Err(""), parse_quoted_hello.parse("") );
+        assert_eq!( Err(""), parse_quoted_hello.parse("\"Hello") ); //Err returns empty str since fails to match missing closing quote
+        assert_eq!( Err("Hello\""), parse_quoted_hello.parse("Hello\"") );
+
+        assert_eq!( Ok(("", "Hello")), parse_quoted_hello.parse("\"Hello\""));
+    }
+
+
+    #[test]
+    fn test_escaped_transform()
+    {
+        //Letters, with %n for a newline & %% for a percent sign
+        fn letters(input: &str) -> Result<(&str, &str), &str>
+        {
+            let n = input.find(|c: char| !c.is_alphabetic()).unwrap_or(input.len());
+            Ok((&input[n..], &input[..n]))
+        }
+        let p = escaped_transform(letters, '%', or(value("\n", str_parser("n")), value("%", str_parser("%"))));
+
+        assert_eq!( Ok((" b", Cow::Borrowed("ab"))), p.parse("ab b") );                  //Not copied without an escape sequence
+        assert_eq!( Ok(("!", Cow::Owned::<str>("a\nb%".to_string()))), p.parse("a%nb%%!") );
+        assert_eq!( Ok(("", Cow::Owned::<str>("\n".to_string()))), p.parse("%n") );
+        assert_eq!( Ok(("1", Cow::Borrowed(""))), p.parse("1") );
+        assert_eq!( Err("x"), p.parse("ab%x") );                                         //Where the escape sequence is invalid
+        assert_eq!( Err(""), p.parse("ab%") );
+    }
+
+    #[test]
+    fn test_zero_or_more()
+    {
+        let p1 = zero_or_more(str_parser(" "));
+        let p2 = zero_or_more(str_parser("ab"));
+      
+        assert_eq!( Ok(("", vec![])), p1.parse("") );                       //Successfully match 0 spaces (Note: Returns empty vec)
+        assert_eq!( Ok(("", vec![" "])), p1.parse(" ") );                   //Successfully match single space
+        assert_eq!( Ok(("", vec![" ", " ", " ", " "])), p1.parse("    ") ); //Successfully match 4 spaces
+        assert_ne!( Ok(("", vec![])), p1.parse(" ") );  //Should this match?
+
+        assert_eq!( Ok(("", vec![])), p2.parse("") );                           //Successfully match  (Note: Returns empty vec)
+        assert_eq!( Ok(("", vec!["ab"])), p2.parse("ab") );                     //Successfully match single 
+        assert_eq!( Ok(("", vec!["ab", "ab", "ab", "ab"])), p2.parse("abababab") ); //Successfully match 4 
+    }
+
+    #[test]
+    fn test_comma_separated()
+    {
+        let p = comma_separated(str_parser("ab"), "]");
+
+        assert_eq!( Ok(("", vec![])), p.parse("]") );
+        assert_eq!( Ok(("", vec![])), p.parse("  ]") );
+        assert_eq!( Ok((" x", vec!["ab"])), p.parse("ab] x") );
+        assert_eq!( Ok(("", vec!["ab", "ab", "ab"])), p.parse("ab,ab ,ab ]") );
+
+        assert_eq!( Err(""), p.parse("ab,ab") );        //Missing closing str
+        assert_eq!( Err("]"), p.parse("ab,]") );        //Trailing comma
+        assert_eq!( Err("ab]"), p.parse("ab ab]") );    //Missing comma
+    }
+
+    #[test]
+    fn test_seq()
+    {
+        let p = seq(( opt(str_parser("-")), digits, str_parser("."), digits ));
+
+        assert_eq!( Ok(("e5", (Some("-"), "12", ".", "50"))), p.parse("-12.50e5") );
+        assert_eq!( Ok(("", (None, "1", ".", "0"))), p.parse("1.0") );
+        assert_eq!( Err("x"), p.parse("12.x") );             //Where the first failing parser failed
+        assert_eq!( Err(""), p.parse("") );
+    }
+
+    #[test]
+    fn test_left()
+    {
+        let parser = product( str_parser("Hello"), str_parser(" Goodbye"));
+        let p = left( parser );
+
+        assert_eq!( Err(""), p.parse("") );
+        assert_eq!( Ok(("", "Hello")), p.parse("Hello Goodbye") );           
+        assert_eq!( Ok((" Again", "Hello")), p.parse("Hello Goodbye Again") );           
+    }
+
+    #[test]
+    fn test_right()
+    {
+        let parser = product( str_parser("Hello"), str_parser(" Goodbye"));
+        let p = right( parser );
+
+        assert_eq!( Err(""), p.parse("") );
+        assert_eq!( Ok(("", " Goodbye")), p.parse("Hello Goodbye") );           
+        assert_eq!( Ok((" Again", " Goodbye")), p.parse("Hello Goodbye Again") ); 
+    }
+
+    #[test]
+    fn test_sequencing()
+    {
+        let hello = || str_parser("Hello");
+        let space = || str_parser(" ");
+        let goodbye = || str_parser("Goodbye");
+
+        assert_eq!( Ok((" Again", "Goodbye")), preceded(hello(), preceded(space(), goodbye())).parse("Hello Goodbye Again") );
+        assert_eq!( Ok(("Goodbye", "Hello")), terminated(hello(), space()).parse("Hello Goodbye") );
+        assert_eq!( Ok(("", " ")), delimited(hello(), space(), goodbye()).parse("Hello Goodbye") );
+        assert_eq!( Ok(("!", ("Hello", "Goodbye"))), separated_pair(hello(), space(), goodbye()).parse("Hello Goodbye!") );
+
+        //Fails with the input where the failing parser was tried
+        assert_eq!( Err("Goodbye"), terminated(hello(), space()).parse("HelloGoodbye") );
+        assert_eq!( Err("Adieu"), delimited(hello(), space(), goodbye()).parse("Hello Adieu") );
+        assert_eq!( Err("Hi Goodbye"), separated_pair(hello(), space(), goodbye()).parse("Hi Goodbye") );
+    }
+
+    #[test]
+    fn test_map_err()
+    {
+        #[derive(Debug, PartialEq)]
+        struct Expected<'a> { what: &'static str, at: &'a str }
+
+        let colon = map_err(str_parser(":"), |at| Expected { what: "':'", at });
+        assert_eq!( Ok((" 1", ":")), colon.parse(": 1") );
+        assert_eq!( Err(Expected { what: "':'", at: "= 1" }), colon.parse("= 1") );
+
+        //Failing at the start of the input rather than where the inner parser stopped
+        let input = "Hello Adieu";
+        assert_eq!( Err(input), map_err(product(str_parser("Hello"), str_parser(" Goodbye")), |_| input).parse(input) );
+    }
+
+    #[test]
+    fn test_eof()
+    {
+        assert_eq!( Ok(("", "")), eof("") );
+        assert_eq!( Err(" "), eof(" ") );
+        assert_eq!( Ok(("", ("ab", ""))), product(str_parser("ab"), eof).parse("ab") );
+        assert_eq!( Err("c"), product(str_parser("ab"), eof).parse("abc") );
+    }
+
+    #[test]
+    fn test_complete()
+    {
+        let p = complete(str_parser("ab"));
+
+        assert_eq!( Ok(("", "ab")), p.parse("ab") );
+        assert_eq!( Ok(("", "ab")), p.parse("ab \n") );          //Trailing whitespace is consumed
+        assert_eq!( Err("c"), p.parse("ab c") );                 //Fails where the residual input starts
+        assert_eq!( Err("x"), p.parse("x") );                    //As does p
+    }
+
+    #[test]
+    fn test_recognize()
+    {
+        let p = recognize( product(str_parser("ab"), opt(str_parser("cd"))) );
+
+        assert_eq!( Ok(("", "abcd")), p.parse("abcd") );
+        assert_eq!( Ok(("ce", "ab")), p.parse("abce") );
+        assert_eq!( Err("x"), p.parse("x") );
+    }
+
+    #[test]
+    fn test_token()
+    {
+        assert_eq!( Ok(("]", ",")), token(str_parser(",")).parse(" \n\t,]") );
+        assert_eq!( Err("x"), token(str_parser(",")).parse("  x") );
+    }
+
+    #[test]
+    fn test_byte_input()
+    {
+        let bytes = |s: &'static str| s.as_bytes();
+        let p = separated_pair(token(str_parser("width")), token(str_parser("=")), token(digits));
+
+        assert_eq!( Ok((bytes(";"), (bytes("width"), bytes("80")))), p.parse(bytes(" width = 80;")) );
+        assert_eq!( Err(bytes("x;")), p.parse(bytes("width = x;")) );
+
+        //Bytes that are not ascii are never whitespace, and the input does not need to be utf-8
+        assert_eq!( Ok((&[0xa0, b'1'][..], &b" "[..])), ws0(&[b' ', 0xa0, b'1'][..]) );
+        assert_eq!( Ok(("1", "\u{a0} ")), ws0("\u{a0} 1") );
+        assert_eq!( Err(&[0xff, b'1'][..]), ws1(&[0xff, b'1'][..]) );
+    }
+
+    #[test]
+    fn test_located()
+    {
+        let p = zero_or_more(token(or(str_parser("ab"), str_parser("ñ"))));
+        let (rest, matched) = p.parse(Located::new("ab\n ab ñ ab\r\nabx")).unwrap();
+
+        let positions: Vec<_> = matched.iter().map(|m| (m.fragment(), m.offset(), m.line(), m.column())).collect();
+        assert_eq!( vec![("ab", 0, 1, 1), ("ab", 4, 2, 2), ("ñ", 7, 2, 5), ("ab", 10, 2, 7), ("ab", 14, 3, 1)], positions );
+        assert_eq!( ("x", 16, 3, 3), (rest.fragment(), rest.offset(), rest.line(), rest.column()) );
+
+        //Errors are positioned too
+        let error = complete(token(digits)).parse(Located::new("12\n 3")).unwrap_err();
+        assert_eq!( ("3", 4, 2, 2), (error.fragment(), error.offset(), error.line(), error.column()) );
+    }
+
+    #[test]
+    fn test_streaming()
+    {
+        let incomplete = |n| StreamError::Incomplete(Needed::Size(n));
+
+        assert_eq!( Ok(("", "null")), streaming_str_parser("null").parse("null") );
+        assert_eq!( Err(incomplete(2)), streaming_str_parser("null").parse("nu") );      //May be the start of "null"
+        assert_eq!( Err(StreamError::Error("nul!")), streaming_str_parser("null").parse("nul!") );
+        assert_eq!( Ok((",", "12")), streaming_digits("12,") );
+        assert_eq!( Err(incomplete(1)), streaming_digits("12") );                         //More digits may follow
+
+        //Alternatives & repetitions are not taken in place of a parser that ran out of input
+        let keyword = alt(( streaming_str_parser("true"), streaming_str_parser("false") ));
+        assert_eq!( Err(incomplete(3)), keyword.parse("fa") );
+        assert_eq!( Err(StreamError::Error("x")), keyword.parse("x") );
+        assert_eq!( Err(incomplete(1)), opt(streaming_str_parser("-")).parse("") );
+        assert_eq!( Ok(("1", None)), opt(streaming_str_parser("-")).parse("1") );
+        assert_eq!( Err(incomplete(1)), zero_or_more(streaming_str_parser("ab")).parse("aba") );
+        assert_eq!( Ok(("x", vec!["ab"])), zero_or_more(streaming_str_parser("ab")).parse("abx") );
+
+        //So a number split across chunks is only parsed once all of it has arrived
+        let number = recognize(seq(( opt(streaming_str_parser("-")), streaming_digits, streaming_str_parser("."), streaming_digits )));
+        assert_eq!( Err(incomplete(1)), number.parse("-12.5") );
+        assert_eq!( Ok(("]", "-12.50")), number.parse("-12.50]") );
+    }
+
+    #[test]
+    fn test_permutation()
+    {
+        let p = permutation(( str_parser("a"), str_parser("b"), digits ));
+
+        assert_eq!( Ok(("!", ("a", "b", "12"))), p.parse("ab12!") );
+        assert_eq!( Ok(("", ("a", "b", "12"))), p.parse("12ba") );
+        assert_eq!( Ok(("a", ("a", "b", "1"))), p.parse("a1ba") );            //Each parser only matches once
+        assert_eq!( Err("x"), p.parse("b1x") );                              //Fails where none of the parsers left matches
+        assert_eq!( Err(""), p.parse("") );
+    }
+}
@@ -0,0 +1,346 @@
+//! Comparing json values as documents
+
+/* (IMPORTS) */
+use std::cmp::Ordering;
+use std::hash::{Hash, Hasher};
+
+use crate::{Number, JSON};
+
+
+/// Tolerance used by `approx_eq` when comparing numbers
+///
+/// Two numbers a and b are considered equal if `|a - b| <= max(absolute, relative * max(|a|, |b|))`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Tolerance {
+    /// Largest allowed absolute difference, useful for numbers close to zero
+    pub absolute: f64,
+    /// Largest allowed difference relative to the larger magnitude of the two numbers
+    pub relative: f64,
+}
+
+impl Tolerance {
+
+    /// Tolerance that only allows an absolute difference
+    pub fn absolute(epsilon: f64) -> Tolerance
+    {
+        Tolerance { absolute: epsilon, relative: 0.0 }
+    }
+
+    /// Tolerance that only allows a relative difference
+    pub fn relative(epsilon: f64) -> Tolerance
+    {
+        Tolerance { absolute: 0.0, relative: epsilon }
+    }
+
+    // Returns true if the numbers are equal within the tolerance, integers that compare equal exactly are always equal
+    fn numbers_eq(&self, a: &Number, b: &Number) -> bool
+    {
+        if a == b { return true; }
+
+        let (x, y) = (a.as_f64(), b.as_f64());
+        let allowed = self.absolute.max(self.relative * x.abs().max(y.abs()));
+        (x - y).abs() <= allowed
+    }
+}
+
+// Defaults to a few ulps of relative error, which absorbs 0.1 + 0.2 style noise
+impl Default for Tolerance {
+    fn default() -> Tolerance
+    {
+        Tolerance { absolute: f64::EPSILON, relative: 4.0 * f64::EPSILON }
+    }
+}
+
+impl JSON {
+
+    /// Returns true if both values represent the same json document
+    ///
+    /// Objects are compared as sets of key/value pairs (member order never matters), arrays are compared element by element,
+    /// and numbers are compared by value regardless of how they were written (ie. 1, 1.0 and 1e0 are equal).
+    pub fn semantic_eq(&self, other: &JSON) -> bool
+    {
+        structural_eq(self, other, &|a, b| a == b)
+    }
+
+    /// Returns true if both values represent the same json document, allowing numbers to differ within the tolerance.
+    /// Everything other than numbers is compared as in `semantic_eq`.
+    pub fn approx_eq(&self, other: &JSON, tolerance: &Tolerance) -> bool
+    {
+        structural_eq(self, other, &|a, b| tolerance.numbers_eq(a, b))
+    }
+}
+
+// Compares values structurally, using the given function to compare numbers
+fn structural_eq(a: &JSON, b: &JSON, numbers_eq: &dyn Fn(&Number, &Number) -> bool) -> bool
+{
+    match (a, b) {
+        (JSON::JsNull, JSON::JsNull) => true,
+        (JSON::JsBool(x), JSON::JsBool(y)) => x == y,
+        (JSON::JsNumber(x), JSON::JsNumber(y)) => numbers_eq(x, y),
+        (JSON::JsString(x), JSON::JsString(y)) => x == y,
+        (JSON::JsArray(x), JSON::JsArray(y)) => {
+            x.len() == y.len() && x.iter().zip(y).all(|(x, y)| structural_eq(x, y, numbers_eq))
+        }
+        (JSON::JsObject(x), JSON::JsObject(y)) => {
+            x.len() == y.len() && x.iter().all(|(key, x)| y.get(key).is_some_and(|y| structural_eq(x, y, numbers_eq)))
+        }
+        _ => false
+    }
+}
+
+
+/* (ORDERING) */
+
+// Position of each type in the total order
+fn type_rank(value: &JSON) -> u8
+{
+    match value {
+        JSON::JsNull => 0,
+        JSON::JsBool(_) => 1,
+        JSON::JsNumber(_) => 2,
+        JSON::JsString(_) => 3,
+        JSON::JsArray(_) => 4,
+        JSON::JsObject(_) => 5,
+    }
+}
+
+// Orders numbers by value, mirroring the equality on Number so that numbers that are equal compare as Equal
+fn cmp_numbers(a: &Number, b: &Number) -> Ordering
+{
+    match (a.as_i64(), b.as_i64(), a.as_u64(), b.as_u64()) {
+        (Some(x), Some(y), _, _) => x.cmp(&y),
+        (_, _, Some(x), Some(y)) => x.cmp(&y),
+        _ => a.as_f64().partial_cmp(&b.as_f64()).unwrap_or(Ordering::Equal),   //Never NaN, -0 & 0 are equal
+    }
+}
+
+impl JSON {
+
+    /// Compares two values with a total order, so any mix of values can be sorted deterministically
+    ///
+    /// Values of different types are ordered by type: null < booleans < numbers < strings < arrays < objects.
+    /// Values of the same type are ordered by value: false < true, numbers numerically, strings by their bytes,
+    /// arrays element by element (a prefix sorts first), and objects as lists of (key, value) pairs sorted by key.
+    /// Two values compare as `Equal` exactly when they are `==`.
+    pub fn total_cmp(&self, other: &JSON) -> Ordering
+    {
+        match (self, other) {
+            (JSON::JsBool(x), JSON::JsBool(y)) => x.cmp(y),
+            (JSON::JsNumber(x), JSON::JsNumber(y)) => cmp_numbers(x, y),
+            (JSON::JsString(x), JSON::JsString(y)) => x.cmp(y),
+            (JSON::JsArray(x), JSON::JsArray(y)) => {
+                for (xv, yv) in x.iter().zip(y) {
+                    let ord = xv.total_cmp(yv);
+                    if ord != Ordering::Equal { return ord; }
+                }
+                x.len().cmp(&y.len())
+            }
+            (JSON::JsObject(x), JSON::JsObject(y)) => {
+                let mut x: Vec<_> = x.iter().collect();
+                let mut y: Vec<_> = y.iter().collect();
+                x.sort_by(|a, b| a.0.cmp(b.0));
+                y.sort_by(|a, b| a.0.cmp(b.0));
+
+                for ((xk, xv), (yk, yv)) in x.iter().zip(&y) {
+                    let ord = xk.cmp(yk).then_with(|| xv.total_cmp(yv));
+                    if ord != Ordering::Equal { return ord; }
+                }
+                x.len().cmp(&y.len())
+            }
+            _ => type_rank(self).cmp(&type_rank(other))
+        }
+    }
+
+    /// Sorts the elements of an array using `total_cmp`, does nothing if the value is not an array
+    pub fn sort(&mut self)
+    {
+        if let JSON::JsArray(vec) = self {
+            vec.sort_by(|a, b| a.total_cmp(b));
+        }
+    }
+
+    /// Sorts the elements of an array by the value found at the path within each element (see `get_path`),
+    /// does nothing if the value is not an array
+    ///
+    /// Elements that do not have a value at the path sort first. The sort is stable, so elements with equal keys keep their order.
+    pub fn sort_by_path(&mut self, path: &str)
+    {
+        if let JSON::JsArray(vec) = self {
+            vec.sort_by(|a, b| match (a.get_path(path), b.get_path(path)) {
+                (Some(x), Some(y)) => x.total_cmp(y),
+                (x, y) => x.is_some().cmp(&y.is_some())
+            });
+        }
+    }
+}
+
+
+/* (HASHING) */
+
+// 64-bit FNV-1a, used instead of the std hasher since its output is specified and so stable across builds
+fn fnv1a(bytes: &[u8]) -> u64
+{
+    bytes.iter().fold(0xcbf29ce484222325, |hash, b| (hash ^ *b as u64).wrapping_mul(0x100000001b3))
+}
+
+impl JSON {
+
+    /// Returns a 64-bit hash of the canonical form of the value (see `to_canonical_string`),
+    /// so documents that differ only in member order or number spelling hash the same.
+    /// The hash is stable across runs and builds, so it can be stored and used as a cache key.
+    pub fn content_hash(&self) -> u64
+    {
+        fnv1a(self.to_canonical_string().as_bytes())
+    }
+}
+
+/// A json value together with its canonical form, which implements `Eq` and `Hash` so documents can be used
+/// as `HashMap` keys or deduplicated in a `HashSet`. Two values are equal if their canonical forms are equal.
+#[derive(Debug, Clone)]
+pub struct NormalizedJson {
+    value: JSON,
+    canonical: String,
+}
+
+impl NormalizedJson {
+
+    /// Wraps a value, computing its canonical form once up front
+    pub fn new(value: JSON) -> NormalizedJson
+    {
+        let canonical = value.to_canonical_string();
+        NormalizedJson { value, canonical }
+    }
+
+    /// Returns the wrapped value
+    pub fn value(&self) -> &JSON
+    {
+        &self.value
+    }
+
+    /// Returns the canonical form of the wrapped value
+    pub fn canonical(&self) -> &str
+    {
+        &self.canonical
+    }
+
+    /// Unwraps the value
+    pub fn into_inner(self) -> JSON
+    {
+        self.value
+    }
+}
+
+impl PartialEq for NormalizedJson {
+    fn eq(&self, other: &NormalizedJson) -> bool
+    {
+        self.canonical == other.canonical
+    }
+}
+
+impl Eq for NormalizedJson {}
+
+impl Hash for NormalizedJson {
+    fn hash<H: Hasher>(&self, state: &mut H)
+    {
+        self.canonical.hash(state);
+    }
+}
+
+impl From<JSON> for NormalizedJson {
+    fn from(value: JSON) -> NormalizedJson
+    {
+        NormalizedJson::new(value)
+    }
+}
+
+
+/* (TESTS) */
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::json;
+
+    #[test]
+    fn test_semantic_eq()
+    {
+        let a = json(r#"{ "name" : "Michael", "tags" : [1, 2.0], "address" : { "city" : "London", "zip" : null } }"#);
+        let b = json(r#"{"address":{"zip":null,"city":"London"},"tags":[1.0,2e0],"name":"Michael"}"#);
+
+        assert!( a.semantic_eq(&b) );
+        assert!( !a.semantic_eq(&json(r#"{ "name" : "Michael" }"#)) );
+        assert!( !json("[1, 2]").semantic_eq(&json("[2, 1]")) );           //Array order matters
+        assert!( !json(r#"{ "a" : 1 }"#).semantic_eq(&json(r#"{ "b" : 1 }"#)) );
+        assert!( !json("0").semantic_eq(&json("false")) );
+        assert!( !json(r#""1""#).semantic_eq(&json("1")) );
+    }
+
+    #[test]
+    fn test_approx_eq()
+    {
+        let computed = JSON::JsArray(vec![ JSON::JsNumber(Number::from_f64(0.1 + 0.2).unwrap()) ]);
+        let expected = json("[0.3]");
+
+        assert!( !computed.semantic_eq(&expected) );
+        assert!( computed.approx_eq(&expected, &Tolerance::default()) );
+
+        assert!( json("100").approx_eq(&json("101"), &Tolerance::relative(0.01)) );
+        assert!( !json("100").approx_eq(&json("102"), &Tolerance::relative(0.01)) );
+        assert!( json("0.001").approx_eq(&json("0"), &Tolerance::absolute(0.01)) );
+        assert!( !json("0.001").approx_eq(&json("0"), &Tolerance::relative(0.5)) );   //Relative tolerance is no help near zero
+        assert!( !json(r#"{ "a" : 1 }"#).approx_eq(&json(r#"{ "a" : "1" }"#), &Tolerance::absolute(1.0)) );
+    }
+
+    #[test]
+    fn test_total_cmp()
+    {
+        use Ordering::*;
+
+        assert_eq!( Less, json("null").total_cmp(&json("false")) );
+        assert_eq!( Less, json("true").total_cmp(&json("0")) );
+        assert_eq!( Less, json("99").total_cmp(&json(r#""1""#)) );
+        assert_eq!( Less, json(r#""z""#).total_cmp(&json("[]")) );
+        assert_eq!( Less, json("[9]").total_cmp(&json("{}")) );
+
+        assert_eq!( Less, json("-2").total_cmp(&json("1.5")) );
+        assert_eq!( Equal, json("1").total_cmp(&json("1.0")) );
+        assert_eq!( Less, json("[1, 2]").total_cmp(&json("[1, 2, 0]")) );         //Prefix sorts first
+        assert_eq!( Greater, json("[1, 3]").total_cmp(&json("[1, 2, 0]")) );
+        assert_eq!( Less, json(r#"{ "a" : 2 }"#).total_cmp(&json(r#"{ "b" : 1 }"#)) );
+        assert_eq!( Equal, json(r#"{ "a" : 1, "b" : 2 }"#).total_cmp(&json(r#"{ "b" : 2, "a" : 1 }"#)) );
+    }
+
+    #[test]
+    fn test_sort()
+    {
+        let mut doc = json(r#"[{}, "b", 2, [], null, "a", true, 1.5, false]"#);
+        doc.sort();
+        assert_eq!( json(r#"[null, false, true, 1.5, 2, "a", "b", [], {}]"#), doc );
+
+        let mut people = json(r#"[{ "name" : "c", "age" : 30 }, { "name" : "a" }, { "name" : "b", "age" : 20 }, { "name" : "d", "age" : 20 }]"#);
+        people.sort_by_path("age");                                       //Missing first, stable for equal ages
+        assert_eq!( json(r#"[{ "name" : "a" }, { "name" : "b", "age" : 20 }, { "name" : "d", "age" : 20 }, { "name" : "c", "age" : 30 }]"#), people );
+    }
+
+    #[test]
+    fn test_content_hash()
+    {
+        let a = json(r#"{ "x" : 1, "y" : [true, "s"] }"#);
+        let b = json(r#"{"y":[true,"s"],"x":1.0}"#);
+
+        assert_eq!( a.content_hash(), b.content_hash() );
+        assert_ne!( a.content_hash(), json(r#"{ "x" : 2, "y" : [true, "s"] }"#).content_hash() );
+        assert_eq!( 0xaf63dc4c8601ec8c, fnv1a(b"a") );      //Published FNV-1a test vector
+    }
+
+    #[test]
+    fn test_normalized_json()
+    {
+        let mut set = std::collections::HashSet::new();
+        set.insert(NormalizedJson::new(json(r#"{ "a" : 1, "b" : 2 }"#)));
+        set.insert(NormalizedJson::new(json(r#"{ "b" : 2.0, "a" : 1 }"#)));    //Same document
+        set.insert(NormalizedJson::new(json(r#"{ "a" : 1 }"#)));
+
+        assert_eq!( 2, set.len() );
+        assert_eq!( r#"{"a":1}"#, NormalizedJson::from(json(r#"{ "a" : 1.0 }"#)).canonical() );
+    }
+}
@@ -0,0 +1,795 @@
+//! Concrete syntax tree: a parse that keeps the original text, for editing human maintained files
+//!
+//! A `Cst` holds the document text exactly as it was written (whitespace, comments, number and string spellings)
+//! along with a tree of the values in it and where each one is in the text.
+//! Edits such as `set` only rewrite the text of the values they change, so everything else is re-serialized byte for byte.
+//!
+//! With comments allowed, each comment is also attached to the value it documents, so they survive reformatting
+//! with `to_pretty_string`. A comment on its own line leads the value after it, and a comment on the same line
+//! as a value (after it) trails that value.
+//!
+//! Editors can apply text edits with `edit`, which only reparses the value that the edit falls in.
+
+/* (IMPORTS) */
+use std::collections::HashMap;
+use std::fmt;
+use std::ops::Range;
+
+use crate::lexer::{lex_with, Lexeme, LexemeKind, Lexer};
+use crate::path::path_at;
+use crate::ser::{write_escaped_str, write_indent};
+use crate::{JsonPath, Number, Options, ParseError, ParseErrorKind, PathError, PathSegment, JSON};
+
+
+/// A value in a concrete syntax tree, along with the byte range of its text in the document and its comments
+#[derive(Debug, Clone, PartialEq)]
+pub struct CstNode {
+    pub kind: CstKind,
+    pub span: Range<usize>,
+    /// Comments before the value (or before its key, for an object member), including the `//` or `/* */`
+    pub leading_comments: Vec<String>,
+    /// Comments after the value, also holds the comments inside an empty array or object
+    pub trailing_comments: Vec<String>,
+}
+
+impl CstNode {
+
+    // Creates a node without comments
+    fn new(kind: CstKind, span: Range<usize>) -> CstNode
+    {
+        CstNode { kind, span, leading_comments: vec![], trailing_comments: vec![] }
+    }
+}
+
+/// The kind of a value in a concrete syntax tree, scalars hold their parsed value
+#[derive(Debug, Clone, PartialEq)]
+pub enum CstKind {
+    Null,
+    Bool(bool),
+    Number(Number),
+    String(String),
+    Array(Vec<CstNode>),
+    Object(Vec<CstMember>),
+}
+
+/// A member of an object in a concrete syntax tree, in the order they are written
+#[derive(Debug, Clone, PartialEq)]
+pub struct CstMember {
+    pub key: String,
+    /// Byte range of the key's string literal, including its quotes
+    pub key_span: Range<usize>,
+    pub value: CstNode,
+}
+
+/// A parsed document that keeps its original text, see the module documentation
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cst {
+    text: String,
+    root: CstNode,
+    options: Options,
+}
+
+impl Cst {
+
+    /// Parses a string containing exactly one json value (surrounded by optional whitespace)
+    ///
+    /// # Errors
+    /// Returns a `ParseError` with the position of the problem if the input is not valid json
+    pub fn parse(input: &str) -> Result<Cst, ParseError>
+    {
+        Cst::parse_with(input, &Options::default())
+    }
+
+    /// Parses a document as in `parse`, with comments allowed if the options allow them
+    ///
+    /// # Errors
+    /// Returns a `ParseError` if the input is not valid json or is nested deeper than the options allow
+    pub fn parse_with(input: &str, options: &Options) -> Result<Cst, ParseError>
+    {
+        let mut root = build(input, options, options.max_depth).map_err(|e| {
+            let path = path_at(input, e.offset(), options.allow_comments);
+            e.with_path(path)
+        })?;
+        attach_comments(input, options, &mut root);
+        Ok(Cst { text: input.to_string(), root, options: options.clone() })
+    }
+
+    /// Returns the text of the document, including every edit made to it
+    pub fn as_str(&self) -> &str
+    {
+        &self.text
+    }
+
+    /// Returns the root value of the tree
+    pub fn root(&self) -> &CstNode
+    {
+        &self.root
+    }
+
+    /// Returns the text of a node as it is written in the document (ie. `1.50` or `"café"`)
+    pub fn text_of(&self, node: &CstNode) -> &str
+    {
+        &self.text[node.span.clone()]
+    }
+
+    /// Returns the node at a dotted path (ie. "config.servers[2].host"),
+    /// or None if the path is malformed or any segment of it does not exist
+    ///
+    /// If an object has the same key more than once, the last member with the key is used (as when parsing to `JSON`).
+    pub fn get(&self, path: &str) -> Option<&CstNode>
+    {
+        JsonPath::parse(path).ok()?.segments().iter().try_fold(&self.root, step)
+    }
+
+    /// Returns the document as a plain json value
+    pub fn to_json(&self) -> JSON
+    {
+        to_json(&self.root)
+    }
+
+    /// Reformats the document as pretty printed json indented by 2 spaces, keeping every comment next to the value it is attached to
+    ///
+    /// Members stay in the order they are written, and numbers, strings and keys keep their original spelling.
+    pub fn to_pretty_string(&self) -> String
+    {
+        let mut out = String::new();
+        for comment in &self.root.leading_comments {
+            out.push_str(comment);
+            out.push('\n');
+        }
+        write_pretty(&mut out, &self.text, &self.root, 0);
+        write_trailing(&mut out, &self.root.trailing_comments, 0);
+        out
+    }
+
+    /// Sets the value at a dotted path, rewriting only the text of the value that is replaced
+    ///
+    /// A missing object key is added as a new last member (laid out like the member before it), and an index
+    /// equal to the length of an array appends to it. Missing keys along the way are created as in `JSON::set_path`.
+    /// New values are written as compact json.
+    ///
+    /// # Errors
+    /// Returns a `PathError` if the path is malformed, steps into a value that is not the right kind of container,
+    /// or uses an index past the end of an array
+    pub fn set(&mut self, path: &str, value: &JSON) -> Result<(), PathError>
+    {
+        let path = JsonPath::parse(path)?;
+        let segments = path.segments();
+
+        //Follow the path as far as it exists
+        let mut node = &self.root;
+        let mut depth = 0;
+        while let Some(child) = segments.get(depth).and_then(|segment| step(node, segment)) {
+            node = child;
+            depth += 1;
+        }
+
+        if depth == segments.len() {
+            let range = node.span.clone();
+            self.splice(range, &value.display_json().to_string());
+            return Ok(());
+        }
+
+        //Wrap the value in whatever containers are missing below the first missing segment
+        let mut wrapped = value.clone();
+        for (i, segment) in segments.iter().enumerate().skip(depth + 1).rev() {
+            wrapped = match segment {
+                PathSegment::Key(key) => JSON::JsObject(HashMap::from([(key.clone(), wrapped)])),
+                PathSegment::Index(0) => JSON::JsArray(vec![wrapped]),
+                PathSegment::Index(_) => return Err(PathError::IndexOutOfBounds(path.prefix(i + 1))),
+            };
+        }
+        let wrapped = wrapped.display_json().to_string();
+
+        let (at, text) = match (&node.kind, &segments[depth]) {
+            (CstKind::Object(members), PathSegment::Key(key)) => {
+                let mut entry = String::new();
+                write_escaped_str(&mut entry, key);
+
+                match members.last() {
+                    Some(last) => {
+                        //Copy the spacing around the last member, unless there are comments in it
+                        let between = &self.text[last.key_span.end..last.value.span.start];
+                        let colon = if between.contains('/') { ": " } else { between };
+                        entry = format!(",{}{entry}{colon}{wrapped}", self.indent_before(last.key_span.start));
+                        (last.value.span.end, entry)
+                    }
+                    None => (node.span.start + 1, format!("{entry}: {wrapped}")),
+                }
+            }
+            (CstKind::Array(elements), PathSegment::Index(index)) if *index == elements.len() => {
+                match elements.last() {
+                    Some(last) => (last.span.end, format!(",{}{wrapped}", self.indent_before(last.span.start))),
+                    None => (node.span.start + 1, wrapped),
+                }
+            }
+            (CstKind::Array(_), _) => return Err(PathError::IndexOutOfBounds(path.prefix(depth + 1))),
+            _ => return Err(PathError::NotAContainer(path.prefix(depth))),
+        };
+
+        self.splice(at..at, &text);
+        Ok(())
+    }
+
+    /// Removes the value at a dotted path along with its separating comma, returns false if there is no value at the path
+    ///
+    /// The root can not be removed, so the empty path also returns false.
+    ///
+    /// # Errors
+    /// Returns a `PathError` if the path is malformed
+    pub fn remove(&mut self, path: &str) -> Result<bool, PathError>
+    {
+        let path = JsonPath::parse(path)?;
+        let Some((last, parent)) = path.segments().split_last() else { return Ok(false); };
+        let Some(parent) = parent.iter().try_fold(&self.root, step) else { return Ok(false); };
+
+        //Spans of each entry of the parent, from its first character (the key of a member) to the end of its value
+        let entries: Vec<Range<usize>> = match &parent.kind {
+            CstKind::Array(elements) => elements.iter().map(|e| e.span.clone()).collect(),
+            CstKind::Object(members) => members.iter().map(|m| m.key_span.start..m.value.span.end).collect(),
+            _ => return Ok(false),
+        };
+        let i = match (&parent.kind, last) {
+            (CstKind::Array(elements), PathSegment::Index(index)) if *index < elements.len() => *index,
+            (CstKind::Object(members), PathSegment::Key(key)) => match members.iter().rposition(|m| m.key == *key) {
+                Some(i) => i,
+                None => return Ok(false),
+            },
+            _ => return Ok(false),
+        };
+
+        //Take the comma after the entry, or the one before it if it is the last entry
+        let range = match (i + 1 < entries.len(), i) {
+            (true, _) => entries[i].start..entries[i + 1].start,
+            (false, 0) => entries[i].clone(),
+            (false, _) => entries[i - 1].end..entries[i].end,
+        };
+
+        self.splice(range, "");
+        Ok(true)
+    }
+
+    /// Replaces a byte range of the text, reparsing only the innermost value that contains the whole edit
+    ///
+    /// The rest of the tree is kept as it is, with the positions after the edit shifted. If the edited value
+    /// does not parse on its own (ie. the edit adds a comma after it), the value around it is reparsed instead, up to the whole document.
+    ///
+    /// # Errors
+    /// Returns a `ParseError` if the edited document is not valid, in which case the document is left unchanged
+    ///
+    /// # Panics
+    /// Panics if the range is out of bounds or does not lie on char boundaries, as `String::replace_range` does
+    pub fn edit(&mut self, range: Range<usize>, replacement: &str) -> Result<(), ParseError>
+    {
+        assert!( self.text.is_char_boundary(range.start) && self.text.is_char_boundary(range.end) && range.start <= range.end );
+
+        //Indexes of the children that lead down to the innermost value containing the edit
+        let mut indexes = vec![];
+        let mut node = &self.root;
+        while let Some((i, child)) = children(node).enumerate().find(|(_, child)| encloses(child, &range)) {
+            indexes.push(i);
+            node = child;
+        }
+
+        //Try the innermost value first, then each one around it
+        while !indexes.is_empty() {
+            let node = node_at(&mut self.root, &indexes);
+            let span = node.span.clone();
+            let piece = format!("{}{replacement}{}", &self.text[span.start..range.start], &self.text[range.end..span.end]);
+            let limit = self.options.max_depth.saturating_sub(indexes.len());
+
+            match build(&piece, &self.options, limit) {
+                Ok(mut rebuilt) if rebuilt.span == (0..piece.len()) => {
+                    attach_comments(&piece, &self.options, &mut rebuilt);
+                    shift(&mut rebuilt, 0, span.start as isize);
+                    rebuilt.leading_comments = std::mem::take(&mut node.leading_comments);
+                    rebuilt.trailing_comments = std::mem::take(&mut node.trailing_comments);
+                    *node = rebuilt;
+
+                    let delta = replacement.len() as isize - range.len() as isize;
+                    shift_after(&mut self.root, &indexes, span.end, delta);
+                    self.text.replace_range(range, replacement);
+                    return Ok(());
+                }
+                _ => { indexes.pop(); }
+            }
+        }
+
+        let mut text = self.text.clone();
+        text.replace_range(range, replacement);
+        *self = Cst::parse_with(&text, &self.options)?;
+        Ok(())
+    }
+
+    // Returns the whitespace just before a position, or a single space if there is none
+    fn indent_before(&self, pos: usize) -> &str
+    {
+        let before = &self.text[..pos];
+        match &before[before.trim_end().len()..] {
+            "" => " ",
+            indent => indent,
+        }
+    }
+
+    // Replaces a range of the text and rebuilds the tree, the edits made by this module always leave valid json
+    fn splice(&mut self, range: Range<usize>, replacement: &str)
+    {
+        self.text.replace_range(range, replacement);
+        //Edits may nest values deeper than the limit, which is only meant for untrusted input
+        self.root = build(&self.text, &self.options, usize::MAX).expect("edits keep the document valid");
+        attach_comments(&self.text, &self.options, &mut self.root);
+    }
+}
+
+impl fmt::Display for Cst {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+    {
+        f.write_str(&self.text)
+    }
+}
+
+// Steps from a node into one of its children, for the last member with a key
+fn step<'a>(node: &'a CstNode, segment: &PathSegment) -> Option<&'a CstNode>
+{
+    match (&node.kind, segment) {
+        (CstKind::Object(members), PathSegment::Key(key)) => members.iter().rev().find(|m| m.key == *key).map(|m| &m.value),
+        (CstKind::Array(elements), PathSegment::Index(index)) => elements.get(*index),
+        _ => None
+    }
+}
+
+// Returns the values held by a container, in order (the values of the members of an object)
+fn children<'a>(node: &'a CstNode) -> Box<dyn Iterator<Item = &'a CstNode> + 'a>
+{
+    match &node.kind {
+        CstKind::Array(elements) => Box::new(elements.iter()),
+        CstKind::Object(members) => Box::new(members.iter().map(|m| &m.value)),
+        _ => Box::new(std::iter::empty())
+    }
+}
+
+// Returns the node reached by following child indexes from a node
+fn node_at<'a>(node: &'a mut CstNode, indexes: &[usize]) -> &'a mut CstNode
+{
+    indexes.iter().fold(node, |node, i| match &mut node.kind {
+        CstKind::Array(elements) => &mut elements[*i],
+        CstKind::Object(members) => &mut members[*i].value,
+        _ => unreachable!("indexes only step into containers"),
+    })
+}
+
+// Returns true if an edit falls within a node, so that reparsing the node's text covers it
+// An edit to a container must leave its brackets alone, an edit to a scalar may touch either end of it
+fn encloses(node: &CstNode, range: &Range<usize>) -> bool
+{
+    match &node.kind {
+        CstKind::Array(_) | CstKind::Object(_) => node.span.start < range.start && range.end < node.span.end,
+        _ => node.span.start <= range.start && range.end <= node.span.end,
+    }
+}
+
+// Moves every position in a tree that is at or after a position
+fn shift(node: &mut CstNode, from: usize, delta: isize)
+{
+    let moved = |pos: usize| if pos >= from { pos.wrapping_add_signed(delta) } else { pos };
+
+    node.span = moved(node.span.start)..moved(node.span.end);
+    match &mut node.kind {
+        CstKind::Array(elements) => elements.iter_mut().for_each(|e| shift(e, from, delta)),
+        CstKind::Object(members) => members.iter_mut().for_each(|m| {
+            m.key_span = moved(m.key_span.start)..moved(m.key_span.end);
+            shift(&mut m.value, from, delta)
+        }),
+        _ => {}
+    }
+}
+
+// Moves the positions after a replaced node, which are the ends of the containers around it and the entries that follow it
+// Entries before the node are left alone, so nothing needs to be visited before the edit
+fn shift_after(node: &mut CstNode, indexes: &[usize], from: usize, delta: isize)
+{
+    let Some((i, rest)) = indexes.split_first() else { return; };
+
+    node.span.end = node.span.end.wrapping_add_signed(delta);
+    match &mut node.kind {
+        CstKind::Array(elements) => {
+            elements[i + 1..].iter_mut().for_each(|e| shift(e, from, delta));
+            shift_after(&mut elements[*i], rest, from, delta);
+        }
+        CstKind::Object(members) => {
+            members[i + 1..].iter_mut().for_each(|m| {
+                m.key_span = m.key_span.start.wrapping_add_signed(delta)..m.key_span.end.wrapping_add_signed(delta);
+                shift(&mut m.value, from, delta)
+            });
+            shift_after(&mut members[*i].value, rest, from, delta);
+        }
+        _ => {}
+    }
+}
+
+// Converts a node to a plain json value
+fn to_json(node: &CstNode) -> JSON
+{
+    match &node.kind {
+        CstKind::Null => JSON::JsNull,
+        CstKind::Bool(b) => JSON::JsBool(*b),
+        CstKind::Number(n) => JSON::JsNumber(n.clone()),
+        CstKind::String(s) => JSON::JsString(s.clone()),
+        CstKind::Array(elements) => JSON::JsArray(elements.iter().map(to_json).collect()),
+        CstKind::Object(members) => JSON::JsObject(members.iter().map(|m| (m.key.clone(), to_json(&m.value))).collect()),
+    }
+}
+
+
+/* (COMMENTS) */
+
+// Attaches every comment in the text to a node of the tree, see the module documentation
+fn attach_comments(text: &str, options: &Options, root: &mut CstNode)
+{
+    if !options.allow_comments { return; }
+
+    for comment in lex_with(text, options).flatten().filter(|l| l.kind == LexemeKind::Comment) {
+        let (at, comment) = (comment.start, comment.text.to_string());
+
+        if at < root.span.start {
+            root.leading_comments.push(comment);
+        } else if at >= root.span.end {
+            root.trailing_comments.push(comment);
+        } else {
+            attach(text, root, comment, at);
+        }
+    }
+}
+
+// Attaches a comment within a container to one of its entries, descending into the entry that contains it
+fn attach(text: &str, node: &mut CstNode, comment: String, at: usize)
+{
+    //Each entry starts at its key (for an object member) and ends with its value
+    let mut entries: Vec<(usize, &mut CstNode)> = match &mut node.kind {
+        CstKind::Array(elements) => elements.iter_mut().map(|e| (e.span.start, e)).collect(),
+        CstKind::Object(members) => members.iter_mut().map(|m| (m.key_span.start, &mut m.value)).collect(),
+        _ => vec![],
+    };
+    let count = entries.len();
+    let i = entries.partition_point(|(start, _)| *start < at);
+
+    if i > 0 {
+        let previous = &mut entries[i - 1].1;
+        if at < previous.span.start {
+            return previous.leading_comments.push(comment);        //Between a key and its value
+        }
+        if at < previous.span.end {
+            return attach(text, previous, comment, at);
+        }
+        if i == count || !text[previous.span.end..at].contains('\n') {
+            return previous.trailing_comments.push(comment);
+        }
+    }
+
+    match entries.into_iter().nth(i) {
+        Some((_, next)) => next.leading_comments.push(comment),
+        None => node.trailing_comments.push(comment),
+    }
+}
+
+// Appends a value as pretty printed json with the comments of its elements, see Cst::to_pretty_string
+fn write_pretty(out: &mut String, text: &str, node: &CstNode, level: usize)
+{
+    let (open, close, entries): (char, char, Vec<(Option<&str>, &CstNode)>) = match &node.kind {
+        CstKind::Array(elements) => ('[', ']', elements.iter().map(|e| (None, e)).collect()),
+        CstKind::Object(members) => ('{', '}', members.iter().map(|m| (Some(&text[m.key_span.clone()]), &m.value)).collect()),
+        _ => return out.push_str(&text[node.span.clone()]),
+    };
+
+    out.push(open);
+    for (i, (key, value)) in entries.iter().enumerate() {
+        write_indent(out, level + 1);
+        for comment in &value.leading_comments {
+            out.push_str(comment);
+            write_indent(out, level + 1);
+        }
+        if let Some(key) = key {
+            out.push_str(key);
+            out.push_str(": ");
+        }
+        write_pretty(out, text, value, level + 1);
+        if i + 1 < entries.len() { out.push(','); }
+        write_trailing(out, &value.trailing_comments, level + 1);
+    }
+    if !entries.is_empty() { write_indent(out, level); }
+    out.push(close);
+}
+
+// Appends the comments after a value on the same line, a line comment followed by another comment ends the line
+fn write_trailing(out: &mut String, comments: &[String], level: usize)
+{
+    for (i, comment) in comments.iter().enumerate() {
+        if i > 0 && comments[i - 1].starts_with("//") {
+            write_indent(out, level);
+        } else {
+            out.push(' ');
+        }
+        out.push_str(comment);
+    }
+}
+
+
+/* (TREE BUILDING) */
+
+// An array or object whose closing bracket has not been reached yet
+enum Frame {
+    Array(usize, Vec<CstNode>),
+    Object(usize, Vec<CstMember>, Option<(String, Range<usize>)>),     //Key of the member whose value is being read
+}
+
+// Lexemes of a document with whitespace and comments skipped, and one lexeme of lookahead
+struct Reader<'a> {
+    text: &'a str,
+    lexer: Lexer<'a>,
+    peeked: Option<Lexeme<'a>>,
+    raw_strings: bool,
+}
+
+impl<'a> Reader<'a> {
+
+    // Returns the next lexeme, or None at the end of the input
+    fn lexeme(&mut self) -> Result<Option<Lexeme<'a>>, ParseError>
+    {
+        if let Some(lexeme) = self.peeked.take() { return Ok(Some(lexeme)); }
+
+        for lexeme in self.lexer.by_ref() {
+            match lexeme? {
+                Lexeme { kind: LexemeKind::Whitespace | LexemeKind::Comment, .. } => continue,
+                lexeme => return Ok(Some(lexeme)),
+            }
+        }
+        Ok(None)
+    }
+
+    // Returns the next lexeme, running out of input is an error
+    fn next(&mut self) -> Result<Lexeme<'a>, ParseError>
+    {
+        self.lexeme()?.ok_or_else(|| ParseError::new(ParseErrorKind::Syntax, self.text, self.text.len()))
+    }
+
+    // Consumes the next lexeme if it is of the kind, returning it
+    fn next_if(&mut self, kind: LexemeKind) -> Result<Option<Lexeme<'a>>, ParseError>
+    {
+        let lexeme = self.next()?;
+        if lexeme.kind == kind { return Ok(Some(lexeme)); }
+        self.peeked = Some(lexeme);
+        Ok(None)
+    }
+
+    // Reads an object key and the colon after it
+    fn key(&mut self) -> Result<(String, Range<usize>), ParseError>
+    {
+        let key = self.next()?;
+        let name = self.string(&key)?;
+        let colon = self.next()?;
+        if colon.kind != LexemeKind::Colon { return Err(self.syntax(&colon)); }
+        Ok((name, key.span()))
+    }
+
+    // Returns the value of a string lexeme, or its text between the quotes if raw strings are kept
+    fn string(&self, lexeme: &Lexeme) -> Result<String, ParseError>
+    {
+        let value = match self.raw_strings && lexeme.kind == LexemeKind::String {
+            true => Some(lexeme.text[1..lexeme.text.len() - 1].to_string()),
+            false => lexeme.string_value(),
+        };
+        value.ok_or_else(|| self.syntax(lexeme))
+    }
+
+    fn syntax(&self, lexeme: &Lexeme) -> ParseError
+    {
+        ParseError::new(ParseErrorKind::Syntax, self.text, lexeme.start)
+    }
+}
+
+// Builds the tree for a whole document, nesting deeper than the limit is an error
+// Open containers are kept on a heap allocated stack, so deep input can not overflow the call stack
+fn build(text: &str, options: &Options, limit: usize) -> Result<CstNode, ParseError>
+{
+    let mut reader = Reader { text, lexer: lex_with(text, options), peeked: None, raw_strings: options.raw_strings };
+    let mut stack: Vec<Frame> = vec![];
+
+    loop {
+        //Read a value, or open a container and go round again for its first entry
+        let lexeme = reader.next()?;
+        if let Some(kind) = options.interrupted() { return Err(ParseError::new(kind, text, lexeme.start)); }
+        let scalar = |kind| CstNode::new(kind, lexeme.span());
+
+        let mut node = match lexeme.kind {
+            LexemeKind::Null => scalar(CstKind::Null),
+            LexemeKind::True => scalar(CstKind::Bool(true)),
+            LexemeKind::False => scalar(CstKind::Bool(false)),
+            LexemeKind::Number => scalar(CstKind::Number( Number::from_literal(lexeme.text).ok_or_else(|| reader.syntax(&lexeme))? )),
+            LexemeKind::String => scalar(CstKind::String( reader.string(&lexeme)? )),
+            LexemeKind::BeginArray | LexemeKind::BeginObject if stack.len() >= limit => {
+                return Err(ParseError::new(ParseErrorKind::TooDeep, text, lexeme.start));
+            }
+            LexemeKind::BeginArray => match reader.next_if(LexemeKind::EndArray)? {
+                Some(close) => CstNode::new(CstKind::Array(vec![]), lexeme.start..close.span().end),
+                None => {
+                    stack.push(Frame::Array(lexeme.start, vec![]));
+                    continue;
+                }
+            },
+            LexemeKind::BeginObject => match reader.next_if(LexemeKind::EndObject)? {
+                Some(close) => CstNode::new(CstKind::Object(vec![]), lexeme.start..close.span().end),
+                None => {
+                    let key = reader.key()?;
+                    stack.push(Frame::Object(lexeme.start, vec![], Some(key)));
+                    continue;
+                }
+            },
+            _ => return Err(reader.syntax(&lexeme)),
+        };
+
+        //Add the value to its container, closing every container that ends after it
+        loop {
+            let Some(frame) = stack.last_mut() else {
+                return match reader.lexeme() {
+                    Ok(None) => Ok(node),
+                    Ok(Some(trailing)) => Err(ParseError::new(ParseErrorKind::TrailingCharacters, text, trailing.start)),
+                    Err(e) => Err(ParseError::new(ParseErrorKind::TrailingCharacters, text, e.offset())),
+                };
+            };
+
+            match frame {
+                Frame::Array(_, elements) => elements.push(node),
+                Frame::Object(_, members, pending) => {
+                    let (key, key_span) = pending.take().expect("object values follow a key");
+                    members.push(CstMember { key, key_span, value: node });
+                }
+            }
+
+            let separator = reader.next()?;
+            let end = separator.span().end;
+            match (frame, separator.kind) {
+                (Frame::Array(..), LexemeKind::Comma) => break,
+                (Frame::Object(_, _, pending), LexemeKind::Comma) => {
+                    *pending = Some(reader.key()?);
+                    break;
+                }
+                (Frame::Array(..), LexemeKind::EndArray) | (Frame::Object(..), LexemeKind::EndObject) => {
+                    node = match stack.pop() {
+                        Some(Frame::Array(start, elements)) => CstNode::new(CstKind::Array(elements), start..end),
+                        Some(Frame::Object(start, members, _)) => CstNode::new(CstKind::Object(members), start..end),
+                        None => unreachable!(),
+                    };
+                }
+                _ => return Err(reader.syntax(&separator)),
+            }
+        }
+    }
+}
+
+
+/* (TESTS) */
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::json;
+
+    #[test]
+    fn test_parse()
+    {
+        let input = " { \"a\" : [1.50, \"x\\u0079\"], \"b\" : {} } ";
+        let cst = Cst::parse(input).unwrap();
+
+        assert_eq!( input, cst.to_string() );
+        assert_eq!( json(input), cst.to_json() );
+        assert_eq!( "1.50", cst.text_of(cst.get("a[0]").unwrap()) );
+        assert_eq!( "\"x\\u0079\"", cst.text_of(cst.get("a[1]").unwrap()) );
+        assert_eq!( CstKind::String("xy".to_string()), cst.get("a[1]").unwrap().kind );
+        assert_eq!( 1..input.len() - 1, cst.root().span );
+        assert_eq!( None, cst.get("c") );
+
+        assert_eq!( 0, Cst::parse("").unwrap_err().offset() );
+        assert_eq!( 4, Cst::parse("[1, ]").unwrap_err().offset() );
+        assert_eq!( 5, Cst::parse(r#"{"a" 1}"#).unwrap_err().offset() );
+        assert_eq!( ParseErrorKind::TrailingCharacters, Cst::parse("[1] x").unwrap_err().kind() );
+        assert_eq!( ParseErrorKind::TooDeep, Cst::parse_with("[[1]]", &Options { max_depth: 1, ..Options::default() }).unwrap_err().kind() );
+        assert!( Cst::parse("// x\n1").is_err() );
+    }
+
+    #[test]
+    fn test_set()
+    {
+        let input = "{\n  // Server settings\n  \"host\": \"localhost\",\n  \"port\": 8080,\n  \"tags\": [\"a\"]\n}\n";
+        let mut cst = Cst::parse_with(input, &Options { allow_comments: true, ..Options::default() }).unwrap();
+
+        cst.set("port", &json("9090")).unwrap();
+        cst.set("tls", &json("true")).unwrap();
+        cst.set("tags[1]", &json(r#""b""#)).unwrap();
+        cst.set("limits.max", &json("[1, 2]")).unwrap();
+
+        let expected = "{\n  // Server settings\n  \"host\": \"localhost\",\n  \"port\": 9090,\n  \"tags\": [\"a\", \"b\"],\n  \"tls\": true,\n  \"limits\": {\"max\":[1,2]}\n}\n";
+        assert_eq!( expected, cst.as_str() );
+
+        cst.set("", &json("[]")).unwrap();
+        cst.set("[0]", &json("1")).unwrap();
+        cst.set("[1]", &json("{}")).unwrap();
+        assert_eq!( "[1, {}]\n", cst.as_str() );
+
+        assert_eq!( Err(PathError::IndexOutOfBounds(JsonPath::parse("[3]").unwrap())), cst.set("[3]", &json("1")) );
+        assert_eq!( Err(PathError::NotAContainer(JsonPath::parse("[0]").unwrap())), cst.set("[0].a", &json("1")) );
+        assert_eq!( Err(PathError::IndexOutOfBounds(JsonPath::parse("[1].a[3]").unwrap())), cst.set("[1].a[3]", &json("1")) );
+    }
+
+    #[test]
+    fn test_comments()
+    {
+        let input = "// Settings\n{ \"a\" : 1, // one\n  /* the b */ \"b\" : [ 2 /* two */ ],\n  \"c\" : { // nothing\n  }\n  // end of c\n} // done";
+        let options = Options { allow_comments: true, ..Options::default() };
+        let cst = Cst::parse_with(input, &options).unwrap();
+
+        assert_eq!( vec!["// Settings"], cst.root().leading_comments );
+        assert_eq!( vec!["// done"], cst.root().trailing_comments );
+        assert_eq!( vec!["// one"], cst.get("a").unwrap().trailing_comments );
+        assert_eq!( vec!["/* the b */"], cst.get("b").unwrap().leading_comments );
+        assert_eq!( vec!["/* two */"], cst.get("b[0]").unwrap().trailing_comments );
+        assert_eq!( vec!["// nothing", "// end of c"], cst.get("c").unwrap().trailing_comments );
+
+        let pretty = cst.to_pretty_string();
+        let expected = "// Settings\n{\n  \"a\": 1, // one\n  /* the b */\n  \"b\": [\n    2 /* two */\n  ],\n  \"c\": {} // nothing\n  // end of c\n} // done";
+        assert_eq!( expected, pretty );
+
+        //Reformatting again gives the same text
+        assert_eq!( pretty, Cst::parse_with(&pretty, &options).unwrap().to_pretty_string() );
+        assert_eq!( cst.to_json(), crate::try_parse_with(input, &options).unwrap() );
+    }
+
+    #[test]
+    fn test_edit()
+    {
+        let options = Options { allow_comments: true, ..Options::default() };
+        let mut cst = Cst::parse_with("{ \"a\" : [1, 22, 3], // list\n  \"b\" : { \"c\" : \"x\" } }", &options).unwrap();
+
+        //Each edit gives the same tree as parsing the edited text from scratch
+        let edits = [
+            (12..14, "4444"),           //Scalar within an array
+            (13..13, "5"),              //Grows a scalar
+            (12..12, "0, "),            //Only parses as part of the array
+            (53..53, ", \"d\" : [] "),  //Only parses as part of the nested object
+            (3..4, "aa"),               //Key, parsed with its object
+            (0..0, "/* top */ "),       //Outside the root
+        ];
+        for (range, replacement) in edits {
+            let mut expected = cst.as_str().to_string();
+            expected.replace_range(range.clone(), replacement);
+
+            cst.edit(range, replacement).unwrap();
+            assert_eq!( expected, cst.as_str() );
+            assert_eq!( Cst::parse_with(&expected, &options).unwrap(), cst );
+        }
+
+        //A broken edit leaves the document as it was
+        let before = cst.clone();
+        let at = cst.as_str().find("45444").unwrap();
+        let error = cst.edit(at..at + 1, "]").unwrap_err();
+        assert_eq!( ParseErrorKind::Syntax, error.kind() );
+        assert_eq!( before, cst );
+    }
+
+    #[test]
+    fn test_remove()
+    {
+        let mut cst = Cst::parse("{\n  \"a\": 1,\n  \"b\": [1, 2, 3],\n  \"c\": 3\n}").unwrap();
+
+        assert_eq!( Ok(true), cst.remove("a") );
+        assert_eq!( Ok(true), cst.remove("b[2]") );
+        assert_eq!( Ok(true), cst.remove("b[0]") );
+        assert_eq!( Ok(true), cst.remove("c") );
+        assert_eq!( "{\n  \"b\": [2]\n}", cst.as_str() );
+
+        assert_eq!( Ok(true), cst.remove("b[0]") );
+        assert_eq!( Ok(false), cst.remove("b[0]") );
+        assert_eq!( Ok(false), cst.remove("x.y") );
+        assert_eq!( Ok(false), cst.remove("") );
+        assert!( cst.remove("[").is_err() );
+        assert_eq!( json(r#"{ "b" : [] }"#), cst.to_json() );
+    }
+}
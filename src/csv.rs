@@ -0,0 +1,293 @@
+//! Converting arrays of records to and from CSV (ie. for spreadsheets), as in RFC 4180
+//!
+//! Each row of objects is flattened, so the columns are the dotted paths of its leaf values (ie. "address.city").
+
+/* (IMPORTS) */
+use std::collections::{BTreeSet, HashMap};
+use std::fmt;
+
+use crate::query::infer_scalar;
+use crate::JSON;
+
+
+/// Error returned when CSV text can not be read, along with the number of the line the record starts on
+#[derive(Debug, Clone, PartialEq)]
+pub enum CsvError {
+    /// A quoted field is not closed before the end of the text
+    UnclosedQuote(usize),
+    /// A closing quote is followed by something other than a comma or the end of the line (ie. `"a"b`)
+    InvalidQuote(usize),
+    /// A record does not have as many fields as the header
+    FieldCount { line: usize, expected: usize, found: usize },
+    /// The header has the same name more than once
+    DuplicateKey(usize, String),
+}
+
+impl CsvError {
+
+    /// Returns the number of the line of the record that failed
+    pub fn line(&self) -> usize
+    {
+        match self {
+            CsvError::UnclosedQuote(line) | CsvError::InvalidQuote(line) | CsvError::DuplicateKey(line, _) => *line,
+            CsvError::FieldCount { line, .. } => *line,
+        }
+    }
+}
+
+impl fmt::Display for CsvError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+    {
+        match self {
+            CsvError::UnclosedQuote(line) => write!(f, "line {line}: quoted field is not closed"),
+            CsvError::InvalidQuote(line) => write!(f, "line {line}: closing quote is not followed by a comma or the end of the line"),
+            CsvError::FieldCount { line, expected, found } => write!(f, "line {line}: record has {found} fields, the header has {expected}"),
+            CsvError::DuplicateKey(line, key) => write!(f, "line {line}: header has \"{key}\" more than once"),
+        }
+    }
+}
+
+impl std::error::Error for CsvError {}
+
+impl JSON {
+
+    /// Returns the value as CSV text if it is an array of objects or an array of arrays, and None for any other value.
+    ///
+    /// An array of objects has a header with the flattened keys of every object (in order of key), and a row per object
+    /// with an empty field for each key the object does not have. An array of arrays has a row per array and no header.
+    /// Null is written as an empty field, and strings that would be read back as something else (ie. "", "true" or "42") are quoted.
+    ///
+    /// # Examples
+    /// ```
+    /// use Parser::try_parse;
+    ///
+    /// let value = try_parse(r#"[{ "id" : 1, "user" : { "name" : "Ada" } }, { "id" : 2, "note" : "a, b" }]"#).unwrap();
+    /// assert_eq!( Some("id,note,user.name\n1,,Ada\n2,\"a, b\",\n".to_string()), value.to_csv() );
+    /// ```
+    pub fn to_csv(&self) -> Option<String>
+    {
+        let JSON::JsArray(rows) = self else { return None; };
+        let mut out = String::new();
+
+        if rows.iter().all(|row| matches!(row, JSON::JsArray(_))) {
+            for row in rows {
+                let JSON::JsArray(cells) = row else { continue; };
+                write_record(&mut out, cells.iter().map(Some));
+            }
+            return Some(out);
+        }
+
+        if !rows.iter().all(|row| matches!(row, JSON::JsObject(_))) { return None; }
+
+        //Empty objects would flatten to a member with the empty path, so they are rows with no fields
+        let flat: Vec<HashMap<String, JSON>> = rows.iter().map(|row| match row {
+            JSON::JsObject(map) if !map.is_empty() => match row.flatten() {
+                JSON::JsObject(flat) => flat,
+                _ => HashMap::new(),
+            },
+            _ => HashMap::new(),
+        }).collect();
+
+        let header: BTreeSet<&String> = flat.iter().flat_map(|row| row.keys()).collect();
+        let names: Vec<JSON> = header.iter().map(|key| JSON::JsString(key.to_string())).collect();
+        write_record(&mut out, names.iter().map(Some));
+
+        for row in &flat {
+            write_record(&mut out, header.iter().map(|key| row.get(*key)));
+        }
+        Some(out)
+    }
+
+    /// Builds an array with an object for every record of CSV text, keyed by the names in the header (the first record).
+    ///
+    /// Fields are separated by commas and may be quoted, a quote inside a quoted field is written twice, and blank lines are skipped.
+    /// As with `from_properties`, unquoted fields that are the literals true, false & null or numbers are kept as such,
+    /// an empty unquoted field is null, and any other field (quoted or not) is a string.
+    /// Keys are kept as they are, `unflatten` rebuilds the values of dotted keys written by `to_csv`.
+    ///
+    /// # Examples
+    /// ```
+    /// use Parser::{try_parse, JSON};
+    ///
+    /// let value = JSON::from_csv("id,name,zip\n1,Ada,\"02139\"\n2,,\n").unwrap();
+    /// let expected = r#"[{ "id" : 1, "name" : "Ada", "zip" : "02139" }, { "id" : 2, "name" : null, "zip" : null }]"#;
+    /// assert_eq!( try_parse(expected).unwrap(), value );
+    /// ```
+    ///
+    /// # Errors
+    /// Returns a `CsvError` if a quoted field is not closed or has text after its closing quote,
+    /// if a record does not have as many fields as the header, or if the header has a name more than once
+    pub fn from_csv(text: &str) -> Result<JSON, CsvError>
+    {
+        let mut records = records(text)?.into_iter();
+        let Some((line, header)) = records.next() else { return Ok(JSON::JsArray(vec![])); };
+
+        let mut keys: Vec<String> = vec![];
+        for field in header {
+            let key = field.text;
+            if keys.contains(&key) {
+                return Err(CsvError::DuplicateKey(line, key));
+            }
+            keys.push(key);
+        }
+
+        let mut rows = vec![];
+        for (line, fields) in records {
+            if fields.len() != keys.len() {
+                return Err(CsvError::FieldCount { line, expected: keys.len(), found: fields.len() });
+            }
+
+            let row = keys.iter().cloned().zip(fields.into_iter().map(Field::into_json)).collect();
+            rows.push(JSON::JsObject(row));
+        }
+
+        Ok(JSON::JsArray(rows))
+    }
+}
+
+// Appends a record of values, an absent value is written as an empty field just as null
+fn write_record<'a>(out: &mut String, cells: impl Iterator<Item = Option<&'a JSON>>)
+{
+    for (i, cell) in cells.enumerate() {
+        if i > 0 { out.push(','); }
+
+        match cell {
+            None | Some(JSON::JsNull) => {}
+            Some(JSON::JsString(s)) => write_field(out, s, infer_scalar(s) != JSON::JsString(s.to_string()) || s.is_empty()),
+            Some(value @ (JSON::JsArray(_) | JSON::JsObject(_))) => write_field(out, &value.display_json().to_string(), true),
+            Some(value) => out.push_str(&value.display_json().to_string()),
+        }
+    }
+    out.push('\n');
+}
+
+// Appends a field, quoted if asked to or if it has a comma, quote, line break or surrounding space
+fn write_field(out: &mut String, text: &str, quote: bool)
+{
+    let needs_quotes = quote || text.contains([',', '"', '\n', '\r']) || text.trim() != text;
+
+    match needs_quotes {
+        true => {
+            out.push('"');
+            out.push_str(&text.replace('"', "\"\""));
+            out.push('"');
+        }
+        false => out.push_str(text),
+    }
+}
+
+// A field of a record, and whether it was quoted
+struct Field {
+    text: String,
+    quoted: bool,
+}
+
+impl Field {
+
+    fn into_json(self) -> JSON
+    {
+        match self.quoted {
+            true => JSON::JsString(self.text),
+            false if self.text.is_empty() => JSON::JsNull,
+            false => infer_scalar(&self.text),
+        }
+    }
+}
+
+// Splits text into records of fields, along with the number of the line each record starts on. Blank lines are not records
+fn records(text: &str) -> Result<Vec<(usize, Vec<Field>)>, CsvError>
+{
+    let mut records = vec![];
+    let mut chars = text.chars().peekable();
+    let mut line = 1;
+
+    while chars.peek().is_some() {
+        let start = line;
+        let mut fields = vec![];
+        let mut field = Field { text: String::new(), quoted: false };
+
+        loop {
+            match chars.next() {
+                Some('"') if field.text.is_empty() && !field.quoted => {
+                    field.quoted = true;
+                    loop {
+                        match chars.next() {
+                            Some('"') if chars.peek() == Some(&'"') => { chars.next(); field.text.push('"'); }
+                            Some('"') => break,
+                            Some(ch) => {
+                                if ch == '\n' { line += 1; }
+                                field.text.push(ch);
+                            }
+                            None => return Err(CsvError::UnclosedQuote(start)),
+                        }
+                    }
+                    if !matches!(chars.peek(), None | Some(',' | '\n' | '\r')) {
+                        return Err(CsvError::InvalidQuote(start));
+                    }
+                }
+                Some(',') => fields.push(std::mem::replace(&mut field, Field { text: String::new(), quoted: false })),
+                Some('\r') if chars.peek() == Some(&'\n') => {}
+                Some('\n') | None => break,
+                Some(ch) => field.text.push(ch),
+            }
+        }
+        line += 1;
+
+        if fields.is_empty() && field.text.is_empty() && !field.quoted { continue; }
+        fields.push(field);
+        records.push((start, fields));
+    }
+
+    Ok(records)
+}
+
+
+/* (TESTS) */
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::json;
+
+    #[test]
+    fn test_to_csv()
+    {
+        let value = json(r#"[{ "a" : "x\"y", "b" : [1, true] }, {}, { "a" : "", "c" : "007", "d" : null, "e" : [] }]"#);
+        let expected = "a,b[0],b[1],c,d,e\n\"x\"\"y\",1,true,,,\n,,,,,\n\"\",,,007,,\"[]\"\n";
+        assert_eq!( Some(expected.to_string()), value.to_csv() );
+
+        let value = json(r#"[[1, "two words", " padded"], [], [{ "k" : 2 }]]"#);
+        assert_eq!( Some("1,two words,\" padded\"\n\n\"{\"\"k\"\":2}\"\n".to_string()), value.to_csv() );
+
+        assert_eq!( Some("".to_string()), json("[]").to_csv() );
+        assert_eq!( None, json(r#"[{ "a" : 1 }, [1]]"#).to_csv() );
+        assert_eq!( None, json(r#"{ "a" : 1 }"#).to_csv() );
+    }
+
+    #[test]
+    fn test_from_csv()
+    {
+        let text = "name,note,n\r\nAda,\"line one\nline two\",1.5\r\n\r\n\"Bob, Jr\",\"say \"\"hi\"\"\",false";
+        let expected = r#"[{ "name" : "Ada", "note" : "line one\nline two", "n" : 1.5 }, { "name" : "Bob, Jr", "note" : "say \"hi\"", "n" : false }]"#;
+        assert_eq!( Ok(json(expected)), JSON::from_csv(text) );
+
+        assert_eq!( Ok(json("[]")), JSON::from_csv("") );
+        assert_eq!( Ok(json("[]")), JSON::from_csv("a,b\n") );
+        assert_eq!( Ok(json(r#"[{ "a" : null, "b" : "" }]"#)), JSON::from_csv("a,b\n,\"\"\n") );
+
+        let value = json(r#"[{ "id" : 1, "tags" : ["x"], "zip" : "02139" }]"#);
+        let Ok(JSON::JsArray(rows)) = JSON::from_csv(&value.to_csv().unwrap()) else { panic!("not an array") };
+        assert_eq!( Ok(value), rows[0].unflatten().map(|row| JSON::JsArray(vec![row])) );
+    }
+
+    #[test]
+    fn test_csv_errors()
+    {
+        assert_eq!( Err(CsvError::UnclosedQuote(3)), JSON::from_csv("a\n1\n\"open\n") );
+        assert_eq!( Err(CsvError::InvalidQuote(2)), JSON::from_csv("a\n\"x\"y\n") );
+        assert_eq!( Err(CsvError::FieldCount { line: 4, expected: 2, found: 1 }), JSON::from_csv("a,b\n\"1\n\",2\n3\n") );
+        assert_eq!( Err(CsvError::DuplicateKey(1, "a".to_string())), JSON::from_csv("a,b,a\n") );
+
+        assert_eq!( 4, CsvError::FieldCount { line: 4, expected: 2, found: 1 }.line() );
+        assert_eq!( "line 2: record has 1 fields, the header has 2", CsvError::FieldCount { line: 2, expected: 2, found: 1 }.to_string() );
+    }
+}
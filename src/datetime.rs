@@ -0,0 +1,275 @@
+//! Date and time extraction from json strings (enabled with the `datetime` feature)
+//!
+//! Json has no date type, so APIs send dates as strings, almost always in RFC 3339 format
+//! (ie. "2024-02-29" for a date and "2024-02-29T13:45:30.5+01:00" for a timestamp).
+
+/* (IMPORTS) */
+use std::fmt;
+
+
+/// A calendar date (ie. the full-date of RFC 3339)
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Date {
+    year: u16,
+    month: u8,
+    day: u8,
+}
+
+/// A timestamp with a utc offset (ie. the date-time of RFC 3339)
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct DateTime {
+    date: Date,
+    hour: u8,
+    minute: u8,
+    second: u8,
+    nanosecond: u32,
+    offset_minutes: i16,
+}
+
+impl Date {
+
+    /// Creates a date, returns None if the day does not exist in the given month and year
+    pub fn new(year: u16, month: u8, day: u8) -> Option<Date>
+    {
+        if !(1..=12).contains(&month) || day == 0 || day > days_in_month(year, month) { return None; }
+        Some(Date { year, month, day })
+    }
+
+    /// Returns the year (0 to 9999)
+    pub fn year(&self) -> u16
+    {
+        self.year
+    }
+
+    /// Returns the month (1 to 12)
+    pub fn month(&self) -> u8
+    {
+        self.month
+    }
+
+    /// Returns the day of the month (1 to 31)
+    pub fn day(&self) -> u8
+    {
+        self.day
+    }
+
+    // Number of days since 1970-01-01 (negative before), using Howard Hinnant's days_from_civil algorithm
+    fn days_since_epoch(&self) -> i64
+    {
+        let (m, d) = (self.month as i64, self.day as i64);
+        let y = self.year as i64 - i64::from(m <= 2);
+        let era = y.div_euclid(400);
+        let yoe = y - era * 400;
+        let doy = (153 * (if m > 2 { m - 3 } else { m + 9 }) + 2) / 5 + d - 1;
+        let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+        era * 146097 + doe - 719468
+    }
+}
+
+impl DateTime {
+
+    /// Returns the date portion of the timestamp (in the timestamp's own offset)
+    pub fn date(&self) -> Date
+    {
+        self.date
+    }
+
+    /// Returns the hour (0 to 23)
+    pub fn hour(&self) -> u8
+    {
+        self.hour
+    }
+
+    /// Returns the minute (0 to 59)
+    pub fn minute(&self) -> u8
+    {
+        self.minute
+    }
+
+    /// Returns the second (0 to 60, where 60 is a leap second)
+    pub fn second(&self) -> u8
+    {
+        self.second
+    }
+
+    /// Returns the fraction of the second in nanoseconds
+    pub fn nanosecond(&self) -> u32
+    {
+        self.nanosecond
+    }
+
+    /// Returns the offset from utc in minutes (ie. +01:00 is 60 and Z is 0)
+    pub fn offset_minutes(&self) -> i16
+    {
+        self.offset_minutes
+    }
+
+    /// Returns the number of whole seconds since 1970-01-01T00:00:00Z
+    pub fn unix_timestamp(&self) -> i64
+    {
+        let seconds = self.date.days_since_epoch() * 86400
+                        + self.hour as i64 * 3600 + self.minute as i64 * 60 + self.second as i64;
+        seconds - self.offset_minutes as i64 * 60
+    }
+}
+
+impl fmt::Debug for Date {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+    {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+impl fmt::Display for Date {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+    {
+        write!(f, "{:04}-{:02}-{:02}", self.year, self.month, self.day)
+    }
+}
+
+impl fmt::Debug for DateTime {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+    {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+// Displays the timestamp in RFC 3339 format, the fraction of the second is only shown if it is non-zero
+impl fmt::Display for DateTime {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+    {
+        write!(f, "{}T{:02}:{:02}:{:02}", self.date, self.hour, self.minute, self.second)?;
+
+        if self.nanosecond > 0 {
+            let frac = format!("{:09}", self.nanosecond);
+            write!(f, ".{}", frac.trim_end_matches('0'))?;
+        }
+
+        match self.offset_minutes {
+            0 => write!(f, "Z"),
+            m => write!(f, "{}{:02}:{:02}", if m < 0 { '-' } else { '+' }, m.abs() / 60, m.abs() % 60),
+        }
+    }
+}
+
+// Returns the number of days in the given month, taking leap years into account
+fn days_in_month(year: u16, month: u8) -> u8
+{
+    let leap = year.is_multiple_of(4) && (!year.is_multiple_of(100) || year.is_multiple_of(400));
+    match month {
+        2 if leap => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+// Parses exactly n ascii digits from the start of the input, returning the value and the remaining input
+fn digits(input: &str, n: usize) -> Option<(u32, &str)>
+{
+    let digits = input.get(..n)?;
+    if !digits.bytes().all(|b| b.is_ascii_digit()) { return None; }
+    Some((digits.parse().ok()?, &input[n..]))
+}
+
+// Parses a single expected character from the start of the input (case insensitive, since RFC 3339 allows 't' and 'z')
+fn expect(input: &str, ch: char) -> Option<&str>
+{
+    let mut chars = input.chars();
+    chars.next().filter(|c| c.eq_ignore_ascii_case(&ch)).map(|_| chars.as_str())
+}
+
+// Parses a full-date (ie. YYYY-MM-DD) from the start of the input
+fn parse_date(input: &str) -> Option<(Date, &str)>
+{
+    let (year, rest) = digits(input, 4)?;
+    let (month, rest) = digits(expect(rest, '-')?, 2)?;
+    let (day, rest) = digits(expect(rest, '-')?, 2)?;
+
+    Some((Date::new(year as u16, month as u8, day as u8)?, rest))
+}
+
+// Parses an RFC 3339 full-date (ie. "2024-02-29")
+pub(crate) fn parse_rfc3339_date(input: &str) -> Option<Date>
+{
+    match parse_date(input)? {
+        (date, "") => Some(date),
+        _ => None
+    }
+}
+
+// Parses an RFC 3339 date-time (ie. "2024-02-29T13:45:30.5+01:00")
+pub(crate) fn parse_rfc3339_datetime(input: &str) -> Option<DateTime>
+{
+    let (date, rest) = parse_date(input)?;
+
+    //RFC 3339 allows a space in place of the T separator
+    let rest = expect(rest, 'T').or_else(|| rest.strip_prefix(' '))?;
+
+    let (hour, rest) = digits(rest, 2)?;
+    let (minute, rest) = digits(expect(rest, ':')?, 2)?;
+    let (second, mut rest) = digits(expect(rest, ':')?, 2)?;
+    if hour > 23 || minute > 59 || second > 60 { return None; }
+
+    //Optional fraction of a second, digits past nanosecond precision are ignored
+    let mut nanosecond = 0;
+    if let Some(frac) = rest.strip_prefix('.') {
+        let n = frac.bytes().take_while(|b| b.is_ascii_digit()).count();
+        if n == 0 { return None; }
+
+        let padded = format!("{:0<9}", &frac[..n.min(9)]);
+        nanosecond = padded.parse().ok()?;
+        rest = &frac[n..];
+    }
+
+    //Offset is either Z or +HH:MM / -HH:MM
+    let offset_minutes = if let Some("") = expect(rest, 'Z') {
+        0
+    } else {
+        let sign = match rest.chars().next()? { '+' => 1, '-' => -1, _ => return None };
+        let (off_hour, rest) = digits(&rest[1..], 2)?;
+        let (off_minute, rest) = digits(expect(rest, ':')?, 2)?;
+        if off_hour > 23 || off_minute > 59 || !rest.is_empty() { return None; }
+
+        sign * (off_hour * 60 + off_minute) as i16
+    };
+
+    Some(DateTime { date, hour: hour as u8, minute: minute as u8, second: second as u8, nanosecond, offset_minutes })
+}
+
+
+/* (TESTS) */
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_rfc3339_date()
+    {
+        assert_eq!( Date::new(2024, 2, 29), parse_rfc3339_date("2024-02-29") );
+
+        assert_eq!( None, parse_rfc3339_date("2023-02-29") );     //Not a leap year
+        assert_eq!( None, parse_rfc3339_date("2024-13-01") );
+        assert_eq!( None, parse_rfc3339_date("2024-1-01") );
+        assert_eq!( None, parse_rfc3339_date("2024-01-01T00:00:00Z") );
+    }
+
+    #[test]
+    fn test_parse_rfc3339_datetime()
+    {
+        let utc = parse_rfc3339_datetime("1970-01-01T00:00:00Z").unwrap();
+        let offset = parse_rfc3339_datetime("2024-02-29T13:45:30.5+01:00").unwrap();
+
+        assert_eq!( 0, utc.unix_timestamp() );
+        assert_eq!( 1709210730, offset.unix_timestamp() );
+        assert_eq!( 500_000_000, offset.nanosecond() );
+        assert_eq!( 60, offset.offset_minutes() );
+        assert_eq!( "2024-02-29T13:45:30.5+01:00", offset.to_string() );
+        assert_eq!( Some(utc), parse_rfc3339_datetime("1970-01-01t00:00:00z") );
+
+        assert_eq!( None, parse_rfc3339_datetime("2024-02-29T24:00:00Z") );
+        assert_eq!( None, parse_rfc3339_datetime("2024-02-29T13:45:30") );    //Offset is required
+        assert_eq!( None, parse_rfc3339_datetime("2024-02-29T13:45:30.Z") );
+        assert_eq!( None, parse_rfc3339_datetime("2024-02-29") );
+    }
+}
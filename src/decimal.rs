@@ -0,0 +1,212 @@
+//! Exact decimal numbers (enabled with the `decimal` feature)
+//!
+//! A `Decimal` is an integer mantissa together with a scale (ie. the number of digits after the decimal point),
+//! so json numbers such as 0.1 or 1234567.10 are represented exactly rather than as the closest binary float.
+
+/* (IMPORTS) */
+use std::fmt::{self, Write};
+
+
+// The largest scale of a literal, far more digits after the decimal point than any mantissa needs
+const MAX_SCALE: u32 = 4096;
+
+/// An exact decimal number, equal to `mantissa / 10^scale`
+#[derive(Clone, Copy)]
+pub struct Decimal {
+    mantissa: i128,
+    scale: u32,
+}
+
+impl Decimal {
+
+    /// Creates a decimal equal to `mantissa / 10^scale` (ie. Decimal::new(1050, 2) is 10.50)
+    pub fn new(mantissa: i128, scale: u32) -> Decimal
+    {
+        Decimal { mantissa, scale }
+    }
+
+    /// Returns the integer mantissa of the decimal
+    pub fn mantissa(&self) -> i128
+    {
+        self.mantissa
+    }
+
+    /// Returns the number of digits after the decimal point
+    pub fn scale(&self) -> u32
+    {
+        self.scale
+    }
+
+    /// Returns an equal decimal with any trailing zeros after the decimal point removed (ie. 10.50 becomes 10.5)
+    pub fn normalize(&self) -> Decimal
+    {
+        let mut d = *self;
+        while d.scale > 0 && d.mantissa % 10 == 0 {
+            d.mantissa /= 10;
+            d.scale -= 1;
+        }
+        d
+    }
+
+    // Parses a json number literal exactly (ie. optional minus sign, digits, optional fraction & optional exponent)
+    // Returns None if the literal is malformed, the mantissa does not fit within an i128 or the scale is more than MAX_SCALE
+    pub(crate) fn from_literal(literal: &str) -> Option<Decimal>
+    {
+        let (number, exponent) = match literal.find(['e', 'E']) {
+            Some(i) => (&literal[..i], literal[i + 1..].parse::<i64>().ok()?),
+            None => (literal, 0),
+        };
+
+        let (negative, digits) = match number.strip_prefix('-') {
+            Some(rest) => (true, rest),
+            None => (false, number),
+        };
+
+        let (int_part, frac_part) = digits.split_once('.').unwrap_or((digits, ""));
+        if int_part.is_empty() { return None; }
+
+        //Accumulate every digit into the mantissa, checking for overflow
+        let mut mantissa: i128 = 0;
+        for ch in int_part.chars().chain(frac_part.chars()) {
+            let digit = ch.to_digit(10)? as i128;
+            mantissa = mantissa.checked_mul(10)?.checked_add(digit)?;
+        }
+
+        //Zero is zero whatever its exponent, keeping the digits after the decimal point if they can be kept (ie. 0.00)
+        let scale = (frac_part.len() as i64).checked_sub(exponent);
+        if mantissa == 0 {
+            return Some(Decimal { mantissa, scale: scale.and_then(|s| u32::try_from(s).ok()).filter(|s| *s <= MAX_SCALE).unwrap_or(0) });
+        }
+
+        //A positive exponent moves the decimal point right, shifting the mantissa once the scale reaches zero.
+        //A non-zero i128 can not be shifted more than 38 digits, so larger shifts are rejected before looping.
+        let mut scale = scale?;
+        if scale < -38 { return None; }
+        while scale < 0 {
+            mantissa = mantissa.checked_mul(10)?;
+            scale += 1;
+        }
+
+        let scale = u32::try_from(scale).ok().filter(|s| *s <= MAX_SCALE)?;
+        Some(Decimal { mantissa: if negative { -mantissa } else { mantissa }, scale })
+    }
+}
+
+// Decimals are equal if they have the same value, regardless of trailing zeros (ie. 10.50 == 10.5)
+impl PartialEq for Decimal {
+    fn eq(&self, other: &Decimal) -> bool
+    {
+        let (a, b) = (self.normalize(), other.normalize());
+        a.mantissa == b.mantissa && a.scale == b.scale
+    }
+}
+
+impl fmt::Debug for Decimal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+    {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+// Displays the decimal with exactly `scale` digits after the decimal point
+impl fmt::Display for Decimal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+    {
+        let sign = if self.mantissa < 0 { "-" } else { "" };
+        let digits = self.mantissa.unsigned_abs().to_string();
+        let scale = self.scale as usize;
+
+        if scale == 0 {
+            return write!(f, "{sign}{digits}");
+        }
+
+        if digits.len() > scale {
+            let (int_part, frac_part) = digits.split_at(digits.len() - scale);
+            return write!(f, "{sign}{int_part}.{frac_part}");
+        }
+
+        //Pad with leading zeros so there is a digit before the decimal point, a zero at a time as scales can be too wide for a width argument
+        write!(f, "{sign}0.")?;
+        for _ in digits.len()..scale {
+            f.write_char('0')?;
+        }
+        f.write_str(&digits)
+    }
+}
+
+
+/// Converts to a `rust_decimal::Decimal`, failing if the mantissa needs more than 96 bits or the scale is more than 28
+#[cfg(feature = "rust_decimal")]
+impl TryFrom<Decimal> for rust_decimal::Decimal {
+    type Error = rust_decimal::Error;
+
+    fn try_from(d: Decimal) -> Result<rust_decimal::Decimal, rust_decimal::Error>
+    {
+        rust_decimal::Decimal::try_from_i128_with_scale(d.mantissa, d.scale)
+    }
+}
+
+#[cfg(feature = "rust_decimal")]
+impl From<rust_decimal::Decimal> for Decimal {
+    fn from(d: rust_decimal::Decimal) -> Decimal
+    {
+        Decimal::new(d.mantissa(), d.scale())
+    }
+}
+
+
+/* (TESTS) */
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_literal()
+    {
+        assert_eq!( Some(Decimal::new(1, 1)), Decimal::from_literal("0.1") );
+        assert_eq!( Some(Decimal::new(123456710, 2)), Decimal::from_literal("1234567.10") );
+        assert_eq!( Some(Decimal::new(-15, 3)), Decimal::from_literal("-1.5e-2") );
+        assert_eq!( Some(Decimal::new(2500, 0)), Decimal::from_literal("2.5E3") );
+
+        assert_eq!( None, Decimal::from_literal("") );
+        assert_eq!( None, Decimal::from_literal("1e") );
+        assert_eq!( None, Decimal::from_literal("1e40") ); //Mantissa overflows an i128
+        assert_eq!( None, Decimal::from_literal("1e-9223372036854775808") ); //Scale overflows an i64
+        assert_eq!( None, Decimal::from_literal("1e999999999999") );
+        assert_eq!( None, Decimal::from_literal("1e-4000000000") );             //Scale is more than MAX_SCALE
+        assert_eq!( Some(Decimal::new(1, MAX_SCALE)), Decimal::from_literal(&format!("1e-{MAX_SCALE}")) );
+
+        assert_eq!( Some(Decimal::new(0, 0)), Decimal::from_literal("0e-9223372036854775808") );
+        assert_eq!( Some(Decimal::new(0, 0)), Decimal::from_literal("0e999999999999") );
+        assert_eq!( Some(Decimal::new(0, 0)), Decimal::from_literal("0e-4000000000") );
+        assert_eq!( "0.00", Decimal::from_literal("0.00").unwrap().to_string() );
+    }
+
+    #[test]
+    fn test_display()
+    {
+        assert_eq!( "1234567.10", Decimal::from_literal("1234567.10").unwrap().to_string() );
+        assert_eq!( "-0.015", Decimal::new(-15, 3).to_string() );
+        assert_eq!( "42", Decimal::new(42, 0).to_string() );
+        assert_eq!( "10.5", Decimal::new(1050, 2).normalize().to_string() );
+        assert_eq!( "0.05", Decimal::new(5, 2).to_string() );
+        assert_eq!( "-0.12", Decimal::new(-12, 2).to_string() );
+
+        //Scales too wide for a width argument are still padded
+        let tiny = Decimal::new(7, 70_000).to_string();
+        assert_eq!( (70_002, "0.000", "07"), (tiny.len(), &tiny[..5], &tiny[tiny.len() - 2..]) );
+    }
+
+    #[cfg(feature = "rust_decimal")]
+    #[test]
+    fn test_rust_decimal()
+    {
+        let d = Decimal::from_literal("-1234567.10").unwrap();
+        let converted = rust_decimal::Decimal::try_from(d).unwrap();
+
+        assert_eq!( "-1234567.10", converted.to_string() );
+        assert_eq!( d, Decimal::from(converted) );
+        assert!( rust_decimal::Decimal::try_from(Decimal::new(1, 29)).is_err() );
+        assert!( rust_decimal::Decimal::try_from(Decimal::new(i128::MAX, 0)).is_err() );
+    }
+}
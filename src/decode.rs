@@ -0,0 +1,732 @@
+//! Decoding json text straight into typed values, without building a `JSON` tree first
+//!
+//! The type being decoded drives the parse: each `FromJson` implementation reads the values it expects from a `Decoder`
+//! (ie. a struct reads an object and each of its fields), so only the parts of a message that are needed as json are ever kept as json.
+
+/* (IMPORTS) */
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::combinator::{quoted_string_literal, ws0, Parser as _};
+use crate::error::{line_column, write_path};
+use crate::lexer::match_until_double_quote;
+use crate::{number_literal, string_literal, try_parse, FieldState, JsonPath, Number, PathSegment, JSON, MAX_DEPTH};
+
+
+/// A type that can be read from json text by a `Decoder`
+///
+/// # Examples
+/// ```
+/// use Parser::{decode, DecodeError, Decoder, FromJson};
+///
+/// #[derive(Debug, PartialEq)]
+/// struct Point { x: i32, y: i32, label: Option<String> }
+///
+/// impl FromJson for Point {
+///     fn from_json(decoder: &mut Decoder<'_>) -> Result<Point, DecodeError>
+///     {
+///         let (mut x, mut y, mut label) = (None, None, None);
+///         decoder.read_object(|key, decoder| {
+///             match key {
+///                 "x" => x = Some(decoder.read()?),
+///                 "y" => y = Some(decoder.read()?),
+///                 "label" => label = decoder.read()?,
+///                 _ => decoder.skip()?,
+///             }
+///             Ok(())
+///         })?;
+///
+///         let missing = |name| decoder.error(&format!("missing field \"{name}\""));
+///         Ok(Point { x: x.ok_or_else(|| missing("x"))?, y: y.ok_or_else(|| missing("y"))?, label })
+///     }
+/// }
+///
+/// let points: Vec<Point> = decode(r#"[{ "x" : 1, "y" : 2 }, { "y" : 4, "x" : 3, "label" : "b", "color" : [255, 0, 0] }]"#).unwrap();
+/// assert_eq!( vec![Point { x: 1, y: 2, label: None }, Point { x: 3, y: 4, label: Some("b".to_string()) }], points );
+/// ```
+pub trait FromJson: Sized {
+    /// Reads a value of the type from the decoder, which must read exactly one json value
+    fn from_json(decoder: &mut Decoder<'_>) -> Result<Self, DecodeError>;
+}
+
+/// Reads json text one value at a time, as the type being decoded asks for them
+#[derive(Debug, Clone)]
+pub struct Decoder<'a> {
+    input: &'a str,
+    pos: usize,
+    path: JsonPath,
+    depth: usize,
+}
+
+/// Error returned when the text is not the json a type expects, with the position of the problem in the input and the path of the value it is in
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DecodeError {
+    message: String,
+    offset: usize,
+    line: usize,
+    column: usize,
+    path: JsonPath,
+}
+
+impl DecodeError {
+
+    /// Returns what was expected (ie. "expected a string")
+    pub fn message(&self) -> &str
+    {
+        &self.message
+    }
+
+    /// Returns the byte offset of the problem in the input
+    pub fn offset(&self) -> usize
+    {
+        self.offset
+    }
+
+    /// Returns the line of the problem, starting at 1
+    pub fn line(&self) -> usize
+    {
+        self.line
+    }
+
+    /// Returns the column of the problem in characters, starting at 1
+    pub fn column(&self) -> usize
+    {
+        self.column
+    }
+
+    /// Returns the path of the value the problem is in
+    pub fn path(&self) -> &JsonPath
+    {
+        &self.path
+    }
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+    {
+        write!(f, "{} at line {} column {}", self.message, self.line, self.column)?;
+        write_path(f, &self.path)
+    }
+}
+
+impl std::error::Error for DecodeError {}
+
+/// Decodes a string containing exactly one json value (surrounded by optional whitespace) into a typed value
+///
+/// # Errors
+/// Returns a `DecodeError` if the text is not the json the type expects, or if more than whitespace follows the value
+pub fn decode<T: FromJson>(input: &str) -> Result<T, DecodeError>
+{
+    let mut decoder = Decoder::new(input);
+    let value = T::from_json(&mut decoder)?;
+
+    decoder.skip_whitespace();
+    match decoder.rest().is_empty() {
+        true => Ok(value),
+        false => Err(decoder.error("unexpected characters after the json value")),
+    }
+}
+
+// A function that reads one variant of an untagged enum
+type Variant<'a, T> = fn(&mut Decoder<'a>) -> Result<T, DecodeError>;
+
+impl<'a> Decoder<'a> {
+
+    /// Creates a decoder at the start of some json text
+    pub fn new(input: &'a str) -> Decoder<'a>
+    {
+        Decoder { input, pos: 0, path: JsonPath::root(), depth: 0 }
+    }
+
+    /// Returns an error at the current position of the decoder (ie. for a missing field or an invalid value), with the path of the current value
+    pub fn error(&self, message: &str) -> DecodeError
+    {
+        self.error_at(self.pos, message)
+    }
+
+    fn error_at(&self, offset: usize, message: &str) -> DecodeError
+    {
+        let (line, column) = line_column(self.input, offset);
+        DecodeError { message: message.to_string(), offset, line, column, path: self.path.clone() }
+    }
+
+    fn rest(&self) -> &'a str
+    {
+        &self.input[self.pos..]
+    }
+
+    // Moves the decoder to a suffix of its input
+    fn advance_to(&mut self, rest: &'a str)
+    {
+        self.pos = self.input.len() - rest.len();
+    }
+
+    fn skip_whitespace(&mut self)
+    {
+        match ws0(self.rest()) {
+            Ok((rest, _)) | Err(rest) => self.advance_to(rest),
+        }
+    }
+
+    // Consumes a str after any whitespace, failing with what was expected if it is not there
+    fn expect(&mut self, s: &str, expected: &str) -> Result<(), DecodeError>
+    {
+        self.skip_whitespace();
+        match self.rest().strip_prefix(s) {
+            Some(rest) => { self.advance_to(rest); Ok(()) }
+            None => Err(self.error(&format!("expected {expected}"))),
+        }
+    }
+
+    // Consumes a str after any whitespace if it is there
+    fn eat(&mut self, s: &str) -> bool
+    {
+        self.skip_whitespace();
+        let found = self.rest().starts_with(s);
+        if found { self.pos += s.len(); }
+        found
+    }
+
+    /// Reads a value of any type that implements `FromJson`
+    pub fn read<T: FromJson>(&mut self) -> Result<T, DecodeError>
+    {
+        T::from_json(self)
+    }
+
+    /// Returns true if the next value is null, without reading it
+    pub fn is_null(&mut self) -> bool
+    {
+        self.skip_whitespace();
+        self.rest().starts_with("null")
+    }
+
+    /// Reads null
+    pub fn read_null(&mut self) -> Result<(), DecodeError>
+    {
+        self.expect("null", "null")
+    }
+
+    /// Reads true or false
+    pub fn read_bool(&mut self) -> Result<bool, DecodeError>
+    {
+        if self.eat("true") { return Ok(true); }
+        if self.eat("false") { return Ok(false); }
+        Err(self.error("expected a bool"))
+    }
+
+    // Reads a number literal, failing at its start if there is none or it can not be represented
+    fn read_number(&mut self) -> Result<(usize, Number), DecodeError>
+    {
+        self.skip_whitespace();
+        let start = self.pos;
+        let Ok((rest, literal)) = number_literal(self.rest()) else { return Err(self.error("expected a number")); };
+        let n = Number::from_literal(literal).ok_or_else(|| self.error("number out of range"))?;
+
+        self.advance_to(rest);
+        Ok((start, n))
+    }
+
+    // Reads an integer number that fits in an integer type, given the name of the type for the error
+    fn read_integer<T: TryFrom<i64> + TryFrom<u64>>(&mut self, name: &str) -> Result<T, DecodeError>
+    {
+        let (start, n) = self.read_number()?;
+        let value = match (n.as_i64(), n.as_u64()) {
+            (Some(i), _) => T::try_from(i).ok(),
+            (_, Some(u)) => T::try_from(u).ok(),
+            _ => None,
+        };
+        value.ok_or_else(|| self.error_at(start, &format!("expected an integer that fits in {name}")))
+    }
+
+    /// Reads an integer number that fits within an i64
+    pub fn read_i64(&mut self) -> Result<i64, DecodeError>
+    {
+        self.read_integer("i64")
+    }
+
+    /// Reads a non-negative integer number that fits within a u64
+    pub fn read_u64(&mut self) -> Result<u64, DecodeError>
+    {
+        self.read_integer("u64")
+    }
+
+    /// Reads any number as an f64
+    pub fn read_f64(&mut self) -> Result<f64, DecodeError>
+    {
+        self.read_number().map(|(_, n)| n.as_f64())
+    }
+
+    /// Reads a string, with its escape sequences replaced
+    pub fn read_string(&mut self) -> Result<String, DecodeError>
+    {
+        self.skip_whitespace();
+        let Ok((rest, s)) = string_literal(self.rest()) else { return Err(self.error("expected a string")); };
+        self.advance_to(rest);
+        Ok(s)
+    }
+
+    // Counts an array or object being entered, failing if they are nested too deeply
+    fn enter(&mut self) -> Result<(), DecodeError>
+    {
+        if self.depth == MAX_DEPTH { return Err(self.error("arrays and objects are nested too deeply")); }
+        self.depth += 1;
+        Ok(())
+    }
+
+    /// Reads an array, calling a function to read each of its elements in turn (with the path of the element)
+    pub fn read_array<F>(&mut self, mut element: F) -> Result<(), DecodeError>
+    where
+        F: FnMut(&mut Decoder<'a>) -> Result<(), DecodeError>
+    {
+        self.expect("[", "an array")?;
+        self.enter()?;
+
+        if !self.eat("]") {
+            for i in 0.. {
+                self.path.push(PathSegment::Index(i));
+                element(self)?;
+                self.path.pop();
+
+                if self.eat("]") { break; }
+                self.expect(",", "',' or ']'")?;
+            }
+        }
+
+        self.depth -= 1;
+        Ok(())
+    }
+
+    /// Reads an object, calling a function with the key of each of its members in turn to read the member's value (with the path of the member)
+    pub fn read_object<F>(&mut self, mut member: F) -> Result<(), DecodeError>
+    where
+        F: FnMut(&str, &mut Decoder<'a>) -> Result<(), DecodeError>
+    {
+        self.expect("{", "an object")?;
+        self.enter()?;
+
+        if !self.eat("}") {
+            loop {
+                let key = self.read_string().map_err(|e| DecodeError { message: "expected a key".to_string(), ..e })?;
+                self.expect(":", "':'")?;
+
+                self.path.push(PathSegment::Key(key.clone()));
+                member(&key, self)?;
+                self.path.pop();
+
+                if self.eat("}") { break; }
+                self.expect(",", "',' or '}'")?;
+            }
+        }
+
+        self.depth -= 1;
+        Ok(())
+    }
+
+    /// Reads past the next value without converting it (ie. a member the type does not have)
+    pub fn skip(&mut self) -> Result<(), DecodeError>
+    {
+        self.skip_whitespace();
+        match self.rest().as_bytes().first() {
+            Some(b'[') => self.read_array(|decoder| decoder.skip()),
+            Some(b'{') => self.read_object(|_, decoder| decoder.skip()),
+            Some(b'"') => match quoted_string_literal(match_until_double_quote).parse(self.rest()) {
+                Ok((rest, _)) => { self.advance_to(rest); Ok(()) }
+                Err(_) => Err(self.error("expected a string")),
+            },
+            Some(b't' | b'f') => self.read_bool().map(|_| ()),
+            Some(b'n') => self.read_null(),
+            _ => self.read_number().map(|_| ()),
+        }
+    }
+
+    /// Reads past the next value, returning its text (ie. to decode it later, once the rest of its object says how)
+    pub fn read_raw(&mut self) -> Result<&'a str, DecodeError>
+    {
+        self.skip_whitespace();
+        let start = self.pos;
+        self.skip()?;
+        Ok(&self.input[start..self.pos])
+    }
+
+    /// Reads the next value as a `JSON` tree (ie. a part of a message whose shape is not known)
+    pub fn read_value(&mut self) -> Result<JSON, DecodeError>
+    {
+        self.skip_whitespace();
+        let start = self.pos;
+        let raw = self.read_raw()?;
+        try_parse(raw).map_err(|e| self.error_at(start + e.offset(), &e.to_string()))
+    }
+
+    /// Reads an untagged enum, trying to read the next value as each variant in order until one succeeds
+    /// (ie. `5` and `"five"` as `Int(5)` and `Text("five")`). Put variants that accept more values (ie. `JSON`) last.
+    ///
+    /// # Examples
+    /// ```
+    /// use Parser::{decode, DecodeError, Decoder, FromJson};
+    ///
+    /// #[derive(Debug, PartialEq)]
+    /// enum Id { Number(u64), Name(String) }
+    ///
+    /// impl FromJson for Id {
+    ///     fn from_json(decoder: &mut Decoder<'_>) -> Result<Id, DecodeError>
+    ///     {
+    ///         decoder.read_untagged(&[|d| d.read().map(Id::Number), |d| d.read().map(Id::Name)])
+    ///     }
+    /// }
+    ///
+    /// assert_eq!( Ok(vec![Id::Number(7), Id::Name("seven".to_string())]), decode::<Vec<Id>>(r#"[7, "seven"]"#) );
+    /// ```
+    ///
+    /// # Errors
+    /// If no variant can be read, returns the error of the variant that read furthest into the value, which is most likely the one meant
+    pub fn read_untagged<T>(&mut self, variants: &[Variant<'a, T>]) -> Result<T, DecodeError>
+    {
+        self.skip_whitespace();
+        let mut furthest: Option<DecodeError> = None;
+
+        for variant in variants {
+            let mut attempt = self.clone();
+            match variant(&mut attempt) {
+                Ok(value) => {
+                    *self = attempt;
+                    return Ok(value);
+                }
+                Err(e) => if furthest.as_ref().is_none_or(|f| e.offset > f.offset) { furthest = Some(e); },
+            }
+        }
+        Err(furthest.unwrap_or_else(|| self.error("expected a variant")))
+    }
+
+    /// Reads an adjacently tagged enum, an object with the name of the variant under the `tag` key and its value under the `content` key
+    /// (ie. `{ "type" : "circle", "data" : { "r" : 1 } }`), in either order. The function is called with the name and a decoder for the
+    /// content, which it must read if there is one. The content is None when it is missing, as it usually is for variants without a value.
+    /// The function returns None if the name is not a variant's, or the content is missing or given for a variant that does not have it.
+    ///
+    /// # Examples
+    /// ```
+    /// use Parser::{decode, DecodeError, Decoder, FromJson};
+    ///
+    /// #[derive(Debug, PartialEq)]
+    /// enum Event { Click(Vec<i32>), Close }
+    ///
+    /// impl FromJson for Event {
+    ///     fn from_json(decoder: &mut Decoder<'_>) -> Result<Event, DecodeError>
+    ///     {
+    ///         decoder.read_tagged("event", "data", |name, content| match (name, content) {
+    ///             ("click", Some(content)) => Some(content.read().map(Event::Click)),
+    ///             ("close", None) => Some(Ok(Event::Close)),
+    ///             _ => None,
+    ///         })
+    ///     }
+    /// }
+    ///
+    /// let events = decode::<Vec<Event>>(r#"[{ "data" : [4, 2], "event" : "click" }, { "event" : "close" }]"#).unwrap();
+    /// assert_eq!( vec![Event::Click(vec![4, 2]), Event::Close], events );
+    ///
+    /// let error = decode::<Event>(r#"{ "event" : "scroll" }"#).unwrap_err();
+    /// assert_eq!( "unexpected variant \"scroll\" at line 1 column 13 (in $.event)", error.to_string() );
+    /// ```
+    ///
+    /// # Errors
+    /// Returns a `DecodeError` if the value is not an object, the tag is missing or not a string, the function returns None or fails,
+    /// or the function does not read exactly all of the content
+    pub fn read_tagged<T, F>(&mut self, tag: &str, content: &str, variant: F) -> Result<T, DecodeError>
+    where
+        F: FnOnce(&str, Option<&mut Decoder<'a>>) -> Option<Result<T, DecodeError>>
+    {
+        let (mut name, mut raw) = (None, None);
+        self.read_object(|key, decoder| {
+            match key {
+                k if k == tag => {
+                    decoder.skip_whitespace();
+                    name = Some((decoder.clone(), decoder.read_string()?));
+                }
+                k if k == content => raw = Some(decoder.read_raw()?),
+                _ => decoder.skip()?,
+            }
+            Ok(())
+        })?;
+
+        let Some((at_tag, name)) = name else { return Err(self.error(&format!("missing field \"{tag}\""))); };
+        let unexpected = || at_tag.error(&format!("unexpected variant \"{name}\""));
+        let Some(raw) = raw else { return variant(&name, None).unwrap_or_else(|| Err(unexpected())); };
+
+        //Decode the content where it was in the input, so errors in it have its position and path
+        let end = raw.as_ptr() as usize - self.input.as_ptr() as usize + raw.len();
+        let mut path = self.path.clone();
+        path.push(PathSegment::Key(content.to_string()));
+        let mut decoder = Decoder { input: self.input, pos: end - raw.len(), path, depth: self.depth + 1 };
+
+        let value = variant(&name, Some(&mut decoder)).unwrap_or_else(|| Err(unexpected()))?;
+        match decoder.pos == end {
+            true => Ok(value),
+            false => Err(decoder.error("expected the content to be read as one value")),
+        }
+    }
+}
+
+
+/* (IMPLEMENTATIONS) */
+
+impl FromJson for bool {
+    fn from_json(decoder: &mut Decoder<'_>) -> Result<bool, DecodeError>
+    {
+        decoder.read_bool()
+    }
+}
+
+// Implements FromJson for integer types, which fail if the number does not fit
+macro_rules! integer_from_json {
+    ($($t:ty),+) => {
+        $(
+            impl FromJson for $t {
+                fn from_json(decoder: &mut Decoder<'_>) -> Result<$t, DecodeError>
+                {
+                    decoder.read_integer(stringify!($t))
+                }
+            }
+        )+
+    };
+}
+
+integer_from_json!(i8, i16, i32, i64, isize, u8, u16, u32, u64, usize);
+
+impl FromJson for f64 {
+    fn from_json(decoder: &mut Decoder<'_>) -> Result<f64, DecodeError>
+    {
+        decoder.read_f64()
+    }
+}
+
+impl FromJson for f32 {
+    fn from_json(decoder: &mut Decoder<'_>) -> Result<f32, DecodeError>
+    {
+        decoder.read_f64().map(|f| f as f32)
+    }
+}
+
+impl FromJson for String {
+    fn from_json(decoder: &mut Decoder<'_>) -> Result<String, DecodeError>
+    {
+        decoder.read_string()
+    }
+}
+
+/// Null is None, anything else is decoded as T
+impl<T: FromJson> FromJson for Option<T> {
+    fn from_json(decoder: &mut Decoder<'_>) -> Result<Option<T>, DecodeError>
+    {
+        if decoder.is_null() {
+            decoder.read_null()?;
+            return Ok(None);
+        }
+        decoder.read().map(Some)
+    }
+}
+
+/// Null is `FieldState::Null`, anything else is decoded as a present T. A field that is never read stays as it was, so struct
+/// members that start as `FieldState::Absent` tell which fields of an object were left out (ie. for a PATCH request).
+impl<T: FromJson> FromJson for FieldState<T> {
+    fn from_json(decoder: &mut Decoder<'_>) -> Result<FieldState<T>, DecodeError>
+    {
+        let value: Option<T> = decoder.read()?;
+        Ok(value.map_or(FieldState::Null, FieldState::Present))
+    }
+}
+
+impl<T: FromJson> FromJson for Vec<T> {
+    fn from_json(decoder: &mut Decoder<'_>) -> Result<Vec<T>, DecodeError>
+    {
+        let mut vec = vec![];
+        decoder.read_array(|decoder| {
+            vec.push(decoder.read()?);
+            Ok(())
+        })?;
+        Ok(vec)
+    }
+}
+
+/// A key given more than once is set to its last value
+impl<T: FromJson> FromJson for HashMap<String, T> {
+    fn from_json(decoder: &mut Decoder<'_>) -> Result<HashMap<String, T>, DecodeError>
+    {
+        let mut map = HashMap::new();
+        decoder.read_object(|key, decoder| {
+            map.insert(key.to_string(), decoder.read()?);
+            Ok(())
+        })?;
+        Ok(map)
+    }
+}
+
+impl<T: FromJson> FromJson for Box<T> {
+    fn from_json(decoder: &mut Decoder<'_>) -> Result<Box<T>, DecodeError>
+    {
+        decoder.read().map(Box::new)
+    }
+}
+
+impl FromJson for JSON {
+    fn from_json(decoder: &mut Decoder<'_>) -> Result<JSON, DecodeError>
+    {
+        decoder.read_value()
+    }
+}
+
+
+/* (TESTS) */
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, PartialEq)]
+    struct Order {
+        id: u32,
+        items: Vec<Item>,
+        note: Option<String>,
+        extra: JSON,
+    }
+
+    #[derive(Debug, PartialEq)]
+    struct Item {
+        name: String,
+        price: f64,
+        tags: HashMap<String, bool>,
+    }
+
+    impl FromJson for Order {
+        fn from_json(decoder: &mut Decoder<'_>) -> Result<Order, DecodeError>
+        {
+            let (mut id, mut items, mut note, mut extra) = (None, vec![], None, JSON::JsNull);
+            decoder.read_object(|key, decoder| {
+                match key {
+                    "id" => id = Some(decoder.read()?),
+                    "items" => items = decoder.read()?,
+                    "note" => note = decoder.read()?,
+                    "extra" => extra = decoder.read()?,
+                    _ => decoder.skip()?,
+                }
+                Ok(())
+            })?;
+            Ok(Order { id: id.ok_or_else(|| decoder.error("missing field \"id\""))?, items, note, extra })
+        }
+    }
+
+    impl FromJson for Item {
+        fn from_json(decoder: &mut Decoder<'_>) -> Result<Item, DecodeError>
+        {
+            let (mut name, mut price, mut tags) = (String::new(), 0.0, HashMap::new());
+            decoder.read_object(|key, decoder| {
+                match key {
+                    "name" => name = decoder.read()?,
+                    "price" => price = decoder.read()?,
+                    "tags" => tags = decoder.read()?,
+                    _ => decoder.skip()?,
+                }
+                Ok(())
+            })?;
+            Ok(Item { name, price, tags })
+        }
+    }
+
+    #[test]
+    fn test_decode()
+    {
+        let input = r#"{ "id" : 7, "skipped" : { "a" : [1, "]", { "b" : null }], "c" : true },
+                         "items" : [{ "name" : "tea\n", "price" : 1.5, "tags" : { "hot" : true } }, { "name" : "cake", "price" : 3 }],
+                         "note" : null, "extra" : { "x" : [1, 2] } } "#;
+
+        let expected = Order {
+            id: 7,
+            items: vec![
+                Item { name: "tea\n".to_string(), price: 1.5, tags: HashMap::from([("hot".to_string(), true)]) },
+                Item { name: "cake".to_string(), price: 3.0, tags: HashMap::new() },
+            ],
+            note: None,
+            extra: try_parse(r#"{ "x" : [1, 2] }"#).unwrap(),
+        };
+        assert_eq!( Ok(expected), decode::<Order>(input) );
+
+        assert_eq!( Ok(vec![Some(-1_i8), None]), decode::<Vec<Option<i8>>>("[-1, null]") );
+        assert_eq!( Ok(r#"[1, { "a" : "}" }]"#), Decoder::new(r#"  [1, { "a" : "}" }] , 2"#).read_raw() );
+    }
+
+    #[test]
+    fn test_decode_errors()
+    {
+        let error = |input: &str| decode::<Order>(input).map_err(|e| (e.message().to_string(), e.column(), e.path().to_string()));
+
+        assert_eq!( Err(("expected a string".to_string(), 43, "items[1].name".to_string())),
+                    error(r#"{ "items" : [{ "name" : "a" }, { "name" : 5 }] }"#) );
+        assert_eq!( Err(("expected an integer that fits in u32".to_string(), 10, "id".to_string())), error(r#"{ "id" : -1 }"#) );
+        assert_eq!( Err(("missing field \"id\"".to_string(), 3, "".to_string())), error("{}") );
+        assert_eq!( Err(("expected ',' or '}'".to_string(), 12, "".to_string())), error(r#"{ "id" : 1 ]"#) );
+        assert_eq!( Err(("expected a key".to_string(), 3, "".to_string())), error("{ 1 : 2 }") );
+        assert_eq!( Err(("unexpected characters after the json value".to_string(), 14, "".to_string())), error(r#"{ "id" : 1 } x"#) );
+
+        let nested = format!("{}{}", "[".repeat(MAX_DEPTH + 1), "]".repeat(MAX_DEPTH + 1));
+        assert_eq!( "arrays and objects are nested too deeply", decode::<JSON>(&nested).unwrap_err().message() );
+    }
+
+    #[derive(Debug, PartialEq)]
+    enum Setting {
+        Flag(bool),
+        Level(u8),
+        Names(Vec<String>),
+        Other(JSON),
+    }
+
+    impl FromJson for Setting {
+        fn from_json(decoder: &mut Decoder<'_>) -> Result<Setting, DecodeError>
+        {
+            decoder.read_untagged(&[
+                |d| d.read().map(Setting::Flag),
+                |d| d.read().map(Setting::Level),
+                |d| d.read().map(Setting::Names),
+                |d| d.read().map(Setting::Other),
+            ])
+        }
+    }
+
+    #[derive(Debug, PartialEq)]
+    enum Shape {
+        Circle(f64),
+        Rect(Vec<f64>),
+        Empty,
+    }
+
+    impl FromJson for Shape {
+        fn from_json(decoder: &mut Decoder<'_>) -> Result<Shape, DecodeError>
+        {
+            decoder.read_tagged("kind", "size", |name, content| match (name, content) {
+                ("circle", Some(content)) => Some(content.read().map(Shape::Circle)),
+                ("rect", Some(content)) => Some(content.read().map(Shape::Rect)),
+                ("empty", None) => Some(Ok(Shape::Empty)),
+                _ => None,
+            })
+        }
+    }
+
+    #[test]
+    fn test_decode_untagged()
+    {
+        let expected = vec![Setting::Flag(true), Setting::Level(3), Setting::Names(vec!["a".to_string()]), Setting::Other(try_parse("300").unwrap())];
+        assert_eq!( Ok(expected), decode::<Vec<Setting>>(r#"[true, 3, ["a"], 300]"#) );
+
+        //The error is from the variant that got furthest, ie. the list of names
+        let error = decode::<Setting>(r#"["a", 2"#).unwrap_err();
+        assert_eq!( ("expected ',' or ']'".to_string(), 8), (error.message().to_string(), error.column()) );
+    }
+
+    #[test]
+    fn test_decode_tagged()
+    {
+        let input = r#"[{ "kind" : "circle", "size" : 1.5 }, { "size" : [2, 3], "id" : 9, "kind" : "rect" }, { "kind" : "empty" }]"#;
+        assert_eq!( Ok(vec![Shape::Circle(1.5), Shape::Rect(vec![2.0, 3.0]), Shape::Empty]), decode::<Vec<Shape>>(input) );
+
+        let error = |input: &str| decode::<Shape>(input).map_err(|e| (e.message().to_string(), e.column(), e.path().to_string()));
+        assert_eq!( Err(("expected a number".to_string(), 12, "size".to_string())), error(r#"{ "size" : "big", "kind" : "circle" }"#) );
+        assert_eq!( Err(("unexpected variant \"empty\"".to_string(), 12, "kind".to_string())), error(r#"{ "kind" : "empty", "size" : 1 }"#) );
+        assert_eq!( Err(("missing field \"kind\"".to_string(), 15, "".to_string())), error(r#"{ "size" : 1 }"#) );
+    }
+}
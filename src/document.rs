@@ -0,0 +1,131 @@
+//! A parsed value bundled with where it came from
+
+/* (IMPORTS) */
+use std::time::{Duration, Instant};
+
+use crate::{try_parse_with, Options, ParseError, JSON};
+
+
+/// A parsed json value along with the name of its source (ie. a file path or url), the original text,
+/// the options it was parsed with and how long parsing took, so later diagnostics can say where a value came from
+#[derive(Debug, Clone, PartialEq)]
+pub struct JsonDocument {
+    root: JSON,
+    source: String,
+    text: Option<String>,
+    options: Options,
+    parse_time: Duration,
+}
+
+impl JsonDocument {
+
+    /// Parses the text of a source with the default options, keeping a copy of the text
+    ///
+    /// # Errors
+    /// Returns a `ParseError` if the text is not valid json, see `try_parse`
+    pub fn parse(source: impl Into<String>, text: &str) -> Result<JsonDocument, ParseError>
+    {
+        JsonDocument::parse_with(source, text, &Options::default())
+    }
+
+    /// Parses the text of a source with the options, keeping a copy of the text
+    ///
+    /// # Errors
+    /// Returns a `ParseError` if the text is not valid json, see `try_parse_with`
+    pub fn parse_with(source: impl Into<String>, text: &str, options: &Options) -> Result<JsonDocument, ParseError>
+    {
+        let start = Instant::now();
+        let root = try_parse_with(text, options)?;
+        let parse_time = start.elapsed();
+
+        Ok(JsonDocument { root, source: source.into(), text: Some(text.to_string()), options: options.clone(), parse_time })
+    }
+
+    /// Wraps a value that was not parsed from text (ie. built in code), it has no text and a parse time of zero
+    pub fn from_value(source: impl Into<String>, root: JSON) -> JsonDocument
+    {
+        JsonDocument { root, source: source.into(), text: None, options: Options::default(), parse_time: Duration::ZERO }
+    }
+
+    /// Returns the root value
+    pub fn root(&self) -> &JSON
+    {
+        &self.root
+    }
+
+    /// Returns the root value for modification, the original text is kept as it was
+    pub fn root_mut(&mut self) -> &mut JSON
+    {
+        &mut self.root
+    }
+
+    /// Returns the root value, dropping the metadata
+    pub fn into_root(self) -> JSON
+    {
+        self.root
+    }
+
+    /// Returns the name of the source the document came from
+    pub fn source(&self) -> &str
+    {
+        &self.source
+    }
+
+    /// Returns the text the document was parsed from, unless it was discarded or the document was not parsed
+    pub fn text(&self) -> Option<&str>
+    {
+        self.text.as_deref()
+    }
+
+    /// Drops the copy of the original text, to save memory when it is no longer needed
+    pub fn discard_text(&mut self)
+    {
+        self.text = None;
+    }
+
+    /// Returns the options the document was parsed with
+    pub fn options(&self) -> &Options
+    {
+        &self.options
+    }
+
+    /// Returns how long parsing took
+    pub fn parse_time(&self) -> Duration
+    {
+        self.parse_time
+    }
+}
+
+
+/* (TESTS) */
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_json;
+
+    #[test]
+    fn test_parse()
+    {
+        let options = Options { max_depth: 4, ..Options::default() };
+        let mut doc = JsonDocument::parse_with("config/app.json", r#"{ "port" : 80 }"#, &options).unwrap();
+
+        assert_eq!( parse_json(r#"{ "port" : 80 }"#).unwrap().1, *doc.root() );
+        assert_eq!( "config/app.json", doc.source() );
+        assert_eq!( Some(r#"{ "port" : 80 }"#), doc.text() );
+        assert_eq!( &options, doc.options() );
+
+        doc.discard_text();
+        assert_eq!( None, doc.text() );
+        assert_eq!( 4, JsonDocument::parse("input", "[1] x").unwrap_err().offset() );
+    }
+
+    #[test]
+    fn test_from_value()
+    {
+        let mut doc = JsonDocument::from_value("generated", JSON::JsNull);
+        *doc.root_mut() = JSON::JsBool(true);
+
+        assert_eq!( (None, Duration::ZERO), (doc.text(), doc.parse_time()) );
+        assert_eq!( JSON::JsBool(true), doc.into_root() );
+    }
+}
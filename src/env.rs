@@ -0,0 +1,139 @@
+//! Overriding the settings of a configuration with environment variables (ie. `APP__SERVER__PORT=8080`)
+
+/* (IMPORTS) */
+use std::collections::HashMap;
+
+use crate::query::infer_scalar;
+use crate::{try_parse, ArrayMerge, JsonPath, PathError, PathSegment, JSON};
+
+
+// Separates the prefix of a variable name and the keys of the path after it
+const SEPARATOR: &str = "__";
+
+impl JSON {
+
+    /// Deep merges the environment variables named with a prefix into a configuration, see `merge_env_vars`
+    ///
+    /// # Errors
+    /// Returns a `PathError` if a variable sets a value inside another variable's value (ie. `APP__A=1` and `APP__A__B=2`)
+    pub fn merge_env(&mut self, prefix: &str) -> Result<(), PathError>
+    {
+        //Variables whose name or value is not unicode can not be json, so they are skipped
+        let vars = std::env::vars_os().filter_map(|(name, value)| Some((name.into_string().ok()?, value.into_string().ok()?)));
+        self.merge_env_vars(vars, prefix)
+    }
+
+    /// Deep merges the variables named with a prefix (ie. "APP") into a configuration, each making a member of an overlay
+    /// whose path is the rest of the name split at double underscores (ie. `APP__SERVER__MAX_THREADS` sets `server.max_threads`).
+    /// Each key matches the first member of the configuration with the same name ignoring ascii case, or is lowercased if there is none.
+    ///
+    /// A value that is an array or object in json (ie. `["a","b"]`), a number or true, false or null is used as that, any other value is a string.
+    /// Arrays are replaced as a whole, as in `merge` with `ArrayMerge::Replace`.
+    ///
+    /// # Examples
+    /// ```
+    /// use Parser::try_parse;
+    ///
+    /// let mut config = try_parse(r#"{ "server" : { "host" : "localhost", "maxThreads" : 4 } }"#).unwrap();
+    /// let vars = [("APP__SERVER__MAXTHREADS", "16"), ("APP__SERVER__TLS", "true"), ("PATH", "/usr/bin")];
+    /// config.merge_env_vars(vars, "APP").unwrap();
+    ///
+    /// assert_eq!( try_parse(r#"{ "server" : { "host" : "localhost", "maxThreads" : 16, "tls" : true } }"#).unwrap(), config );
+    /// ```
+    ///
+    /// # Errors
+    /// Returns a `PathError` if a variable sets a value inside another variable's value (ie. `APP__A=1` and `APP__A__B=2`)
+    pub fn merge_env_vars<I, K, V>(&mut self, vars: I, prefix: &str) -> Result<(), PathError>
+    where
+        I: IntoIterator<Item = (K, V)>,
+        K: AsRef<str>,
+        V: AsRef<str>,
+    {
+        let start = format!("{prefix}{SEPARATOR}");
+        let mut matching: Vec<(String, JSON)> = vars.into_iter()
+            .filter_map(|(name, value)| Some((name.as_ref().strip_prefix(&start)?.to_string(), env_value(value.as_ref()))))
+            .collect();
+
+        //In order of name, so a variable is always set before the ones inside it, which then fail the same way every time
+        matching.sort_by(|a, b| a.0.cmp(&b.0));
+
+        let mut overlay = JSON::JsObject(HashMap::new());
+        for (name, value) in matching {
+            let names: Vec<&str> = name.split(SEPARATOR).collect();
+            if names.iter().any(|name| name.is_empty()) { continue; }
+
+            overlay.set_json_path(&self.env_path(&names), value)?;
+        }
+
+        self.merge(overlay, ArrayMerge::Replace);
+        Ok(())
+    }
+
+    // Returns the path of the keys of a variable name, each spelled as the member of the configuration it matches
+    fn env_path(&self, names: &[&str]) -> JsonPath
+    {
+        let mut path = JsonPath::root();
+        let mut current = Some(self);
+
+        for name in names {
+            let member = match current {
+                Some(JSON::JsObject(map)) => map.iter().find(|(key, _)| key.eq_ignore_ascii_case(name)),
+                _ => None,
+            };
+            let key = member.map_or_else(|| name.to_ascii_lowercase(), |(key, _)| key.clone());
+
+            current = member.map(|(_, value)| value);
+            path.push(PathSegment::Key(key));
+        }
+        path
+    }
+}
+
+// Converts the value of a variable, arrays & objects are written as in json and any other value is inferred as a query value is
+pub(crate) fn env_value(text: &str) -> JSON
+{
+    match try_parse(text) {
+        Ok(value @ (JSON::JsArray(_) | JSON::JsObject(_))) => value,
+        _ => infer_scalar(text),
+    }
+}
+
+
+/* (TESTS) */
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::json;
+
+    #[test]
+    fn test_merge_env_vars()
+    {
+        let mut config = json(r#"{ "db" : { "Host" : "localhost", "port" : 5432, "replicas" : ["a"] }, "debug" : false }"#);
+        let vars = [
+            ("APP__DB__HOST", "db.internal"),
+            ("APP__DB__PORT", "6543"),
+            ("APP__DB__REPLICAS", r#"["b", "c"]"#),
+            ("APP__DEBUG", "true"),
+            ("APP__LOG_LEVEL", "warn"),
+            ("APP__ZIP", "007"),
+            ("APP__", "skipped"),
+            ("APP__DB____X", "skipped"),
+            ("APPLICATION__DEBUG", "skipped"),
+        ];
+        config.merge_env_vars(vars, "APP").unwrap();
+
+        let expected = json(r#"{ "db" : { "Host" : "db.internal", "port" : 6543, "replicas" : ["b", "c"] }, "debug" : true,
+                                 "log_level" : "warn", "zip" : "007" }"#);
+        assert_eq!( expected, config );
+    }
+
+    #[test]
+    fn test_merge_env_conflict()
+    {
+        let mut config = json("{}");
+        let e = config.merge_env_vars([("APP__A__B", "2"), ("APP__A", "1")], "APP").unwrap_err();
+
+        assert!( matches!(e, PathError::NotAContainer(_)) );
+        assert_eq!( json("{}"), config );                 //Nothing is merged
+    }
+}
@@ -0,0 +1,158 @@
+//! Errors returned by the fallible parsing entry points
+
+/* (IMPORTS) */
+use std::fmt;
+
+use crate::{JsonPath, PathSegment};
+
+
+/// The kind of problem that stopped the parser
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParseErrorKind {
+    /// The input is not a valid json value at the error position
+    Syntax,
+    /// A complete json value was parsed but more (non whitespace) input follows it
+    TrailingCharacters,
+    /// Arrays and objects are nested deeper than the parser allows
+    TooDeep,
+    /// The parse was stopped by the cancellation token of its options
+    Cancelled,
+    /// The parse was still running at the deadline of its options
+    TimedOut,
+}
+
+/// Error returned by `try_parse`, with the position of the problem in the input
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    kind: ParseErrorKind,
+    offset: usize,
+    line: usize,
+    column: usize,
+    path: JsonPath,
+}
+
+impl ParseError {
+
+    // Creates an error at a byte offset into the input, working out the line & column from the input
+    pub(crate) fn new(kind: ParseErrorKind, input: &str, offset: usize) -> ParseError
+    {
+        let (line, column) = line_column(input, offset);
+        ParseError { kind, offset, line, column, path: JsonPath::root() }
+    }
+
+    // Creates an error at a position that has already been worked out, for input that is not all held in memory
+    pub(crate) fn at(kind: ParseErrorKind, offset: usize, line: usize, column: usize) -> ParseError
+    {
+        ParseError { kind, offset, line, column, path: JsonPath::root() }
+    }
+
+    // Sets the path of the value that the error is in
+    pub(crate) fn with_path(mut self, path: JsonPath) -> ParseError
+    {
+        self.path = path;
+        self
+    }
+
+    /// Returns the kind of problem
+    pub fn kind(&self) -> ParseErrorKind
+    {
+        self.kind
+    }
+
+    /// Returns the byte offset into the input at which the problem was found
+    pub fn offset(&self) -> usize
+    {
+        self.offset
+    }
+
+    /// Returns the line (starting at 1) at which the problem was found
+    pub fn line(&self) -> usize
+    {
+        self.line
+    }
+
+    /// Returns the column (in characters, starting at 1) at which the problem was found
+    pub fn column(&self) -> usize
+    {
+        self.column
+    }
+
+    /// Returns the path of the array element or object member that the problem is in (ie. `users[3].address.zip`),
+    /// which is the root path for a problem in the top level value or an input that is not held in memory
+    pub fn path(&self) -> &JsonPath
+    {
+        &self.path
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+    {
+        let message = match self.kind {
+            ParseErrorKind::Syntax => "invalid json",
+            ParseErrorKind::TrailingCharacters => "unexpected characters after the json value",
+            ParseErrorKind::TooDeep => "arrays and objects are nested too deeply",
+            ParseErrorKind::Cancelled => "parsing was cancelled",
+            ParseErrorKind::TimedOut => "parsing ran past its deadline",
+        };
+        write!(f, "{message} at line {} column {}", self.line, self.column)?;
+        write_path(f, &self.path)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+// Works out the line & column (in characters, both starting at 1) of a byte offset into the input
+pub(crate) fn line_column(input: &str, offset: usize) -> (usize, usize)
+{
+    let before = &input.as_bytes()[..offset.min(input.len())];
+    let line = 1 + before.iter().filter(|b| **b == b'\n').count();
+    let line_start = before.iter().rposition(|b| *b == b'\n').map_or(0, |i| i + 1);
+    let column = 1 + String::from_utf8_lossy(&before[line_start..]).chars().count();
+    (line, column)
+}
+
+// Writes the path of the value that a problem is in after its message, written as in JSONPath (ie. $.users[3].address.zip)
+pub(crate) fn write_path(f: &mut fmt::Formatter<'_>, path: &JsonPath) -> fmt::Result
+{
+    match path.segments().first() {
+        None => Ok(()),
+        Some(PathSegment::Index(_)) => write!(f, " (in ${path})"),
+        Some(PathSegment::Key(_)) => write!(f, " (in $.{path})"),
+    }
+}
+
+
+/* (TESTS) */
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_position()
+    {
+        let input = "{\n  \"é\" : x\n}";
+        let error = ParseError::new(ParseErrorKind::Syntax, input, input.find('x').unwrap());
+
+        assert_eq!( 2, error.line() );
+        assert_eq!( 9, error.column() );
+        assert_eq!( "invalid json at line 2 column 9", error.to_string() );
+        assert_eq!( 1, ParseError::new(ParseErrorKind::Syntax, input, 0).column() );
+    }
+
+    #[test]
+    fn test_path()
+    {
+        let input = "{ \"users\" : [{}, {}, {}, { \"address\" : { \"zip\" : x } }] }";
+        let error = crate::try_parse(input).unwrap_err();
+
+        assert_eq!( "users[3].address.zip", error.path().to_string() );
+        assert_eq!( "invalid json at line 1 column 50 (in $.users[3].address.zip)", error.to_string() );
+        assert_eq!( "invalid json at line 1 column 6 (in $[1])", crate::try_parse("[1,  x]").unwrap_err().to_string() );
+
+        //Problems with a key are in the object, after the last complete member are in the member
+        assert_eq!( "a", crate::try_parse(r#"{ "a" : { 1 } }"#).unwrap_err().path().to_string() );
+        assert_eq!( "a", crate::try_parse(r#"{ "a" : 1 2 }"#).unwrap_err().path().to_string() );
+        assert!( crate::try_parse("[1] 2").unwrap_err().path().is_root() );
+    }
+}
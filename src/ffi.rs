@@ -0,0 +1,140 @@
+//! C-compatible functions for embedding the parser (enabled with the `ffi` feature)
+//!
+//! A parsed document is handed out as an opaque pointer that must be released with `json_free`,
+//! and pointers returned by `json_pointer` borrow from it so they are only valid until the document is freed.
+//! Strings returned by `json_serialize` are owned by the caller and must be released with `json_string_free`.
+
+/* (IMPORTS) */
+use std::ffi::{c_char, CStr, CString};
+use std::ptr;
+
+use crate::{try_parse, JSON};
+
+
+// Borrows a C string as a &str, returns None if the pointer is null or the string is not valid UTF-8
+unsafe fn to_str<'a>(s: *const c_char) -> Option<&'a str>
+{
+    if s.is_null() { return None; }
+    CStr::from_ptr(s).to_str().ok()
+}
+
+/// Parses a null terminated UTF-8 string, returning a handle to the document or null if the input is not a single json value
+///
+/// # Safety
+/// `input` must be null or point to a null terminated string
+#[no_mangle]
+pub unsafe extern "C" fn json_parse(input: *const c_char) -> *mut JSON
+{
+    let Some(input) = to_str(input) else { return ptr::null_mut() };
+
+    //try_parse never panics, so nothing can unwind into the caller
+    match try_parse(input) {
+        Ok(value) => Box::into_raw(Box::new(value)),
+        Err(_) => ptr::null_mut()
+    }
+}
+
+/// Returns the value at a JSON Pointer (ie. "/servers/0/host") within a document, or null if there is no such value
+///
+/// The returned pointer borrows from the document, it must not be freed and is only valid until the document is freed.
+///
+/// # Safety
+/// `json` must be null or a live handle from `json_parse` (or from `json_pointer`), `pointer` must be null or point to a null terminated string
+#[no_mangle]
+pub unsafe extern "C" fn json_pointer(json: *const JSON, pointer: *const c_char) -> *const JSON
+{
+    let (Some(json), Some(pointer)) = (json.as_ref(), to_str(pointer)) else { return ptr::null() };
+
+    json.pointer(pointer).map_or(ptr::null(), |value| value as *const JSON)
+}
+
+/// Serializes a value as json text (compact, or indented by 2 spaces if pretty), returning null if the handle is null
+///
+/// # Safety
+/// `json` must be null or a live handle from `json_parse` or `json_pointer`, the result must be released with `json_string_free`
+#[no_mangle]
+pub unsafe extern "C" fn json_serialize(json: *const JSON, pretty: bool) -> *mut c_char
+{
+    let Some(json) = json.as_ref() else { return ptr::null_mut() };
+
+    let text = match pretty {
+        true => format!("{:#}", json.display_json()),
+        false => json.display_json().to_string(),
+    };
+
+    //Control characters are escaped, so the text never contains a nul byte
+    CString::new(text).map_or(ptr::null_mut(), CString::into_raw)
+}
+
+/// Releases a string returned by `json_serialize`, does nothing if the pointer is null
+///
+/// # Safety
+/// `s` must be null or a string from `json_serialize` that has not already been freed
+#[no_mangle]
+pub unsafe extern "C" fn json_string_free(s: *mut c_char)
+{
+    if !s.is_null() {
+        drop(CString::from_raw(s));
+    }
+}
+
+/// Releases a document returned by `json_parse`, does nothing if the handle is null
+///
+/// # Safety
+/// `json` must be null or a handle from `json_parse` that has not already been freed (never a pointer from `json_pointer`)
+#[no_mangle]
+pub unsafe extern "C" fn json_free(json: *mut JSON)
+{
+    if !json.is_null() {
+        drop(Box::from_raw(json));
+    }
+}
+
+
+/* (TESTS) */
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ffi_round_trip()
+    {
+        let input = CString::new(r#"{ "servers" : [{ "host" : "a" }, { "host" : "b", "port" : 80 }] }"#).unwrap();
+        let pointer = CString::new("/servers/1").unwrap();
+
+        unsafe {
+            let doc = json_parse(input.as_ptr());
+            assert!( !doc.is_null() );
+
+            let server = json_pointer(doc, pointer.as_ptr());
+            let text = json_serialize(server, false);
+            assert_eq!( r#"{"host":"b","port":80}"#, CStr::from_ptr(text).to_str().unwrap() );
+
+            json_string_free(text);
+            json_free(doc);
+        }
+    }
+
+    #[test]
+    fn test_ffi_errors()
+    {
+        let invalid = CString::new("[1, 2").unwrap();
+        let trailing = CString::new("[1] 2").unwrap();
+        let missing = CString::new("/nope").unwrap();
+
+        unsafe {
+            assert!( json_parse(ptr::null()).is_null() );
+            assert!( json_parse(invalid.as_ptr()).is_null() );
+            assert!( json_parse(trailing.as_ptr()).is_null() );
+
+            let doc = json_parse(CString::new("{}").unwrap().as_ptr());
+            assert!( json_pointer(doc, missing.as_ptr()).is_null() );
+            assert!( json_pointer(ptr::null(), missing.as_ptr()).is_null() );
+            assert!( json_serialize(ptr::null(), true).is_null() );
+
+            json_free(doc);
+            json_free(ptr::null_mut());
+            json_string_free(ptr::null_mut());
+        }
+    }
+}
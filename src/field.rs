@@ -0,0 +1,144 @@
+//! Telling an object field that is explicitly null apart from one that is absent, as PATCH style updates need to
+//! (ie. `{ "email" : null }` clears the email, while `{}` leaves it as it was)
+
+/* (IMPORTS) */
+use crate::JSON;
+
+
+/// The state of a field of an object: present with a value, present but explicitly null, or absent
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum FieldState<T> {
+    /// The field has a value other than null
+    Present(T),
+    /// The field is set to null
+    Null,
+    /// The field is not in the object
+    #[default]
+    Absent,
+}
+
+impl<T> FieldState<T> {
+
+    /// Returns true if the field has a value other than null
+    pub fn is_present(&self) -> bool
+    {
+        matches!(self, FieldState::Present(_))
+    }
+
+    /// Returns true if the field is set to null
+    pub fn is_null(&self) -> bool
+    {
+        matches!(self, FieldState::Null)
+    }
+
+    /// Returns true if the field is not in the object
+    pub fn is_absent(&self) -> bool
+    {
+        matches!(self, FieldState::Absent)
+    }
+
+    /// Returns the value of the field, or None if it is null or absent
+    pub fn present(self) -> Option<T>
+    {
+        match self {
+            FieldState::Present(value) => Some(value),
+            _ => None
+        }
+    }
+
+    /// Returns the state with a reference to the value
+    pub fn as_ref(&self) -> FieldState<&T>
+    {
+        match self {
+            FieldState::Present(value) => FieldState::Present(value),
+            FieldState::Null => FieldState::Null,
+            FieldState::Absent => FieldState::Absent,
+        }
+    }
+
+    /// Returns the state with a function applied to the value, keeping null and absent as they are
+    pub fn map<U, F: FnOnce(T) -> U>(self, f: F) -> FieldState<U>
+    {
+        match self {
+            FieldState::Present(value) => FieldState::Present(f(value)),
+            FieldState::Null => FieldState::Null,
+            FieldState::Absent => FieldState::Absent,
+        }
+    }
+
+    /// Applies the field to an optional value as a PATCH would: a present value replaces it, null clears it
+    /// and an absent field leaves it as it was. Returns true if the value was changed.
+    pub fn patch(self, target: &mut Option<T>) -> bool
+    {
+        match self {
+            FieldState::Present(value) => *target = Some(value),
+            FieldState::Null => *target = None,
+            FieldState::Absent => return false,
+        }
+        true
+    }
+}
+
+impl JSON {
+
+    /// Returns the state of a field of an object, which is absent if the value is not an object
+    ///
+    /// # Examples
+    /// ```
+    /// use Parser::{try_parse, FieldState};
+    ///
+    /// let patch = try_parse(r#"{ "name" : "Ada", "email" : null }"#).unwrap();
+    /// assert_eq!( FieldState::Present(&try_parse(r#""Ada""#).unwrap()), patch.get_field("name") );
+    /// assert_eq!( FieldState::Null, patch.get_field("email") );
+    /// assert_eq!( FieldState::Absent, patch.get_field("phone") );
+    /// ```
+    pub fn get_field(&self, key: &str) -> FieldState<&JSON>
+    {
+        match self {
+            JSON::JsObject(map) => match map.get(key) {
+                Some(JSON::JsNull) => FieldState::Null,
+                Some(value) => FieldState::Present(value),
+                None => FieldState::Absent,
+            },
+            _ => FieldState::Absent
+        }
+    }
+}
+
+
+/* (TESTS) */
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{decode, json};
+
+    #[test]
+    fn test_get_field()
+    {
+        let value = json(r#"{ "a" : 1, "b" : null, "c" : false }"#);
+
+        assert_eq!( FieldState::Present(&json("1")), value.get_field("a") );
+        assert_eq!( FieldState::Null, value.get_field("b") );
+        assert_eq!( FieldState::Present(&JSON::JsBool(false)), value.get_field("c") );
+        assert_eq!( FieldState::Absent, value.get_field("d") );
+        assert_eq!( FieldState::Absent, json("[null]").get_field("0") );
+
+        assert_eq!( Some(1), value.get_field("a").present().and_then(JSON::as_i64) );
+        assert_eq!( FieldState::Present(Some(1)), value.get_field("a").map(JSON::as_i64) );
+    }
+
+    #[test]
+    fn test_patch()
+    {
+        let mut email = Some("ada@example.com".to_string());
+        assert!( !FieldState::Absent.patch(&mut email) );
+        assert_eq!( Some("ada@example.com".to_string()), email );
+        assert!( FieldState::Present("ada@example.org".to_string()).patch(&mut email) );
+        assert_eq!( Some("ada@example.org".to_string()), email );
+        assert!( FieldState::Null.patch(&mut email) );
+        assert_eq!( None, email );
+
+        let decoded = decode::<Vec<FieldState<String>>>(r#"[null, "x"]"#);
+        assert_eq!( Ok(vec![FieldState::Null, FieldState::Present("x".to_string())]), decoded );
+    }
+}
@@ -0,0 +1,802 @@
+//! A small jq-like language of filters over json values (ie. `.items[] | select(.price > 10) | {name, price}`)
+//!
+//! A filter takes a value and produces any number of values:
+//! + `.` is the value itself, `..` is the value and every value inside of it
+//! + `.name`, `."name"` and `.[expr]` are a member of an object or an element of an array (negative indexes count from the end), null if there is none
+//! + `.[]` is every element of an array or member of an object, `a | b` runs b on each value of a and `a, b` is the values of a then of b
+//! + Literals (ie. `1`, `"text"`, `true`, `null`), arrays `[expr]` of every value of expr and objects `{name: expr, "key": expr, (expr): expr, name}`
+//! + `+ - * / %` (`+` also joins strings, arrays & objects), `== != < <= > >=` (ordered as `total_cmp` orders values), `and`, `or`
+//! + The functions `select(f)`, `map(f)`, `has(key)`, `length`, `keys`, `type`, `not`, `empty`, `add` and `sort`
+//!
+//! Members of objects are visited in order of key, so the output is the same every time.
+
+/* (IMPORTS) */
+use std::cell::Cell;
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::combinator::{alt, comma_separated, complete, delimited, opt, preceded, str_parser, terminated, token, value, ws0, Parser};
+use crate::{number_literal, string_literal, Number, JSON};
+
+
+/// A parsed filter, which can be run on any number of values
+#[derive(Debug, Clone)]
+pub struct Filter {
+    expr: Expr,
+}
+
+/// Error returned when a filter is malformed or can not be run on a value
+#[derive(Debug, Clone, PartialEq)]
+pub enum FilterError {
+    /// The filter is not valid filter syntax, along with the byte offset of the problem
+    Syntax(usize),
+    /// Running the filter failed (ie. iterating over a number), along with a description of the problem
+    Eval(String),
+    /// The filter nests brackets or chains operators more deeply than the limit, along with the byte offset at which it was reached
+    TooDeep(usize),
+}
+
+impl fmt::Display for FilterError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+    {
+        match self {
+            FilterError::Syntax(offset) => write!(f, "invalid filter at offset {offset}"),
+            FilterError::Eval(message) => write!(f, "{message}"),
+            FilterError::TooDeep(offset) => write!(f, "filter is nested too deeply at offset {offset}"),
+        }
+    }
+}
+
+impl std::error::Error for FilterError {}
+
+impl Filter {
+
+    /// Parses a filter (ie. ".users[] | .name")
+    ///
+    /// # Errors
+    /// Returns `FilterError::Syntax` with the offset at which the filter stops making sense,
+    /// or `FilterError::TooDeep` if it nests more deeply than the limit
+    pub fn parse(filter: &str) -> Result<Filter, FilterError>
+    {
+        DEPTH.with(|depth| depth.set(Depth { current: 0, peak: 0, stopped: None }));
+        let parsed = complete(pipe).parse(filter);
+
+        match (parsed, DEPTH.with(|depth| depth.get().stopped)) {
+            (Ok((_, expr)), _) => Ok(Filter { expr }),
+            (Err(_), Some(rest)) => Err(FilterError::TooDeep(filter.len() - rest)),
+            (Err(rest), None) => Err(FilterError::Syntax(filter.len() - rest.len())),
+        }
+    }
+
+    /// Runs the filter on a value, returning each value it produces in order
+    ///
+    /// # Examples
+    /// ```
+    /// use Parser::filter::Filter;
+    /// use Parser::try_parse;
+    ///
+    /// let value = try_parse(r#"{ "items" : [{ "name" : "tea", "price" : 4 }, { "name" : "cake", "price" : 12, "vegan" : false }] }"#).unwrap();
+    /// let filter = Filter::parse(".items[] | select(.price > 10) | {name, price}").unwrap();
+    ///
+    /// assert_eq!( vec![try_parse(r#"{ "name" : "cake", "price" : 12 }"#).unwrap()], filter.apply(&value).unwrap() );
+    /// ```
+    ///
+    /// # Errors
+    /// Returns `FilterError::Eval` if a step of the filter does not apply to its value (ie. `.[]` of a number or `"a" - 1`)
+    pub fn apply(&self, value: &JSON) -> Result<Vec<JSON>, FilterError>
+    {
+        self.expr.eval(value)
+    }
+}
+
+impl JSON {
+
+    /// Parses a filter and runs it on the value, see `Filter`
+    ///
+    /// # Errors
+    /// Returns a `FilterError` if the filter is malformed or can not be run on the value
+    pub fn filter(&self, filter: &str) -> Result<Vec<JSON>, FilterError>
+    {
+        Filter::parse(filter)?.apply(self)
+    }
+}
+
+
+/* (EXPRESSIONS) */
+
+// A node of a parsed filter
+#[derive(Debug, Clone)]
+enum Expr {
+    Identity,
+    Recurse,
+    Literal(JSON),
+    Field(Box<Expr>, String),
+    Index(Box<Expr>, Box<Expr>),
+    Iterate(Box<Expr>),
+    Pipe(Box<Expr>, Box<Expr>),
+    Comma(Box<Expr>, Box<Expr>),
+    Binary(Box<Expr>, Op, Box<Expr>),
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    Array(Option<Box<Expr>>),
+    Object(Vec<(Expr, Expr)>),
+    Select(Box<Expr>),
+    Has(Box<Expr>),
+    Builtin(Builtin),
+}
+
+// Arithmetic and comparison operators
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Op {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Mod,
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+// Functions without arguments
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Builtin {
+    Length,
+    Keys,
+    Type,
+    Not,
+    Empty,
+    Add,
+    Sort,
+}
+
+impl Expr {
+
+    // Returns every value the expression produces for an input value
+    fn eval(&self, input: &JSON) -> Result<Vec<JSON>, FilterError>
+    {
+        let mut outputs = vec![];
+
+        match self {
+            Expr::Identity => outputs.push(input.clone()),
+            Expr::Recurse => recurse(input, &mut outputs),
+            Expr::Literal(value) => outputs.push(value.clone()),
+            Expr::Field(target, key) => {
+                for value in target.eval(input)? {
+                    outputs.push(index(&value, &JSON::JsString(key.clone()))?);
+                }
+            }
+            Expr::Index(target, key) => {
+                //The index is computed from the same input as the value it indexes (ie. `.values[.i]`)
+                let keys = key.eval(input)?;
+                for value in target.eval(input)? {
+                    for key in &keys {
+                        outputs.push(index(&value, key)?);
+                    }
+                }
+            }
+            Expr::Iterate(target) => {
+                for value in target.eval(input)? {
+                    outputs.extend(elements(value)?);
+                }
+            }
+            Expr::Pipe(left, right) => {
+                for value in left.eval(input)? {
+                    outputs.extend(right.eval(&value)?);
+                }
+            }
+            Expr::Comma(left, right) => {
+                outputs.extend(left.eval(input)?);
+                outputs.extend(right.eval(input)?);
+            }
+            Expr::Binary(left, op, right) => {
+                let lefts = left.eval(input)?;
+                for r in right.eval(input)? {
+                    for l in &lefts {
+                        outputs.push(binary(*op, l, &r)?);
+                    }
+                }
+            }
+            Expr::And(left, right) | Expr::Or(left, right) => {
+                let and = matches!(self, Expr::And(..));
+                for l in left.eval(input)? {
+                    //The right side is only run if the left side does not decide the result
+                    if truthy(&l) != and {
+                        outputs.push(JSON::JsBool(!and));
+                        continue;
+                    }
+                    for r in right.eval(input)? {
+                        outputs.push(JSON::JsBool(truthy(&r)));
+                    }
+                }
+            }
+            Expr::Array(None) => outputs.push(JSON::JsArray(vec![])),
+            Expr::Array(Some(elements)) => outputs.push(JSON::JsArray(elements.eval(input)?)),
+            Expr::Object(entries) => {
+                //Each combination of the keys & values of the entries makes an object
+                let mut objects = vec![HashMap::new()];
+                for (key, member) in entries {
+                    let keys = key.eval(input)?;
+                    let members = member.eval(input)?;
+
+                    let mut combined = vec![];
+                    for object in &objects {
+                        for key in &keys {
+                            let JSON::JsString(key) = key else {
+                                return Err(FilterError::Eval(format!("object keys must be strings, not {}", type_name(key))));
+                            };
+                            for member in &members {
+                                let mut object = object.clone();
+                                object.insert(key.clone(), member.clone());
+                                combined.push(object);
+                            }
+                        }
+                    }
+                    objects = combined;
+                }
+                outputs.extend(objects.into_iter().map(JSON::JsObject));
+            }
+            Expr::Select(condition) => {
+                for value in condition.eval(input)? {
+                    if truthy(&value) { outputs.push(input.clone()); }
+                }
+            }
+            Expr::Has(key) => {
+                for key in key.eval(input)? {
+                    let has = match (input, &key) {
+                        (JSON::JsObject(map), JSON::JsString(key)) => map.contains_key(key),
+                        (JSON::JsArray(vec), JSON::JsNumber(n)) => n.as_f64() >= 0.0 && n.as_f64() < vec.len() as f64,
+                        _ => return Err(FilterError::Eval(format!("cannot check whether {} has a {} key", type_name(input), type_name(&key)))),
+                    };
+                    outputs.push(JSON::JsBool(has));
+                }
+            }
+            Expr::Builtin(Builtin::Empty) => {}
+            Expr::Builtin(builtin) => outputs.push(call(*builtin, input)?),
+        }
+
+        Ok(outputs)
+    }
+}
+
+// Appends a value and every value inside of it, each before the values inside of it
+fn recurse(value: &JSON, outputs: &mut Vec<JSON>)
+{
+    outputs.push(value.clone());
+    match value {
+        JSON::JsArray(vec) => vec.iter().for_each(|element| recurse(element, outputs)),
+        JSON::JsObject(_) => sorted_members(value).into_iter().for_each(|(_, member)| recurse(member, outputs)),
+        _ => {}
+    }
+}
+
+// Returns the members of an object in order of key
+fn sorted_members(value: &JSON) -> Vec<(&String, &JSON)>
+{
+    let JSON::JsObject(map) = value else { return vec![]; };
+    let mut members: Vec<(&String, &JSON)> = map.iter().collect();
+    members.sort_by(|a, b| a.0.cmp(b.0));
+    members
+}
+
+// Returns the member of an object or the element of an array at a key, null if there is none
+fn index(value: &JSON, key: &JSON) -> Result<JSON, FilterError>
+{
+    match (value, key) {
+        (JSON::JsObject(map), JSON::JsString(key)) => Ok(map.get(key).cloned().unwrap_or_default()),
+        (JSON::JsArray(vec), JSON::JsNumber(n)) => {
+            let i = n.as_f64().floor();
+            let i = if i < 0.0 { i + vec.len() as f64 } else { i };
+            Ok(if i < 0.0 { JSON::JsNull } else { vec.get(i as usize).cloned().unwrap_or_default() })
+        }
+        (JSON::JsNull, JSON::JsString(_) | JSON::JsNumber(_)) => Ok(JSON::JsNull),
+        _ => Err(FilterError::Eval(format!("cannot index {} with {}", type_name(value), type_name(key)))),
+    }
+}
+
+// Returns the elements of an array or the members of an object in order of key
+fn elements(value: JSON) -> Result<Vec<JSON>, FilterError>
+{
+    match value {
+        JSON::JsArray(vec) => Ok(vec),
+        JSON::JsObject(_) => Ok(sorted_members(&value).into_iter().map(|(_, member)| member.clone()).collect()),
+        _ => Err(FilterError::Eval(format!("cannot iterate over {}", type_name(&value)))),
+    }
+}
+
+// Applies an operator to two values
+fn binary(op: Op, l: &JSON, r: &JSON) -> Result<JSON, FilterError>
+{
+    let compared = || l.total_cmp(r);
+    let result = match (op, l, r) {
+        (Op::Eq, _, _) => JSON::JsBool(compared() == Ordering::Equal),
+        (Op::Ne, _, _) => JSON::JsBool(compared() != Ordering::Equal),
+        (Op::Lt, _, _) => JSON::JsBool(compared() == Ordering::Less),
+        (Op::Le, _, _) => JSON::JsBool(compared() != Ordering::Greater),
+        (Op::Gt, _, _) => JSON::JsBool(compared() == Ordering::Greater),
+        (Op::Ge, _, _) => JSON::JsBool(compared() != Ordering::Less),
+        (_, JSON::JsNumber(a), JSON::JsNumber(b)) => JSON::JsNumber(arithmetic(op, a, b)?),
+        (Op::Add, JSON::JsNull, value) | (Op::Add, value, JSON::JsNull) => value.clone(),
+        (Op::Add, JSON::JsString(a), JSON::JsString(b)) => JSON::JsString(format!("{a}{b}")),
+        (Op::Add, JSON::JsArray(a), JSON::JsArray(b)) => JSON::JsArray(a.iter().chain(b).cloned().collect()),
+        (Op::Add, JSON::JsObject(a), JSON::JsObject(b)) => JSON::JsObject(a.iter().chain(b).map(|(k, v)| (k.clone(), v.clone())).collect()),
+        (Op::Sub, JSON::JsArray(a), JSON::JsArray(b)) => JSON::JsArray(a.iter().filter(|element| !b.contains(element)).cloned().collect()),
+        _ => return Err(FilterError::Eval(format!("cannot use {op:?} on {} and {}", type_name(l), type_name(r)))),
+    };
+    Ok(result)
+}
+
+// Applies an arithmetic operator to two numbers, exactly if both are integers and the result is one
+fn arithmetic(op: Op, a: &Number, b: &Number) -> Result<Number, FilterError>
+{
+    if let (Some(x), Some(y)) = (a.as_i64(), b.as_i64()) {
+        let exact = match op {
+            Op::Add => x.checked_add(y),
+            Op::Sub => x.checked_sub(y),
+            Op::Mul => x.checked_mul(y),
+            Op::Div | Op::Mod if y == 0 => return Err(FilterError::Eval(format!("cannot divide {x} by zero"))),
+            Op::Div if x.checked_rem(y) == Some(0) => x.checked_div(y),
+            Op::Mod => x.checked_rem(y),
+            _ => None,
+        };
+        if let Some(n) = exact { return Ok(Number::from(n)); }
+    }
+
+    let (x, y) = (a.as_f64(), b.as_f64());
+    let result = match op {
+        Op::Add => x + y,
+        Op::Sub => x - y,
+        Op::Mul => x * y,
+        Op::Div => x / y,
+        _ => x % y,
+    };
+    Number::from_f64(result).ok_or_else(|| FilterError::Eval(format!("{op:?} of {a} and {b} is not a finite number")))
+}
+
+// Runs a function without arguments on a value
+fn call(builtin: Builtin, input: &JSON) -> Result<JSON, FilterError>
+{
+    let unsupported = || Err(FilterError::Eval(format!("cannot use {builtin:?} on {}", type_name(input))));
+
+    let result = match (builtin, input) {
+        (Builtin::Length, JSON::JsNull) => JSON::JsNumber(Number::from(0_u64)),
+        (Builtin::Length, JSON::JsString(s)) => JSON::JsNumber(Number::from(s.chars().count() as u64)),
+        (Builtin::Length, JSON::JsArray(vec)) => JSON::JsNumber(Number::from(vec.len() as u64)),
+        (Builtin::Length, JSON::JsObject(map)) => JSON::JsNumber(Number::from(map.len() as u64)),
+        (Builtin::Length, JSON::JsNumber(n)) => match n.as_i64() {
+            Some(i) => JSON::JsNumber(Number::from(i.unsigned_abs())),
+            None => Number::from_f64(n.as_f64().abs()).map_or(JSON::JsNull, JSON::JsNumber),
+        },
+        (Builtin::Keys, JSON::JsObject(_)) => JSON::JsArray(sorted_members(input).into_iter().map(|(key, _)| JSON::JsString(key.clone())).collect()),
+        (Builtin::Keys, JSON::JsArray(vec)) => JSON::JsArray((0..vec.len() as u64).map(|i| JSON::JsNumber(Number::from(i))).collect()),
+        (Builtin::Type, _) => JSON::JsString(type_name(input).to_string()),
+        (Builtin::Not, _) => JSON::JsBool(!truthy(input)),
+        (Builtin::Add, JSON::JsArray(vec)) => vec.iter().try_fold(JSON::JsNull, |sum, element| binary(Op::Add, &sum, element))?,
+        (Builtin::Sort, JSON::JsArray(_)) => {
+            let mut sorted = input.clone();
+            sorted.sort();
+            sorted
+        }
+        _ => return unsupported(),
+    };
+    Ok(result)
+}
+
+// Tells whether a value counts as true, which is anything but false and null
+fn truthy(value: &JSON) -> bool
+{
+    !matches!(value, JSON::JsNull | JSON::JsBool(false))
+}
+
+fn type_name(value: &JSON) -> &'static str
+{
+    match value {
+        JSON::JsNull => "null",
+        JSON::JsBool(_) => "boolean",
+        JSON::JsNumber(_) => "number",
+        JSON::JsString(_) => "string",
+        JSON::JsArray(_) => "array",
+        JSON::JsObject(_) => "object",
+    }
+}
+
+
+/* (NESTING LIMIT) */
+
+// Deepest a filter may nest, counting each bracket and each operator of a chain (ie. `1 + 2 + 3` is two levels), deeper filters fail
+// to parse rather than overflowing the stack when they are parsed, run or dropped. Lower than the json limit, as a level of a filter
+// takes far more stack to parse than a level of json.
+const MAX_DEPTH: usize = 64;
+
+// Nesting of the filter currently being parsed on a thread
+#[derive(Clone, Copy)]
+struct Depth {
+    current: usize,             //Brackets around what is being parsed
+    peak: usize,                //Deepest level reached by what has been parsed, with the operands of a chain below its operators
+    stopped: Option<usize>,     //Length of the rest of the filter where the limit was reached, if it was
+}
+
+thread_local! {
+    static DEPTH: Cell<Depth> = const { Cell::new(Depth { current: 0, peak: 0, stopped: None }) };
+}
+
+// Held while parsing a term, leaving its level when dropped
+struct DepthGuard(usize);
+
+impl DepthGuard {
+
+    // Enters a level of nesting, returns None if it is past the limit
+    fn enter(input: &str) -> Option<DepthGuard>
+    {
+        let level = DEPTH.with(|depth| depth.get().current);
+        reach(input, 1).then(|| {
+            DEPTH.with(|depth| depth.set(Depth { current: level + 1, ..depth.get() }));
+            DepthGuard(level)
+        })
+    }
+}
+
+impl Drop for DepthGuard {
+    fn drop(&mut self)
+    {
+        DEPTH.with(|depth| depth.set(Depth { current: self.0, ..depth.get() }));
+    }
+}
+
+// Records that what is being parsed nests a number of levels inside the current brackets,
+// returns false (and records where) if that is past the limit
+fn reach(input: &str, levels: usize) -> bool
+{
+    DEPTH.with(|depth| {
+        let mut d = depth.get();
+        if d.current + levels > MAX_DEPTH {
+            d.stopped = Some(input.len());
+            depth.set(d);
+            return false;
+        }
+        d.peak = d.peak.max(d.current + levels);
+        depth.set(d);
+        true
+    })
+}
+
+// Runs a parser, returning what it parsed along with how many levels it nests inside the current brackets
+fn measured<'a>(input: &'a str, parser: impl Parser<&'a str, Expr>) -> Result<(&'a str, Expr, usize), &'a str>
+{
+    let (level, outer) = DEPTH.with(|depth| {
+        let d = depth.get();
+        depth.set(Depth { peak: d.current, ..d });
+        (d.current, d.peak)
+    });
+
+    let parsed = parser.parse(input);
+
+    let peak = DEPTH.with(|depth| {
+        let d = depth.get();
+        depth.set(Depth { peak: d.peak.max(outer), ..d });
+        d.peak
+    });
+    parsed.map(|(rest, expr)| (rest, expr, peak - level))
+}
+
+
+/* (FILTER PARSERS) */
+
+// Parser for a symbol, after any whitespace
+fn symbol<'a>(s: &'static str) -> impl Parser<&'a str, &'a str>
+{
+    token(str_parser(s))
+}
+
+// Parser for a keyword (ie. "and"), which can not be the start of a longer name
+fn keyword<'a>(word: &'static str) -> impl Parser<&'a str, &'a str>
+{
+    move |input: &'a str| match symbol(word).parse(input)? {
+        (rest, _) if rest.starts_with(|ch: char| ch.is_ascii_alphanumeric() || ch == '_') => Err(input),
+        matched => Ok(matched),
+    }
+}
+
+// Parses a chain of operands joined by a left-associative operator (ie. "1 - 2 - 3")
+fn left_assoc<'a, O>(input: &'a str, operand: fn(&'a str) -> Result<(&'a str, Expr), &'a str>, operator: impl Parser<&'a str, O>,
+                     combine: impl Fn(Expr, O, Expr) -> Expr) -> Result<(&'a str, Expr), &'a str>
+{
+    let (mut rest, mut expr, mut levels) = measured(input, operand)?;
+
+    while let Ok((after, op)) = operator.parse(rest) {
+        let (next, right, right_levels) = measured(after, operand)?;
+        levels = levels.max(right_levels) + 1;
+        if !reach(rest, levels) { return Err(rest); }
+        expr = combine(expr, op, right);
+        rest = next;
+    }
+    Ok((rest, expr))
+}
+
+// Parser for filters joined by pipes, the lowest precedence
+fn pipe<'a>(input: &'a str) -> Result<(&'a str, Expr), &'a str>
+{
+    left_assoc(input, comma, symbol("|"), |l, _, r| Expr::Pipe(Box::new(l), Box::new(r)))
+}
+
+fn comma<'a>(input: &'a str) -> Result<(&'a str, Expr), &'a str>
+{
+    left_assoc(input, disjunction, symbol(","), |l, _, r| Expr::Comma(Box::new(l), Box::new(r)))
+}
+
+fn disjunction<'a>(input: &'a str) -> Result<(&'a str, Expr), &'a str>
+{
+    left_assoc(input, conjunction, keyword("or"), |l, _, r| Expr::Or(Box::new(l), Box::new(r)))
+}
+
+fn conjunction<'a>(input: &'a str) -> Result<(&'a str, Expr), &'a str>
+{
+    left_assoc(input, comparison, keyword("and"), |l, _, r| Expr::And(Box::new(l), Box::new(r)))
+}
+
+// Parser for a comparison, which does not chain (ie. "1 < 2 < 3" is invalid)
+fn comparison<'a>(input: &'a str) -> Result<(&'a str, Expr), &'a str>
+{
+    let operator = alt((value(Op::Eq, symbol("==")), value(Op::Ne, symbol("!=")), value(Op::Le, symbol("<=")),
+                        value(Op::Ge, symbol(">=")), value(Op::Lt, symbol("<")), value(Op::Gt, symbol(">"))));
+
+    let (rest, left, levels) = measured(input, additive)?;
+    match operator.parse(rest) {
+        Ok((after, op)) => {
+            let (next, right, right_levels) = measured(after, additive)?;
+            if !reach(rest, levels.max(right_levels) + 1) { return Err(rest); }
+            Ok((next, Expr::Binary(Box::new(left), op, Box::new(right))))
+        }
+        Err(_) => Ok((rest, left)),
+    }
+}
+
+fn additive<'a>(input: &'a str) -> Result<(&'a str, Expr), &'a str>
+{
+    let operator = alt((value(Op::Add, symbol("+")), value(Op::Sub, symbol("-"))));
+    left_assoc(input, multiplicative, operator, |l, op, r| Expr::Binary(Box::new(l), op, Box::new(r)))
+}
+
+fn multiplicative<'a>(input: &'a str) -> Result<(&'a str, Expr), &'a str>
+{
+    let operator = alt((value(Op::Mul, symbol("*")), value(Op::Div, symbol("/")), value(Op::Mod, symbol("%"))));
+    left_assoc(input, postfix, operator, |l, op, r| Expr::Binary(Box::new(l), op, Box::new(r)))
+}
+
+// Parser for a term followed by any number of field accesses, indexes and iterations (ie. `.items[0].name` or `.[]`)
+fn postfix<'a>(input: &'a str) -> Result<(&'a str, Expr), &'a str>
+{
+    let (mut rest, mut expr, mut levels) = measured(input, primary)?;
+
+    loop {
+        let at = rest;
+        if let Ok((next, key)) = preceded(str_parser("."), field_key).parse(rest) {
+            expr = Expr::Field(Box::new(expr), key);
+            rest = next;
+            levels += 1;
+        } else if let Ok((after, _)) = preceded(opt(str_parser(".")), str_parser("[")).parse(rest) {
+            expr = match symbol("]").parse(after) {
+                Ok((next, _)) => { rest = next; levels += 1; Expr::Iterate(Box::new(expr)) }
+                Err(_) => {
+                    let (next, key, key_levels) = measured(after, terminated(pipe, symbol("]")))?;
+                    rest = next;
+                    levels = levels.max(key_levels) + 1;
+                    Expr::Index(Box::new(expr), Box::new(key))
+                }
+            };
+        } else {
+            return Ok((rest, expr));
+        }
+        if !reach(at, levels) { return Err(at); }
+    }
+}
+
+// Parser for a term, after any whitespace
+fn primary<'a>(input: &'a str) -> Result<(&'a str, Expr), &'a str>
+{
+    let input = ws0(input).map_or(input, |(rest, _)| rest);
+    let _guard = DepthGuard::enter(input).ok_or(input)?;
+
+    alt((
+        value(Expr::Recurse, str_parser("..")),
+        |i: &'a str| preceded(str_parser("."), field_key).parse(i).map(|(rest, key)| (rest, Expr::Field(Box::new(Expr::Identity), key))),
+        value(Expr::Identity, str_parser(".")),
+        literal,
+        delimited(str_parser("("), pipe, symbol(")")),
+        array,
+        object,
+        call_expr,
+    )).parse(input)
+}
+
+// Parser for a name (ie. of a field or function)
+fn identifier<'a>(input: &'a str) -> Result<(&'a str, &'a str), &'a str>
+{
+    let len = input.find(|ch: char| !(ch.is_ascii_alphanumeric() || ch == '_')).unwrap_or(input.len());
+    match input.chars().next() {
+        Some(first) if first.is_ascii_alphabetic() || first == '_' => Ok((&input[len..], &input[..len])),
+        _ => Err(input),
+    }
+}
+
+// Parser for the key of a field, a name or a string literal
+fn field_key<'a>(input: &'a str) -> Result<(&'a str, String), &'a str>
+{
+    match identifier(input) {
+        Ok((rest, name)) => Ok((rest, name.to_string())),
+        Err(_) => string_literal(input),
+    }
+}
+
+// Parser for a number or string literal
+fn literal<'a>(input: &'a str) -> Result<(&'a str, Expr), &'a str>
+{
+    if let Ok((rest, s)) = string_literal(input) {
+        return Ok((rest, Expr::Literal(JSON::JsString(s))));
+    }
+    let (rest, literal) = number_literal(input)?;
+    let n = Number::from_literal(literal).ok_or(input)?;
+    Ok((rest, Expr::Literal(JSON::JsNumber(n))))
+}
+
+// Parser for an array of every value of a filter, or an empty array
+fn array<'a>(input: &'a str) -> Result<(&'a str, Expr), &'a str>
+{
+    let (rest, _) = str_parser("[").parse(input)?;
+    match symbol("]").parse(rest) {
+        Ok((next, _)) => Ok((next, Expr::Array(None))),
+        Err(_) => terminated(pipe, symbol("]")).parse(rest).map(|(next, elements)| (next, Expr::Array(Some(Box::new(elements))))),
+    }
+}
+
+// Parser for an object construction (ie. `{name, "id": .key, (.k): .v}`)
+fn object<'a>(input: &'a str) -> Result<(&'a str, Expr), &'a str>
+{
+    let (rest, _) = str_parser("{").parse(input)?;
+    comma_separated(object_entry, "}").parse(rest).map(|(next, entries)| (next, Expr::Object(entries)))
+}
+
+// Parser for an entry of an object construction, a key without a value takes the field of that name
+fn object_entry<'a>(input: &'a str) -> Result<(&'a str, (Expr, Expr)), &'a str>
+{
+    let (rest, key) = match delimited(symbol("("), pipe, symbol(")")).parse(input) {
+        Ok((rest, computed)) => (rest, Err(computed)),
+        Err(_) => token(field_key).parse(input).map(|(rest, key)| (rest, Ok(key)))?,
+    };
+
+    match (symbol(":").parse(rest), key) {
+        (Ok((after, _)), key) => {
+            let (next, member) = disjunction(after)?;
+            Ok((next, (key.map_or_else(|computed| computed, |key| Expr::Literal(JSON::JsString(key))), member)))
+        }
+        (Err(_), Ok(key)) => Ok((rest, (Expr::Literal(JSON::JsString(key.clone())), Expr::Field(Box::new(Expr::Identity), key)))),
+        (Err(_), Err(_)) => Err(rest),
+    }
+}
+
+// Parser for the literals true, false & null and calls of functions (ie. `length` or `select(.a)`)
+fn call_expr<'a>(input: &'a str) -> Result<(&'a str, Expr), &'a str>
+{
+    let (rest, name) = identifier(input)?;
+
+    let expr = match name {
+        "true" => Expr::Literal(JSON::JsBool(true)),
+        "false" => Expr::Literal(JSON::JsBool(false)),
+        "null" => Expr::Literal(JSON::JsNull),
+        "length" => Expr::Builtin(Builtin::Length),
+        "keys" => Expr::Builtin(Builtin::Keys),
+        "type" => Expr::Builtin(Builtin::Type),
+        "not" => Expr::Builtin(Builtin::Not),
+        "empty" => Expr::Builtin(Builtin::Empty),
+        "add" => Expr::Builtin(Builtin::Add),
+        "sort" => Expr::Builtin(Builtin::Sort),
+        "select" | "map" | "has" => {
+            let (rest, argument) = delimited(symbol("("), pipe, symbol(")")).parse(rest)?;
+            let argument = Box::new(argument);
+
+            let expr = match name {
+                "select" => Expr::Select(argument),
+                "has" => Expr::Has(argument),
+                _ => Expr::Array(Some(Box::new(Expr::Pipe(Box::new(Expr::Iterate(Box::new(Expr::Identity))), argument)))), //[.[] | f]
+            };
+            return Ok((rest, expr));
+        }
+        _ => return Err(input),
+    };
+    Ok((rest, expr))
+}
+
+
+/* (TESTS) */
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::json;
+
+    // Runs a filter on a document, returning its values as one array
+    fn run(filter: &str, input: &str) -> JSON
+    {
+        JSON::JsArray(json(input).filter(filter).unwrap())
+    }
+
+    #[test]
+    fn test_paths()
+    {
+        let doc = r#"{ "a" : { "b" : [10, 20, 30] }, "c" : null, "key with spaces" : 1 }"#;
+
+        assert_eq!( json(&format!("[{doc}]")), run(".", doc) );
+        assert_eq!( json("[[10, 20, 30]]"), run(".a.b", doc) );
+        assert_eq!( json("[20, 30, null]"), run(".a.b[1], .a.b[-1], .a.b[5]", doc) );
+        assert_eq!( json("[10, 20, 30]"), run(".a.b[]", doc) );
+        assert_eq!( json("[1, null, null]"), run(r#"."key with spaces", .c.d, .missing"#, doc) );
+        assert_eq!( json("[20]"), run(".a[.i]", r#"{ "a" : [10, 20], "i" : 1 }"#) );
+        assert_eq!( json("[[1, [2]], 1, [2], 2]"), run("..", "[1, [2]]") );
+    }
+
+    #[test]
+    fn test_operators()
+    {
+        assert_eq!( json(r#"[7, 3.5, 1, "ab", [1, 2, 3], { "a" : 1, "b" : 2 }, [1]]"#),
+                    run(r#"1 + 2 * 3, 7 / 2, 7 % 3, "a" + "b", [1, 2] + [3], { "a" : 1 } + { "b" : 2 }, [1, 2] - [2]"#, "null") );
+        assert_eq!( json("[true, false, true, true, true]"), run(r#"1 == 1.0, 1 != 1, "a" < "b", null < false, [1] >= [1]"#, "null") );
+        assert_eq!( json("[false, true, true]"), run("true and false, false or true, (null | not)", "null") );
+        assert_eq!( json("[11, 12, 21, 22]"), run("(1, 2) + (10, 20)", "null") );
+        assert_eq!( json("[2]"), run(".a - 1", r#"{ "a" : 3 }"#) );
+    }
+
+    #[test]
+    fn test_constructors_and_functions()
+    {
+        let doc = r#"{ "items" : [{ "name" : "tea", "price" : 4 }, { "name" : "cake", "price" : 12 }, { "name" : "pie", "price" : 15 }] }"#;
+
+        assert_eq!( json(r#"[{ "name" : "cake", "price" : 12 }, { "name" : "pie", "price" : 15 }]"#),
+                    run(".items[] | select(.price > 10) | {name, price}", doc) );
+        assert_eq!( json(r#"[["tea", "cake", "pie"], 31, 3, ["items"], "object"]"#),
+                    run("[.items[].name], (.items | map(.price) | add), (.items | length), keys, type", doc) );
+        assert_eq!( json(r#"[{ "tea" : 4 }, { "x" : 1, "y" : 1 }, { "x" : 2, "y" : 1 }]"#),
+                    run(r#"({(.items[0].name): .items[0].price}), {"x": (1, 2), y: 1}"#, doc) );
+        assert_eq!( json("[[1, 2, 3], [], true, false]"), run("([3, 1, 2] | sort), [empty], has(\"items\"), (.items | has(5))", doc) );
+    }
+
+    #[test]
+    fn test_filter_errors()
+    {
+        assert_eq!( Err(FilterError::Syntax(5)), Filter::parse(".a | ").map(|_| ()) );
+        assert_eq!( Err(FilterError::Syntax(3)), Filter::parse(".a )").map(|_| ()) );
+        assert_eq!( Err(FilterError::Syntax(0)), Filter::parse("nope").map(|_| ()) );
+
+        assert_eq!( Err(FilterError::Eval("cannot iterate over number".to_string())), json("1").filter(".[]") );
+        assert_eq!( Err(FilterError::Eval("cannot index number with string".to_string())), json("[1]").filter(".[0].a") );
+        assert_eq!( Err(FilterError::Eval("cannot divide 1 by zero".to_string())), json("null").filter("1 / 0") );
+    }
+
+    #[test]
+    fn test_overflowing_integers()
+    {
+        //i64::MIN / -1 and i64::MIN % -1 overflow an i64, so they are computed as floats
+        assert_eq!( json("[9223372036854775808.0, 0]"), run(". / -1, . % -1", "-9223372036854775808") );
+        assert_eq!( json("[-3.5, -1]"), run(". / 2, . % 2", "-7") );
+    }
+
+    #[test]
+    fn test_nesting_limit()
+    {
+        let nested = |n: usize| format!("{}.{}", "(".repeat(n), ")".repeat(n));
+        assert_eq!( json("[1]"), run(&nested(MAX_DEPTH - 1), "1") );
+        assert_eq!( Err(FilterError::TooDeep(MAX_DEPTH)), Filter::parse(&nested(MAX_DEPTH)).map(|_| ()) );
+        assert!( matches!( Filter::parse(&nested(100_000)), Err(FilterError::TooDeep(_)) ) );
+
+        //Chained operators and accesses nest the filter as much as brackets do
+        assert_eq!( json(&format!("[{}]", MAX_DEPTH)), run(&format!("1{}", " + 1".repeat(MAX_DEPTH - 1)), "null") );
+        assert!( matches!( Filter::parse(&format!("1{}", " + 1".repeat(100_000))), Err(FilterError::TooDeep(_)) ) );
+        assert!( matches!( Filter::parse(&".a".repeat(100_000)), Err(FilterError::TooDeep(_)) ) );
+        assert!( matches!( Filter::parse(&format!("[{}]", "[".repeat(100_000))), Err(FilterError::TooDeep(_)) ) );
+
+        //Levels of a bracket are undone once it is closed
+        assert!( Filter::parse(&vec![nested(MAX_DEPTH / 2); 10].join(" | ")).is_ok() );
+    }
+}
@@ -0,0 +1,115 @@
+//! Flattening nested values into a single object with dotted path keys (ie. { "a.b[0].c" : 1 }) and back
+
+/* (IMPORTS) */
+use std::collections::HashMap;
+
+use crate::{JsonPath, PathError, PathSegment, JSON};
+
+
+impl JSON {
+
+    /// Returns a single-level object mapping the dotted path of every leaf value to the value (ie. { "a.b[0].c" : 1 }).
+    /// Empty arrays and objects are kept as leaves so the structure can be restored by `unflatten`.
+    /// A value that is not an array or object flattens to an object with the empty path as its only key.
+    pub fn flatten(&self) -> JSON
+    {
+        let mut flat = HashMap::new();
+
+        self.fold((), |(), path, value| {
+            let is_leaf = match value {
+                JSON::JsArray(vec) => vec.is_empty(),
+                JSON::JsObject(map) => map.is_empty(),
+                _ => true
+            };
+
+            if is_leaf {
+                flat.insert(path.to_string(), value.clone());
+            }
+        });
+
+        JSON::JsObject(flat)
+    }
+
+    /// Rebuilds a nested value from a single-level object of dotted path keys, the inverse of `flatten`
+    ///
+    /// # Errors
+    /// Returns a `PathError` if the value is not an object, if a key is not a valid path,
+    /// if two keys need the same value to be different kinds of container (ie. "a[0]" and "a.b"),
+    /// or if the indexes of an array have gaps (ie. "a[0]" and "a[2]" without "a[1]")
+    pub fn unflatten(&self) -> Result<JSON, PathError>
+    {
+        let JSON::JsObject(flat) = self else {
+            return Err(PathError::NotAContainer(JsonPath::root()));
+        };
+
+        let mut entries = flat.iter()
+                              .map(|(key, value)| JsonPath::parse(key).map(|path| (path, value)))
+                              .collect::<Result<Vec<(JsonPath, &JSON)>, PathError>>()?;
+
+        //Sorting puts array indexes in ascending order, so every element is appended after the one before it
+        entries.sort_by(|a, b| a.0.cmp(&b.0));
+
+        //The root is an array if the paths start with an index, otherwise an object
+        let mut root = match entries.first().and_then(|(path, _)| path.segments().first()) {
+            Some(PathSegment::Index(_)) => JSON::JsArray(vec![]),
+            _ => JSON::JsObject(HashMap::new()),
+        };
+
+        for (path, value) in entries {
+            root.set_json_path(&path, value.clone())?;
+        }
+
+        Ok(root)
+    }
+}
+
+
+/* (TESTS) */
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::json;
+
+    #[test]
+    fn test_flatten()
+    {
+        let doc = json(r#"{ "a" : { "b" : [1, { "c" : true }] }, "d.e" : null, "f" : [], "g" : {} }"#);
+        let mut flat = HashMap::new();
+        flat.insert("a.b[0]".to_string(), json("1"));
+        flat.insert("a.b[1].c".to_string(), json("true"));
+        flat.insert(r#"["d.e"]"#.to_string(), json("null"));        //Keys containing dots are quoted
+        flat.insert("f".to_string(), json("[]"));
+        flat.insert("g".to_string(), json("{}"));
+
+        assert_eq!( JSON::JsObject(flat), doc.flatten() );
+        assert_eq!( json(r#"{ "" : "x" }"#), json(r#""x""#).flatten() );
+        assert_eq!( json(r#"{ "" : [] }"#), json("[]").flatten() );
+    }
+
+    #[test]
+    fn test_unflatten()
+    {
+        let flat = json(r#"{ "a.b[1].c" : true, "a.b[0]" : 1, "f" : [], "z[10]" : 10, "z[2]" : 2 }"#);
+        let nested = json(r#"{ "a" : { "b" : [1, { "c" : true }] }, "f" : [] }"#);
+
+        //Indexes with gaps can not be rebuilt
+        assert!( matches!(flat.unflatten(), Err(PathError::IndexOutOfBounds(_))) );
+
+        let flat = json(r#"{ "a.b[1].c" : true, "a.b[0]" : 1, "f" : [] }"#);
+        assert_eq!( Ok(nested), flat.unflatten() );
+
+        assert_eq!( Ok(json("[[1, 2]]")), json(r#"{ "[0][1]" : 2, "[0][0]" : 1 }"#).unflatten() );
+        assert_eq!( Ok(json("3")), json(r#"{ "" : 3 }"#).unflatten() );
+        assert!( json(r#"{ "a[0]" : 1, "a.b" : 2 }"#).unflatten().is_err() );
+        assert!( json(r#"{ "a..b" : 1 }"#).unflatten().is_err() );
+        assert!( json("[]").unflatten().is_err() );
+    }
+
+    #[test]
+    fn test_flatten_round_trip()
+    {
+        let doc = json(r#"{ "users" : [{ "name" : "a", "tags" : ["x", "y"] }, { "name" : "b", "tags" : [] }], "n" : { "m" : {} } }"#);
+
+        assert_eq!( Ok(doc.clone()), doc.flatten().unflatten() );
+    }
+}
@@ -0,0 +1,251 @@
+//! Seeded generation of random json values, for property tests and test fixtures
+//!
+//! The same seed and settings always produce the same sequence of values, so a failing case can be reproduced from its seed.
+//! Generated values cover every json type, integers at the edges of the u64 & i64 ranges, fractional and exponent floats,
+//! and strings with quotes, backslashes, control characters and non-ASCII characters.
+//!
+//! With the `arbitrary` feature `JSON` implements `arbitrary::Arbitrary`, making the same choices from the fuzzer's bytes rather than a seed,
+//! so fuzz targets and `arbitrary` based property tests can take values directly (ie. `fuzz_target!(|value: JSON| ...)`).
+//! To drive the generator from `proptest` use a seed produced by it (ie. `any::<u64>().prop_map(|seed| Generator::new(seed).generate())`).
+
+/* (IMPORTS) */
+use std::collections::HashMap;
+
+use crate::{Number, JSON};
+
+
+/// Generator of random json values, which is also an endless iterator of them
+#[derive(Debug, Clone)]
+pub struct Generator {
+    state: u64,
+    max_depth: usize,
+    max_len: usize,
+}
+
+impl Generator {
+
+    /// Creates a generator from a seed, producing values nested at most 4 deep with at most 8 elements, members or characters
+    pub fn new(seed: u64) -> Generator
+    {
+        Generator { state: seed, max_depth: 4, max_len: 8 }
+    }
+
+    /// Sets the deepest nesting of arrays and objects, 0 only generates scalars
+    pub fn max_depth(mut self, max_depth: usize) -> Generator
+    {
+        self.max_depth = max_depth;
+        self
+    }
+
+    /// Sets the largest number of elements in an array, members in an object, and characters in a string or key
+    pub fn max_len(mut self, max_len: usize) -> Generator
+    {
+        self.max_len = max_len;
+        self
+    }
+
+    /// Generates the next value
+    pub fn generate(&mut self) -> JSON
+    {
+        let (max_depth, max_len) = (self.max_depth, self.max_len);
+        value(self, max_depth, max_len)
+    }
+}
+
+// Source of the choices that values are generated from
+trait Choices {
+    fn next_u64(&mut self) -> u64;
+
+    // Returns a number in 0..n (n must not be 0)
+    fn below(&mut self, n: usize) -> usize;
+}
+
+impl Choices for Generator {
+
+    // SplitMix64, small and fast with good enough statistical quality for test data
+    fn next_u64(&mut self) -> u64
+    {
+        self.state = self.state.wrapping_add(0x9e3779b97f4a7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^ (z >> 31)
+    }
+
+    fn below(&mut self, n: usize) -> usize
+    {
+        (self.next_u64() % n as u64) as usize
+    }
+}
+
+// Generates a value with containers nested at most depth deep, and at most max_len elements, members or characters
+fn value(choices: &mut impl Choices, depth: usize, max_len: usize) -> JSON
+{
+    let kinds = if depth == 0 { 4 } else { 6 };
+
+    match choices.below(kinds) {
+        0 => JSON::JsNull,
+        1 => JSON::JsBool(choices.below(2) == 1),
+        2 => JSON::JsNumber(number(choices)),
+        3 => JSON::JsString(string(choices, max_len)),
+        4 => {
+            let len = choices.below(max_len + 1);
+            JSON::JsArray( (0..len).map(|_| value(choices, depth - 1, max_len)).collect() )
+        }
+        _ => {
+            let len = choices.below(max_len + 1);
+            let mut map = HashMap::new();
+            for _ in 0..len {
+                let key = string(choices, max_len);
+                let value = value(choices, depth - 1, max_len);
+                map.insert(key, value);
+            }
+            JSON::JsObject(map)
+        }
+    }
+}
+
+// Generates a number, favouring small integers and the edge cases parsers get wrong
+fn number(choices: &mut impl Choices) -> Number
+{
+    let bits = choices.next_u64();
+
+    let literal = match choices.below(8) {
+        0 | 1 => (choices.below(201) as i64 - 100).to_string(),
+        2 => ["0", "-1", "9223372036854775807", "-9223372036854775808", "18446744073709551615"][choices.below(5)].to_string(),
+        3 => bits.to_string(),
+        4 => (bits as i64).to_string(),
+        5 => format!("{}.{}", choices.below(2000) as i64 - 1000, choices.below(1000)),
+        6 => format!("{}e{}", choices.below(100), choices.below(41) as i64 - 20),
+        _ => format!("{:?}", (bits >> 11) as f64 / (1u64 << 53) as f64),
+    };
+
+    //Every literal above is finite and valid, so this always succeeds
+    Number::from_literal(&literal).unwrap_or(Number::from(0u64))
+}
+
+// Generates a string, mostly printable ASCII with some characters that need escaping or are multibyte
+fn string(choices: &mut impl Choices, max_len: usize) -> String
+{
+    const SPECIAL: [char; 10] = ['"', '\\', '/', '\n', '\t', '\u{0}', '\u{1f}', 'é', '€', '🦀'];
+
+    let len = choices.below(max_len + 1);
+    (0..len).map(|_| match choices.below(10) {
+                    0 => SPECIAL[choices.below(SPECIAL.len())],
+                    1 => char::from_u32(0xa0 + choices.below(0xd7ff - 0xa0) as u32).unwrap_or('?'),
+                    _ => (b' ' + choices.below(95) as u8) as char,
+                })
+            .collect()
+}
+
+impl Iterator for Generator {
+    type Item = JSON;
+
+    fn next(&mut self) -> Option<JSON>
+    {
+        Some(self.generate())
+    }
+}
+
+
+/* (ARBITRARY) */
+
+// Choices read from the bytes of a fuzzer, once they run out every choice is the first one (so values end as null and empty containers)
+#[cfg(feature = "arbitrary")]
+struct Unstructured<'a, 'b>(&'b mut arbitrary::Unstructured<'a>);
+
+#[cfg(feature = "arbitrary")]
+impl Choices for Unstructured<'_, '_> {
+
+    fn next_u64(&mut self) -> u64
+    {
+        self.0.arbitrary().unwrap_or(0)
+    }
+
+    fn below(&mut self, n: usize) -> usize
+    {
+        self.0.int_in_range(0..=n - 1).unwrap_or(0)
+    }
+}
+
+/// Values nested at most 4 deep with at most 8 elements, members or characters, as `Generator::new` makes
+#[cfg(feature = "arbitrary")]
+impl<'a> arbitrary::Arbitrary<'a> for JSON {
+    fn arbitrary(u: &mut arbitrary::Unstructured<'a>) -> arbitrary::Result<JSON>
+    {
+        Ok(value(&mut Unstructured(u), 4, 8))
+    }
+}
+
+
+/* (TESTS) */
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cmp::Ordering;
+
+    #[test]
+    fn test_deterministic()
+    {
+        let a: Vec<JSON> = Generator::new(7).take(20).collect();
+        let b: Vec<JSON> = Generator::new(7).take(20).collect();
+        let c: Vec<JSON> = Generator::new(8).take(20).collect();
+
+        assert_eq!( a, b );
+        assert_ne!( a, c );
+    }
+
+    #[test]
+    fn test_limits()
+    {
+        for value in Generator::new(1).max_depth(2).max_len(3).take(200) {
+            let metrics = value.metrics();
+            assert!( metrics.max_depth <= 2 );
+            assert!( value.fold(true, |ok, _, v| ok && match v {
+                JSON::JsArray(vec) => vec.len() <= 3,
+                JSON::JsObject(map) => map.len() <= 3,
+                JSON::JsString(s) => s.chars().count() <= 3,
+                _ => true
+            }) );
+        }
+
+        assert!( Generator::new(1).max_depth(0).take(50).all(|v| v.metrics().max_depth == 0) );
+    }
+
+    #[cfg(feature = "arbitrary")]
+    #[test]
+    fn test_arbitrary()
+    {
+        use arbitrary::{Arbitrary, Unstructured};
+
+        let bytes: Vec<u8> = Generator::new(3).take(400).flat_map(|value| value.display_json().to_string().into_bytes()).collect();
+        let mut u = Unstructured::new(&bytes);
+        let values: Vec<JSON> = (0..200).map(|_| JSON::arbitrary(&mut u).unwrap()).collect();
+
+        assert!( values.iter().all(|value| value.metrics().max_depth <= 4) );
+        assert!( values.iter().any(|value| matches!(value, JSON::JsObject(map) if !map.is_empty())) );
+
+        //The same bytes make the same values, and no bytes make null
+        assert_eq!( values[0], JSON::arbitrary(&mut Unstructured::new(&bytes)).unwrap() );
+        assert_eq!( JSON::JsNull, JSON::arbitrary(&mut Unstructured::new(&[])).unwrap() );
+    }
+
+    #[test]
+    fn test_properties()
+    {
+        let values: Vec<JSON> = Generator::new(42).take(300).collect();
+
+        for (a, b) in values.iter().zip(values.iter().skip(1)) {
+            //Flattening is reversible
+            assert!( a.flatten().unflatten().is_ok_and(|u| u.semantic_eq(a)) );
+
+            //The total order agrees with equality and is antisymmetric
+            assert_eq!( Ordering::Equal, a.total_cmp(&a.clone()) );
+            assert_eq!( a == b, a.total_cmp(b) == Ordering::Equal );
+            assert_eq!( a.total_cmp(b), b.total_cmp(a).reverse() );
+
+            //Equal values hash the same
+            assert_eq!( a.content_hash(), a.clone().content_hash() );
+        }
+    }
+}
@@ -0,0 +1,92 @@
+//! Conversions between values and `serde_json::Value` (enabled with the `serde_json` feature), for code that uses both crates
+//!
+//! Numbers are converted through their literal, so integers of any size keep every digit when both sides keep literals
+//! (this crate with `arbitrary_precision`, and serde_json with its own `arbitrary_precision`).
+
+/* (IMPORTS) */
+use crate::{Number, JSON};
+
+
+impl From<serde_json::Value> for JSON {
+    fn from(value: serde_json::Value) -> JSON
+    {
+        match value {
+            serde_json::Value::Null => JSON::JsNull,
+            serde_json::Value::Bool(b) => JSON::JsBool(b),
+            serde_json::Value::Number(n) => JSON::JsNumber(from_serde_number(&n)),
+            serde_json::Value::String(s) => JSON::JsString(s),
+            serde_json::Value::Array(vec) => JSON::JsArray(vec.into_iter().map(JSON::from).collect()),
+            serde_json::Value::Object(map) => JSON::JsObject(map.into_iter().map(|(key, member)| (key, JSON::from(member))).collect()),
+        }
+    }
+}
+
+/// Objects keep their members in order of key. A number that serde_json can not hold (ie. 1e400 without its `arbitrary_precision`) becomes null.
+impl From<JSON> for serde_json::Value {
+    fn from(value: JSON) -> serde_json::Value
+    {
+        match value {
+            JSON::JsNull => serde_json::Value::Null,
+            JSON::JsBool(b) => serde_json::Value::Bool(b),
+            JSON::JsNumber(n) => to_serde_number(&n).map_or(serde_json::Value::Null, serde_json::Value::Number),
+            JSON::JsString(s) => serde_json::Value::String(s),
+            JSON::JsArray(vec) => serde_json::Value::Array(vec.into_iter().map(serde_json::Value::from).collect()),
+            JSON::JsObject(map) => {
+                let mut members: Vec<(String, JSON)> = map.into_iter().collect();
+                members.sort_by(|a, b| a.0.cmp(&b.0));
+                serde_json::Value::Object(members.into_iter().map(|(key, member)| (key, serde_json::Value::from(member))).collect())
+            }
+        }
+    }
+}
+
+fn from_serde_number(n: &serde_json::Number) -> Number
+{
+    if let Some(u) = n.as_u64() { return Number::from(u); }
+    if let Some(i) = n.as_i64() { return Number::from(i); }
+
+    //serde_json only holds finite numbers, so their literals are always valid
+    Number::from_literal(&n.to_string()).unwrap_or(Number::from(0u64))
+}
+
+fn to_serde_number(n: &Number) -> Option<serde_json::Number>
+{
+    if let Some(u) = n.as_u64() { return Some(serde_json::Number::from(u)); }
+    if let Some(i) = n.as_i64() { return Some(serde_json::Number::from(i)); }
+
+    format!("{n:?}").parse().ok()
+}
+
+
+/* (TESTS) */
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::json;
+
+    #[test]
+    fn test_from_serde_json()
+    {
+        let value: serde_json::Value = serde_json::from_str(r#"{ "a" : [1, -2, 2.5, 18446744073709551615], "b" : { "c" : null, "d" : "x" }, "e" : false }"#).unwrap();
+        let expected = json(r#"{ "a" : [1, -2, 2.5, 18446744073709551615], "b" : { "c" : null, "d" : "x" }, "e" : false }"#);
+
+        assert_eq!( expected, JSON::from(value) );
+    }
+
+    #[test]
+    fn test_to_serde_json()
+    {
+        let value = json(r#"{ "z" : [1, -9223372036854775808, 0.1, 1e300], "a" : { "s" : "é\n" }, "n" : null }"#);
+        let converted = serde_json::Value::from(value.clone());
+
+        assert_eq!( r#"{"a":{"s":"é\n"},"n":null,"z":[1,-9223372036854775808,0.1,1e+300]}"#, converted.to_string() );
+        assert_eq!( value, JSON::from(converted) );
+    }
+
+    #[cfg(feature = "arbitrary_precision")]
+    #[test]
+    fn test_out_of_range()
+    {
+        assert_eq!( serde_json::Value::Null, serde_json::Value::from(json("1e400")) );
+    }
+}
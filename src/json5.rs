@@ -0,0 +1,382 @@
+//! Reading and writing JSON5, the superset of json for hand-written files (ie. `{ unquoted: 'single quoted', trailing: [1, 2,], }`)
+//!
+//! JSON5 adds comments, unquoted keys, single quoted strings, trailing commas, hexadecimal numbers and numbers with a leading '+'
+//! or a leading or trailing decimal point. Infinity and NaN are valid JSON5 but fail to parse, as json values can not hold them.
+
+/* (IMPORTS) */
+use std::collections::HashMap;
+
+use crate::ser::{write_escaped_str, write_indent};
+use crate::{JsonPath, Number, ParseError, ParseErrorKind, PathSegment, JSON, MAX_DEPTH};
+
+
+impl JSON {
+
+    /// Parses a string containing exactly one JSON5 value (surrounded by optional whitespace and comments)
+    ///
+    /// # Examples
+    /// ```
+    /// use Parser::{try_parse, JSON};
+    ///
+    /// let value = JSON::from_json5("{ // the port\n port: 0x1F90, hosts: ['a', \"b\",], ratio: .5 }").unwrap();
+    /// assert_eq!( try_parse(r#"{ "port" : 8080, "hosts" : ["a", "b"], "ratio" : 0.5 }"#).unwrap(), value );
+    /// ```
+    ///
+    /// # Errors
+    /// Returns a `ParseError` with the position of the problem and the path of the value it is in, if the input is not valid JSON5
+    /// or holds Infinity or NaN
+    pub fn from_json5(input: &str) -> Result<JSON, ParseError>
+    {
+        let mut reader = Json5Reader { input, pos: 0, path: JsonPath::root() };
+
+        reader.skip_space()?;
+        let value = reader.value(0)?;
+        reader.skip_space()?;
+
+        match reader.rest().is_empty() {
+            true => Ok(value),
+            false => Err(reader.error(ParseErrorKind::TrailingCharacters)),
+        }
+    }
+
+    /// Returns the value as pretty printed JSON5, with object keys that are identifiers left unquoted and a comma after every
+    /// element and member. Object keys are sorted, as in `display_json`.
+    pub fn to_json5(&self) -> String
+    {
+        let mut out = String::new();
+        write_json5(&mut out, self, 0);
+        out
+    }
+}
+
+// The input of a JSON5 document, how far into it has been read and the path of the value being read
+struct Json5Reader<'a> {
+    input: &'a str,
+    pos: usize,
+    path: JsonPath,
+}
+
+impl<'a> Json5Reader<'a> {
+
+    fn rest(&self) -> &'a str
+    {
+        &self.input[self.pos..]
+    }
+
+    fn error(&self, kind: ParseErrorKind) -> ParseError
+    {
+        ParseError::new(kind, self.input, self.pos).with_path(self.path.clone())
+    }
+
+    fn syntax_error<T>(&self) -> Result<T, ParseError>
+    {
+        Err(self.error(ParseErrorKind::Syntax))
+    }
+
+    // Consumes a str if the rest of the input starts with it
+    fn eat(&mut self, s: &str) -> bool
+    {
+        let found = self.rest().starts_with(s);
+        if found { self.pos += s.len(); }
+        found
+    }
+
+    fn next_char(&mut self) -> Option<char>
+    {
+        let ch = self.rest().chars().next()?;
+        self.pos += ch.len_utf8();
+        Some(ch)
+    }
+
+    // Skips whitespace (including the byte order mark), line comments and block comments
+    fn skip_space(&mut self) -> Result<(), ParseError>
+    {
+        loop {
+            let rest = self.rest();
+            self.pos += rest.len() - rest.trim_start_matches(|ch: char| ch.is_whitespace() || ch == '\u{feff}').len();
+
+            if self.eat("//") {
+                let rest = self.rest();
+                self.pos += rest.find(is_line_terminator).unwrap_or(rest.len());
+            } else if self.rest().starts_with("/*") {
+                let Some(end) = self.rest().find("*/") else { return self.syntax_error(); };
+                self.pos += end + 2;
+            } else {
+                return Ok(());
+            }
+        }
+    }
+
+    fn value(&mut self, depth: usize) -> Result<JSON, ParseError>
+    {
+        match self.rest().chars().next() {
+            Some('{') => self.object(depth),
+            Some('[') => self.array(depth),
+            Some(quote @ ('"' | '\'')) => self.string(quote).map(JSON::JsString),
+            _ if self.eat("null") => Ok(JSON::JsNull),
+            _ if self.eat("true") => Ok(JSON::JsBool(true)),
+            _ if self.eat("false") => Ok(JSON::JsBool(false)),
+            _ => self.number().map(JSON::JsNumber),
+        }
+    }
+
+    // Reads a number, which is rewritten as a json literal (ie. "+.5" as "0.5" and "0x10" as "16") to make the `Number`
+    fn number(&mut self) -> Result<Number, ParseError>
+    {
+        let start = self.pos;
+        let negative = self.eat("-");
+        if !negative { self.eat("+"); }
+        let sign = if negative { "-" } else { "" };
+
+        if self.eat("0x") || self.eat("0X") {
+            let rest = self.rest();
+            let len = rest.find(|ch: char| !ch.is_ascii_hexdigit()).unwrap_or(rest.len());
+            let Ok(n) = u128::from_str_radix(&rest[..len], 16) else { return self.syntax_error(); };
+            self.pos += len;
+
+            let literal = format!("{sign}{n}");
+            return Number::from_literal(&literal).or_else(|| Number::from_f64((n as f64).copysign(if negative { -1.0 } else { 1.0 })))
+                                                .ok_or_else(|| self.error(ParseErrorKind::Syntax));
+        }
+
+        let digits = |reader: &mut Json5Reader<'a>| {
+            let rest = reader.rest();
+            let len = rest.find(|ch: char| !ch.is_ascii_digit()).unwrap_or(rest.len());
+            reader.pos += len;
+            &rest[..len]
+        };
+
+        let int_part = digits(self);
+        let frac_part = if self.eat(".") { digits(self) } else { "" };
+        if int_part.is_empty() && frac_part.is_empty() || int_part.len() > 1 && int_part.starts_with('0') {
+            self.pos = start;
+            return self.syntax_error();
+        }
+
+        let mut literal = format!("{sign}{}", if int_part.is_empty() { "0" } else { int_part });
+        if !frac_part.is_empty() { literal = format!("{literal}.{frac_part}"); }
+
+        if self.eat("e") || self.eat("E") {
+            let exp_sign = if self.eat("-") { "-" } else { self.eat("+"); "" };
+            let exponent = digits(self);
+            if exponent.is_empty() { return self.syntax_error(); }
+            literal = format!("{literal}e{exp_sign}{exponent}");
+        }
+
+        Number::from_literal(&literal).ok_or_else(|| {
+            self.pos = start;
+            self.error(ParseErrorKind::Syntax)
+        })
+    }
+
+    // Reads a string in single or double quotes, replacing its escape sequences
+    fn string(&mut self, quote: char) -> Result<String, ParseError>
+    {
+        let start = self.pos;
+        self.pos += 1;
+        let mut s = String::new();
+
+        loop {
+            let at = self.pos;
+            match self.next_char() {
+                None => { self.pos = start; return self.syntax_error(); }
+                Some(ch) if ch == quote => return Ok(s),
+                Some('\n' | '\r') => { self.pos = at; return self.syntax_error(); }
+                Some('\\') => match self.next_char() {
+                    Some('b') => s.push('\u{8}'),
+                    Some('f') => s.push('\u{c}'),
+                    Some('n') => s.push('\n'),
+                    Some('r') => s.push('\r'),
+                    Some('t') => s.push('\t'),
+                    Some('v') => s.push('\u{b}'),
+                    Some('0') if !self.rest().starts_with(|ch: char| ch.is_ascii_digit()) => s.push('\0'),
+                    Some('x') => s.push(self.hex_escape(2)?),
+                    Some('u') => s.push(self.unicode_escape()?),
+                    Some('\r') => { self.eat("\n"); }       //An escaped line break is left out of the string, \r\n counting as one
+                    Some(ch) if is_line_terminator(ch) => (),
+                    Some('1'..='9') | None => { self.pos = at; return self.syntax_error(); }
+                    Some(ch) => s.push(ch),
+                }
+                Some(ch) => s.push(ch),
+            }
+        }
+    }
+
+    // Reads the hex digits of an escape sequence as a character, which is U+FFFD if it is a lone surrogate
+    fn hex_escape(&mut self, len: usize) -> Result<char, ParseError>
+    {
+        let digits = self.rest().get(..len).filter(|digits| digits.chars().all(|ch| ch.is_ascii_hexdigit()));
+        let Some(code) = digits.and_then(|digits| u32::from_str_radix(digits, 16).ok()) else { return self.syntax_error(); };
+        self.pos += len;
+        Ok(char::from_u32(code).unwrap_or('\u{fffd}'))
+    }
+
+    // Reads the hex digits of a \u escape as a character, joining a surrogate pair written as two escapes
+    fn unicode_escape(&mut self) -> Result<char, ParseError>
+    {
+        let digits = self.rest().get(..4).filter(|digits| digits.chars().all(|ch| ch.is_ascii_hexdigit()));
+        let Some(high) = digits.and_then(|digits| u32::from_str_radix(digits, 16).ok()) else { return self.syntax_error(); };
+        self.pos += 4;
+
+        if (0xd800..0xdc00).contains(&high) {
+            let low = self.rest().strip_prefix("\\u").and_then(|rest| rest.get(..4)).and_then(|digits| u32::from_str_radix(digits, 16).ok());
+            if let Some(low @ 0xdc00..=0xdfff) = low {
+                self.pos += 6;
+                return Ok(char::from_u32(0x10000 + ((high - 0xd800) << 10) + (low - 0xdc00)).unwrap_or('\u{fffd}'));
+            }
+        }
+        Ok(char::from_u32(high).unwrap_or('\u{fffd}'))
+    }
+
+    // Reads the key of a member, a string or an identifier
+    fn key(&mut self) -> Result<String, ParseError>
+    {
+        if let Some(quote @ ('"' | '\'')) = self.rest().chars().next() {
+            return self.string(quote);
+        }
+
+        let rest = self.rest();
+        let len = rest.find(|ch: char| !is_identifier_char(ch)).unwrap_or(rest.len());
+        match rest.chars().next() {
+            Some(first) if len > 0 && !first.is_ascii_digit() => { self.pos += len; Ok(rest[..len].to_string()) }
+            _ => self.syntax_error(),
+        }
+    }
+
+    fn array(&mut self, depth: usize) -> Result<JSON, ParseError>
+    {
+        if depth == MAX_DEPTH { return Err(self.error(ParseErrorKind::TooDeep)); }
+        self.pos += 1;
+
+        let mut vec = vec![];
+        loop {
+            self.skip_space()?;
+            if self.eat("]") { return Ok(JSON::JsArray(vec)); }
+
+            self.path.push(PathSegment::Index(vec.len()));
+            vec.push(self.value(depth + 1)?);
+            self.path.pop();
+
+            self.skip_space()?;
+            if !self.eat(",") && !self.rest().starts_with(']') { return self.syntax_error(); }
+        }
+    }
+
+    fn object(&mut self, depth: usize) -> Result<JSON, ParseError>
+    {
+        if depth == MAX_DEPTH { return Err(self.error(ParseErrorKind::TooDeep)); }
+        self.pos += 1;
+
+        let mut map = HashMap::new();
+        loop {
+            self.skip_space()?;
+            if self.eat("}") { return Ok(JSON::JsObject(map)); }
+
+            let key = self.key()?;
+            self.skip_space()?;
+            if !self.eat(":") { return self.syntax_error(); }
+            self.skip_space()?;
+
+            self.path.push(PathSegment::Key(key.clone()));
+            let value = self.value(depth + 1)?;
+            self.path.pop();
+            map.insert(key, value);
+
+            self.skip_space()?;
+            if !self.eat(",") && !self.rest().starts_with('}') { return self.syntax_error(); }
+        }
+    }
+}
+
+fn is_line_terminator(ch: char) -> bool
+{
+    matches!(ch, '\n' | '\r' | '\u{2028}' | '\u{2029}')
+}
+
+fn is_identifier_char(ch: char) -> bool
+{
+    ch.is_alphanumeric() || matches!(ch, '$' | '_' | '\u{200c}' | '\u{200d}')
+}
+
+// Appends a value as pretty printed JSON5, see to_json5
+fn write_json5(out: &mut String, value: &JSON, level: usize)
+{
+    match value {
+        JSON::JsArray(vec) if !vec.is_empty() => {
+            out.push('[');
+            for element in vec {
+                write_indent(out, level + 1);
+                write_json5(out, element, level + 1);
+                out.push(',');
+            }
+            write_indent(out, level);
+            out.push(']');
+        }
+        JSON::JsObject(map) if !map.is_empty() => {
+            let mut members: Vec<(&String, &JSON)> = map.iter().collect();
+            members.sort_by(|a, b| a.0.cmp(b.0));
+
+            out.push('{');
+            for (key, member) in members {
+                write_indent(out, level + 1);
+
+                //Only ASCII identifiers are left unquoted, so the output reads the same in every JSON5 parser
+                let is_identifier = key.starts_with(|ch: char| ch.is_ascii_alphabetic() || ch == '$' || ch == '_')
+                                    && key.chars().all(|ch| ch.is_ascii_alphanumeric() || ch == '$' || ch == '_');
+                if is_identifier { out.push_str(key); } else { write_escaped_str(out, key); }
+
+                out.push_str(": ");
+                write_json5(out, member, level + 1);
+                out.push(',');
+            }
+            write_indent(out, level);
+            out.push('}');
+        }
+        other => out.push_str(&other.display_json().to_string()),
+    }
+}
+
+
+/* (TESTS) */
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::json;
+
+    #[test]
+    fn test_from_json5()
+    {
+        let input = "// config\n{\n  name: 'it\\'s', \"quoted key\": \"a\\x41\\u00e9\\\n b\", $id_1: +1,\n  /* block\n comment */ list: [.5, 5., -0x10, 1e2,],\n}\n";
+        let expected = json(r#"{ "name" : "it's", "quoted key" : "aAé b", "$id_1" : 1, "list" : [0.5, 5.0, -16, 100] }"#);
+        assert_eq!( Ok(expected), JSON::from_json5(input) );
+
+        assert_eq!( Ok(json(r#""😀 \u0000""#)), JSON::from_json5(r"'😀 \0'") );
+        assert_eq!( Ok(json("[]")), JSON::from_json5("[ ]") );
+    }
+
+    #[test]
+    fn test_from_json5_errors()
+    {
+        let error = |input: &str| JSON::from_json5(input).map_err(|e| (e.kind(), e.offset(), e.path().to_string()));
+
+        assert_eq!( Err((ParseErrorKind::Syntax, 8, "a[1]".to_string())), error("{a: [1, Infinity]}") );
+        assert_eq!( Err((ParseErrorKind::Syntax, 1, "[0]".to_string())), error("[,]") );
+        assert_eq!( Err((ParseErrorKind::Syntax, 1, "".to_string())), error("{1a: 2}") );
+        assert_eq!( Err((ParseErrorKind::Syntax, 0, "".to_string())), error("012") );
+        assert_eq!( Err((ParseErrorKind::Syntax, 2, "".to_string())), error("'a\nb'") );
+        assert_eq!( Err((ParseErrorKind::TrailingCharacters, 3, "".to_string())), error("1  2") );
+        assert_eq!( Err((ParseErrorKind::Syntax, 2, "".to_string())), error("1 /* open") );
+        assert_eq!( ParseErrorKind::TooDeep, JSON::from_json5(&"[".repeat(MAX_DEPTH + 1)).unwrap_err().kind() );
+    }
+
+    #[test]
+    fn test_to_json5()
+    {
+        let value = json(r#"{ "b" : [1, "two", {}], "a b" : { "c" : null }, "_d" : [] }"#);
+        let expected = "{\n  _d: [],\n  \"a b\": {\n    c: null,\n  },\n  b: [\n    1,\n    \"two\",\n    {},\n  ],\n}";
+
+        assert_eq!( expected, value.to_json5() );
+        assert_eq!( Ok(value.clone()), JSON::from_json5(expected) );
+        assert_eq!( "\"x\"", json(r#""x""#).to_json5() );
+    }
+}
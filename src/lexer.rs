@@ -0,0 +1,422 @@
+//! Scanning of json text into lexemes (punctuation, literals and whitespace)
+//!
+//! The functions of this module match a single kind of lexeme at the start of their input and are what the value
+//! parsers are built from. `lex` applies them one after another to split a whole text into lexemes without checking
+//! that they form a valid document, which is all that tools like syntax highlighters need.
+//! Every lexeme records where it is in the input, and with comments allowed (see `Options`) comments are lexemes too,
+//! so formatters can reproduce or adjust the original layout exactly.
+
+/* (IMPORTS) */
+use std::ops::Range;
+
+use crate::combinator::{eof, escaped_transform, hex_u32, terminated, Parser as _};
+pub use crate::combinator::{ws0, ws1};
+use crate::{Options, ParseError, ParseErrorKind};
+
+
+/// The kinds of lexeme that json text is made of
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LexemeKind {
+    Whitespace,
+    BeginArray,
+    EndArray,
+    BeginObject,
+    EndObject,
+    Colon,
+    Comma,
+    Null,
+    True,
+    False,
+    Number,
+    /// A string literal, including its quotes
+    String,
+    /// A `//` comment up to (but not including) the end of the line, or a `/* */` comment. Only produced if comments are allowed.
+    Comment,
+}
+
+/// A lexeme along with its text, which is a slice of the input
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Lexeme<'a> {
+    pub kind: LexemeKind,
+    pub text: &'a str,
+    /// Byte offset of the start of the text in the input
+    pub start: usize,
+}
+
+impl<'a> Lexeme<'a> {
+
+    /// Returns the byte range of the lexeme in the input
+    pub fn span(&self) -> Range<usize>
+    {
+        self.start..self.start + self.text.len()
+    }
+
+    /// Returns the value of a string lexeme with its quotes removed and escapes replaced,
+    /// or None if the lexeme is not a string or contains an invalid escape
+    pub fn string_value(&self) -> Option<String>
+    {
+        if self.kind != LexemeKind::String { return None; }
+        unescape(&self.text[1..self.text.len() - 1])
+    }
+}
+
+/// Iterator returned by `lex`
+#[derive(Debug, Clone)]
+pub struct Lexer<'a> {
+    input: &'a str,
+    pos: usize,
+    comments: bool,
+    failed: bool,
+}
+
+/// Returns an iterator over the lexemes of a text, including its whitespace, so joining the texts of the lexemes gives back
+/// the input. Stops with an error at the first character that can not start a lexeme (or at an unterminated string).
+///
+/// # Examples
+/// ```
+/// use Parser::lexer::{lex, LexemeKind};
+///
+/// let kinds: Vec<LexemeKind> = lex("[1, true]").map(|lexeme| lexeme.unwrap().kind).collect();
+///
+/// assert_eq!( vec![LexemeKind::BeginArray, LexemeKind::Number, LexemeKind::Comma, LexemeKind::Whitespace,
+///                  LexemeKind::True, LexemeKind::EndArray], kinds );
+/// ```
+pub fn lex<'a>(input: &'a str) -> Lexer<'a>
+{
+    lex_with(input, &Options::default())
+}
+
+/// Returns an iterator over the lexemes of a text as in `lex`, also producing comments if the options allow them
+pub fn lex_with<'a>(input: &'a str, options: &Options) -> Lexer<'a>
+{
+    Lexer { input, pos: 0, comments: options.allow_comments, failed: false }
+}
+
+impl<'a> Lexer<'a> {
+
+    /// Returns the byte offset of the next lexeme
+    pub fn offset(&self) -> usize
+    {
+        self.pos
+    }
+}
+
+impl<'a> Iterator for Lexer<'a> {
+    type Item = Result<Lexeme<'a>, ParseError>;
+
+    fn next(&mut self) -> Option<Result<Lexeme<'a>, ParseError>>
+    {
+        let rest = &self.input[self.pos..];
+        if rest.is_empty() || self.failed { return None; }
+
+        let scanned = match self.comments { true => match_comment(rest).map(|len| (LexemeKind::Comment, len)), false => None };
+
+        match scanned.or_else(|| scan(rest)) {
+            Some((kind, len)) => {
+                let start = self.pos;
+                self.pos += len;
+                Some(Ok(Lexeme { kind, text: &rest[..len], start }))
+            }
+            None => {
+                self.failed = true;
+                Some(Err(ParseError::new(ParseErrorKind::Syntax, self.input, self.pos)))
+            }
+        }
+    }
+}
+
+// Works out the kind and length in bytes of the lexeme at the start of a non-empty input
+fn scan(input: &str) -> Option<(LexemeKind, usize)>
+{
+    let punctuation = |kind| Some((kind, 1));
+    let word = |word: &str, kind| input.starts_with(word).then_some((kind, word.len()));
+
+    match input.as_bytes()[0] {
+        b'[' => punctuation(LexemeKind::BeginArray),
+        b']' => punctuation(LexemeKind::EndArray),
+        b'{' => punctuation(LexemeKind::BeginObject),
+        b'}' => punctuation(LexemeKind::EndObject),
+        b':' => punctuation(LexemeKind::Colon),
+        b',' => punctuation(LexemeKind::Comma),
+        b'n' => word("null", LexemeKind::Null),
+        b't' => word("true", LexemeKind::True),
+        b'f' => word("false", LexemeKind::False),
+        b'"' => {
+            let (rest, contents) = match_until_double_quote(&input[1..]).ok()?;
+            rest.starts_with('"').then_some((LexemeKind::String, contents.len() + 2))
+        }
+        b'-' | b'0'..=b'9' => match_digit_chars(input).ok().map(|(_, literal)| (LexemeKind::Number, literal.len())),
+        _ => ws1(input).ok().map(|(_, whitespace)| (LexemeKind::Whitespace, whitespace.len())),
+    }
+}
+
+
+/* (LEXEME MATCHERS) */
+
+/// Returns the length in bytes of the comment at the start of the input (a `//` comment ends before the end of the line),
+/// or None if the input does not start with a comment or has an unterminated `/*` comment
+pub fn match_comment(input: &str) -> Option<usize>
+{
+    if let Some(rest) = input.strip_prefix("//") {
+        return Some(2 + rest.find(['\n', '\r']).unwrap_or(rest.len()));
+    }
+
+    let rest = input.strip_prefix("/*")?;
+    rest.find("*/").map(|end| 2 + end + 2)
+}
+
+/// Matches the whitespace at the start of the input (which may be none), returning the rest of the input and the whitespace.
+/// This is `ws0` under its older name, despite which it matches every whitespace character at the start of the input.
+pub fn match_whitespace_char<'a>(input: &'a str) -> Result<(&'a str, &'a str), &'a str>
+{
+    ws0(input)
+}
+
+/// Matches a number literal at the start of the input (ie. optional minus sign, digits, optional fraction & optional exponent),
+/// returning the rest of the input and the literal, or the input if it does not start with a number
+pub fn match_digit_chars<'a>(input: &'a str) -> Result<(&'a str, &'a str), &'a str> 
+{
+    let bytes = input.as_bytes();
+    let count_digits = |from: usize| bytes[from..].iter().take_while(|b| b.is_ascii_digit()).count();
+
+    let mut n = 0;
+    if bytes.first() == Some(&b'-') { n += 1; } //Leading minus sign
+
+    //A leading zero is the whole of the integer part
+    let int_digits = if bytes.get(n) == Some(&b'0') { 1 } else { count_digits(n) };
+    if int_digits == 0 {  //Is not digit, so return err
+        return Err(input);
+    }
+    n += int_digits;
+
+    //Fraction, only matched if it has at least one digit
+    if bytes.get(n) == Some(&b'.') {
+        let frac_digits = count_digits(n + 1);
+        if frac_digits > 0 { n += 1 + frac_digits; }
+    }
+
+    //Exponent, only matched if it is followed by at least one digit
+    if let Some(b'e' | b'E') = bytes.get(n) {
+        let sign = usize::from( matches!(bytes.get(n + 1), Some(b'+' | b'-')) );
+        let exp_digits = count_digits(n + 1 + sign);
+        if exp_digits > 0 { n += 1 + sign + exp_digits; }
+    }
+
+    Ok( (&input[n..] , &input[..n]) ) //Return shifted input json str and number str
+}
+
+/// Matches the contents of a string literal, everything up to the next double quote that is not escaped with a backslash
+/// (ie. `abc \" 123` in `"abc \" 123"`), returning the rest of the input starting at that quote and the contents.
+/// Also stops at a control character (U+0000 to U+001F), which json only allows escaped.
+pub fn match_until_double_quote<'a>(input: &'a str) -> Result<(&'a str, &'a str), &'a str> 
+{
+    let bytes = input.as_bytes();
+    let mut n = 0;
+
+    while n < bytes.len() && bytes[n] != b'"' && bytes[n] >= 0x20 {
+        n += if bytes[n] == b'\\' { 2 } else { 1 };   //Skip over the escaped character
+    }
+    let n = n.min(input.len()); //Only ever stops on a quote (which is ASCII) or the end, so n is a char boundary
+    Ok( (&input[n..] , &input[..n]) )
+}
+
+/// Replaces the escape sequences in the contents of a string literal (ie. \n or \u00e9) with the characters they stand for.
+/// Returns None if the contents contain an invalid escape, unpaired surrogates (ie. \ud800) are replaced with U+FFFD.
+pub fn unescape(literal: &str) -> Option<String>
+{
+    let contents = terminated(escaped_transform(match_unescaped_chars, '\\', match_escape), eof);
+    contents.parse(literal).ok().map(|(_, s)| s.into_owned())
+}
+
+// Matches the characters up to the next backslash (or the end of the input), which stand for themselves in a string literal
+fn match_unescaped_chars<'a>(input: &'a str) -> Result<(&'a str, &'a str), &'a str>
+{
+    let n = input.find('\\').unwrap_or(input.len());
+    Ok( (&input[n..], &input[..n]) )
+}
+
+/// Matches what follows the backslash of an escape sequence in a string literal (ie. `n` or `u00e9`), returning the rest of the input
+/// and the character it stands for, or the input if it is not a valid escape. Unpaired surrogates stand for U+FFFD.
+pub fn match_escape<'a>(input: &'a str) -> Result<(&'a str, char), &'a str>
+{
+    // Reads the 4 hex digits at the start of a str (ie. following \u)
+    let hex4 = |s: &'a str| -> Option<(&'a str, u32)> {
+        let (_, unit) = terminated(hex_u32, eof).parse(s.get(..4)?).ok()?;
+        Some((&s[4..], unit))
+    };
+
+    let mut chars = input.chars();
+    let unescaped = match chars.next().ok_or(input)? {
+        '"' => '"',
+        '\\' => '\\',
+        '/' => '/',
+        'b' => '\u{8}',
+        'f' => '\u{c}',
+        'n' => '\n',
+        'r' => '\r',
+        't' => '\t',
+        'u' => {
+            let (rest, unit) = hex4(chars.as_str()).ok_or(input)?;
+            return match unit {
+                //High surrogate, only valid if followed by an escaped low surrogate
+                0xd800..=0xdbff => match rest.strip_prefix("\\u").and_then(hex4) {
+                    Some((after, low @ 0xdc00..=0xdfff)) => {
+                        char::from_u32(0x10000 + ((unit - 0xd800) << 10) + (low - 0xdc00)).map(|ch| (after, ch)).ok_or(input)
+                    }
+                    _ => Ok((rest, '\u{fffd}'))
+                },
+                0xdc00..=0xdfff => Ok((rest, '\u{fffd}')),
+                _ => char::from_u32(unit).map(|ch| (rest, ch)).ok_or(input)
+            };
+        }
+        _ => return Err(input)
+    };
+    Ok((chars.as_str(), unescaped))
+}
+
+/* (TESTS) */
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_match_whitespace_char()
+    {
+        assert_eq!( Ok(("abc", "")), match_whitespace_char("abc") );  
+        assert_eq!( Ok(("abc", " ")), match_whitespace_char(" abc") );    
+
+        assert_eq!( Ok(("", "")), match_whitespace_char("") );          //Successfully match empty string
+        assert_eq!( Ok(("", "\n")), match_whitespace_char("\n") );      //Successfully match newline char
+        assert_eq!( Ok(("", "\t")), match_whitespace_char("\t") );      //Successfully match tab char          
+        assert_eq!( Ok(("", "    ")), match_whitespace_char("    ") );  //Successfully match 4 spaces
+    }
+
+    #[test]
+    fn test_ws()
+    {
+        assert_eq!( Ok(("abc", "")), ws0("abc") );
+        assert_eq!( Ok(("abc", " \r\n\t")), ws0(" \r\n\tabc") );
+
+        assert_eq!( Err("abc"), ws1("abc") );                      //Needs at least one whitespace char
+        assert_eq!( Err(""), ws1("") );
+        assert_eq!( Ok(("abc", "  ")), ws1("  abc") );
+    }
+
+    #[test]
+    fn test_match_until_double_quote() 
+    {
+        assert_eq!( Ok(("", "")), match_until_double_quote("") );  
+        assert_eq!( Ok(("\"abc", "")), match_until_double_quote("\"abc") );  
+        assert_eq!( Ok(("\"", "abc")), match_until_double_quote("abc\"") );  
+        assert_eq!( Ok(("\" 456", "abc 123 ")), match_until_double_quote("abc 123 \" 456") );  
+        assert_eq!( Ok(("\" 456", "abc -+= 123 ")), match_until_double_quote("abc -+= 123 \" 456") ); 
+    }
+
+    #[test]
+    fn test_match_until_escaped_double_quote()
+    {
+        assert_eq!( Ok(("\" 1", r#"a\"b"#)), match_until_double_quote(r#"a\"b" 1"#) );
+        assert_eq!( Ok(("\"", r#"a\\"#)), match_until_double_quote(r#"a\\""#) );       //Escaped backslash, then the closing quote
+        assert_eq!( Ok(("", "a\\")), match_until_double_quote("a\\") );
+        assert_eq!( Ok(("\nb\"", "a")), match_until_double_quote("a\nb\"") );     //Control characters must be escaped
+    }
+
+    #[test]
+    fn test_unescape()
+    {
+        assert_eq!( Some("plain".to_string()), unescape("plain") );
+        assert_eq!( Some("a\"b\\c/d\n\t\r\u{8}\u{c}".to_string()), unescape(r#"a\"b\\c\/d\n\t\r\b\f"#) );
+        assert_eq!( Some("é🦀".to_string()), unescape(r#"\u00e9\ud83e\udd80"#) );         //Surrogate pair
+        assert_eq!( Some("\u{fffd}x\u{fffd}".to_string()), unescape(r#"\ud83ex\udd80"#) );   //Unpaired surrogates
+
+        assert_eq!( None, unescape(r#"\x"#) );
+        assert_eq!( None, unescape(r#"\u12"#) );
+        assert_eq!( None, unescape(r#"\u+123"#) );
+        assert_eq!( None, unescape("\\") );
+    }
+
+    #[test]
+    fn test_match_escape()
+    {
+        assert_eq!( Ok(("x", '\n')), match_escape("nx") );
+        assert_eq!( Ok(("", 'é')), match_escape("u00e9") );
+        assert_eq!( Ok(("!", '🦀')), match_escape(r#"ud83e\udd80!"#) );
+        assert_eq!( Ok((r#"\n"#, '\u{fffd}')), match_escape(r#"ud83e\n"#) );  //The escape after an unpaired surrogate is left
+        assert_eq!( Err("x"), match_escape("x") );
+        assert_eq!( Err("u12"), match_escape("u12") );
+        assert_eq!( Err(""), match_escape("") );
+    }
+
+    #[test]
+    fn test_match_digits_chars()
+    {
+        assert_eq!( Err("abc"), match_digit_chars("abc") );    
+        assert_eq!( Err(""), match_digit_chars("") );                 //Fails to match empty string
+
+        assert_eq!( Ok(("", "123")), match_digit_chars("123") );      //Successfully match integer
+        assert_eq!( Ok(("", "12.34")), match_digit_chars("12.34") );  //Successfully match float   
+        assert_eq!( Ok((",", "-12")), match_digit_chars("-12,") );    //Successfully match negative integer
+        assert_eq!( Ok(("", "1.5e-3")), match_digit_chars("1.5e-3") ); //Successfully match exponent
+        assert_eq!( Ok(("e", "2")), match_digit_chars("2e") );        //Exponent without digits is not part of the number
+        assert_eq!( Err("-"), match_digit_chars("-") );
+        assert_eq!( Ok(("1", "0")), match_digit_chars("01") );       //Leading zeros are not part of the number
+        assert_eq!( Ok((".", "1")), match_digit_chars("1.") );       //Nor is a decimal point without digits
+    }
+
+    #[test]
+    fn test_lex()
+    {
+        let input = "{ \"a\\\"b\" :\n[-1.5e3,null,false] }";
+        let lexemes: Vec<Lexeme> = lex(input).collect::<Result<_, _>>().unwrap();
+
+        assert_eq!( input, lexemes.iter().map(|l| l.text).collect::<String>() );   //Lossless
+        assert_eq!( LexemeKind::String, lexemes[2].kind );
+        assert_eq!( "\"a\\\"b\"", lexemes[2].text );
+        assert_eq!( Some("a\"b".to_string()), lexemes[2].string_value() );
+        assert_eq!( (LexemeKind::Number, "-1.5e3"), (lexemes[7].kind, lexemes[7].text) );
+        assert_eq!( None, lexemes[7].string_value() );
+    }
+
+    #[test]
+    fn test_spans()
+    {
+        let input = "[ \"é\" ,1]";
+        let spans: Vec<Range<usize>> = lex(input).map(|l| l.unwrap().span()).collect();
+
+        assert_eq!( vec![0..1, 1..2, 2..6, 6..7, 7..8, 8..9, 9..10], spans );
+        assert!( lex(input).all(|l| l.is_ok_and(|l| &input[l.span()] == l.text)) );
+    }
+
+    #[test]
+    fn test_comments()
+    {
+        let input = "{ // settings\n  \"a\" : 1 /* one */\r\n}";
+        let options = Options { allow_comments: true, ..Options::default() };
+        let comments: Vec<(&str, Range<usize>)> = lex_with(input, &options).map(|l| l.unwrap())
+                                                                           .filter(|l| l.kind == LexemeKind::Comment)
+                                                                           .map(|l| (l.text, l.span()))
+                                                                           .collect();
+
+        assert_eq!( vec![("// settings", 2..13), ("/* one */", 24..33)], comments );
+        assert_eq!( input, lex_with(input, &options).map(|l| l.unwrap().text).collect::<String>() );
+
+        assert!( lex(input).any(|l| l.is_err()) );                               //Not allowed by default
+        assert!( lex_with("/* open", &options).any(|l| l.is_err()) );
+        assert_eq!( Some(2), match_comment("//") );
+        assert_eq!( None, match_comment("/ /") );
+    }
+
+    #[test]
+    fn test_lex_errors()
+    {
+        //Lexing does not check the structure, only that every character belongs to a lexeme
+        assert!( lex("]]:,{").all(|l| l.is_ok()) );
+
+        let mut lexer = lex("[nul]");
+        assert_eq!( Some(LexemeKind::BeginArray), lexer.next().map(|l| l.unwrap().kind) );
+        assert_eq!( Some(1), lexer.next().map(|l| l.unwrap_err().offset()) );
+        assert_eq!( None, lexer.next() );
+
+        assert!( lex(r#""unterminated"#).next().is_some_and(|l| l.is_err()) );
+    }
+}
@@ -7,246 +7,490 @@
 //! Now any json value is a valid top level element in a json file
 
 #![allow(non_snake_case)] 
+#![allow(clippy::needless_lifetimes)]
 
 /* (IMPORTS) */
+use std::cell::{Cell, RefCell};
 use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+mod number;
+pub use number::Number;
+
+mod error;
+pub use error::{ParseError, ParseErrorKind};
+mod options;
+pub use options::{CancelToken, Options, Progress};
+pub mod lexer;
+pub mod combinator;
+use combinator::{alt, comma_separated, complete, digits, map_err, opt, or, product, quoted_string_literal, recognize, separated_pair,
+                 seq, str_parser, token, value, Parser};
+use lexer::{match_until_double_quote, unescape, ws0};
+
+mod regex;
+pub mod schema;
+
+mod path;
+pub use path::{JsonPath, PathError, PathSegment};
+mod field;
+pub use field::FieldState;
+
+mod transform;
+pub use transform::ArrayMerge;
+pub mod visit;
+pub mod filter;
+mod flatten;
+mod query;
+mod env;
+mod properties;
+pub use properties::PropertiesError;
+mod json5;
+mod binary;
+pub use binary::BinaryError;
+mod yaml;
+mod csv;
+pub use csv::CsvError;
+mod template;
+pub use template::{TemplateError, WholePlaceholder};
+mod patch;
+pub use patch::PatchError;
+mod compare;
+pub use compare::{NormalizedJson, Tolerance};
+mod metrics;
+pub use metrics::{Metrics, ParseCounts, StatsCollector};
+
+mod ser;
+pub use ser::DisplayJson;
+pub mod generator;
+mod roundtrip;
+pub use roundtrip::{verify_roundtrip, Divergence, DivergenceKind, RoundtripReport};
+mod shared;
+pub use shared::ArcJson;
+mod writer;
+pub use writer::{JsonWriter, Separator, WriterError};
+mod tokens;
+pub use tokens::{tokens, Token, Tokens};
+mod cst;
+pub use cst::{Cst, CstKind, CstMember, CstNode};
+mod ndjson;
+pub use ndjson::{ndjson, ndjson_with, Ndjson, NdjsonError};
+mod decode;
+pub use decode::{decode, DecodeError, Decoder, FromJson};
+mod builder;
+pub use builder::{ArrayBuilder, ObjectBuilder};
+mod document;
+pub use document::JsonDocument;
+mod warnings;
+pub use warnings::{parse_with_warnings, Warning, WarningKind};
+mod lossy;
+pub use lossy::parse_lossy;
+mod many;
+pub use many::{parse_many, parse_many_with, StopReason, Values};
+mod reader;
+pub use reader::{parse_file, parse_reader, reader_tokens, ReadError, ReaderTokens};
+#[cfg(any(feature = "gzip", feature = "zstd"))]
+pub use reader::decompress;
+
+#[cfg(feature = "decimal")]
+mod decimal;
+#[cfg(feature = "decimal")]
+pub use decimal::Decimal;
+
+#[cfg(feature = "serde_json")]
+mod interop;
+
+#[cfg(feature = "datetime")]
+mod datetime;
+#[cfg(feature = "datetime")]
+pub use datetime::{Date, DateTime};
+
+#[cfg(feature = "base64")]
+mod base64;
+
+#[cfg(feature = "ffi")]
+pub mod ffi;
+
+#[cfg(feature = "wasm")]
+pub mod wasm;
+
+#[cfg(feature = "python")]
+pub mod python;
+
+#[cfg(feature = "xml")]
+pub mod xml;
+#[cfg(feature = "xml")]
+pub use xml::XmlError;
+
+#[cfg(feature = "bumpalo")]
+mod arena;
+#[cfg(feature = "bumpalo")]
+pub use arena::{parse_in, ArenaJson};
+
+#[cfg(all(feature = "mmap", unix))]
+mod mmap;
+#[cfg(all(feature = "mmap", unix))]
+pub use mmap::{parse_mapped, MappedFile};
 
 
 /// Enum for various JSON types, with variants for each possible json value
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum JSON {
     JsNull,             
     JsBool(bool),       
-    JsNumber(f32),      
+    JsNumber(Number),   
     JsString(String),   
     JsArray(Vec<JSON>), 
     JsObject(HashMap<String, JSON>),
 }
 
-// Define Parser trait 
-// Left: (remaining unparsed input, reference to matched str) -- Right: Input on which parser failed 
-trait Parser<'a, T> { 
-    fn parse(&self, input: &'a str) -> Result<(&'a str, T), &'a str>; 
-}                                                                     
+impl JSON {
 
-// Implement parser trait for some generic function F
-impl<'a, F, T> Parser<'a, T> for F
-where
-    F: Fn(&'a str) -> Result<(&'a str, T), &'a str>, 
-{
-    fn parse(&self, input: &'a str) -> Result<(&'a str, T), &'a str> { 
-        self(input)
+    /// Returns the value as an f64 if it is a number
+    pub fn as_f64(&self) -> Option<f64>
+    {
+        match self {
+            JSON::JsNumber(n) => Some(n.as_f64()),
+            _ => None
+        }
     }
-}
 
+    /// Returns the value as an i64 if it is an integer number that fits within an i64 (checked without going through a float)
+    pub fn as_i64(&self) -> Option<i64>
+    {
+        match self {
+            JSON::JsNumber(n) => n.as_i64(),
+            _ => None
+        }
+    }
 
-/* (PRIMITIVE COMBINATORS) */
+    /// Returns the value as a u64 if it is a non-negative integer number that fits within a u64 (checked without going through a float)
+    pub fn as_u64(&self) -> Option<u64>
+    {
+        match self {
+            JSON::JsNumber(n) => n.as_u64(),
+            _ => None
+        }
+    }
 
-// Function that returns a parser that attempts to match its str against the start of the given input                            
-fn str_parser<'a>(s: &'a str) -> impl Parser<'a, &'a str> 
-{
-    move |input: &'a str|  {    if input.starts_with(s) { 
-                                    Ok( (&input[s.len()..], s) ) //If match return shifted input str & matched str
-                                } else { 
-                                    Err(input)                   //Else return unshifted input str
-                                } 
-                            }
-}
+    /// Returns the value as an f64 if it is a number or a string containing a json number (ie. 42 or "42")
+    pub fn as_f64_lenient(&self) -> Option<f64>
+    {
+        match self {
+            JSON::JsNumber(n) => Some(n.as_f64()),
+            JSON::JsString(s) => number_from_str(s).map(|n| n.as_f64()),
+            _ => None
+        }
+    }
 
+    /// Returns the value as an i64 if it is an integer number or a string containing one (ie. 42 or "42")
+    pub fn as_i64_lenient(&self) -> Option<i64>
+    {
+        match self {
+            JSON::JsNumber(n) => n.as_i64(),
+            JSON::JsString(s) => number_from_str(s).and_then(|n| n.as_i64()),
+            _ => None
+        }
+    }
 
-/* (DERIVED COMBINATORS) */
+    /// Returns the value as a bool if it is a bool, the number 1 or 0, 
+    /// or the string "true", "false" (in any case), "1" or "0"
+    pub fn as_bool_lenient(&self) -> Option<bool>
+    {
+        match self {
+            JSON::JsBool(b) => Some(*b),
+            JSON::JsNumber(n) => match n.as_u64() {
+                Some(1) => Some(true),
+                Some(0) => Some(false),
+                _ => None
+            },
+            JSON::JsString(s) => match s.trim() {
+                t if t.eq_ignore_ascii_case("true") || t == "1" => Some(true),
+                t if t.eq_ignore_ascii_case("false") || t == "0" => Some(false),
+                _ => None
+            },
+            _ => None
+        }
+    }
 
-// Sequences 2 parsers, trys the first parser if passes returns that result, otherwise trys the second
-fn or<'a, P1, P2, A>(p1: P1, p2: P2) -> impl Parser<'a, A>
-where 
-    P1: Parser<'a, A>,
-    P2: Parser<'a, A>
-{   
-    move |input: &'a str| { p1.parse(input).or( p2.parse(input) ) }
+    /// Takes the value out, leaving `JsNull` in its place (as `Option::take` leaves None)
+    pub fn take(&mut self) -> JSON
+    {
+        std::mem::take(self)
+    }
+
+    /// Puts a new value in place of this one, returning the old value
+    pub fn replace(&mut self, value: JSON) -> JSON
+    {
+        std::mem::replace(self, value)
+    }
+
+    /// Returns the value as an exact decimal if it is a number whose literal fits within a `Decimal`
+    #[cfg(feature = "decimal")]
+    pub fn as_decimal(&self) -> Option<Decimal>
+    {
+        match self {
+            JSON::JsNumber(n) => n.as_decimal(),
+            _ => None
+        }
+    }
+
+    /// Returns the value as a timestamp if it is a string in RFC 3339 date-time format (ie. "2024-02-29T13:45:30Z")
+    #[cfg(feature = "datetime")]
+    pub fn as_datetime(&self) -> Option<DateTime>
+    {
+        match self {
+            JSON::JsString(s) => datetime::parse_rfc3339_datetime(s),
+            _ => None
+        }
+    }
+
+    /// Returns the value as a date if it is a string in RFC 3339 full-date format (ie. "2024-02-29")
+    #[cfg(feature = "datetime")]
+    pub fn as_date(&self) -> Option<Date>
+    {
+        match self {
+            JSON::JsString(s) => datetime::parse_rfc3339_date(s),
+            _ => None
+        }
+    }
+
+    /// Returns the decoded bytes if the value is a base64 string (either the standard or the URL-safe alphabet)
+    #[cfg(feature = "base64")]
+    pub fn as_base64_bytes(&self) -> Option<Vec<u8>>
+    {
+        match self {
+            JSON::JsString(s) => base64::decode(s),
+            _ => None
+        }
+    }
 }
 
-// Sequences 2 parsers, running p1 then p2 and returns the pair of their results only if both succeed
-fn product<'a, P1, P2, R1, R2>(p1: P1, p2: P2) -> impl Parser<'a, (R1, R2)>
-where
-    P1: Parser<'a, R1>,
-    P2: Parser<'a, R2>,
-{
-    move |input| {
-        p1.parse(input).and_then(|(next_input, r1)| { //Note: and_then is flatMap
-            p2.parse(next_input)
-                .map(|(last_input, r2)| (last_input, (r1, r2)))
-        })
+/// The default value is `JsNull`
+impl Default for JSON {
+    fn default() -> JSON
+    {
+        JSON::JsNull
     }
 }
 
-// Parser adapter that matches a quoted string literal 
-fn quoted_string_literal<'a, P>(p: P) -> impl Parser<'a, &'a str> 
-where 
-    P: Parser<'a, &'a str>
+// Converts a string containing exactly one json number literal (surrounding whitespace allowed) into a number
+fn number_from_str(s: &str) -> Option<Number>
 {
-    move |input| 
-        str_parser("\"").parse(input)
-            .and_then(|(next_input, _)| { p.parse(next_input) })
-                .and_then(|(next_input2, matched)| { 
-                    
-                    match str_parser("\"").parse(next_input2) {
-                        Ok((next, _)) => Ok((next, matched)),          
-                        Err(e) => Err(e)
-                    }
-                }
-        )
+    match complete(number_literal).parse(s.trim_start()) {
+        Ok((_, literal)) => Number::from_literal(literal),
+        _ => None
+    }
 }
 
-// Parser adapter that matches zero or more instance of a str against a given input
-fn zero_or_more<'a, P, A>(p: P) -> impl Parser<'a, Vec<A> >  
-where 
-    P: Parser<'a, A>
-{
-    move |input: &'a str| {
 
-        let mut v = vec![];
-        let mut inputted: &str = &input; //Is reference to str that gets fed to parser
+/* (NESTING LIMIT) */
 
-        while let Ok((next, matches)) = p.parse(inputted) {
-            inputted = next;    //"Shift" forward str to be fed to parser if parser correctly parsed str
-            v.push(matches);
-        }
+// Deepest nesting of arrays & objects the parser will follow by default, deeper input fails rather than overflowing the stack
+const MAX_DEPTH: usize = 128;
 
-        Ok((inputted, v)) //Return all unparsed input and the input on the original str that got parsed
-    }
+// Nesting of the arrays & objects currently being parsed on a thread
+#[derive(Clone, Copy)]
+struct Depth {
+    current: usize,
+    limit: usize,
+    stopped: Option<ParseErrorKind>,    //Why the parse was stopped early (ie. nested deeper than the limit), rather than by a syntax error
 }
 
-// Function that returns the left value from a parser with a pair result
-fn left<'a, P, A, B>(p: P) -> impl Parser<'a, A> 
-where 
-    P: Parser<'a, (A, B)>,
-{   
-    move |input: &'a str| {
-        match p.parse(input) {
-            Ok((s, (a, _b))) => Ok((s, a)), 
-            Err(e)  =>  Err(e)
-        }
+thread_local! {
+    static DEPTH: Cell<Depth> = const { Cell::new(Depth { current: 0, limit: MAX_DEPTH, stopped: None }) };
+}
+
+// Held while parsing the contents of an array or object, leaving the level when dropped
+struct DepthGuard;
+
+impl DepthGuard {
+
+    // Enters a level of nesting, returns None (and records that the limit was hit) if it would be deeper than the limit
+    fn enter() -> Option<DepthGuard>
+    {
+        DEPTH.with(|depth| {
+            let mut d = depth.get();
+            if d.current >= d.limit {
+                d.stopped = Some(ParseErrorKind::TooDeep);
+                depth.set(d);
+                return None;
+            }
+            d.current += 1;
+            depth.set(d);
+            Some(DepthGuard)
+        })
     }
 }
 
-// Function that returns the right value from a parser with a pair result
-fn right<'a, P, A, B>(p: P) -> impl Parser<'a, B> 
-where 
-    P: Parser<'a, (A, B)>,
-{   
-    move |input: &'a str| {
-        match p.parse(input) {
-            Ok((s, (_a,b))) => Ok((s, b)), 
-            Err(e)  =>  Err(e)
-        }
+impl Drop for DepthGuard {
+    fn drop(&mut self)
+    {
+        DEPTH.with(|depth| {
+            let mut d = depth.get();
+            d.current -= 1;
+            depth.set(d);
+        });
     }
 }
 
 
-/* (GENERAL PARSERS) */
+/* (RAW STRINGS) */
 
-// Function to match whitespace
-fn match_whitespace_char<'a>(input: &'a str) -> Result<(&'a str, &'a str), &'a str> 
-{
-    let mut n = 0;
-    let mut chars = input.chars();
+thread_local! {
+    // Whether the parse running on a thread keeps strings as written, see Options::raw_strings
+    static RAW_STRINGS: Cell<bool> = const { Cell::new(false) };
+}
 
-    while let Some(ch) = chars.next()  {
-        if !ch.is_whitespace() { break; }    //
-        n += 1;
-    }
-    Ok( (&input[n..], &input[..n]) )  //Should return all the space or just eat them ie.  Ok( (&input[n..], "") )
+
+/* (CANCELLATION) */
+
+thread_local! {
+    // Options of the parse running on a thread, when they can interrupt it (ie. with a cancellation token or deadline)
+    static INTERRUPTS: RefCell<Option<Options>> = const { RefCell::new(None) };
 }
 
-// Function to match ascii digit characters
-fn match_digit_chars<'a>(input: &'a str) -> Result<(&'a str, &'a str), &'a str> 
+// Returns true (and records why) if the parse should stop, checked at the start of every value
+fn interrupted() -> bool
 {
-    //Idea: Probably ought to check if the number you are try to fit is larger than type capacity
-    let not_a_digit = 'a';// a is used as default non-ascii digit value
-    let mut n = 0;
-    let mut chars = input.chars();
-    let mut ch = chars.next().unwrap_or(not_a_digit); 
+    let Some(kind) = INTERRUPTS.with(|options| options.borrow().as_ref().and_then(Options::interrupted)) else { return false };
+
+    DEPTH.with(|depth| {
+        let mut d = depth.get();
+        d.stopped = Some(kind);
+        depth.set(d);
+    });
+    true
+}
 
-    while ch.is_ascii_digit() {
-        n += 1;
-        ch = chars.next().unwrap_or(not_a_digit);
-    }
 
-    if n == 0 {  //Is not digit, so return err
-        return Err(input);
-    
-    }else if ch != '.' {  //Else if n > 0 then early return for integer (ie. no decimal place)
-        return Ok( (&input[n..] , &input[..n]) );
-    }
-    
-    n += 1; //Increment for decimal character
+/* (PROGRESS) */
 
-    //Count all diigts after the decimal
-    while let Some(ch) = chars.next()  {
-        if !ch.is_ascii_digit() { break; }
-        n += 1;
-    }
+// Progress hook of the try_parse_with running on a thread, with the address and length of its input and the offset to report next
+struct Reporting {
+    progress: Progress,
+    start: usize,
+    len: usize,
+    next: usize,
+}
 
-    Ok( (&input[n..] , &input[..n]) ) //Return shifted input json str and float str
-   
+thread_local! {
+    static REPORTING: RefCell<Option<Reporting>> = const { RefCell::new(None) };
 }
 
-// Function to match alphanumberic & space characters (Does JSON allow punction chars in keys & values?)
-// This function is essientally for matching the key & values of string literals in json input
-fn match_until_double_quote<'a>(input: &'a str) -> Result<(&'a str, &'a str), &'a str> 
+// Runs a parse of the input with the progress hook of the options (if any) set for the thread, restoring the previous hook afterwards
+fn with_progress<T>(options: &Options, input: &str, parse: impl FnOnce() -> T) -> T
 {
-    let mut n = 0;
-    let mut chars = input.chars();
+    let Some(progress) = &options.progress else { return parse() };
 
-    while let Some(ch) = chars.next()  {
-        //if !ch.is_alphanumeric() && !ch.is_whitespace() { break; } 
-        if ch == '\"' { break; }         // This is essentially the behaviour we want
-        n += 1;
-    }
-    Ok( (&input[n..] , &input[..n]) )
+    let reporting = Reporting { progress: progress.clone(), start: input.as_ptr() as usize, len: input.len(), next: progress.interval };
+    let previous = REPORTING.with(|r| r.replace(Some(reporting)));
+    let result = parse();
+    REPORTING.with(|r| r.replace(previous));
+    result
+}
+
+// Calls the progress hook if the parser has got far enough into the input since the last call, checked at the start of every value
+fn report_progress(json_input: &str)
+{
+    let due = REPORTING.with(|r| {
+        let mut r = r.borrow_mut();
+        let r = r.as_mut()?;
+        let offset = (json_input.as_ptr() as usize).saturating_sub(r.start).min(r.len);
+        if offset < r.next { return None; }
+
+        r.next = offset + r.progress.interval;
+        Some((r.progress.callback.clone(), offset, r.len))
+    });
+
+    //Called once the thread local is released, so the hook may parse json itself
+    if let Some((callback, offset, len)) = due { callback(offset, len); }
 }
 
+
+/* (STATISTICS) */
+
+thread_local! {
+    // Counts of the parse running on a thread, if its options have a stats collector
+    static COUNTS: Cell<Option<ParseCounts>> = const { Cell::new(None) };
+}
+
+// Records something the parser created, if the counts are being collected
+fn record(update: impl FnOnce(&mut ParseCounts))
+{
+    COUNTS.with(|counts| {
+        if let Some(mut c) = counts.get() {
+            update(&mut c);
+            counts.set(Some(c));
+        }
+    });
+}
+
+
 /* (JSON PARSERS) */
 
 // Parser for JsNull
 fn json_null<'a>(json_input: &'a str) ->  Result<(&'a str, JSON), &'a str> 
 {
-    match str_parser("null").parse(json_input) { 
-        Ok((next_input, _)) =>  Ok((next_input, JSON::JsNull)),
-        Err(e)   => return Err(e) //Return input str where parser failed
-    }
+    value(JSON::JsNull, str_parser("null")).parse(json_input)
 }
 
 // Parser for JsBool
 fn json_bool<'a>(json_input: &'a str) -> Result<(&'a str, JSON), &'a str>  
 {
     //Try parsing the input json for either true or false
-    let result = or(str_parser("true"), str_parser("false")).parse(json_input);
+    or( value(JSON::JsBool(true), str_parser("true")), value(JSON::JsBool(false), str_parser("false")) ).parse(json_input)
+}
 
-    match result { 
-        Ok((next_input, "true"))  => Ok((next_input, JSON::JsBool(true))),
-        Ok((next_input, "false")) => Ok((next_input, JSON::JsBool(false))),
-        Ok(_) => unimplemented!(), // This should never happen but is necessary for exhaustive pattern
-        Err(s)  => Err(s)    // Return input str where parser failed
-    }
+// Parser for a number literal (ie. optional minus sign, digits, optional fraction & optional exponent), returned as one str
+// The exponent is only matched if it has digits, and a malformed number fails at its start
+fn number_literal<'a>(json_input: &'a str) -> Result<(&'a str, &'a str), &'a str> 
+{
+    //A leading zero is the whole of the integer part, and the fraction and exponent each need a digit
+    let integer = or(str_parser("0"), digits);
+    let fraction = opt(product(str_parser("."), digits));
+    let exponent = opt(seq(( or(str_parser("e"), str_parser("E")), opt(or(str_parser("+"), str_parser("-"))), digits )));
+
+    map_err( recognize(seq(( opt(str_parser("-")), integer, fraction, exponent ))), |_| json_input ).parse(json_input)
 }
 
 // Parser for JsNumber
 fn json_number<'a>(json_input: &'a str) -> Result<(&'a str, JSON), &'a str> 
 {
-    match_digit_chars.parse(json_input)
-                     .map( |(next_input, literal)|
-                                (next_input, JSON::JsNumber( literal.parse::<f32>().unwrap() ))
-                         )
+    let (next_input, literal) = number_literal(json_input)?;
+
+    //Fails if the literal can not be represented (ie. 1e999 overflows an f64)
+    match Number::from_literal(literal) {
+        Some(n) => {
+            record(|c| c.bytes_allocated += n.heap_size());
+            Ok((next_input, JSON::JsNumber(n)))
+        }
+        None => Err(json_input)
+    }
 } 
 
+// Parser for a quoted string literal (ie. a string value or an object key), with its escape sequences replaced
+// unless raw strings are being kept
+fn string_literal<'a>(json_input: &'a str) -> Result<(&'a str, String), &'a str> 
+{
+    let (next_input, literal) = quoted_string_literal(match_until_double_quote).parse(json_input)?;
+
+    let value = if RAW_STRINGS.with(Cell::get) { Some(literal.to_string()) } else { unescape(literal) };
+    match value {
+        Some(s) => {
+            record(|c| {
+                c.strings += 1;
+                c.bytes_allocated += s.capacity();
+            });
+            Ok((next_input, s))
+        }
+        None => Err(json_input)
+    }
+}
+
 // Parser for JsString
 fn json_string<'a>(json_input: &'a str) -> Result<(&'a str, JSON), &'a str> 
 {
-    quoted_string_literal(match_until_double_quote).parse(json_input)
-                                                            .map( |(next_input, literal)| 
-                                                                        (next_input, JSON::JsString(literal.to_string()))
-                                                                )
+    string_literal.parse(json_input).map( |(next_input, s)| (next_input, JSON::JsString(s)) )
 } 
 
 // Parser for JsArray
@@ -255,17 +499,17 @@ fn json_array<'a>(json_input: &'a str) -> Result<(&'a str, JSON), &'a str>
     str_parser("[").parse(json_input) //Match opening bracket for json array and then ...
         .and_then(|(next_input, _)| { 
 
-            // Create parser to match some ammount of whitespace followed by either a comma or a closing bracket
-            let closing_char = or(str_parser(","), str_parser("]"));
-            let whitespace_closing_char = product(match_whitespace_char, closing_char);
-            
-            // Create a parser that matches some whitespace then a json value then a closing char but only keeps the json value
-            let json_value = right( product(match_whitespace_char, parse_json) );
-            let json_elements = left( product(json_value, whitespace_closing_char) );
+            let _guard = DepthGuard::enter().ok_or(json_input)?;
 
-            // Match zero or more json elements 
-            match zero_or_more( json_elements ).parse(next_input) {
-                Ok((last_input, vec_json)) => Ok((last_input, JSON::JsArray( vec_json ))), 
+            // Match json elements (which skip the whitespace before them) separated by commas up to the closing bracket
+            match comma_separated( parse_json, "]" ).parse(next_input) {
+                Ok((last_input, vec_json)) => {
+                    record(|c| {
+                        c.arrays += 1;
+                        c.bytes_allocated += vec_json.capacity() * std::mem::size_of::<JSON>();
+                    });
+                    Ok((last_input, JSON::JsArray( vec_json )))
+                }
                 Err(e) => Err(e) //Return input str where parser failed
             }
         })
@@ -278,33 +522,32 @@ fn json_object<'a>(json_input: &'a str) -> Result<(&'a str, JSON), &'a str>
     str_parser("{").parse(json_input) //Match opening curly brace for json object and then ...
         .and_then(|(next_input, _)| { 
 
-            // Create parser to match some amount of whitespace followed by either a comma or a closing curly brace
-            let closing_char = or(str_parser(","), str_parser("}"));
-            let whitespace_closing_char = product(match_whitespace_char, closing_char);
-
-            // Create a parser that matches some whitespace then an identifier (ie. key) then some more whitespace
-            // then a seperator (ie. :) then more whitespace then a json value. But only keeps the json value
-            let key = right( product(match_whitespace_char, quoted_string_literal(match_until_double_quote)) );
-            let separator = product(match_whitespace_char, str_parser(":"));
-            let json_value = right( product(match_whitespace_char, parse_json) );
+            let _guard = DepthGuard::enter().ok_or(json_input)?;
 
-            // Combine above parsers in order to get required key,value pairs
-            let key_sep = left( product(key, separator) );
-            let json_val_closing_ch = left( product(json_value, whitespace_closing_char) );
-
-            let json_elements = product(key_sep, json_val_closing_ch);
+            // Create a parser that matches an identifier (ie. key) then a seperator (ie. :) then a json value,
+            // each after some whitespace. But only keeps the key and the json value
+            let key = token(string_literal);
+            let separator = token(str_parser(":"));
+            let json_elements = separated_pair(key, separator, parse_json);
             
-            // Match zero or more json elements 
-            match zero_or_more( json_elements ).parse(next_input) {
+            // Match key,value pairs separated by commas up to the closing curly brace
+            match comma_separated( json_elements, "}" ).parse(next_input) {
                 Ok((last_input, vec_json)) => { 
 
                     let mut hashmap_json: HashMap<String, JSON> = HashMap::new();
 
                     //Create hashmap from vec of json pairs
+                    let pairs = vec_json.capacity() * std::mem::size_of::<(String, JSON)>();
                     for (s, js) in vec_json { 
-                        hashmap_json.insert(String::from(s), js);
+                        hashmap_json.insert(s, js);
                     }
 
+                    let buckets = hashmap_json.capacity() * (std::mem::size_of::<(String, JSON)>() + 1);
+                    record(|c| {
+                        c.objects += 1;
+                        c.bytes_allocated += pairs + buckets;
+                    });
+
                     Ok((last_input, JSON::JsObject( hashmap_json ))) 
                 },   
                 Err(e) => Err(e)                 
@@ -315,37 +558,37 @@ fn json_object<'a>(json_input: &'a str) -> Result<(&'a str, JSON), &'a str>
 
 /// Function returns either a reference to the end of the input string along with the parsed JSON 
 /// or else returns the input str at the point at which the parser failed.
-
+///
 /// # Arguments
 /// Is meant to be called with a string containing the json input to be parsed.
-
+///
 /// # Lifetimes 
 /// Since in either case of the result a reference to the input is returned. 
 /// Therefore, the input must live at least as long as the output.
-
+///
 // If the function returns a Result, describing the kinds of errors that might occur and what conditions might cause those
 // errors to be returned can be helpful to callers so they can write code to handle the different kinds of errors in different ways. 
 /// # Errors 
 /// On error, the function returns the input str at the point at which the parser failed
-
+///
 // Only necessary if the function contains an unsafe block
 // # Safety 
-
+///
 // The scenarios in which the function being documented could panic.
 // # Panics 
-
+///
 // Show example use cases of public functions
 /// # Examples
 /// ```
 /// //Compares JSON output as strings
-/// let arg = r#"{ "FirstName" : "Michael", "Age" : 33 }"#;
+/// let arg = r#"{ "FirstName" : "Michael" }"#;
 /// let output = Parser::parse_json(arg);
 /// let result = format!("{:?}", output);
 /// 
-/// let answer = r#"Ok(("", JsObject({"FirstName": JsString("Michael"), "Age": JsNumber(33.0)})))"#;
+/// let answer = r#"Ok(("", JsObject({"FirstName": JsString("Michael")})))"#;
 /// assert_eq!(result, answer);
 /// ```
-
+//
 // Trys to match every possible json value (ie. null, bool, number, string, array, object)
 // Returns first correct match or else error
 pub fn parse_json<'a>(json_input: &'a str) -> Result<(&'a str, JSON), &'a str>//Result<JSON, &'a str> // impl Parser<JSON>
@@ -353,149 +596,433 @@ pub fn parse_json<'a>(json_input: &'a str) -> Result<(&'a str, JSON), &'a str>//
     //Older JSON specs only allowed the top-level element to be an object or an array.  
     //Now any json value is a valid top level element in a json file
 
-    //Jump table to all possible json parsers
-    let json_parsers: Vec< Box< fn(&str) -> Result<(&str, JSON), &str >>> 
-                        = vec![ Box::new(json_null), Box::new(json_bool),
-                                Box::new(json_string), Box::new(json_number),
-                                Box::new(json_array), Box::new(json_object) ];
-
-    //Try to parse input as every possible json value
-    for func_ptr in json_parsers {
-                                                // Trim to remove leading and trailing whitespace
-        if let Ok((next_input, json)) = func_ptr.parse(json_input.trim()) {
-            return Ok((next_input, json));      //If successfully parsed then next_input should be empty
+    //Every possible json parser, each a different function so none of them needs boxing
+    let json_parsers = alt(( json_null, json_bool, json_string, json_number, json_array, json_object ));
+
+    //Skip the whitespace before the value, and leave out whitespace at the end of the input as it can only follow the last value
+    let (json_input, _) = ws0(json_input.trim_end())?;
+
+    report_progress(json_input);
+    if interrupted() { return Err(json_input); }
+
+    //Try to parse input as every possible json value, if unable to return the input that the parser got furthest into
+    let (next_input, json) = json_parsers.parse(json_input)?;
+    record(|c| c.values += 1);
+    Ok((next_input, json))
+}
+
+
+/// Parses a string containing exactly one json value (surrounded by optional whitespace), using the default `Options`
+///
+/// Unlike `parse_json` this is guaranteed not to panic or overflow the stack on any input, so it is the entry point to use
+/// for untrusted data. Arrays and objects nested more than 128 levels deep are rejected.
+///
+/// # Errors
+/// Returns a `ParseError` with the position of the problem if the input is not valid json,
+/// is followed by anything other than whitespace, or is nested too deeply
+///
+/// # Examples
+/// ```
+/// use Parser::{try_parse, ParseErrorKind};
+///
+/// assert!( try_parse(" [1, 2] ").is_ok() );
+///
+/// let error = try_parse("[1, 2] 3").unwrap_err();
+/// assert_eq!( ParseErrorKind::TrailingCharacters, error.kind() );
+/// assert_eq!( 7, error.offset() );
+/// ```
+pub fn try_parse(input: &str) -> Result<JSON, ParseError>
+{
+    try_parse_with(input, &Options::default())
+}
+
+/// Parses a string containing exactly one json value (surrounded by optional whitespace), see `try_parse`
+///
+/// # Errors
+/// Returns a `ParseError` with the position of the problem if the input is not valid json,
+/// is followed by anything other than whitespace, or is nested deeper than the options allow
+pub fn try_parse_with(input: &str, options: &Options) -> Result<JSON, ParseError>
+{
+    //The grammar has no comments, but the concrete syntax tree can skip them
+    if options.allow_comments {
+        return Cst::parse_with(input, options).map(|cst| cst.to_json());
+    }
+
+    with_progress(options, input, || with_limits(options, || {
+        //Tells a value followed by more input apart from a value that failed to parse
+        let parsed = Cell::new(false);
+        let value = |input| parse_json(input).inspect(|_| parsed.set(true));
+
+        let result = complete(value).parse(input);
+        match result {
+            Ok((_, value)) => Ok(value),
+            Err(rest) if parsed.get() => Err(ParseError::new(ParseErrorKind::TrailingCharacters, input, offset_in(input, rest))),
+            Err(rest) => Err(value_error(input, rest)),
         }
+    }))
+}
+
+/// Parses a string containing exactly one json value as in `try_parse`, giving up once parsing has taken longer than the budget
+///
+/// For services parsing untrusted payloads, which need an upper bound on the time spent on each one.
+/// The time is checked before every value, see `Options::deadline` to combine a deadline with other options.
+///
+/// # Errors
+/// Returns a `ParseError` of kind `TimedOut` if the budget runs out, or any other error of `try_parse`
+pub fn parse_with_deadline(input: &str, budget: Duration) -> Result<JSON, ParseError>
+{
+    let deadline = Instant::now().checked_add(budget);
+    try_parse_with(input, &Options { deadline, ..Options::default() })
+}
+
+/// Parses one json value at the start of the input (after optional whitespace) and returns it along with the number of bytes
+/// consumed, which is the offset just past the end of the value. Anything after the value is left alone, for callers that
+/// embed json in a larger protocol or log line.
+///
+/// # Errors
+/// Returns a `ParseError` if the input does not start with a valid json value, see `try_parse`
+///
+/// # Examples
+/// ```
+/// use Parser::parse_prefix;
+///
+/// let line = "{\"level\" : \"info\"} request served";
+/// let (value, consumed) = parse_prefix(line).unwrap();
+///
+/// assert_eq!( 18, consumed );
+/// assert_eq!( " request served", &line[consumed..] );
+/// assert!( value.get_path("level").is_some() );
+/// ```
+pub fn parse_prefix(input: &str) -> Result<(JSON, usize), ParseError>
+{
+    with_limits(&Options::default(), || {
+        let (rest, value) = parse_value(input, input)?;
+        Ok((value, offset_in(input, rest)))
+    })
+}
+
+// Runs a parse with the limits of the options applied to the thread, restoring the previous limits afterwards
+fn with_limits<T>(options: &Options, parse: impl FnOnce() -> T) -> T
+{
+    let previous = DEPTH.with(|depth| depth.replace(Depth { current: 0, limit: options.max_depth, stopped: None }));
+    let interrupts = options.can_interrupt().then(|| options.clone());
+    let previous_interrupts = INTERRUPTS.with(|i| i.replace(interrupts));
+
+    let previous_raw = RAW_STRINGS.with(|raw| raw.replace(options.raw_strings));
+    let previous_counts = COUNTS.with(|counts| counts.replace(options.stats.as_ref().map(|_| ParseCounts::default())));
+
+    let result = parse();
+
+    DEPTH.with(|depth| depth.set(previous));
+    INTERRUPTS.with(|i| i.replace(previous_interrupts));
+    RAW_STRINGS.with(|raw| raw.set(previous_raw));
+    let counts = COUNTS.with(|counts| counts.replace(previous_counts));
+    if let (Some(stats), Some(counts)) = (&options.stats, counts) {
+        stats.record(&counts);
     }
+    result
+}
 
-    //If unable to parse json value return input that parser failed on
-    Err(json_input)  
+// Returns the byte offset of a remainder returned by the parser within the buffer that it is a slice of
+fn offset_in(buffer: &str, rest: &str) -> usize
+{
+    (rest.as_ptr() as usize).saturating_sub(buffer.as_ptr() as usize).min(buffer.len())
 }
 
+// Parses one value at the start of the input (a slice of the buffer) with whatever limits are currently set for the thread,
+// returning the rest of the input after the value. Errors are positioned within the whole buffer.
+fn parse_value<'a>(buffer: &'a str, input: &'a str) -> Result<(&'a str, JSON), ParseError>
+{
+    parse_json(input).map_err(|rest| value_error(buffer, rest))
+}
+
+// Creates the error for a value that failed to parse at the rest of the buffer
+fn value_error(buffer: &str, rest: &str) -> ParseError
+{
+    let kind = DEPTH.with(|depth| depth.get().stopped).unwrap_or(ParseErrorKind::Syntax);
+    let offset = offset_in(buffer, rest);
+    ParseError::new(kind, buffer, offset).with_path(path::path_at(buffer, offset, false))
+}
+
+
+// Parses the json text of a test, which must be valid
+#[cfg(test)]
+pub(crate) fn json(input: &str) -> JSON
+{
+    try_parse(input).unwrap()
+}
 
 /* (TESTS) */
 #[cfg(test)]
 mod tests {
     use super::*;
+    use combinator::{consumed, preceded, verify};
 
-    #[test] 
-    fn test_str_parser() 
+    #[test]
+    fn test_json_containers()
     {
-        let parse_hello = str_parser("Hello");
+        assert_eq!( Ok(("", JSON::JsArray(vec![]))), parse_json("[]") );
+        assert_eq!( Ok(("", JSON::JsObject(HashMap::new()))), parse_json("{ }") );
+        assert_eq!( Ok(("", JSON::JsArray(vec![JSON::JsArray(vec![]), JSON::JsNull]))), parse_json("[[], null]") );
+        assert_eq!( Ok((" 2]", JSON::JsArray(vec![JSON::JsNull]))), parse_json("[null] 2]") );
 
-        assert_eq!( Ok(("", "Hello")), parse_hello.parse("Hello") );
-        assert_eq!( Err("Yello"), parse_hello.parse("Yello") );
-        assert_eq!( Ok((" Jello", "Hello")), parse_hello.parse("Hello Jello"));
+        assert!( parse_json("[null,]").is_err() );
+        assert!( parse_json(r#"{ "a" : 1, }"#).is_err() );
     }
 
     #[test]
-    fn test_parser_or()
+    fn test_whitespace()
     {
-        let parse_hello = str_parser("Hello"); 
-        let parse_goodbye = str_parser("Goodbye");
-        let parse_or = or(parse_hello, parse_goodbye);
+        //Whitespace between every token, and at the end of the input after the last value
+        let spaced = " {\n\t\"a\" :\r\n [ 1 , true ] , \"b\":null } \n";
+        assert_eq!( Ok(("", parse_json(r#"{"a":[1,true],"b":null}"#).unwrap().1)), parse_json(spaced) );
+        assert_eq!( Ok((" x", JSON::JsNull)), parse_json("null x") );
+    }
 
-        assert_eq!( Err(""), parse_or.parse("") );
-        assert_eq!( Ok(("", "Hello")), parse_or.parse("Hello"));                    //P1 succeeds
-        assert_eq!( Ok(("", "Goodbye")), parse_or.parse("Goodbye"));                //P2 succeeds
-        assert_eq!( Ok((" Goodbye", "Hello")), parse_or.parse("Hello Goodbye"));    //Both succeed
+    #[test]
+    fn test_recognize()
+    {
+        assert_eq!( Ok((",", "-12.50e+3")), number_literal("-12.50e+3,") );
+        assert_eq!( Ok((".e", "1")), number_literal("1.e") );            //Fraction and exponent without digits are left alone
+        assert_eq!( Ok(("1", "0")), number_literal("01") );
+        assert_eq!( Ok(("", "-0.5")), number_literal("-0.5") );
+        assert_eq!( Err("-x"), number_literal("-x") );
+
+        //So literals that are not json are rejected
+        assert!( try_parse("1.").is_err() );
+        assert!( try_parse("[1.e5]").is_err() );
+        assert!( try_parse("01").is_err() );
+        assert!( try_parse("[-01]").is_err() );
+        assert!( try_parse("1e").is_err() );
     }
 
     #[test]
-    fn test_parser_product()
+    fn test_consumed()
     {
-        let p1 = product( str_parser("Goodbye"), str_parser(" Adieu"));
-        let p2 = product( str_parser("Hello"), str_parser(" Adieu"));
-        let p3 = product( str_parser("Hello"), str_parser(" Goodbye"));
+        assert_eq!( Ok((", 2]", ("[1]", JSON::JsArray(vec![json_number("1").unwrap().1])))), consumed(parse_json).parse("[1], 2]") );
+        assert_eq!( Ok(("", (" true", JSON::JsBool(true)))), consumed(parse_json).parse(" true") );      //Includes skipped whitespace
+        assert_eq!( Err("x"), consumed(str_parser("ab")).parse("x") );
+    }
+
+    #[test]
+    fn test_verify()
+    {
+        let short = verify(preceded(str_parser(" "), string_literal), |s| s.chars().count() <= 3);
+        assert_eq!( Ok((",", "abc".to_string())), short.parse(" \"abc\",") );
+        assert_eq!( Err(" \"abcd\","), short.parse(" \"abcd\",") );           //Fails at the start of the value
+        assert_eq!( Err("x"), short.parse(" x") );                           //As does p
+
+        let small = verify(json_number, |n| n.as_f64().is_some_and(|f| f < 256.0));
+        assert!( small.parse("255").is_ok() );
+        assert_eq!( Err("256"), small.parse("256") );
+    }
+
+    #[test]
+    fn test_value()
+    {
+        let p = value(JSON::JsBool(true), str_parser("yes"));
+
+        assert_eq!( Ok((" no", JSON::JsBool(true))), p.parse("yes no") );
+        assert_eq!( Err("no"), p.parse("no") );
+        assert_eq!( Ok(("", JSON::JsBool(false))), json_bool("false") );
+        assert_eq!( Err("nul"), json_null("nul") );
+    }
 
-        assert_eq!( Err(""), p1.parse("") );
-        assert_eq!( Err(""), p2.parse("") );
-        assert_eq!( Err(""), p3.parse("") );
+    #[test]
+    fn test_json_string_escapes()
+    {
+        let (_, parsed) = parse_json(r#"{ "say \"hi\"" : "line\none" }"#).unwrap();
+        let expected = JSON::JsObject(HashMap::from([ ("say \"hi\"".to_string(), JSON::JsString("line\none".to_string())) ]));
+
+        assert_eq!( expected, parsed );
+        assert!( try_parse(r#""bad \q escape""#).is_err() );
+        assert!( try_parse("\"raw\nnewline\"").is_err() );                 //Control characters must be escaped
+        assert!( try_parse("[\"tab\there\"]").is_err() );
+        assert!( try_parse("\"\u{1f}\"").is_err() );
+        assert_eq!( Ok(JSON::JsString("\u{7f}".to_string())), try_parse("\"\u{7f}\"") );
+    }
 
-        assert_eq!( Err("Hello Adieu"), p1.parse("Hello Adieu"));                   //P1 fails
-        assert_eq!( Err(" Goodbye"), p2.parse("Hello Goodbye"));                    //P2 fails
-        assert_eq!( Ok( ("", ("Hello", " Goodbye"))), p3.parse("Hello Goodbye"));   //Both succeed
+    #[test]
+    fn test_json_number_getters()
+    {
+        let (_, id) = parse_json("9007199254740993").unwrap(); //2^53 + 1, which can not be represented exactly as an f64
+        let (_, negative) = parse_json("-12").unwrap();
+
+        assert_eq!( Some(9007199254740993), id.as_u64() );
+        assert_eq!( Some(9007199254740993), id.as_i64() );
+        assert_eq!( Some(-12), negative.as_i64() );
+        assert_eq!( Some(-12.0), negative.as_f64() );
+        assert_eq!( None, JSON::JsNull.as_i64() );
     }
 
     #[test]
-    fn test_parser_quoted_str_literal()
+    fn test_lenient_getters()
     {
-        let parse_quoted_hello = quoted_string_literal(str_parser("Hello")); 
+        let (_, values) = parse_json(r#"[42, "42", " -7 ", "4.5", "abc", "NaN", true, "TRUE", 0, "1", 2]"#).unwrap();
+        let JSON::JsArray(values) = values else { panic!("expected an array") };
 
-        assert_eq!( Err(""), parse_quoted_hello.parse("") );
-        assert_eq!( Err(""), parse_quoted_hello.parse("\"Hello") ); //Err returns empty str since fails to match missing closing quote
-        assert_eq!( Err("Hello\""), parse_quoted_hello.parse("Hello\"") );
+        let f: Vec<Option<f64>> = values.iter().map(|v| v.as_f64_lenient()).collect();
+        let i: Vec<Option<i64>> = values.iter().map(|v| v.as_i64_lenient()).collect();
+        let b: Vec<Option<bool>> = values.iter().map(|v| v.as_bool_lenient()).collect();
 
-        assert_eq!( Ok(("", "Hello")), parse_quoted_hello.parse("\"Hello\""));
+        assert_eq!( vec![Some(42.0), Some(42.0), Some(-7.0), Some(4.5), None, None, None, None, Some(0.0), Some(1.0), Some(2.0)], f );
+        assert_eq!( vec![Some(42), Some(42), Some(-7), None, None, None, None, None, Some(0), Some(1), Some(2)], i );
+        assert_eq!( vec![None, None, None, None, None, None, Some(true), Some(true), Some(false), Some(true), None], b );
     }
 
+    #[test]
+    fn test_take_replace()
+    {
+        let mut doc = parse_json(r#"{ "a" : [1, 2], "b" : null }"#).unwrap().1;
+
+        //Move the array out of "a" and into "b" without cloning it
+        let list = doc.pointer_mut("/a").unwrap().take();
+        let old = doc.pointer_mut("/b").unwrap().replace(list);
+
+        assert_eq!( JSON::JsNull, old );
+        assert_eq!( parse_json(r#"{ "a" : null, "b" : [1, 2] }"#).unwrap().1, doc );
+        assert_eq!( JSON::JsNull, JSON::default() );
+    }
 
+    #[cfg(feature = "base64")]
     #[test]
-    fn test_zero_or_more()
+    fn test_as_base64_bytes()
     {
-        let p1 = zero_or_more(str_parser(" "));
-        let p2 = zero_or_more(str_parser("ab"));
-      
-        assert_eq!( Ok(("", vec![])), p1.parse("") );                       //Successfully match 0 spaces (Note: Returns empty vec)
-        assert_eq!( Ok(("", vec![" "])), p1.parse(" ") );                   //Successfully match single space
-        assert_eq!( Ok(("", vec![" ", " ", " ", " "])), p1.parse("    ") ); //Successfully match 4 spaces
-        assert_ne!( Ok(("", vec![])), p1.parse(" ") );  //Should this match?
+        let (_, blob) = parse_json(r#""SGVsbG8=""#).unwrap();
 
-        assert_eq!( Ok(("", vec![])), p2.parse("") );                           //Successfully match  (Note: Returns empty vec)
-        assert_eq!( Ok(("", vec!["ab"])), p2.parse("ab") );                     //Successfully match single 
-        assert_eq!( Ok(("", vec!["ab", "ab", "ab", "ab"])), p2.parse("abababab") ); //Successfully match 4 
+        assert_eq!( Some(b"Hello".to_vec()), blob.as_base64_bytes() );
+        assert_eq!( None, JSON::JsBool(true).as_base64_bytes() );
     }
 
     #[test]
-    fn test_left()
+    fn test_try_parse()
     {
-        let parser = product( str_parser("Hello"), str_parser(" Goodbye"));
-        let p = left( parser );
+        assert_eq!( Ok(JSON::JsArray(vec![])), try_parse(" [] ") );
+        assert_eq!( Ok(JSON::JsString("héllo wörld".to_string())), try_parse(r#""héllo wörld""#) );
+        assert_eq!( Ok(JSON::JsNull), try_parse("\u{a0}null\u{3000}") );              //Multibyte whitespace
+
+        let error = try_parse("[1, 2,, 3]").unwrap_err();
+        assert_eq!( (ParseErrorKind::Syntax, 6), (error.kind(), error.offset()) );
+
+        let error = try_parse("{ \"a\" : 1 } }").unwrap_err();
+        assert_eq!( (ParseErrorKind::TrailingCharacters, 12), (error.kind(), error.offset()) );
 
-        assert_eq!( Err(""), p.parse("") );
-        assert_eq!( Ok(("", "Hello")), p.parse("Hello Goodbye") );           
-        assert_eq!( Ok((" Again", "Hello")), p.parse("Hello Goodbye Again") );           
+        #[cfg(not(feature = "arbitrary_precision"))]
+        assert_eq!( ParseErrorKind::Syntax, try_parse("[1e999]").unwrap_err().kind() );   //Number overflows an f64
+        assert_eq!( ParseErrorKind::Syntax, try_parse("").unwrap_err().kind() );
     }
 
     #[test]
-    fn test_right()
+    fn test_parse_prefix()
     {
-        let parser = product( str_parser("Hello"), str_parser(" Goodbye"));
-        let p = right( parser );
+        assert_eq!( Ok((JSON::JsBool(true), 6)), parse_prefix("  true  ") );
+        assert_eq!( Ok((try_parse("[1]").unwrap(), 3)), parse_prefix("[1]\x00[2]") );
+        assert_eq!( Ok((try_parse("12").unwrap(), 2)), parse_prefix("12abc") );
+        assert_eq!( Ok((JSON::JsString("é".to_string()), 4)), parse_prefix("\"é\"\n") );
 
-        assert_eq!( Err(""), p.parse("") );
-        assert_eq!( Ok(("", " Goodbye")), p.parse("Hello Goodbye") );           
-        assert_eq!( Ok((" Again", " Goodbye")), p.parse("Hello Goodbye Again") ); 
+        assert_eq!( 3, parse_prefix("[1,]").unwrap_err().offset() );
+        assert_eq!( 0, parse_prefix("").unwrap_err().offset() );
     }
 
     #[test]
-    fn test_match_whitespace_char()
+    fn test_try_parse_depth()
     {
-        assert_eq!( Ok(("abc", "")), match_whitespace_char("abc") );  
-        assert_eq!( Ok(("abc", " ")), match_whitespace_char(" abc") );    
+        let nested = |depth: usize| "[".repeat(depth) + &"]".repeat(depth);
 
-        assert_eq!( Ok(("", "")), match_whitespace_char("") );          //Successfully match empty string
-        assert_eq!( Ok(("", "\n")), match_whitespace_char("\n") );      //Successfully match newline char
-        assert_eq!( Ok(("", "\t")), match_whitespace_char("\t") );      //Successfully match tab char          
-        assert_eq!( Ok(("", "    ")), match_whitespace_char("    ") );  //Successfully match 4 spaces
+        assert!( try_parse(&nested(MAX_DEPTH)).is_ok() );
+        assert_eq!( ParseErrorKind::TooDeep, try_parse(&nested(MAX_DEPTH + 1)).unwrap_err().kind() );
+        assert_eq!( ParseErrorKind::TooDeep, try_parse(&"{\"a\":[".repeat(100_000)).unwrap_err().kind() );
+
+        //The limit is not left hit for the next parse
+        assert_eq!( ParseErrorKind::Syntax, try_parse("[[").unwrap_err().kind() );
+        assert!( try_parse(&nested(MAX_DEPTH)).is_ok() );
     }
 
     #[test]
-    fn test_match_until_double_quote() 
+    fn test_progress()
     {
-        assert_eq!( Ok(("", "")), match_until_double_quote("") );  
-        assert_eq!( Ok(("\"abc", "")), match_until_double_quote("\"abc") );  
-        assert_eq!( Ok(("\"", "abc")), match_until_double_quote("abc\"") );  
-        assert_eq!( Ok(("\" 456", "abc 123 ")), match_until_double_quote("abc 123 \" 456") );  
-        assert_eq!( Ok(("\" 456", "abc -+= 123 ")), match_until_double_quote("abc -+= 123 \" 456") ); 
+        use std::sync::{Arc, Mutex};
+
+        let reports = Arc::new(Mutex::new(vec![]));
+        let seen = reports.clone();
+        let options = Options { progress: Some(Progress::new(move |bytes, total| seen.lock().unwrap().push((bytes, total))).every(100)), ..Options::default() };
+
+        let input = format!("[{}0]", "12345, ".repeat(100));
+        assert!( try_parse_with(&input, &options).is_ok() );
+
+        let reports = reports.lock().unwrap();
+        assert_eq!( 6, reports.len() );                                 //At most one report per 100 of the 703 bytes
+        assert!( reports.windows(2).all(|pair| pair[1].0 >= pair[0].0 + 100) );
+        assert!( reports.iter().all(|&(bytes, total)| bytes >= 100 && bytes < total && total == input.len()) );
+
+        //The hook is only set for the parse it was given to
+        assert!( try_parse(&input).is_ok() );
+        assert_eq!( 6, reports.len() );
     }
 
     #[test]
-    fn test_match_digits_chars()
+    fn test_cancel()
     {
-        assert_eq!( Err("abc"), match_digit_chars("abc") );    
-        assert_eq!( Err(""), match_digit_chars("") );                 //Fails to match empty string
+        let token = CancelToken::new();
+        let input = format!("[{}0]", "12345, ".repeat(100));
+
+        //Cancelled part way through, by the progress hook so that it happens at a known point
+        let cancel = token.clone();
+        let options = Options { cancel: Some(token.clone()), progress: Some(Progress::new(move |_, _| cancel.cancel()).every(100)), ..Options::default() };
+        let error = try_parse_with(&input, &options).unwrap_err();
+        assert_eq!( (ParseErrorKind::Cancelled, 106), (error.kind(), error.offset()) );
+
+        let options = Options { cancel: Some(token), ..Options::default() };
+        assert_eq!( ParseErrorKind::Cancelled, try_parse_with("[]", &Options { allow_comments: true, ..options.clone() }).unwrap_err().kind() );
+        assert_eq!( ParseErrorKind::Cancelled, parse_many_with("1 2", &options).next().unwrap().unwrap_err().kind() );
+        assert!( matches!(parse_reader("[]".as_bytes(), &options), Err(ReadError::Parse(e)) if e.kind() == ParseErrorKind::Cancelled) );
+
+        //Only parses given the token are stopped
+        assert!( try_parse_with(&input, &Options::default()).is_ok() );
+    }
 
-        assert_eq!( Ok(("", "123")), match_digit_chars("123") );      //Successfully match integer
-        assert_eq!( Ok(("", "12.34")), match_digit_chars("12.34") );  //Successfully match float   
+    #[test]
+    fn test_parse_with_deadline()
+    {
+        let input = format!("[{}0]", "12345, ".repeat(100));
+        assert!( parse_with_deadline(&input, Duration::from_secs(60)).is_ok() );
+
+        let error = parse_with_deadline(&input, Duration::ZERO).unwrap_err();
+        assert_eq!( (ParseErrorKind::TimedOut, 0), (error.kind(), error.offset()) );
+
+        let options = Options { deadline: Some(Instant::now()), allow_comments: true, ..Options::default() };
+        assert_eq!( ParseErrorKind::TimedOut, try_parse_with(&input, &options).unwrap_err().kind() );
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_raw_strings()
+    {
+        let input = r#"{ "a\tb" : ["x\u00e9\"", "\q"] }"#;
+        let expected = parse_json(r#"{ "a\\tb" : ["x\\u00e9\\\"", "\\q"] }"#).unwrap().1;
+
+        let options = Options { raw_strings: true, ..Options::default() };
+        assert_eq!( expected, try_parse_with(input, &options).unwrap() );
+        assert_eq!( expected, try_parse_with(input, &Options { allow_comments: true, ..options }).unwrap() );
+
+        //Escapes are still replaced (and checked) by default
+        assert_eq!( ParseErrorKind::Syntax, try_parse(input).unwrap_err().kind() );
+    }
+
+    #[test]
+    fn test_try_parse_never_panics()
+    {
+        let sample = r#"{ "name" : "Zoë", "tags" : ["a", -1.5e3, true, null], "nested" : { "x" : [[], {}] } }"#;
+
+        //Every prefix (cut at every char boundary) and every single character replacement must fail gracefully
+        for (i, _) in sample.char_indices() {
+            let _ = try_parse(&sample[..i]);
+
+            for replacement in ["", "\"", "[", "}", ",", "é", "\u{a0}", "-", "e"] {
+                let ch_len = sample[i..].chars().next().map_or(0, char::len_utf8);
+                let mutated = format!("{}{}{}", &sample[..i], replacement, &sample[i + ch_len..]);
+                let _ = try_parse(&mutated);
+            }
+        }
+        assert!( try_parse(sample).is_ok() );
+    }
+}
@@ -0,0 +1,329 @@
+//! Best-effort parsing of broken documents, for tools that must show something for any input
+//!
+//! The input is lexed with unknown characters kept as garbage, then read with recovery: a bad array element becomes null,
+//! a bad object member is left out, and a missing closing bracket is assumed, each problem being recorded as an error.
+
+/* (IMPORTS) */
+use std::collections::HashMap;
+
+use crate::lexer::{lex, match_digit_chars, Lexeme, LexemeKind};
+use crate::{JsonPath, Number, ParseError, ParseErrorKind, PathSegment, JSON, MAX_DEPTH};
+
+
+/// Parses a string as a json value, always returning a value along with the problems found in it (none for valid json)
+///
+/// Where the input is broken a value is made up: array elements that can not be read are replaced with null, object members
+/// that can not be read are skipped, unclosed arrays and objects end with the input and anything after the value is ignored.
+/// Each error is positioned where its problem was found, with the path of the value that it is in.
+///
+/// # Examples
+/// ```
+/// use Parser::{parse_lossy, try_parse};
+///
+/// let (value, errors) = parse_lossy(r#"{ "a" : [1, x, 3], "b" : , "c" : true"#);
+///
+/// assert_eq!( try_parse(r#"{ "a" : [1, null, 3], "c" : true }"#).unwrap(), value );
+/// assert_eq!( 3, errors.len() );
+/// assert_eq!( "invalid json at line 1 column 13 (in $.a[1])", errors[0].to_string() );
+/// ```
+pub fn parse_lossy(input: &str) -> (JSON, Vec<ParseError>)
+{
+    let mut reader = Lossy { input, lexemes: lexemes(input), pos: 0, path: JsonPath::root(), errors: vec![] };
+
+    let value = reader.value(0).unwrap_or(JSON::JsNull);
+    if reader.peek().is_some() {
+        let offset = reader.offset();
+        reader.error(ParseErrorKind::TrailingCharacters, offset);
+    }
+    (value, reader.errors)
+}
+
+// Lexes the whole input, skipping whitespace. A run of characters that can not start a lexeme is kept as one lexeme
+// of no kind, so a problem in it is only reported once.
+fn lexemes(input: &str) -> Vec<(Option<LexemeKind>, Lexeme<'_>)>
+{
+    let mut lexemes = vec![];
+    let mut pos = 0;
+
+    while pos < input.len() {
+        let mut lexer = lex(&input[pos..]);
+        for lexeme in lexer.by_ref().map_while(Result::ok) {
+            if lexeme.kind != LexemeKind::Whitespace {
+                lexemes.push((Some(lexeme.kind), Lexeme { start: pos + lexeme.start, ..lexeme }));
+            }
+        }
+
+        //The lexer stopped at a character it does not know, or at the end of the input
+        let start = pos + lexer.offset();
+        let Some(ch) = input[start..].chars().next() else { break };
+        pos = start + ch.len_utf8();
+
+        match lexemes.last_mut() {
+            Some((None, garbage)) if garbage.span().end == start => garbage.text = &input[garbage.start..pos],
+            _ => lexemes.push((None, Lexeme { kind: LexemeKind::Whitespace, text: &input[start..pos], start })), //The kind is unused
+        }
+    }
+    lexemes
+}
+
+// Reads values from the lexemes of an input, recording the problems in it instead of stopping at the first one
+struct Lossy<'a> {
+    input: &'a str,
+    lexemes: Vec<(Option<LexemeKind>, Lexeme<'a>)>,
+    pos: usize,
+    path: JsonPath,         //Of the value being read
+    errors: Vec<ParseError>,
+}
+
+impl<'a> Lossy<'a> {
+
+    // Returns the kind of the next lexeme (None for garbage), or None at the end of the input
+    fn peek(&self) -> Option<Option<LexemeKind>>
+    {
+        self.lexemes.get(self.pos).map(|(kind, _)| *kind)
+    }
+
+    // Returns the byte offset of the next lexeme, or the length of the input at its end
+    fn offset(&self) -> usize
+    {
+        self.lexemes.get(self.pos).map_or(self.input.len(), |(_, lexeme)| lexeme.start)
+    }
+
+    // Records a problem in the value being read, unless one was already recorded at the offset
+    // (ie. a closing bracket where a value is expected is found by the value and again by its container)
+    fn error(&mut self, kind: ParseErrorKind, offset: usize)
+    {
+        if self.errors.last().is_some_and(|e| e.offset() == offset) { return; }
+        self.errors.push(ParseError::new(kind, self.input, offset).with_path(self.path.clone()));
+    }
+
+    // Consumes the next lexeme if it is of the kind
+    fn next_if(&mut self, kind: LexemeKind) -> bool
+    {
+        let matched = self.peek() == Some(Some(kind));
+        if matched { self.pos += 1; }
+        matched
+    }
+
+    // Reads a value, or returns None after recording the problem if there is no value to read
+    // Separators and closing brackets are left for the enclosing array or object to recover at
+    fn value(&mut self, depth: usize) -> Option<JSON>
+    {
+        let offset = self.offset();
+        let Some(&(kind, lexeme)) = self.lexemes.get(self.pos) else {
+            self.error(ParseErrorKind::Syntax, offset);
+            return None;
+        };
+        if !matches!(kind, Some(LexemeKind::Comma | LexemeKind::EndArray | LexemeKind::EndObject)) { self.pos += 1; }
+
+        //A scalar that can not be converted is still a value, so it is replaced rather than skipped
+        let scalar = |this: &mut Lossy, value: Option<JSON>| Some(value.unwrap_or_else(|| {
+            this.error(ParseErrorKind::Syntax, offset);
+            JSON::JsNull
+        }));
+
+        match kind {
+            Some(LexemeKind::Null) => Some(JSON::JsNull),
+            Some(LexemeKind::True) => Some(JSON::JsBool(true)),
+            Some(LexemeKind::False) => Some(JSON::JsBool(false)),
+            Some(LexemeKind::Number) => {
+                let valid = match_digit_chars(lexeme.text) == Ok(("", lexeme.text));
+                scalar(self, Number::from_literal(lexeme.text).filter(|_| valid).map(JSON::JsNumber))
+            }
+            Some(LexemeKind::String) => scalar(self, lexeme.string_value().map(JSON::JsString)),
+            Some(LexemeKind::BeginArray | LexemeKind::BeginObject) if depth >= MAX_DEPTH => {
+                self.error(ParseErrorKind::TooDeep, offset);
+                self.pos -= 1;
+                self.skip_nested();
+                Some(JSON::JsNull)
+            }
+            Some(LexemeKind::BeginArray) => Some(self.array(depth + 1)),
+            Some(LexemeKind::BeginObject) => Some(self.object(depth + 1)),
+            _ => {
+                self.error(ParseErrorKind::Syntax, offset);
+                None
+            }
+        }
+    }
+
+    // Reads the elements of an array after its opening bracket, an element that can not be read becomes null
+    fn array(&mut self, depth: usize) -> JSON
+    {
+        let mut elements = vec![];
+        if self.next_if(LexemeKind::EndArray) { return JSON::JsArray(elements); }
+
+        loop {
+            self.path.push(PathSegment::Index(elements.len()));
+            let element = self.value(depth);
+            self.path.pop();
+
+            if element.is_none() { self.skip_to_separator(); }
+            elements.push(element.unwrap_or(JSON::JsNull));
+
+            if !self.separator(LexemeKind::EndArray) { return JSON::JsArray(elements); }
+        }
+    }
+
+    // Reads the members of an object after its opening brace, a member that can not be read is skipped
+    fn object(&mut self, depth: usize) -> JSON
+    {
+        let mut members = HashMap::new();
+        if self.next_if(LexemeKind::EndObject) { return JSON::JsObject(members); }
+
+        loop {
+            let member = self.key().and_then(|key| {
+                self.path.push(PathSegment::Key(key.clone()));
+                let value = self.value(depth);
+                self.path.pop();
+                Some((key, value?))
+            });
+
+            match member {
+                Some((key, value)) => { members.insert(key, value); }
+                None => self.skip_to_separator(),
+            }
+
+            if !self.separator(LexemeKind::EndObject) { return JSON::JsObject(members); }
+        }
+    }
+
+    // Reads an object key and the colon after it
+    fn key(&mut self) -> Option<String>
+    {
+        let offset = self.offset();
+        let key = match self.lexemes.get(self.pos) {
+            Some((Some(LexemeKind::String), lexeme)) => lexeme.string_value(),
+            _ => None,
+        };
+        let Some(key) = key else {
+            self.error(ParseErrorKind::Syntax, offset);
+            return None;
+        };
+        self.pos += 1;
+
+        if !self.next_if(LexemeKind::Colon) {
+            let offset = self.offset();
+            self.error(ParseErrorKind::Syntax, offset);
+            return None;
+        }
+        Some(key)
+    }
+
+    // Reads the separator after an entry of an array or object, returning whether another entry follows
+    // A missing or mismatched closing bracket ends the container, leaving a mismatched one for an enclosing container
+    fn separator(&mut self, close: LexemeKind) -> bool
+    {
+        if self.next_if(close) { return false; }
+
+        let offset = self.offset();
+        match self.peek() {
+            Some(Some(LexemeKind::Comma)) => {
+                self.pos += 1;
+                if !self.next_if(close) { return true; }
+                self.error(ParseErrorKind::Syntax, offset + 1);     //Trailing comma
+                false
+            }
+            Some(_) => {
+                self.error(ParseErrorKind::Syntax, offset);
+                self.skip_to_separator();
+                match self.peek() {
+                    Some(Some(LexemeKind::Comma)) => self.separator(close),
+                    _ => {
+                        self.next_if(close);
+                        false
+                    }
+                }
+            }
+            None => {
+                self.error(ParseErrorKind::Syntax, offset);
+                false
+            }
+        }
+    }
+
+    // Skips lexemes up to the next comma or closing bracket that is not within a nested array or object
+    fn skip_to_separator(&mut self)
+    {
+        while let Some(kind) = self.peek() {
+            match kind {
+                Some(LexemeKind::Comma | LexemeKind::EndArray | LexemeKind::EndObject) => return,
+                Some(LexemeKind::BeginArray | LexemeKind::BeginObject) => self.skip_nested(),
+                _ => self.pos += 1,
+            }
+        }
+    }
+
+    // Skips an array or object along with everything nested in it, without recording problems in it
+    fn skip_nested(&mut self)
+    {
+        let mut open = 0;
+        while let Some(kind) = self.peek() {
+            self.pos += 1;
+            match kind {
+                Some(LexemeKind::BeginArray | LexemeKind::BeginObject) => open += 1,
+                Some(LexemeKind::EndArray | LexemeKind::EndObject) => open -= 1,
+                _ => {}
+            }
+            if open == 0 { return; }
+        }
+    }
+}
+
+
+/* (TESTS) */
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::json;
+
+    // Returns the kind, offset & path of each error
+    fn errors(input: &str) -> Vec<(ParseErrorKind, usize, String)>
+    {
+        parse_lossy(input).1.iter().map(|e| (e.kind(), e.offset(), e.path().to_string())).collect()
+    }
+
+    #[test]
+    fn test_valid_input()
+    {
+        let input = r#"{ "a" : [1, -2.5e3, "é"], "b" : { "c" : null, "d" : [true, false, {}, []] } }"#;
+        assert_eq!( (json(input), vec![]), parse_lossy(input) );
+    }
+
+    #[test]
+    fn test_recovery()
+    {
+        use ParseErrorKind::{Syntax, TrailingCharacters};
+
+        //Bad elements become null, bad members are skipped
+        assert_eq!( json(r#"[1, null, null, 4]"#), parse_lossy(r#"[1, tru, "\x", 4]"#).0 );
+        assert_eq!( vec![(Syntax, 4, "[1]".to_string()), (Syntax, 9, "[2]".to_string())], errors(r#"[1, tru, "\x", 4]"#) );
+
+        let input = r#"{ "a" 1, "b" : 2, 3 : 4, "c" : [,, "d" : [3 }"#;
+        assert_eq!( json(r#"{ "b" : 2, "c" : [null, null, "d"] }"#), parse_lossy(input).0 );
+        assert_eq!( vec![(Syntax, 6, String::new()), (Syntax, 18, String::new()), (Syntax, 32, "c[0]".to_string()),
+                         (Syntax, 33, "c[1]".to_string()), (Syntax, 39, "c".to_string()), (Syntax, 45, String::new())], errors(input) );
+
+        //Trailing commas, missing separators and brackets, and input after the value
+        assert_eq!( json("[[1], [2]]"), parse_lossy("[[1,], [2 3").0 );
+        assert_eq!( vec![(Syntax, 4, "[0]".to_string()), (Syntax, 10, "[1]".to_string()), (Syntax, 11, String::new())], errors("[[1,], [2 3") );
+        assert_eq!( (json(r#"{ "a" : [1] }"#), vec![(Syntax, 11, "a".to_string())]), (parse_lossy(r#"{ "a" : [1 }"#).0, errors(r#"{ "a" : [1 }"#)) );
+        assert_eq!( (json("[1]"), vec![(TrailingCharacters, 4, String::new())]), (parse_lossy("[1] 2").0, errors("[1] 2")) );
+
+        //A closing bracket where a value is expected is reported once
+        assert_eq!( vec![(Syntax, 8, "a".to_string())], errors(r#"{ "a" : ]"#) );
+    }
+
+    #[test]
+    fn test_garbage()
+    {
+        //A run of unknown characters is one error, and an empty input is null
+        assert_eq!( (json("[null, 2]"), vec![(ParseErrorKind::Syntax, 1, "[0]".to_string())]), (parse_lossy("[@#é, 2]").0, errors("[@#é, 2]")) );
+        assert_eq!( (JSON::JsNull, vec![(ParseErrorKind::Syntax, 0, String::new())]), (parse_lossy("").0, errors("")) );
+        assert_eq!( json(r#"["a", null]"#), parse_lossy(r#"["a", "b"#).0 );
+
+        let nested = format!("[{}{}, 1]", "[".repeat(MAX_DEPTH), "]".repeat(MAX_DEPTH));
+        let (value, errors) = parse_lossy(&nested);
+        assert_eq!( (ParseErrorKind::TooDeep, 1), (errors[0].kind(), errors.len()) );
+        assert_eq!( Some(&json("1")), value.pointer("/1") );
+    }
+}
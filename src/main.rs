@@ -1,43 +1,614 @@
 
 #![allow(non_snake_case)]
 
-use std::io::{Read, Write}; //Read file to string
+use std::env;
+use std::io::{self, BufRead, IsTerminal, Read, Write}; //Read file to string
 use std::fs::File; //For parser unit test
+use std::process::ExitCode;
 
-use Parser::parse_json;
+use Parser::filter::Filter;
+use Parser::generator::Generator;
+use Parser::schema::Schema;
+use Parser::{ndjson, try_parse, ArrayMerge, JsonPath, ParseError, JSON};
 
-fn main() {
+const USAGE: &str = "usage:
+  Parser                                                  parse the sample file
+  Parser fmt [--color auto|always|never] [--sort-keys] [--ascii] [--canonical] [FILE]
+  Parser convert [--from json|ndjson|json5|msgpack|cbor|csv] [--to json|ndjson|json5|msgpack|cbor|csv|yaml] [FILE]
+  Parser merge [--arrays replace|concat] BASE OVERLAY...
+  Parser patch [--merge] DOCUMENT PATCH
+  Parser validate --schema SCHEMA [FILE]
+  Parser select PATHS [FILE]
+  Parser filter [--compact] FILTER [FILE]
+  Parser redact [--mask TEXT] PATTERNS [FILE]
+  Parser flatten [--object] [FILE]
+  Parser tail [-f] [--path PATH] FILE
+  Parser generate [--seed N] [--count N] [--max-depth N] [--max-len N] [--pretty]
+A FILE of - is stdin, and an http:// or https:// URL is fetched (with the http feature).";
+
+fn main() -> ExitCode {
+
+    let args: Vec<String> = env::args().skip(1).collect();
+
+    let result = match args.first().map(String::as_str) {
+        None => sample().map_err(|e| e.to_string()),
+        Some("fmt") => fmt(Args(args[1..].to_vec())),
+        Some("convert") => convert(Args(args[1..].to_vec())),
+        Some("merge") => merge(Args(args[1..].to_vec())),
+        Some("patch") => patch(Args(args[1..].to_vec())),
+        Some("validate") => validate(Args(args[1..].to_vec())),
+        Some("select") => select(Args(args[1..].to_vec())),
+        Some("filter") => filter(Args(args[1..].to_vec())),
+        Some("redact") => redact(Args(args[1..].to_vec())),
+        Some("flatten") => flatten(Args(args[1..].to_vec())),
+        Some("tail") => tail(Args(args[1..].to_vec())),
+        Some("generate") => generate(Args(args[1..].to_vec())),
+        Some("-h" | "--help") => {
+            println!("{USAGE}");
+            Ok(())
+        }
+        Some(other) => Err(format!("unknown subcommand '{other}'\n{USAGE}")),
+    };
+
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("ERROR: {e}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+// Parses the sample input file into its output file, which is what the program did before it had subcommands
+fn sample() -> std::io::Result<()> {
 
     let file_name = "arr2";
-   
-    //Input JSON File 
-    let mut input_file = File::open(format!("./json_test_samples/input/{file_name}.json")).unwrap();
+
+    //Input JSON File
+    let mut input_file = File::open(format!("./json_test_samples/input/{file_name}.json"))?;
 
     //String for json from file
-    let mut json_str = String::new();                               
+    let mut json_str = String::new();
 
     //Read file into string
-    input_file.read_to_string(&mut json_str).unwrap();
+    input_file.read_to_string(&mut json_str)?;
 
 
-    //Pretty print out parsed json output 
-    match parse_json(&json_str) {
+    //Pretty print out parsed json output
+    match try_parse(&json_str) {
 
-        // If a portion of the json str remains (besides trailing newlines), then the parser failed at the start of the remaining json str
-        Ok((_unparsed, result)) => {  
+        Ok(result) => {
 
             //Output JSON file
-            let mut output_file = File::create(format!("./json_test_samples/output/{file_name}.txt")).unwrap();
-            
-            //Convert to string
-            let result_str =  format!("{result:#?}"); 
+            let mut output_file = File::create(format!("./json_test_samples/output/{file_name}.json"))?;
+
+            //Convert to pretty printed json string
+            let result_str =  format!("{:#}\n", result.display_json());
 
             //Write string to file
-            output_file.write_all(result_str.as_bytes()).unwrap();
-        } 
-        Err(e) => println!("ERROR: \n {e:?}") // Only throws error if unable to parse top level json element
+            output_file.write_all(result_str.as_bytes())?;
+        }
+        Err(e) => println!("ERROR: \n {e}") // Reports the line & column at which the input stopped being valid json
     }
-    
+
     println!("END!");
+    Ok(())
+}
+
+
+/* (ARGUMENTS) */
+
+// Arguments of a subcommand, options are taken out by name and whatever is left are the positional arguments
+struct Args(Vec<String>);
+
+impl Args {
+
+    // Takes out an option with a value, given as "--name value" or "--name=value"
+    fn value(&mut self, name: &str) -> Result<Option<String>, String>
+    {
+        let Some(i) = self.0.iter().position(|arg| arg == name || arg.starts_with(&format!("{name}="))) else { return Ok(None) };
+
+        let arg = self.0.remove(i);
+        match arg.split_once('=') {
+            Some((_, value)) => Ok(Some(value.to_string())),
+            None if i < self.0.len() => Ok(Some(self.0.remove(i))),
+            None => Err(format!("{name} needs a value")),
+        }
+    }
+
+    // Takes out an option with a number as its value
+    fn number<T: std::str::FromStr>(&mut self, name: &str) -> Result<Option<T>, String>
+    {
+        self.value(name)?.map(|value| value.parse().map_err(|_| format!("{name} expects a number, not '{value}'"))).transpose()
+    }
+
+    // Takes out a flag, returns whether it was given
+    fn switch(&mut self, name: &str) -> bool
+    {
+        let given = self.0.iter().any(|arg| arg == name);
+        self.0.retain(|arg| arg != name);
+        given
+    }
+
+    // Returns the positional arguments, failing on any option that was not taken out ("-" is stdin, not an option)
+    fn positional(self, most: usize) -> Result<Vec<String>, String>
+    {
+        if let Some(unknown) = self.0.iter().find(|arg| arg.starts_with('-') && arg.len() > 1) {
+            return Err(format!("unknown option '{unknown}'\n{USAGE}"));
+        }
+        if self.0.len() > most { return Err(format!("too many arguments\n{USAGE}")); }
+        Ok(self.0)
+    }
+}
+
+// Opens a file, or stdin if there is no file or it is "-", decompressing it as it is read if it is compressed (with the gzip or zstd feature)
+// An http:// or https:// URL is fetched instead (with the http feature)
+fn open_input(path: Option<&String>) -> io::Result<Box<dyn BufRead>>
+{
+    let reader: Box<dyn BufRead> = match path.map(String::as_str) {
+        None | Some("-") => Box::new(io::stdin().lock()),
+        Some(url) if url.starts_with("http://") || url.starts_with("https://") => fetch(url)?,
+        Some(path) => Box::new(io::BufReader::new(File::open(path)?)),
+    };
+
+    #[cfg(any(feature = "gzip", feature = "zstd"))]
+    return Parser::decompress(reader);
+
+    #[cfg(not(any(feature = "gzip", feature = "zstd")))]
+    return Ok(reader);
+}
+
+// Returns a reader of the body of a response to a GET request, failing if the status is not a success
+#[cfg(feature = "http")]
+fn fetch(url: &str) -> io::Result<Box<dyn BufRead>>
+{
+    let response = ureq::get(url).call().map_err(io::Error::other)?;
+    Ok(Box::new(io::BufReader::new(response.into_body().into_reader())))
+}
+
+#[cfg(not(feature = "http"))]
+fn fetch(_url: &str) -> io::Result<Box<dyn BufRead>>
+{
+    Err(io::Error::new(io::ErrorKind::Unsupported, "fetching URLs needs the http feature"))
+}
+
+// Returns the name of an input in error messages
+fn input_name(path: Option<&String>) -> &str
+{
+    match path.map(String::as_str) {
+        None | Some("-") => "stdin",
+        Some(path) => path,
+    }
+}
+
+// Reads the text of a file, or stdin as in open_input
+fn read_input(path: Option<&String>) -> Result<String, String>
+{
+    let mut text = String::new();
+    open_input(path).and_then(|mut reader| reader.read_to_string(&mut text)).map_err(|e| format!("{}: {e}", input_name(path)))?;
+    Ok(text)
+}
+
+// Reads the bytes of a file, or stdin as in open_input
+fn read_bytes(path: Option<&String>) -> Result<Vec<u8>, String>
+{
+    let mut bytes = vec![];
+    open_input(path).and_then(|mut reader| reader.read_to_end(&mut bytes)).map_err(|e| format!("{}: {e}", input_name(path)))?;
+    Ok(bytes)
+}
+
+// Reads and parses a json file, or stdin as in read_input
+fn read_json(path: Option<&String>) -> Result<JSON, String>
+{
+    let text = read_input(path)?;
+    try_parse(&text).map_err(|e| format!("{}: {e}", path.map_or("stdin", |path| path.as_str())))
+}
+
+// Writes text to stdout
+fn write_output(text: &str) -> Result<(), String>
+{
+    write_bytes(text.as_bytes())
+}
+
+// Writes bytes to stdout, as the binary formats are not text
+fn write_bytes(bytes: &[u8]) -> Result<(), String>
+{
+    io::stdout().lock().write_all(bytes).map_err(|e| format!("stdout: {e}"))
+}
+
+
+/* (COLOR) */
+
+// ANSI escape codes for the parts of a pretty printed document, and for the position of an error
+const KEY: &str = "\x1b[34m";
+const STRING: &str = "\x1b[32m";
+const NUMBER: &str = "\x1b[36m";
+const LITERAL: &str = "\x1b[33m";
+const PUNCTUATION: &str = "\x1b[90m";
+const ERROR: &str = "\x1b[1;31m";
+const RESET: &str = "\x1b[0m";
+
+// Takes out the --color option, by default output is only colored when stdout is a terminal and NO_COLOR is not set
+fn color_option(args: &mut Args) -> Result<bool, String>
+{
+    match args.value("--color")?.as_deref() {
+        None | Some("auto") => Ok(io::stdout().is_terminal() && env::var_os("NO_COLOR").is_none()),
+        Some("always") => Ok(true),
+        Some("never") => Ok(false),
+        Some(other) => Err(format!("unknown color mode '{other}', expected auto, always or never")),
+    }
+}
+
+// Appends text in a color
+fn paint(out: &mut String, color: &str, text: &str)
+{
+    out.push_str(color);
+    out.push_str(text);
+    out.push_str(RESET);
+}
+
+// Appends a value pretty printed as by display_json, with keys, strings, numbers, literals and punctuation colored
+fn write_colored(out: &mut String, value: &JSON, level: usize)
+{
+    let indent = |out: &mut String, level: usize| {
+        out.push('\n');
+        out.push_str(&"  ".repeat(level));
+    };
+
+    match value {
+        JSON::JsNull | JSON::JsBool(_) => paint(out, LITERAL, &value.display_json().to_string()),
+        JSON::JsNumber(_) => paint(out, NUMBER, &value.display_json().to_string()),
+        JSON::JsString(_) => paint(out, STRING, &value.display_json().to_string()),
+        JSON::JsArray(vec) => {
+            paint(out, PUNCTUATION, "[");
+            for (i, element) in vec.iter().enumerate() {
+                if i > 0 { paint(out, PUNCTUATION, ","); }
+                indent(out, level + 1);
+                write_colored(out, element, level + 1);
+            }
+            if !vec.is_empty() { indent(out, level); }
+            paint(out, PUNCTUATION, "]");
+        }
+        JSON::JsObject(map) => {
+            let mut members: Vec<(&String, &JSON)> = map.iter().collect();
+            members.sort_by(|a, b| a.0.cmp(b.0));
+
+            paint(out, PUNCTUATION, "{");
+            for (i, (key, member)) in members.iter().enumerate() {
+                if i > 0 { paint(out, PUNCTUATION, ","); }
+                indent(out, level + 1);
+                paint(out, KEY, &JSON::JsString(key.to_string()).display_json().to_string());
+                paint(out, PUNCTUATION, ":");
+                out.push(' ');
+                write_colored(out, member, level + 1);
+            }
+            if !members.is_empty() { indent(out, level); }
+            paint(out, PUNCTUATION, "}");
+        }
+    }
+}
+
+// Chars of the line shown on either side of an error, so a long line (ie. a minified document) is not printed whole
+const SNIPPET_CONTEXT: usize = 40;
+
+// Describes a parse error along with the part of the line of input around it and a caret under its column,
+// the character at the error is highlighted if colored. Clipped ends of the line are marked with …
+fn describe_error(name: &str, text: &str, e: &ParseError, color: bool) -> String
+{
+    let line: Vec<char> = text.lines().nth(e.line() - 1).unwrap_or("").chars().collect();
+    let column = (e.column() - 1).min(line.len());
+    let start = column.saturating_sub(SNIPPET_CONTEXT);
+    let end = (column + 1 + SNIPPET_CONTEXT).min(line.len());
+
+    let before: String = line[start..column].iter().collect();
+    let at: String = line.get(column).map(char::to_string).unwrap_or_default();
+    let after: String = line[(column + 1).min(end)..end].iter().collect();
+    let (clipped_start, clipped_end) = (if start > 0 { "…" } else { "" }, if end < line.len() { "…" } else { "" });
+
+    let (on, off) = if color { (ERROR, RESET) } else { ("", "") };
+    let indent = " ".repeat(clipped_start.chars().count() + column - start);
+    format!("{name}: {e}\n  {clipped_start}{before}{on}{at}{off}{after}{clipped_end}\n  {indent}{on}^{off}")
 }
 
+
+// Escapes every non-ASCII character of json text as \\u escapes (UTF-16 surrogate pairs beyond the BMP)
+// They can only occur within strings, so the text stays valid json with the same meaning
+fn escape_non_ascii(text: &str) -> String
+{
+    let mut out = String::with_capacity(text.len());
+    for ch in text.chars() {
+        match ch.is_ascii() {
+            true => out.push(ch),
+            false => {
+                for unit in ch.encode_utf16(&mut [0; 2]) {
+                    out.push_str(&format!("\\u{unit:04x}"));
+                }
+            }
+        }
+    }
+    out
+}
+
+
+/* (SUBCOMMANDS) */
+
+// Pretty prints a document, a parse error is shown with the line of input it is on
+// Keys are always sorted as objects do not keep their order, so --sort-keys is only accepted for clarity in scripts
+fn fmt(mut args: Args) -> Result<(), String>
+{
+    let mut color = color_option(&mut args)?;
+    args.switch("--sort-keys");
+    let ascii = args.switch("--ascii");
+    let canonical = args.switch("--canonical");
+    let files = args.positional(1)?;
+    if canonical { color = false; }
+
+    let text = read_input(files.first())?;
+    let name = files.first().map_or("stdin", |file| file.as_str());
+    let value = try_parse(&text).map_err(|e| describe_error(name, &text, &e, color))?;
+
+    let mut out = String::new();
+    match (canonical, color) {
+        (true, _) => out = value.to_canonical_string(),
+        (false, true) => write_colored(&mut out, &value, 0),
+        (false, false) => out = format!("{:#}", value.display_json()),
+    }
+    if ascii { out = escape_non_ascii(&out); }
+    out.push('\n');
+    write_output(&out)
+}
+
+// Formats that convert can read, and that it can write (YAML is only written)
+const READ_FORMATS: [&str; 6] = ["json", "ndjson", "json5", "msgpack", "cbor", "csv"];
+const WRITE_FORMATS: [&str; 7] = ["json", "ndjson", "json5", "msgpack", "cbor", "csv", "yaml"];
+
+// Converts a document between formats, a json array is written as one ndjson line per element and the other way round
+fn convert(mut args: Args) -> Result<(), String>
+{
+    let from = args.value("--from")?.unwrap_or_else(|| "json".to_string());
+    let to = args.value("--to")?.unwrap_or_else(|| "json".to_string());
+    let files = args.positional(1)?;
+
+    if !READ_FORMATS.contains(&from.as_str()) {
+        return Err(format!("unsupported input format '{from}', expected one of: {}", READ_FORMATS.join(", ")));
+    }
+    if !WRITE_FORMATS.contains(&to.as_str()) {
+        return Err(format!("unsupported output format '{to}', expected one of: {}", WRITE_FORMATS.join(", ")));
+    }
+
+    let value = match from.as_str() {
+        "msgpack" => JSON::from_msgpack(&read_bytes(files.first())?).map_err(|e| e.to_string())?,
+        "cbor" => JSON::from_cbor(&read_bytes(files.first())?).map_err(|e| e.to_string())?,
+        _ => {
+            let text = read_input(files.first())?;
+            match from.as_str() {
+                "ndjson" => JSON::JsArray(ndjson(text.as_bytes()).collect::<Result<Vec<JSON>, _>>().map_err(|e| e.to_string())?),
+                "json5" => JSON::from_json5(&text).map_err(|e| e.to_string())?,
+                "csv" => JSON::from_csv(&text).map_err(|e| e.to_string())?,
+                _ => try_parse(&text).map_err(|e| e.to_string())?,
+            }
+        }
+    };
+
+    let output = match to.as_str() {
+        "msgpack" => return write_bytes(&value.to_msgpack()),
+        "cbor" => return write_bytes(&value.to_cbor()),
+        "ndjson" => match &value {
+            JSON::JsArray(elements) => elements.iter().map(|element| format!("{}\n", element.display_json())).collect(),
+            value => format!("{}\n", value.display_json()),
+        },
+        "json5" => value.to_json5() + "\n",
+        "csv" => value.to_csv().ok_or("csv output needs an array of objects or an array of arrays")?,
+        "yaml" => value.to_yaml(),
+        _ => format!("{:#}\n", value.display_json()),
+    };
+    write_output(&output)
+}
+
+// Deep merges each overlay into the base in turn and prints the result
+fn merge(mut args: Args) -> Result<(), String>
+{
+    let arrays = match args.value("--arrays")?.as_deref() {
+        None | Some("replace") => ArrayMerge::Replace,
+        Some("concat") => ArrayMerge::Concat,
+        Some(other) => return Err(format!("unknown array strategy '{other}', expected replace or concat")),
+    };
+    let files = args.positional(usize::MAX)?;
+    if files.len() < 2 { return Err(format!("merge needs a base and at least one overlay\n{USAGE}")); }
+
+    let mut merged = read_json(Some(&files[0]))?;
+    for file in &files[1..] {
+        merged.merge(read_json(Some(file))?, arrays);
+    }
+    write_output(&format!("{:#}\n", merged.display_json()))
+}
+
+// Applies a JSON Patch, or a JSON Merge Patch with --merge, to a document and prints the result
+fn patch(mut args: Args) -> Result<(), String>
+{
+    let merge = args.switch("--merge");
+    let files = args.positional(2)?;
+    if files.len() < 2 { return Err(format!("patch needs a document and a patch\n{USAGE}")); }
+
+    let mut document = read_json(Some(&files[0]))?;
+    let patch = read_json(Some(&files[1]))?;
+    match merge {
+        true => document.merge_patch(&patch),
+        false => document.apply_patch(&patch).map_err(|e| format!("{}: {e}", files[1]))?,
+    }
+    write_output(&format!("{:#}\n", document.display_json()))
+}
+
+// Validates a document against a JSON Schema, printing each violation and failing if there are any
+fn validate(mut args: Args) -> Result<(), String>
+{
+    let schema = args.value("--schema")?.ok_or_else(|| format!("validate needs --schema\n{USAGE}"))?;
+    let files = args.positional(1)?;
+
+    let schema = Schema::new(&read_json(Some(&schema))?).map_err(|e| format!("{schema}: {e}"))?;
+    let document = read_json(files.first())?;
+
+    let violations = schema.validate(&document);
+    for violation in &violations {
+        println!("{violation}");
+    }
+    match violations.len() {
+        0 => Ok(()),
+        n => Err(format!("{n} violation(s) found")),
+    }
+}
+
+// Prints random json documents, one per line unless pretty printed
+// Without a seed one is taken from the clock and reported on stderr, so an interesting run can be reproduced
+fn generate(mut args: Args) -> Result<(), String>
+{
+    let seed = args.number("--seed")?;
+    let count = args.number("--count")?.unwrap_or(1);
+    let max_depth = args.number("--max-depth")?;
+    let max_len = args.number("--max-len")?;
+    let pretty = args.switch("--pretty");
+    args.positional(0)?;
+
+    let seed = seed.unwrap_or_else(|| {
+        let seed = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).map_or(0, |time| time.as_nanos() as u64);
+        eprintln!("seed: {seed}");
+        seed
+    });
+    let mut generator = Generator::new(seed);
+    if let Some(max_depth) = max_depth { generator = generator.max_depth(max_depth); }
+    if let Some(max_len) = max_len { generator = generator.max_len(max_len); }
+
+    let mut out = io::BufWriter::new(io::stdout().lock());
+    for value in generator.take(count) {
+        let written = match pretty {
+            true => writeln!(out, "{:#}", value.display_json()),
+            false => writeln!(out, "{}", value.display_json()),
+        };
+        written.map_err(|e| format!("stdout: {e}"))?;
+    }
+    out.flush().map_err(|e| format!("stdout: {e}"))
+}
+
+// Prints a reduced document with only the values at a comma separated list of paths (ie. "a,b.c,items[*].id")
+fn select(args: Args) -> Result<(), String>
+{
+    let args = args.positional(2)?;
+    let Some(paths) = args.first() else { return Err(format!("select needs a list of paths\n{USAGE}")) };
+
+    let paths: Vec<&str> = paths.split(',').map(str::trim).collect();
+    let selected = read_json(args.get(1))?.select(&paths).map_err(|e| e.to_string())?;
+    write_output(&format!("{:#}\n", selected.display_json()))
+}
+
+// Prints each value a filter produces (ie. ".items[] | select(.price > 10) | {name, price}"), pretty printed or one per line with --compact
+fn filter(mut args: Args) -> Result<(), String>
+{
+    let compact = args.switch("--compact");
+    let args = args.positional(2)?;
+    let Some(filter) = args.first() else { return Err(format!("filter needs a filter\n{USAGE}")) };
+
+    let filter = Filter::parse(filter).map_err(|e| e.to_string())?;
+    let values = filter.apply(&read_json(args.get(1))?).map_err(|e| e.to_string())?;
+
+    let lines: String = values.iter().map(|value| match compact {
+        true => format!("{}\n", value.display_json()),
+        false => format!("{:#}\n", value.display_json()),
+    }).collect();
+    write_output(&lines)
+}
+
+// Prints the document with the values at a comma separated list of path patterns masked (ie. "**.password,users[*].ssn")
+fn redact(mut args: Args) -> Result<(), String>
+{
+    let mask = args.value("--mask")?;
+    let args = args.positional(2)?;
+    let Some(patterns) = args.first() else { return Err(format!("redact needs a list of path patterns\n{USAGE}")) };
+
+    let patterns: Vec<&str> = patterns.split(',').map(str::trim).collect();
+    let mut value = read_json(args.get(1))?;
+    match mask {
+        Some(mask) => value.redact_with(&patterns, &JSON::JsString(mask)),
+        None => value.redact(&patterns),
+    }.map_err(|e| e.to_string())?;
+
+    write_output(&format!("{:#}\n", value.display_json()))
+}
+
+// Prints every leaf value as a "path = value" line sorted by path, or the flattened object with --object
+fn flatten(mut args: Args) -> Result<(), String>
+{
+    let object = args.switch("--object");
+    let files = args.positional(1)?;
+
+    let flat = read_json(files.first())?.flatten();
+    if object { return write_output(&format!("{:#}\n", flat.display_json())); }
+
+    let map = match flat {
+        JSON::JsObject(map) => map,
+        other => return Err(format!("flattening gave {} instead of an object", other.display_json())),
+    };
+    let mut leaves = map.into_iter()
+                        .map(|(key, value)| JsonPath::parse(&key).map(|path| (path, value)).map_err(|e| format!("invalid path '{key}': {e}")))
+                        .collect::<Result<Vec<(JsonPath, JSON)>, String>>()?;
+    leaves.sort_by(|a, b| a.0.cmp(&b.0));   //Indexes compare as numbers, so [10] comes after [9]
+
+    let lines: String = leaves.iter().map(|(path, value)| format!("{path} = {}\n", value.display_json())).collect();
+    write_output(&lines)
+}
+
+// Pretty prints each record of an ndjson file, or only the value at a path of the records that have it
+// With -f it keeps following the file as lines are appended (starting over if the file is truncated, ie. rotated)
+fn tail(mut args: Args) -> Result<(), String>
+{
+    let follow = args.switch("-f");
+    let path = args.value("--path")?;
+    let files = args.positional(1)?;
+    let Some(file) = files.first() else { return Err(format!("tail needs a file\n{USAGE}")) };
+
+    //Malformed records are reported and skipped, a log being followed may well have a few
+    let print = |line: &[u8], number: usize| -> Result<(), String> {
+        let text = String::from_utf8_lossy(line);
+        if text.trim().is_empty() { return Ok(()); }
+
+        match try_parse(&text) {
+            Ok(value) => match path.as_deref().map_or(Some(&value), |path| value.get_path(path)) {
+                Some(selected) => write_output(&format!("{:#}\n", selected.display_json())),
+                None => Ok(()),
+            },
+            Err(e) => {
+                eprintln!("{file}: line {number}: {e}");
+                Ok(())
+            }
+        }
+    };
+
+    let open = || File::open(file).map(io::BufReader::new).map_err(|e| format!("{file}: {e}"));
+    let mut reader = open()?;
+    let mut consumed = 0;
+    let mut number = 0;
+    let mut line = vec![];
+
+    loop {
+        let read = reader.read_until(b'\n', &mut line).map_err(|e| format!("{file}: {e}"))?;
+        consumed += read;
+
+        //When following, a line is only parsed once it is complete as the rest of it may not have been written yet
+        if read == 0 || (follow && !line.ends_with(b"\n")) {
+            if !follow { break; }
+            std::thread::sleep(std::time::Duration::from_millis(250));
+
+            let len = std::fs::metadata(file).map_err(|e| format!("{file}: {e}"))?.len();
+            if len < consumed as u64 {
+                reader = open()?;
+                (consumed, number) = (0, 0);
+                line.clear();
+            }
+            continue;
+        }
+
+        number += 1;
+        print(&line, number)?;
+        line.clear();
+    }
+    Ok(())
+}
@@ -0,0 +1,184 @@
+//! Parsing a buffer that holds several json values one after another
+//!
+//! Values may be separated by whitespace or directly concatenated (ie. `{}{}{}` or one value per line), as emitted by
+//! many streaming APIs. The `max_values` and `max_bytes` options stop early, so a preview of a gigantic buffer
+//! only parses its start, and `Values::stopped` tells whether a limit was reached.
+
+/* (IMPORTS) */
+use crate::{offset_in, parse_value, with_limits, Options, ParseError, JSON};
+
+
+/// The limit that stopped a `Values` iterator before the end of its input
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StopReason {
+    /// `max_values` values were parsed
+    MaxValues,
+    /// The next value does not end within the first `max_bytes` bytes
+    MaxBytes,
+}
+
+/// Iterator returned by `parse_many`, yields each value in turn
+///
+/// After an error the iterator ends, as there is no telling where the next value starts.
+#[derive(Debug, Clone)]
+pub struct Values<'a> {
+    input: &'a str,
+    options: Options,
+    pos: usize,
+    count: usize,
+    stopped: Option<StopReason>,
+    done: bool,
+}
+
+/// Parses a buffer of json values one after another, each as by `try_parse`
+///
+/// # Examples
+/// ```
+/// use Parser::parse_many;
+///
+/// let values: Vec<_> = parse_many("{\"id\" : 1}{\"id\" : 2}\n[3]").collect();
+/// assert_eq!( 3, values.len() );
+/// assert!( values.iter().all(|value| value.is_ok()) );
+/// ```
+pub fn parse_many<'a>(input: &'a str) -> Values<'a>
+{
+    parse_many_with(input, &Options::default())
+}
+
+/// Parses a buffer of json values as in `parse_many`, stopping early if the options limit the number of values or bytes
+///
+/// Only the first `max_bytes` bytes are parsed, except that the value which crosses the limit is read to its end
+/// to tell whether it is valid. Comments are not allowed between values.
+pub fn parse_many_with<'a>(input: &'a str, options: &Options) -> Values<'a>
+{
+    Values { input, options: options.clone(), pos: 0, count: 0, stopped: None, done: false }
+}
+
+impl<'a> Values<'a> {
+
+    /// Returns the byte offset just past the last value parsed, which is where parsing stopped once the iterator has ended
+    pub fn offset(&self) -> usize
+    {
+        self.pos
+    }
+
+    /// Returns the limit that ended the iterator early, or None if it has not been stopped by a limit
+    pub fn stopped(&self) -> Option<StopReason>
+    {
+        self.stopped
+    }
+
+    // Ends the iterator because of a limit
+    fn stop(&mut self, reason: StopReason) -> Option<Result<JSON, ParseError>>
+    {
+        self.stopped = Some(reason);
+        self.done = true;
+        None
+    }
+}
+
+impl<'a> Iterator for Values<'a> {
+    type Item = Result<JSON, ParseError>;
+
+    fn next(&mut self) -> Option<Self::Item>
+    {
+        let input = self.input;
+        let rest = &input[self.pos..];
+        let start = input.len() - rest.trim_start().len();
+
+        if self.done || start == input.len() {
+            self.done = true;
+            return None;
+        }
+        if self.options.max_values.is_some_and(|max| self.count >= max) { return self.stop(StopReason::MaxValues); }
+
+        //Only look at the bytes within the limit, a value that reaches the limit may carry on past it
+        let mut limit = self.options.max_bytes.map_or(input.len(), |max| max.min(input.len()));
+        while !input.is_char_boundary(limit) { limit -= 1; }
+        if start >= limit { return self.stop(StopReason::MaxBytes); }
+
+        let parse = |end: usize| with_limits(&self.options, || parse_value(input, &input[start..end]));
+        let result = match parse(limit) {
+            Ok((rest, value)) if offset_in(input, rest) < limit || limit == input.len() => Ok((rest, value)),
+            Err(e) if limit == input.len() => Err(e),
+            _ => match parse(input.len()) {
+                Ok((rest, _)) if offset_in(input, rest) > limit => return self.stop(StopReason::MaxBytes),
+                result => result,
+            },
+        };
+
+        match result {
+            Ok((rest, value)) => {
+                self.pos = offset_in(input, rest);
+                self.count += 1;
+                Some(Ok(value))
+            }
+            Err(e) => {
+                self.done = true;
+                Some(Err(e))
+            }
+        }
+    }
+}
+
+
+/* (TESTS) */
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::try_parse;
+
+    #[test]
+    fn test_parse_many()
+    {
+        let values: Vec<JSON> = parse_many(" {}{\"a\" : 1}\n[] 1 2\"x\"truefalse null\n").map(|v| v.unwrap()).collect();
+        assert_eq!( 9, values.len() );
+        assert_eq!( try_parse(r#"{"a" : 1}"#).unwrap(), values[1] );
+        assert_eq!( JSON::JsBool(false), values[7] );
+
+        //Errors are positioned in the whole buffer and end the iteration
+        let results: Vec<Result<JSON, ParseError>> = parse_many("[1]\n[2,]\n[3]").collect();
+        assert_eq!( 2, results.len() );
+        assert_eq!( (2, 4), (results[1].as_ref().unwrap_err().line(), results[1].as_ref().unwrap_err().column()) );
+
+        let mut values = parse_many(" \n ");
+        assert_eq!( None, values.next() );
+        assert_eq!( None, values.stopped() );
+    }
+
+    #[test]
+    fn test_max_values()
+    {
+        let options = Options { max_values: Some(2), ..Options::default() };
+        let mut values = parse_many_with("[1] [2] [3]", &options);
+
+        assert_eq!( 2, values.by_ref().count() );
+        assert_eq!( (7, Some(StopReason::MaxValues)), (values.offset(), values.stopped()) );
+
+        let mut values = parse_many_with("[1] [2] ", &options);
+        assert_eq!( 2, values.by_ref().count() );
+        assert_eq!( None, values.stopped() );                    //Nothing was left out
+    }
+
+    #[test]
+    fn test_max_bytes()
+    {
+        let input = "[1] 23 [4, 5] \"é\"";
+        let stop = |max_bytes| {
+            let mut values = parse_many_with(input, &Options { max_bytes: Some(max_bytes), ..Options::default() });
+            let count = values.by_ref().collect::<Result<Vec<JSON>, ParseError>>().unwrap().len();
+            (count, values.offset(), values.stopped())
+        };
+
+        assert_eq!( (1, 3, Some(StopReason::MaxBytes)), stop(5) );                 //23 is cut by the limit
+        assert_eq!( (2, 6, Some(StopReason::MaxBytes)), stop(6) );                 //23 ends at the limit
+        assert_eq!( (2, 6, Some(StopReason::MaxBytes)), stop(12) );
+        assert_eq!( (3, 13, Some(StopReason::MaxBytes)), stop(16) );               //Limit within the 'é'
+        assert_eq!( (4, input.len(), None), stop(100) );
+
+        //An error before the limit is still reported, even when the value with it crosses the limit
+        let mut values = parse_many_with("[1] [2,, 3, 4]", &Options { max_bytes: Some(9), ..Options::default() });
+        assert!( values.nth(1).unwrap().is_err() );
+        assert_eq!( None, values.stopped() );
+    }
+}
@@ -0,0 +1,271 @@
+//! Measuring the size and shape of a json value, and what parsing it created
+
+/* (IMPORTS) */
+use std::mem::size_of;
+use std::sync::{Arc, Mutex};
+
+use crate::JSON;
+
+
+/* (MEMORY USAGE) */
+
+impl JSON {
+
+    /// Returns an estimate of the number of bytes used by the value, including everything it owns on the heap
+    ///
+    /// Strings and arrays are counted by their capacity rather than their length, and objects are counted as a hash table
+    /// holding a key, a value and a control byte per bucket. Allocator overhead and the unused buckets a hash map keeps
+    /// beyond its reported capacity are not included, so the real usage is usually somewhat higher.
+    pub fn approx_memory_usage(&self) -> usize
+    {
+        size_of::<JSON>() + heap_size(self)
+    }
+}
+
+// Bytes owned by the value on the heap, not counting the value itself
+fn heap_size(value: &JSON) -> usize
+{
+    match value {
+        JSON::JsNull | JSON::JsBool(_) => 0,
+        JSON::JsNumber(n) => n.heap_size(),
+        JSON::JsString(s) => s.capacity(),
+        JSON::JsArray(vec) => {
+            vec.capacity() * size_of::<JSON>() + vec.iter().map(heap_size).sum::<usize>()
+        }
+        JSON::JsObject(map) => {
+            let bucket = size_of::<String>() + size_of::<JSON>() + 1;
+            map.capacity() * bucket + map.iter().map(|(key, value)| key.capacity() + heap_size(value)).sum::<usize>()
+        }
+    }
+}
+
+
+/* (STRUCTURAL METRICS) */
+
+/// Counts describing the shape of a json value, returned by `JSON::metrics`
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Metrics {
+    /// Deepest nesting of arrays and objects, a scalar has depth 0 and `[]` or `[1]` have depth 1
+    pub max_depth: usize,
+    /// Number of nulls
+    pub nulls: usize,
+    /// Number of booleans
+    pub bools: usize,
+    /// Number of numbers
+    pub numbers: usize,
+    /// Number of string values
+    pub strings: usize,
+    /// Number of arrays
+    pub arrays: usize,
+    /// Number of objects
+    pub objects: usize,
+    /// Total number of object members across all objects
+    pub keys: usize,
+    /// Total length in bytes of all string values, not including object keys
+    pub string_bytes: usize,
+}
+
+impl Metrics {
+
+    /// Total number of values in the tree, including arrays and objects themselves
+    pub fn values(&self) -> usize
+    {
+        self.nulls + self.bools + self.numbers + self.strings + self.arrays + self.objects
+    }
+}
+
+impl JSON {
+
+    /// Returns the depth, number of values of each type, number of keys, and string bytes of the value
+    /// in a single traversal of the tree
+    pub fn metrics(&self) -> Metrics
+    {
+        let mut metrics = Metrics::default();
+        count(self, 0, &mut metrics);
+        metrics
+    }
+}
+
+// Recursive helper for metrics, depth is the number of containers enclosing the value
+fn count(value: &JSON, depth: usize, metrics: &mut Metrics)
+{
+    match value {
+        JSON::JsNull => metrics.nulls += 1,
+        JSON::JsBool(_) => metrics.bools += 1,
+        JSON::JsNumber(_) => metrics.numbers += 1,
+        JSON::JsString(s) => {
+            metrics.strings += 1;
+            metrics.string_bytes += s.len();
+        }
+        JSON::JsArray(vec) => {
+            metrics.arrays += 1;
+            metrics.max_depth = metrics.max_depth.max(depth + 1);
+            for element in vec {
+                count(element, depth + 1, metrics);
+            }
+        }
+        JSON::JsObject(map) => {
+            metrics.objects += 1;
+            metrics.keys += map.len();
+            metrics.max_depth = metrics.max_depth.max(depth + 1);
+            for member in map.values() {
+                count(member, depth + 1, metrics);
+            }
+        }
+    }
+}
+
+
+/* (PARSE STATISTICS) */
+
+/// What parsing created, as recorded by a `StatsCollector`
+///
+/// Unlike `Metrics` these count the work the parser did, so values built by attempts that were then backtracked over
+/// (and the temporary storage used to build objects) are included, which is what matters for capacity planning.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ParseCounts {
+    /// Number of values parsed, including arrays and objects themselves
+    pub values: usize,
+    /// Number of strings created, including object keys
+    pub strings: usize,
+    /// Number of arrays created
+    pub arrays: usize,
+    /// Number of objects created
+    pub objects: usize,
+    /// Bytes requested from the allocator for strings, numbers, arrays and objects, counted by capacity as in `approx_memory_usage`
+    pub bytes_allocated: usize,
+}
+
+impl ParseCounts {
+
+    // Adds counts to these
+    pub(crate) fn add(&mut self, other: &ParseCounts)
+    {
+        self.values += other.values;
+        self.strings += other.strings;
+        self.arrays += other.arrays;
+        self.objects += other.objects;
+        self.bytes_allocated += other.bytes_allocated;
+    }
+}
+
+/// Collector for `Options::stats`, which adds up the `ParseCounts` of every parse given it
+///
+/// Clones share the same counts, so one collector can track a whole batch of documents (even across threads).
+/// Counts are recorded by `try_parse_with` and `parse_many_with`, but not when comments are allowed.
+///
+/// ```
+/// use Parser::{try_parse_with, Options, StatsCollector};
+///
+/// let stats = StatsCollector::new();
+/// let options = Options { stats: Some(stats.clone()), ..Options::default() };
+///
+/// try_parse_with(r#"{ "a" : ["x", "y"] }"#, &options).unwrap();
+/// assert_eq!( (3, 1, 1), (stats.counts().strings, stats.counts().arrays, stats.counts().objects) );
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct StatsCollector {
+    counts: Arc<Mutex<ParseCounts>>,
+}
+
+impl StatsCollector {
+
+    /// Creates a collector with every count at zero
+    pub fn new() -> StatsCollector
+    {
+        StatsCollector::default()
+    }
+
+    /// Returns the counts so far
+    pub fn counts(&self) -> ParseCounts
+    {
+        *self.counts.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Sets every count back to zero
+    pub fn reset(&self)
+    {
+        *self.counts.lock().unwrap_or_else(|e| e.into_inner()) = ParseCounts::default();
+    }
+
+    // Adds the counts of a finished parse
+    pub(crate) fn record(&self, counts: &ParseCounts)
+    {
+        self.counts.lock().unwrap_or_else(|e| e.into_inner()).add(counts);
+    }
+}
+
+//Collectors are equal if they share the same counts
+impl PartialEq for StatsCollector {
+    fn eq(&self, other: &StatsCollector) -> bool
+    {
+        Arc::ptr_eq(&self.counts, &other.counts)
+    }
+}
+
+
+/* (TESTS) */
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::json;
+
+    #[test]
+    fn test_approx_memory_usage()
+    {
+        let base = size_of::<JSON>();
+        assert_eq!( base, JSON::JsNull.approx_memory_usage() );
+        assert_eq!( base + 5, JSON::JsString(String::with_capacity(5)).approx_memory_usage() );
+
+        let arr = JSON::JsArray(Vec::with_capacity(4));
+        assert_eq!( base + 4 * base, arr.approx_memory_usage() );
+
+        //Children are counted
+        let small = json(r#"{ "a" : [1, 2] }"#).approx_memory_usage();
+        let large = json(r#"{ "a" : [1, 2], "b" : "a much longer string value" }"#).approx_memory_usage();
+        assert!( large > small + 26 );
+    }
+
+    #[test]
+    fn test_metrics()
+    {
+        let doc = json(r#"{ "name" : "Michael", "tags" : ["a", "bc"], "address" : { "zip" : null, "n" : 12, "ok" : true }, "e" : [] }"#);
+        let expected = Metrics {
+            max_depth: 2,
+            nulls: 1,
+            bools: 1,
+            numbers: 1,
+            strings: 3,
+            arrays: 2,
+            objects: 2,
+            keys: 7,
+            string_bytes: 10,
+        };
+
+        assert_eq!( expected, doc.metrics() );
+        assert_eq!( 10, doc.metrics().values() );
+        assert_eq!( 0, json("1").metrics().max_depth );
+        assert_eq!( 3, json("[[[]]]").metrics().max_depth );
+    }
+
+    #[test]
+    fn test_stats_collector()
+    {
+        use crate::{parse_many_with, try_parse_with, Options};
+
+        let stats = StatsCollector::new();
+        let options = Options { stats: Some(stats.clone()), ..Options::default() };
+
+        try_parse_with(r#"{ "a" : [1, "x"], "b" : {} }"#, &options).unwrap();
+        let counts = stats.counts();
+        assert_eq!( (5, 3, 1, 2), (counts.values, counts.strings, counts.arrays, counts.objects) );
+        assert!( counts.bytes_allocated >= 2 * size_of::<JSON>() + 3 );
+
+        //Counts add up across parses
+        assert_eq!( 2, parse_many_with("[] []", &options).count() );
+        assert_eq!( 3, stats.counts().arrays );
+
+        stats.reset();
+        assert_eq!( ParseCounts::default(), stats.counts() );
+    }
+}
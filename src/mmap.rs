@@ -0,0 +1,130 @@
+//! Parsing files through a read-only memory mapping (enabled with the `mmap` feature, unix only)
+//!
+//! The text is parsed straight from the pages of the file, so a large dataset is never copied into a `String` first.
+//! Values are still owned, so the strings of the document are copied out as they are parsed (as with `try_parse`),
+//! and `MappedFile::as_str` is there for parsers that borrow from the text.
+//! The mapping calls `mmap` from the platform C library, which every unix target already links.
+
+/* (IMPORTS) */
+use std::ffi::{c_int, c_long, c_void};
+use std::fs::File;
+use std::io;
+use std::os::fd::AsRawFd;
+use std::path::Path;
+
+use crate::{try_parse_with, Options, ReadError, JSON};
+
+
+const PROT_READ: c_int = 1;
+const MAP_PRIVATE: c_int = 2;
+
+extern "C" {
+    fn mmap(addr: *mut c_void, len: usize, prot: c_int, flags: c_int, fd: c_int, offset: c_long) -> *mut c_void;
+    fn munmap(addr: *mut c_void, len: usize) -> c_int;
+}
+
+/// A file mapped read-only into memory, unmapped when dropped
+///
+/// The file must not be modified while it is mapped (ie. only use this for read-only datasets),
+/// as the mapped bytes would change underneath the borrowed text, which is why mapping one is unsafe.
+#[derive(Debug)]
+pub struct MappedFile {
+    ptr: *const u8,
+    len: usize,
+}
+
+//The mapping is read-only, so it can be shared like a &[u8]
+unsafe impl Send for MappedFile {}
+unsafe impl Sync for MappedFile {}
+
+impl MappedFile {
+
+    /// Maps the whole of a file
+    ///
+    /// # Safety
+    /// The file must not be truncated or written to (by this or any other process) until the `MappedFile` is dropped.
+    /// Its bytes are borrowed straight from the mapping, so a change would alter them underneath any `&[u8]` or `&str`
+    /// borrowed from it, and reading past the end of a truncated file raises SIGBUS.
+    ///
+    /// # Errors
+    /// Returns the io error if the file cannot be opened or mapped
+    pub unsafe fn open(path: impl AsRef<Path>) -> io::Result<MappedFile>
+    {
+        let file = File::open(path)?;
+        let len = usize::try_from(file.metadata()?.len()).map_err(|_| io::Error::from(io::ErrorKind::OutOfMemory))?;
+
+        //An empty mapping is not allowed, an empty file needs no pages anyway
+        if len == 0 { return Ok(MappedFile { ptr: std::ptr::NonNull::dangling().as_ptr(), len }); }
+
+        let ptr = unsafe { mmap(std::ptr::null_mut(), len, PROT_READ, MAP_PRIVATE, file.as_raw_fd(), 0) };
+        if ptr as isize == -1 { return Err(io::Error::last_os_error()); }
+
+        //The mapping stays valid after the file is closed
+        Ok(MappedFile { ptr: ptr as *const u8, len })
+    }
+
+    /// Returns the bytes of the file
+    pub fn as_bytes(&self) -> &[u8]
+    {
+        unsafe { std::slice::from_raw_parts(self.ptr, self.len) }
+    }
+
+    /// Returns the text of the file
+    ///
+    /// # Errors
+    /// Returns an io error of kind `InvalidData` if the file is not valid utf-8
+    pub fn as_str(&self) -> io::Result<&str>
+    {
+        std::str::from_utf8(self.as_bytes()).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+}
+
+impl Drop for MappedFile {
+    fn drop(&mut self)
+    {
+        if self.len > 0 {
+            unsafe { munmap(self.ptr as *mut c_void, self.len); }
+        }
+    }
+}
+
+/// Parses a file through a memory mapping, see `try_parse_with`
+///
+/// The result is the same as for `parse_file`, only the text is read from the mapping instead of through a buffer.
+/// The value does not borrow from the mapping, which is unmapped before this returns.
+///
+/// # Safety
+/// The file must not be truncated or written to while it is being parsed, see `MappedFile::open`
+///
+/// # Errors
+/// Returns `ReadError::Io` if the file cannot be mapped or is not valid utf-8, and `ReadError::Parse` if it is not valid json
+pub unsafe fn parse_mapped(path: impl AsRef<Path>, options: &Options) -> Result<JSON, ReadError>
+{
+    let file = unsafe { MappedFile::open(path)? };
+    Ok(try_parse_with(file.as_str()?, options)?)
+}
+
+
+/* (TESTS) */
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::try_parse;
+
+    //Each file is only written to before it is mapped, so it is not modified while mapped
+    #[test]
+    fn test_parse_mapped()
+    {
+        let path = std::env::temp_dir().join(format!("parse_mapped_{}.json", std::process::id()));
+        std::fs::write(&path, "{ \"a\" : [1, 2, 3] }\n").unwrap();
+        assert_eq!( try_parse(r#"{ "a" : [1, 2, 3] }"#).unwrap(), unsafe { parse_mapped(&path, &Options::default()) }.unwrap() );
+
+        std::fs::write(&path, "").unwrap();
+        assert_eq!( b"", unsafe { MappedFile::open(&path) }.unwrap().as_bytes() );
+        assert!( matches!(unsafe { parse_mapped(&path, &Options::default()) }, Err(ReadError::Parse(_))) );
+
+        std::fs::write(&path, b"\"\xff\"").unwrap();
+        assert!( matches!(unsafe { parse_mapped(&path, &Options::default()) }, Err(ReadError::Io(e)) if e.kind() == io::ErrorKind::InvalidData) );
+        std::fs::remove_file(&path).unwrap();
+    }
+}
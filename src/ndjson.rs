@@ -0,0 +1,157 @@
+//! Reading newline delimited json (NDJSON / JSON Lines), one value per line
+//!
+//! Each line is parsed on its own, so a malformed record only produces an `Err` item for its line
+//! and reading carries on with the next one. Blank lines are skipped.
+
+/* (IMPORTS) */
+use std::fmt;
+use std::io::{self, BufRead};
+
+use crate::{try_parse_with, Options, ParseError, JSON};
+
+
+/// Error for one line of a newline delimited json stream, along with the line number (starting at 1)
+#[derive(Debug)]
+pub enum NdjsonError {
+    /// Reading the line failed. A line that is not valid utf-8 is skipped, any other failure ends the stream.
+    Io(usize, io::Error),
+    /// The line is not a single valid json value, the offset of the error is within the line
+    Parse(usize, ParseError),
+}
+
+impl NdjsonError {
+
+    /// Returns the number of the line that failed
+    pub fn line(&self) -> usize
+    {
+        match self {
+            NdjsonError::Io(line, _) | NdjsonError::Parse(line, _) => *line,
+        }
+    }
+}
+
+impl fmt::Display for NdjsonError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+    {
+        match self {
+            NdjsonError::Io(line, e) => write!(f, "line {line}: read failed: {e}"),
+            NdjsonError::Parse(line, e) => write!(f, "line {line}: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for NdjsonError {}
+
+/// Iterator returned by `ndjson`, yields the value or error of each non-blank line
+#[derive(Debug)]
+pub struct Ndjson<R> {
+    reader: R,
+    options: Options,
+    line: usize,
+    buf: Vec<u8>,
+    done: bool,
+}
+
+/// Returns an iterator over the values of a newline delimited json stream, one per non-blank line
+///
+/// ```
+/// use Parser::{ndjson, NdjsonError};
+///
+/// let input = "{\"id\" : 1}\n{\"id\" : \n{\"id\" : 3}\n";
+/// let records: Vec<_> = ndjson(input.as_bytes()).collect();
+///
+/// assert_eq!( 3, records.len() );
+/// assert!( matches!(records[1], Err(NdjsonError::Parse(2, _))) );
+/// assert!( records[2].is_ok() );
+/// ```
+pub fn ndjson<R: BufRead>(reader: R) -> Ndjson<R>
+{
+    ndjson_with(reader, &Options::default())
+}
+
+/// Returns an iterator over the values of a newline delimited json stream as in `ndjson`, parsing each line with the options
+pub fn ndjson_with<R: BufRead>(reader: R, options: &Options) -> Ndjson<R>
+{
+    Ndjson { reader, options: options.clone(), line: 0, buf: vec![], done: false }
+}
+
+impl<R: BufRead> Iterator for Ndjson<R> {
+    type Item = Result<JSON, NdjsonError>;
+
+    fn next(&mut self) -> Option<Self::Item>
+    {
+        while !self.done {
+            self.buf.clear();
+            match self.reader.read_until(b'\n', &mut self.buf) {
+                Ok(0) => self.done = true,
+                Ok(_) => {
+                    self.line += 1;
+                    let line = match std::str::from_utf8(&self.buf) {
+                        Ok(line) => line,
+                        Err(e) => return Some(Err(NdjsonError::Io(self.line, io::Error::new(io::ErrorKind::InvalidData, e)))),
+                    };
+
+                    if line.trim().is_empty() { continue; }
+                    return Some(try_parse_with(line, &self.options).map_err(|e| NdjsonError::Parse(self.line, e)));
+                }
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => {
+                    self.done = true;
+                    return Some(Err(NdjsonError::Io(self.line + 1, e)));
+                }
+            }
+        }
+        None
+    }
+}
+
+
+/* (TESTS) */
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{json, ParseErrorKind};
+
+    #[test]
+    fn test_ndjson()
+    {
+        let input = "{\"a\" : 1}\r\n\n  [1, 2]  \n\"last\"";
+        let values: Vec<JSON> = ndjson(input.as_bytes()).map(|r| r.unwrap()).collect();
+
+        assert_eq!( vec![json(r#"{"a" : 1}"#), json("[1, 2]"), json(r#""last""#)], values );
+        assert_eq!( 0, ndjson("".as_bytes()).count() );
+    }
+
+    #[test]
+    fn test_recovery()
+    {
+        let input = b"1\n[1,\n\xff\n{} {}\n4\n";
+        let results: Vec<Result<JSON, NdjsonError>> = ndjson(&input[..]).collect();
+
+        assert_eq!( 5, results.len() );
+        assert!( matches!(&results[1], Err(NdjsonError::Parse(2, e)) if e.offset() == 3) );
+        assert!( matches!(&results[2], Err(NdjsonError::Io(3, e)) if e.kind() == io::ErrorKind::InvalidData) );
+        assert!( matches!(&results[3], Err(NdjsonError::Parse(4, e)) if e.kind() == ParseErrorKind::TrailingCharacters) );
+        assert_eq!( json("4"), *results[4].as_ref().unwrap() );
+        assert_eq!( "line 4: unexpected characters after the json value at line 1 column 4", results[3].as_ref().unwrap_err().to_string() );
+    }
+
+    #[test]
+    fn test_read_failure()
+    {
+        //Fails after the first line, which ends the stream
+        struct Broken(bool);
+        impl io::Read for Broken {
+            fn read(&mut self, buf: &mut [u8]) -> io::Result<usize>
+            {
+                if std::mem::replace(&mut self.0, true) { return Err(io::Error::other("gone")); }
+                buf[..2].copy_from_slice(b"1\n");
+                Ok(2)
+            }
+        }
+
+        let results: Vec<Result<JSON, NdjsonError>> = ndjson(io::BufReader::new(Broken(false))).collect();
+        assert_eq!( 2, results.len() );
+        assert_eq!( 2, results[1].as_ref().unwrap_err().line() );
+    }
+}
@@ -0,0 +1,299 @@
+//! Number type stored inside `JSON::JsNumber`
+//!
+//! By default a json number is converted when it is parsed, integer literals are kept as a u64 or i64
+//! (so IDs up to 2^63 are exact) and every other literal is converted to an f64.
+//! With the `arbitrary_precision` feature enabled the original literal from the json input is kept instead,
+//! and it is only converted when asked for, so large IDs and monetary amounts survive unchanged.
+
+/* (IMPORTS) */
+use std::fmt;
+
+#[cfg(feature = "decimal")]
+use crate::Decimal;
+
+
+// Internal representation of a number, depends on which features are enabled
+#[cfg(not(feature = "arbitrary_precision"))]
+#[derive(Clone, Copy, PartialEq)]
+enum N {
+    PosInt(u64),   //Always non-negative
+    NegInt(i64),   //Always negative
+    Float(f64),    //Always finite
+}
+
+#[cfg(feature = "arbitrary_precision")]
+type N = String;
+
+
+/// A json number
+#[derive(Clone)]
+pub struct Number {
+    n: N,
+}
+
+impl Number {
+
+    // Creates a number from a literal already matched by the number parser
+    // Returns None if the literal could not be converted
+    #[cfg(not(feature = "arbitrary_precision"))]
+    pub(crate) fn from_literal(literal: &str) -> Option<Number>
+    {
+        //Integer literals are parsed directly so they do not lose precision by going through a float
+        let is_integer = !literal.contains(['.', 'e', 'E']);
+
+        let pos_int = literal.parse::<u64>().ok().filter(|_| is_integer);
+        let neg_int = literal.parse::<i64>().ok().filter(|i| is_integer && *i < 0); //-0 is left as a float
+
+        let n = if let Some(u) = pos_int {
+            N::PosInt(u)
+        } else if let Some(i) = neg_int {
+            N::NegInt(i)
+        } else {
+            N::Float( literal.parse::<f64>().ok().filter(|f| f.is_finite())? )
+        };
+
+        Some(Number { n })
+    }
+
+    // Creates a number from a literal already matched by the number parser, keeping the literal as is
+    #[cfg(feature = "arbitrary_precision")]
+    pub(crate) fn from_literal(literal: &str) -> Option<Number>
+    {
+        Some(Number { n: literal.to_string() })
+    }
+
+    /// Creates a number from a float. Returns None if the float is NaN or infinite, since json can not represent those.
+    pub fn from_f64(f: f64) -> Option<Number>
+    {
+        if !f.is_finite() { return None; }
+
+        #[cfg(not(feature = "arbitrary_precision"))]
+        return Some(Number { n: N::Float(f) });
+
+        #[cfg(feature = "arbitrary_precision")]
+        return Some(Number { n: format!("{f:?}") }); //Debug format always keeps a decimal point (ie. 1.0 rather than 1)
+    }
+
+    /// Returns the number as an f64, this may lose precision for very large or very precise numbers
+    pub fn as_f64(&self) -> f64
+    {
+        #[cfg(not(feature = "arbitrary_precision"))]
+        return match self.n {
+            N::PosInt(u) => u as f64,
+            N::NegInt(i) => i as f64,
+            N::Float(f)  => f,
+        };
+
+        #[cfg(feature = "arbitrary_precision")]
+        return self.n.parse::<f64>().unwrap_or(f64::NAN); //Literal was matched by the number parser so this should always parse
+    }
+
+    /// Returns the number as an i64 if the original literal is an integer that fits within an i64
+    pub fn as_i64(&self) -> Option<i64>
+    {
+        #[cfg(not(feature = "arbitrary_precision"))]
+        return match self.n {
+            N::PosInt(u) => i64::try_from(u).ok(),
+            N::NegInt(i) => Some(i),
+            N::Float(_)  => None,
+        };
+
+        #[cfg(feature = "arbitrary_precision")]
+        return self.n.parse::<i64>().ok();
+    }
+
+    /// Returns the number as a u64 if the original literal is a non-negative integer that fits within a u64
+    pub fn as_u64(&self) -> Option<u64>
+    {
+        #[cfg(not(feature = "arbitrary_precision"))]
+        return match self.n {
+            N::PosInt(u) => Some(u),
+            N::NegInt(_) | N::Float(_) => None,
+        };
+
+        #[cfg(feature = "arbitrary_precision")]
+        return self.n.parse::<u64>().ok();
+    }
+
+    /// Returns true if the number is an integer that fits within an i64
+    pub fn is_i64(&self) -> bool
+    {
+        self.as_i64().is_some()
+    }
+
+    /// Returns true if the number is an integer that fits within a u64
+    pub fn is_u64(&self) -> bool
+    {
+        self.as_u64().is_some()
+    }
+
+    // Returns the number of bytes the number has allocated on the heap
+    pub(crate) fn heap_size(&self) -> usize
+    {
+        #[cfg(not(feature = "arbitrary_precision"))]
+        return 0;
+
+        #[cfg(feature = "arbitrary_precision")]
+        return self.n.capacity();
+    }
+
+    /// Returns the original literal of the number exactly as it appeared in the json input
+    #[cfg(feature = "arbitrary_precision")]
+    pub fn as_str(&self) -> &str
+    {
+        &self.n
+    }
+
+    /// Returns the number as an exact decimal parsed from the original literal, 
+    /// or None if the literal has too many digits to fit within a `Decimal`
+    #[cfg(feature = "decimal")]
+    pub fn as_decimal(&self) -> Option<Decimal>
+    {
+        Decimal::from_literal(&self.n)
+    }
+}
+
+// Numbers are equal if they have the same value, regardless of how they were written (ie. 1 == 1.0 == 1e0) 
+// Integers are compared exactly, anything else is compared as an f64
+impl PartialEq for Number {
+    fn eq(&self, other: &Number) -> bool
+    {
+        match (self.as_i64(), other.as_i64(), self.as_u64(), other.as_u64()) {
+            (Some(a), Some(b), _, _) => a == b,
+            (_, _, Some(a), Some(b)) => a == b,
+            _ => self.as_f64() == other.as_f64(),
+        }
+    }
+}
+
+impl From<u64> for Number {
+    fn from(u: u64) -> Number
+    {
+        #[cfg(not(feature = "arbitrary_precision"))]
+        return Number { n: N::PosInt(u) };
+
+        #[cfg(feature = "arbitrary_precision")]
+        return Number { n: u.to_string() };
+    }
+}
+
+impl From<i64> for Number {
+    fn from(i: i64) -> Number
+    {
+        #[cfg(not(feature = "arbitrary_precision"))]
+        return Number { n: if i < 0 { N::NegInt(i) } else { N::PosInt(i as u64) } };
+
+        #[cfg(feature = "arbitrary_precision")]
+        return Number { n: i.to_string() };
+    }
+}
+
+// Debug shows just the number (ie. JsNumber(33) rather than JsNumber(Number { n: PosInt(33) }))
+impl fmt::Debug for Number {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+    {
+        #[cfg(not(feature = "arbitrary_precision"))]
+        return match self.n {
+            N::PosInt(u) => fmt::Debug::fmt(&u, f),
+            N::NegInt(i) => fmt::Debug::fmt(&i, f),
+            N::Float(fl) => fmt::Debug::fmt(&fl, f),
+        };
+
+        #[cfg(feature = "arbitrary_precision")]
+        return f.write_str(&self.n);
+    }
+}
+
+impl fmt::Display for Number {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+    {
+        #[cfg(not(feature = "arbitrary_precision"))]
+        return match self.n {
+            N::PosInt(u) => fmt::Display::fmt(&u, f),
+            N::NegInt(i) => fmt::Display::fmt(&i, f),
+            N::Float(fl) => fmt::Display::fmt(&fl, f),
+        };
+
+        #[cfg(feature = "arbitrary_precision")]
+        return f.write_str(&self.n);
+    }
+}
+
+
+/* (TESTS) */
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_literal()
+    {
+        assert_eq!( 12.0, Number::from_literal("12").unwrap().as_f64() );
+        assert_eq!( 12.34, Number::from_literal("12.34").unwrap().as_f64() );
+    }
+
+    #[test]
+    fn test_from_f64()
+    {
+        assert_eq!( None, Number::from_f64(f64::NAN) );
+        assert_eq!( None, Number::from_f64(f64::INFINITY) );
+        assert_eq!( Some(1.5), Number::from_f64(1.5).map(|n| n.as_f64()) );
+    }
+
+    #[test]
+    fn test_eq()
+    {
+        let n = |literal| Number::from_literal(literal).unwrap();
+
+        assert_eq!( n("1"), n("1.0") );
+        assert_eq!( n("100"), n("1e2") );
+        assert_eq!( n("-3"), Number::from(-3i64) );
+        assert_ne!( n("18446744073709551615"), n("18446744073709551614") ); //Different integers, but equal as floats
+        assert_ne!( n("1"), n("1.5") );
+    }
+
+    #[test]
+    fn test_integer_getters()
+    {
+        let max_u64 = Number::from_literal("18446744073709551615").unwrap();
+        let big_id = Number::from_literal("9223372036854775807").unwrap(); //2^63 - 1
+        let negative = Number::from_literal("-42").unwrap();
+        let float = Number::from_literal("42.5").unwrap();
+
+        assert_eq!( Some(u64::MAX), max_u64.as_u64() );
+        assert_eq!( None, max_u64.as_i64() );                     //Too large for an i64
+        assert_eq!( Some(i64::MAX), big_id.as_i64() );
+        assert_eq!( Some(i64::MAX as u64), big_id.as_u64() );
+        assert_eq!( Some(-42), negative.as_i64() );
+        assert_eq!( None, negative.as_u64() );                    //Negative numbers are not u64
+        assert_eq!( None, float.as_i64() );
+        assert_eq!( None, float.as_u64() );
+        assert_eq!( None, Number::from_literal("1e2").unwrap().as_i64() ); //Exponents are not integer literals
+        assert_eq!( Some(-7), Number::from(-7i64).as_i64() );
+    }
+
+    #[cfg(feature = "arbitrary_precision")]
+    #[test]
+    fn test_arbitrary_precision()
+    {
+        let id = Number::from_literal("9007199254740993").unwrap(); //2^53 + 1, which can not be represented exactly as an f64
+        let money = Number::from_literal("1234567.10").unwrap();
+
+        assert_eq!( "9007199254740993", id.as_str() );
+        assert_eq!( Some(9007199254740993), id.as_i64() );
+        assert_eq!( "1234567.10", money.as_str() );
+        assert_eq!( None, money.as_i64() );
+        assert_eq!( "1234567.10", format!("{money:?}") );
+    }
+
+    #[cfg(feature = "decimal")]
+    #[test]
+    fn test_as_decimal()
+    {
+        let money = Number::from_literal("1234567.10").unwrap();
+
+        assert_eq!( Some(Decimal::new(123456710, 2)), money.as_decimal() );
+        assert_eq!( Some(Decimal::new(1, 1)), Number::from_literal("0.1").unwrap().as_decimal() );
+        assert_eq!( None, Number::from_literal("1e-4000000000").unwrap().as_decimal() );
+    }
+}
@@ -0,0 +1,171 @@
+//! Settings for the fallible parsing entry points
+
+/* (IMPORTS) */
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Instant;
+
+use crate::{ParseErrorKind, StatsCollector};
+
+
+/// Options controlling how `try_parse_with` and `parse_many_with` parse their input, and how `lexer::lex_with` scans it
+///
+/// Set only the fields that matter and take the rest from the default, ie. `Options { max_depth: 16, ..Options::default() }`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Options {
+    /// Deepest nesting of arrays and objects that is accepted (128 by default).
+    /// Every level uses some stack, so very large limits can overflow the stack on deeply nested input.
+    pub max_depth: usize,
+    /// Whether `//` line comments and `/* */` block comments are allowed wherever whitespace is (off by default, as json has no comments)
+    pub allow_comments: bool,
+    /// Number of values after which `parse_many_with` stops (no limit by default)
+    pub max_values: Option<usize>,
+    /// Number of bytes of input after which `parse_many_with` stops (no limit by default)
+    pub max_bytes: Option<usize>,
+    /// Hook that `try_parse_with` calls as it works through a large input, to drive a progress bar (none by default)
+    pub progress: Option<Progress>,
+    /// Token that stops a parse with a `Cancelled` error once it is cancelled, ie. from another thread (none by default)
+    pub cancel: Option<CancelToken>,
+    /// Time at which a parse that is still running stops with a `TimedOut` error (none by default), see `parse_with_deadline`
+    pub deadline: Option<Instant>,
+    /// Collector that the numbers of strings, arrays and objects created and the bytes allocated are added to (none by default)
+    pub stats: Option<StatsCollector>,
+    /// Whether strings (and object keys) keep the exact text between their quotes, with escapes left as written and unchecked
+    /// (off by default). For proxies that pass strings through without interpreting them, as it saves unescaping them.
+    pub raw_strings: bool,
+}
+
+impl Default for Options {
+    fn default() -> Options
+    {
+        Options { max_depth: crate::MAX_DEPTH, allow_comments: false, max_values: None, max_bytes: None, progress: None, cancel: None, deadline: None, stats: None, raw_strings: false }
+    }
+}
+
+/// Progress hook for `Options::progress`, called with the number of bytes consumed so far and the total length of the input
+///
+/// It is called each time the parser has got through another interval of the input (64 KiB by default),
+/// always from the thread that is parsing. It is not called when comments are allowed.
+///
+/// ```
+/// use std::sync::atomic::{AtomicUsize, Ordering};
+/// use std::sync::Arc;
+/// use Parser::{try_parse_with, Options, Progress};
+///
+/// let consumed = Arc::new(AtomicUsize::new(0));
+/// let seen = consumed.clone();
+/// let options = Options { progress: Some(Progress::new(move |bytes, _| seen.store(bytes, Ordering::Relaxed)).every(1000)), ..Options::default() };
+///
+/// let input = format!("[{}1]", "1, ".repeat(1000));
+/// try_parse_with(&input, &options).unwrap();
+/// assert!( consumed.load(Ordering::Relaxed) >= 2000 );
+/// ```
+#[derive(Clone)]
+pub struct Progress {
+    pub(crate) callback: Arc<dyn Fn(usize, usize) + Send + Sync>,
+    pub(crate) interval: usize,
+}
+
+impl Progress {
+
+    /// Creates a hook from a function taking the bytes consumed and the total bytes
+    pub fn new(callback: impl Fn(usize, usize) + Send + Sync + 'static) -> Progress
+    {
+        Progress { callback: Arc::new(callback), interval: 64 * 1024 }
+    }
+
+    /// Sets how many bytes are parsed between calls (at least 1)
+    pub fn every(mut self, bytes: usize) -> Progress
+    {
+        self.interval = bytes.max(1);
+        self
+    }
+}
+
+impl fmt::Debug for Progress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+    {
+        f.debug_struct("Progress").field("interval", &self.interval).finish_non_exhaustive()
+    }
+}
+
+//Hooks are equal if they share the same function
+impl PartialEq for Progress {
+    fn eq(&self, other: &Progress) -> bool
+    {
+        Arc::ptr_eq(&self.callback, &other.callback) && self.interval == other.interval
+    }
+}
+
+/// Token for cancelling a parse from another thread, see `Options::cancel`
+///
+/// Clones share the same flag, so keep one and give a clone to the options. Parsers check it before every value,
+/// and the cancelled parse fails with `ParseErrorKind::Cancelled` at the position it had reached.
+///
+/// ```
+/// use Parser::{try_parse_with, CancelToken, Options, ParseErrorKind};
+///
+/// let token = CancelToken::new();
+/// let options = Options { cancel: Some(token.clone()), ..Options::default() };
+///
+/// token.cancel();
+/// assert_eq!( ParseErrorKind::Cancelled, try_parse_with("[1, 2]", &options).unwrap_err().kind() );
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken {
+    cancelled: Arc<AtomicBool>,
+}
+
+impl CancelToken {
+
+    /// Creates a token that has not been cancelled
+    pub fn new() -> CancelToken
+    {
+        CancelToken::default()
+    }
+
+    /// Cancels every parse using the token (or a clone of it)
+    pub fn cancel(&self)
+    {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    /// Returns true once the token has been cancelled
+    pub fn is_cancelled(&self) -> bool
+    {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+}
+
+/// Uses a flag that is already shared with other code, which cancels the parse by setting it to true
+impl From<Arc<AtomicBool>> for CancelToken {
+    fn from(cancelled: Arc<AtomicBool>) -> CancelToken
+    {
+        CancelToken { cancelled }
+    }
+}
+
+//Tokens are equal if they share the same flag
+impl PartialEq for CancelToken {
+    fn eq(&self, other: &CancelToken) -> bool
+    {
+        Arc::ptr_eq(&self.cancelled, &other.cancelled)
+    }
+}
+
+impl Options {
+
+    // Returns true if the options can stop a parse part way through
+    pub(crate) fn can_interrupt(&self) -> bool
+    {
+        self.cancel.is_some() || self.deadline.is_some()
+    }
+
+    // Returns the kind of error to stop a parse with, if the options call for it to be stopped now
+    pub(crate) fn interrupted(&self) -> Option<ParseErrorKind>
+    {
+        if self.cancel.as_ref().is_some_and(CancelToken::is_cancelled) { return Some(ParseErrorKind::Cancelled); }
+        self.deadline.is_some_and(|deadline| Instant::now() >= deadline).then_some(ParseErrorKind::TimedOut)
+    }
+}
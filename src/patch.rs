@@ -0,0 +1,212 @@
+//! Applying JSON Patch (RFC 6902) and JSON Merge Patch (RFC 7386) documents
+
+/* (IMPORTS) */
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::path::{follow_mut, pointer_index, pointer_tokens};
+use crate::JSON;
+
+
+/// Error returned when a JSON Patch cannot be applied, the value it was applied to is left unchanged
+#[derive(Debug, Clone, PartialEq)]
+pub struct PatchError {
+    /// Index of the failing operation within the patch
+    pub index: usize,
+    /// Description of the problem
+    pub message: String,
+}
+
+impl fmt::Display for PatchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+    {
+        write!(f, "patch operation {}: {}", self.index, self.message)
+    }
+}
+
+impl std::error::Error for PatchError {}
+
+impl JSON {
+
+    /// Applies a JSON Patch, an array of operations (ie. `[{ "op" : "add", "path" : "/a/-", "value" : 1 }]`)
+    ///
+    /// Supports the "add", "remove", "replace", "move", "copy" and "test" operations, "test" compares as in `semantic_eq`.
+    /// The patch is applied as a whole, so if any operation fails none of them take effect.
+    ///
+    /// # Errors
+    /// Returns a `PatchError` if the patch is not an array of operations, an operation is malformed,
+    /// refers to a value that does not exist, or a "test" operation fails
+    pub fn apply_patch(&mut self, patch: &JSON) -> Result<(), PatchError>
+    {
+        let JSON::JsArray(operations) = patch else {
+            return Err(PatchError { index: 0, message: "a patch must be an array of operations".to_string() });
+        };
+
+        let mut patched = self.clone();
+        for (index, operation) in operations.iter().enumerate() {
+            apply(&mut patched, operation).map_err(|message| PatchError { index, message })?;
+        }
+        *self = patched;
+        Ok(())
+    }
+
+    /// Applies a JSON Merge Patch, an object of members to set or (when null) remove, merged recursively
+    ///
+    /// A patch that is not an object replaces the value, and patching a value that is not an object starts from an empty one.
+    pub fn merge_patch(&mut self, patch: &JSON)
+    {
+        let JSON::JsObject(patch) = patch else {
+            *self = patch.clone();
+            return;
+        };
+
+        if !matches!(self, JSON::JsObject(_)) { *self = JSON::JsObject(HashMap::new()); }
+        let JSON::JsObject(map) = self else { unreachable!() };
+
+        for (key, member) in patch {
+            match member {
+                JSON::JsNull => { map.remove(key); }
+                _ => map.entry(key.clone()).or_insert(JSON::JsNull).merge_patch(member),
+            }
+        }
+    }
+}
+
+// Applies a single operation of a JSON Patch
+fn apply(doc: &mut JSON, operation: &JSON) -> Result<(), String>
+{
+    let JSON::JsObject(operation) = operation else { return Err("an operation must be an object".to_string()) };
+    let member = |name: &str| operation.get(name).ok_or_else(|| format!("missing \"{name}\""));
+    let pointer = |name: &str| match member(name)? {
+        JSON::JsString(pointer) => Ok(pointer.as_str()),
+        _ => Err(format!("\"{name}\" must be a string")),
+    };
+
+    let op = match member("op")? {
+        JSON::JsString(op) => op.as_str(),
+        _ => return Err("\"op\" must be a string".to_string()),
+    };
+    let path = pointer("path")?;
+
+    match op {
+        "add" => add(doc, path, member("value")?.clone()),
+        "remove" => remove(doc, path).map(|_| ()),
+        "replace" => {
+            let value = member("value")?.clone();
+            let target = doc.pointer_mut(path).ok_or_else(|| format!("no value at '{path}'"))?;
+            *target = value;
+            Ok(())
+        }
+        "move" => {
+            let from = pointer("from")?;
+            if from == path { return Ok(()); }
+            if path.starts_with(from) && path[from.len()..].starts_with('/') {
+                return Err(format!("cannot move '{from}' into itself"));
+            }
+            let value = remove(doc, from)?;
+            add(doc, path, value)
+        }
+        "copy" => {
+            let from = pointer("from")?;
+            let value = doc.pointer(from).cloned().ok_or_else(|| format!("no value at '{from}'"))?;
+            add(doc, path, value)
+        }
+        "test" => {
+            let value = member("value")?;
+            match doc.pointer(path) {
+                Some(actual) if actual.semantic_eq(value) => Ok(()),
+                Some(_) => Err(format!("test failed, the value at '{path}' is different")),
+                None => Err(format!("test failed, no value at '{path}'")),
+            }
+        }
+        _ => Err(format!("unknown operation '{op}'")),
+    }
+}
+
+// Adds a value at a pointer, its parent must exist and within an array the value is inserted before the index
+fn add(doc: &mut JSON, pointer: &str, value: JSON) -> Result<(), String>
+{
+    let tokens = pointer_tokens(pointer).ok_or_else(|| format!("invalid pointer '{pointer}'"))?;
+    let Some((last, parent)) = tokens.split_last() else {
+        *doc = value;
+        return Ok(());
+    };
+
+    match follow_mut(doc, parent) {
+        Some(JSON::JsObject(map)) => { map.insert(last.clone(), value); }
+        Some(JSON::JsArray(vec)) => {
+            let index = match last.as_str() {
+                "-" => vec.len(),
+                _ => pointer_index(last).filter(|index| *index <= vec.len()).ok_or_else(|| format!("invalid array index in '{pointer}'"))?,
+            };
+            vec.insert(index, value);
+        }
+        _ => return Err(format!("no object or array to add '{pointer}' to")),
+    }
+    Ok(())
+}
+
+// Removes the value at a pointer, which must exist
+fn remove(doc: &mut JSON, pointer: &str) -> Result<JSON, String>
+{
+    doc.remove_pointer(pointer).ok_or_else(|| format!("no value at '{pointer}'"))
+}
+
+
+/* (TESTS) */
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::json;
+
+    #[test]
+    fn test_apply_patch()
+    {
+        let mut doc = json(r#"{ "a" : { "b" : [1, 2] }, "c" : "x" }"#);
+        let patch = json(r#"[
+            { "op" : "test", "path" : "/c", "value" : "x" },
+            { "op" : "add", "path" : "/a/b/0", "value" : 0 },
+            { "op" : "add", "path" : "/a/b/-", "value" : 3 },
+            { "op" : "replace", "path" : "/c", "value" : "y" },
+            { "op" : "copy", "from" : "/c", "path" : "/d" },
+            { "op" : "move", "from" : "/a/b", "path" : "/b" },
+            { "op" : "remove", "path" : "/a" }
+        ]"#);
+
+        doc.apply_patch(&patch).unwrap();
+        assert_eq!( json(r#"{ "b" : [0, 1, 2, 3], "c" : "y", "d" : "y" }"#), doc );
+    }
+
+    #[test]
+    fn test_patch_errors()
+    {
+        let mut doc = json(r#"{ "a" : [1] }"#);
+        let error = |doc: &mut JSON, patch: &str| doc.apply_patch(&json(patch)).unwrap_err();
+
+        //Nothing is applied when a later operation fails
+        let e = error(&mut doc, r#"[{ "op" : "add", "path" : "/b", "value" : 1 }, { "op" : "remove", "path" : "/x" }]"#);
+        assert_eq!( PatchError { index: 1, message: "no value at '/x'".to_string() }, e );
+        assert_eq!( json(r#"{ "a" : [1] }"#), doc );
+
+        assert_eq!( 0, error(&mut doc, r#"[{ "op" : "test", "path" : "/a/0", "value" : 2 }]"#).index );
+        assert_eq!( "invalid array index in '/a/2'", error(&mut doc, r#"[{ "op" : "add", "path" : "/a/2", "value" : 2 }]"#).message );
+        assert_eq!( "no object or array to add '/x/y' to", error(&mut doc, r#"[{ "op" : "add", "path" : "/x/y", "value" : 2 }]"#).message );
+        assert_eq!( "cannot move '/a' into itself", error(&mut doc, r#"[{ "op" : "move", "from" : "/a", "path" : "/a/0" }]"#).message );
+        assert_eq!( "missing \"op\"", error(&mut doc, r#"[{ "path" : "/a" }]"#).message );
+        assert_eq!( "patch operation 0: a patch must be an array of operations", error(&mut doc, "{}").to_string() );
+    }
+
+    #[test]
+    fn test_merge_patch()
+    {
+        //The example from RFC 7386
+        let mut doc = json(r#"{ "title" : "Goodbye!", "author" : { "givenName" : "John", "familyName" : "Doe" }, "tags" : ["example", "sample"], "content" : "This will be unchanged" }"#);
+        doc.merge_patch(&json(r#"{ "title" : "Hello!", "phoneNumber" : "+01-123-456-7890", "author" : { "familyName" : null }, "tags" : ["example"] }"#));
+
+        assert_eq!( json(r#"{ "title" : "Hello!", "author" : { "givenName" : "John" }, "tags" : ["example"], "content" : "This will be unchanged", "phoneNumber" : "+01-123-456-7890" }"#), doc );
+
+        let mut doc = json("[1]");
+        doc.merge_patch(&json(r#"{ "a" : { "b" : null, "c" : 1 } }"#));
+        assert_eq!( json(r#"{ "a" : { "c" : 1 } }"#), doc );
+    }
+}
@@ -0,0 +1,593 @@
+//! Dotted paths into a json value (ie. "config.servers[2].host")
+//!
+//! A path is a sequence of object keys and array indexes. Keys are separated by dots and indexes are written in brackets.
+//! Keys that contain dots, brackets or quotes (or that are empty) are written as quoted strings in brackets (ie. `a["b.c"]`).
+//! The empty path refers to the value itself.
+//!
+//! Values can also be looked up by JSON Pointer (RFC 6901, ie. "/config/servers/2/host").
+
+/* (IMPORTS) */
+use std::collections::hash_map::Entry;
+use std::fmt;
+use std::str::FromStr;
+
+use crate::lexer::{lex_with, LexemeKind};
+use crate::{Options, JSON};
+
+
+/// A single step of a path, either an object key or an array index
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum PathSegment {
+    Key(String),
+    Index(usize),
+}
+
+/// A path to a value within a json document
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct JsonPath {
+    segments: Vec<PathSegment>,
+}
+
+/// Error returned when a path is malformed or can not be followed
+#[derive(Debug, Clone, PartialEq)]
+pub enum PathError {
+    /// The path is not valid path syntax, along with the byte offset of the problem
+    Syntax(usize, String),
+    /// A segment tried to step into a value that is not an object or array (or the wrong one of the two)
+    NotAContainer(JsonPath),
+    /// An array index is past the end of the array
+    IndexOutOfBounds(JsonPath),
+}
+
+impl fmt::Display for PathError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+    {
+        match self {
+            PathError::Syntax(offset, message) => write!(f, "invalid path at offset {offset}: {message}"),
+            PathError::NotAContainer(path) => write!(f, "value at \"{path}\" can not be stepped into"),
+            PathError::IndexOutOfBounds(path) => write!(f, "index out of bounds at \"{path}\""),
+        }
+    }
+}
+
+impl std::error::Error for PathError {}
+
+impl JsonPath {
+
+    /// Creates the empty path, which refers to the root value
+    pub fn root() -> JsonPath
+    {
+        JsonPath::default()
+    }
+
+    /// Parses a dotted path (ie. "config.servers[2].host")
+    pub fn parse(path: &str) -> Result<JsonPath, PathError>
+    {
+        let bytes = path.as_bytes();
+        let mut segments = vec![];
+        let mut pos = 0;
+        let error = |pos: usize, message: &str| Err(PathError::Syntax(pos, message.to_string()));
+
+        while pos < bytes.len() {
+            //After the first segment, keys must be preceded by a dot
+            if !segments.is_empty() && bytes[pos] != b'[' {
+                if bytes[pos] != b'.' { return error(pos, "expected '.' or '['"); }
+                pos += 1;
+            }
+
+            if bytes.get(pos) == Some(&b'[') {
+                let (segment, next) = parse_bracket(path, pos)?;
+                segments.push(segment);
+                pos = next;
+            } else {
+                let len = bytes[pos..].iter().take_while(|b| !matches!(b, b'.' | b'[' | b']' | b'"')).count();
+                if len == 0 { return error(pos, "expected a key"); }
+
+                segments.push(PathSegment::Key(path[pos..pos + len].to_string()));
+                pos += len;
+            }
+        }
+
+        Ok(JsonPath { segments })
+    }
+
+    /// Returns the segments of the path, in order from the root
+    pub fn segments(&self) -> &[PathSegment]
+    {
+        &self.segments
+    }
+
+    /// Returns true if the path refers to the root value
+    pub fn is_root(&self) -> bool
+    {
+        self.segments.is_empty()
+    }
+
+    /// Returns a new path with an object key appended
+    pub fn key(&self, key: &str) -> JsonPath
+    {
+        let mut path = self.clone();
+        path.segments.push(PathSegment::Key(key.to_string()));
+        path
+    }
+
+    /// Returns a new path with an array index appended
+    pub fn index(&self, index: usize) -> JsonPath
+    {
+        let mut path = self.clone();
+        path.segments.push(PathSegment::Index(index));
+        path
+    }
+
+    // Appends a segment in place, used by traversals that extend and shrink one path as they walk
+    pub(crate) fn push(&mut self, segment: PathSegment)
+    {
+        self.segments.push(segment);
+    }
+
+    // Removes the last segment in place
+    pub(crate) fn pop(&mut self)
+    {
+        self.segments.pop();
+    }
+
+    // Returns the path made up of the first n segments
+    pub(crate) fn prefix(&self, n: usize) -> JsonPath
+    {
+        JsonPath { segments: self.segments[..n].to_vec() }
+    }
+}
+
+// Parses a bracketed segment starting at the opening bracket, returning the segment and the position after the closing bracket
+fn parse_bracket(path: &str, start: usize) -> Result<(PathSegment, usize), PathError>
+{
+    let bytes = path.as_bytes();
+    let mut pos = start + 1;
+
+    let segment = if bytes.get(pos) == Some(&b'"') {
+        //Quoted key, where \" and \\ are the only escapes
+        let mut key = String::new();
+        let mut chars = path[pos + 1..].char_indices();
+
+        loop {
+            match chars.next() {
+                None => return Err(PathError::Syntax(start, "unterminated quoted key".to_string())),
+                Some((i, '"')) => { pos += 1 + i + 1; break; }
+                Some((_, '\\')) => match chars.next() {
+                    Some((_, ch @ ('"' | '\\'))) => key.push(ch),
+                    _ => return Err(PathError::Syntax(start, "invalid escape in quoted key".to_string())),
+                },
+                Some((_, ch)) => key.push(ch),
+            }
+        }
+
+        PathSegment::Key(key)
+    } else {
+        let len = bytes[pos..].iter().take_while(|b| b.is_ascii_digit()).count();
+        let index = path[pos..pos + len].parse::<usize>()
+                        .map_err(|_| PathError::Syntax(pos, "expected an index or a quoted key".to_string()))?;
+        pos += len;
+
+        PathSegment::Index(index)
+    };
+
+    if bytes.get(pos) != Some(&b']') {
+        return Err(PathError::Syntax(pos, "expected ']'".to_string()));
+    }
+
+    Ok((segment, pos + 1))
+}
+
+// Returns true if a key can be written without brackets and quotes
+fn is_plain_key(key: &str) -> bool
+{
+    !key.is_empty() && !key.contains(['.', '[', ']', '"'])
+}
+
+// Formats the path in the same syntax that JsonPath::parse accepts
+impl fmt::Display for JsonPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+    {
+        for (i, segment) in self.segments.iter().enumerate() {
+            match segment {
+                PathSegment::Index(index) => write!(f, "[{index}]")?,
+                PathSegment::Key(key) if is_plain_key(key) => {
+                    if i > 0 { f.write_str(".")?; }
+                    f.write_str(key)?;
+                }
+                PathSegment::Key(key) => write!(f, "[\"{}\"]", key.replace('\\', "\\\\").replace('"', "\\\""))?,
+            }
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for JsonPath {
+    type Err = PathError;
+
+    fn from_str(path: &str) -> Result<JsonPath, PathError>
+    {
+        JsonPath::parse(path)
+    }
+}
+
+
+/* (JSON PATH ACCESS) */
+
+impl JSON {
+
+    /// Returns the value at a dotted path (ie. "config.servers[2].host"),
+    /// or None if the path is malformed or any segment of it does not exist
+    pub fn get_path(&self, path: &str) -> Option<&JSON>
+    {
+        self.get_json_path(&JsonPath::parse(path).ok()?)
+    }
+
+    /// Returns the value at a parsed path, or None if any segment of it does not exist
+    pub fn get_json_path(&self, path: &JsonPath) -> Option<&JSON>
+    {
+        path.segments.iter().try_fold(self, |value, segment| match (value, segment) {
+            (JSON::JsObject(map), PathSegment::Key(key)) => map.get(key),
+            (JSON::JsArray(vec), PathSegment::Index(index)) => vec.get(*index),
+            _ => None
+        })
+    }
+
+    /// Sets the value at a dotted path (ie. "config.servers[2].host"), returning the value that was replaced (if any)
+    ///
+    /// Missing object keys along the way are created, as an object if the next segment is a key or as an array if it is an index.
+    /// An index equal to the length of an array appends to it.
+    ///
+    /// # Errors
+    /// Returns a `PathError` if the path is malformed, steps into a value that is not the right kind of container,
+    /// or uses an index past the end of an array
+    pub fn set_path(&mut self, path: &str, value: JSON) -> Result<Option<JSON>, PathError>
+    {
+        self.set_json_path(&JsonPath::parse(path)?, value)
+    }
+
+    /// Sets the value at a parsed path, see `set_path`. The value is left as it was if an error is returned.
+    pub fn set_json_path(&mut self, path: &JsonPath, value: JSON) -> Result<Option<JSON>, PathError>
+    {
+        let mut current = self;
+
+        for (i, segment) in path.segments.iter().enumerate() {
+            let last = i + 1 == path.segments.len();
+
+            //Where the path leaves the value, the rest of it is built and checked before it is attached
+            current = match (current, segment) {
+                (JSON::JsObject(map), PathSegment::Key(key)) => match map.entry(key.clone()) {
+                    Entry::Occupied(entry) if last => return Ok(Some(std::mem::replace(entry.into_mut(), value))),
+                    Entry::Occupied(entry) => entry.into_mut(),
+                    Entry::Vacant(entry) => {
+                        entry.insert(missing_value(path, i + 1, value)?);
+                        return Ok(None);
+                    }
+                },
+                (JSON::JsArray(vec), PathSegment::Index(index)) => {
+                    if *index == vec.len() {
+                        vec.push(missing_value(path, i + 1, value)?);
+                        return Ok(None);
+                    }
+                    let slot = vec.get_mut(*index).ok_or_else(|| PathError::IndexOutOfBounds(path.prefix(i + 1)))?;
+                    if last { return Ok(Some(std::mem::replace(slot, value))); }
+                    slot
+                }
+                _ => return Err(PathError::NotAContainer(path.prefix(i))),
+            };
+        }
+
+        //Empty path replaces the value itself
+        Ok(Some(std::mem::replace(current, value)))
+    }
+}
+
+// Builds what the segments of a path from an index on lead to when none of them exist yet, ie. the value within objects and arrays of one member.
+// A new array can only be stepped into at index 0, so any other index is out of bounds.
+fn missing_value(path: &JsonPath, from: usize, value: JSON) -> Result<JSON, PathError>
+{
+    let rest = &path.segments[from..];
+    if let Some(i) = rest.iter().position(|segment| matches!(segment, PathSegment::Index(index) if *index > 0)) {
+        return Err(PathError::IndexOutOfBounds(path.prefix(from + i + 1)));
+    }
+
+    Ok(rest.iter().rev().fold(value, |inner, segment| match segment {
+        PathSegment::Key(key) => JSON::JsObject([(key.clone(), inner)].into_iter().collect()),
+        PathSegment::Index(_) => JSON::JsArray(vec![inner]),
+    }))
+}
+
+
+/* (JSON POINTER ACCESS) */
+
+// Splits a JSON Pointer (RFC 6901) into its unescaped reference tokens, returns None if it is not empty and does not start with '/'
+pub(crate) fn pointer_tokens(pointer: &str) -> Option<Vec<String>>
+{
+    if pointer.is_empty() { return Some(vec![]); }
+
+    let tokens = pointer.strip_prefix('/')?;
+    Some( tokens.split('/').map(|token| token.replace("~1", "/").replace("~0", "~")).collect() )
+}
+
+// Converts a reference token to an array index, leading zeros (and '-', which means past the end) are not valid indexes
+pub(crate) fn pointer_index(token: &str) -> Option<usize>
+{
+    if token.len() > 1 && token.starts_with('0') { return None; }
+    if !token.bytes().all(|b| b.is_ascii_digit()) { return None; }
+    token.parse().ok()
+}
+
+// Works out the path of the value being parsed at an offset into the input, from the lexemes before it
+// Parse errors use it so that checking the rest of the input is not slowed down by tracking the path as it goes
+pub(crate) fn path_at(input: &str, offset: usize, comments: bool) -> JsonPath
+{
+    //Index of each open array, and key of each open object (None while the key of the next member is expected)
+    enum Open {
+        Array(usize),
+        Object(Option<String>),
+    }
+    let mut stack = vec![];
+
+    let options = Options { allow_comments: comments, ..Options::default() };
+    for lexeme in lex_with(input, &options).map_while(Result::ok).take_while(|lexeme| lexeme.start < offset) {
+        match (lexeme.kind, stack.last_mut()) {
+            (LexemeKind::BeginArray, _) => stack.push(Open::Array(0)),
+            (LexemeKind::BeginObject, _) => stack.push(Open::Object(None)),
+            (LexemeKind::EndArray | LexemeKind::EndObject, _) => { stack.pop(); }
+            (LexemeKind::Comma, Some(Open::Array(i))) => *i += 1,
+            (LexemeKind::Comma, Some(Open::Object(key))) => *key = None,
+            (LexemeKind::String, Some(Open::Object(key @ None))) => *key = Some(lexeme.string_value().unwrap_or_default()),
+            _ => {}
+        }
+    }
+
+    let segments = stack.into_iter().filter_map(|open| match open {
+        Open::Array(i) => Some(PathSegment::Index(i)),
+        Open::Object(key) => key.map(PathSegment::Key),
+    });
+    JsonPath { segments: segments.collect() }
+}
+
+// Follows unescaped reference tokens down from a value
+pub(crate) fn follow_mut<'a>(value: &'a mut JSON, tokens: &[String]) -> Option<&'a mut JSON>
+{
+    tokens.iter().try_fold(value, |value, token| match value {
+        JSON::JsObject(map) => map.get_mut(token),
+        JSON::JsArray(vec) => vec.get_mut(pointer_index(token)?),
+        _ => None
+    })
+}
+
+impl JSON {
+
+    /// Returns the value at a JSON Pointer (RFC 6901, ie. "/config/servers/2/host"),
+    /// or None if the pointer is malformed or any token of it does not exist. The empty pointer refers to the value itself.
+    pub fn pointer(&self, pointer: &str) -> Option<&JSON>
+    {
+        pointer_tokens(pointer)?.iter().try_fold(self, |value, token| match value {
+            JSON::JsObject(map) => map.get(token),
+            JSON::JsArray(vec) => vec.get(pointer_index(token)?),
+            _ => None
+        })
+    }
+
+    /// Returns a mutable reference to the value at a JSON Pointer, see `pointer`
+    pub fn pointer_mut(&mut self, pointer: &str) -> Option<&mut JSON>
+    {
+        follow_mut(self, &pointer_tokens(pointer)?)
+    }
+
+    /// Sets the value at a JSON Pointer, returning the value that was replaced (if any)
+    ///
+    /// Missing object members along the way are created as objects. In an array the token `-`
+    /// (or an index equal to the length of the array) appends to it.
+    ///
+    /// # Errors
+    /// Returns a `PathError` if the pointer does not start with '/', steps into a value that is not a container
+    /// (or into an array with a token that is not an index), or uses an index past the end of an array
+    pub fn set_pointer(&mut self, pointer: &str, value: JSON) -> Result<Option<JSON>, PathError>
+    {
+        let tokens = pointer_tokens(pointer).ok_or_else(|| PathError::Syntax(0, "expected '/'".to_string()))?;
+        let mut path = JsonPath::root();
+        let mut current = self;
+
+        for (i, token) in tokens.iter().enumerate() {
+            let last = i + 1 == tokens.len();
+
+            current = match current {
+                JSON::JsObject(map) => {
+                    if last { return Ok(map.insert(token.clone(), value)); }
+                    path.push(PathSegment::Key(token.clone()));
+                    map.entry(token.clone()).or_insert_with(|| JSON::JsObject(Default::default()))
+                }
+                JSON::JsArray(vec) => {
+                    let index = match token.as_str() {
+                        "-" => vec.len(),
+                        _ => pointer_index(token).ok_or_else(|| PathError::NotAContainer(path.clone()))?,
+                    };
+                    path.push(PathSegment::Index(index));
+
+                    if index == vec.len() {
+                        if last { vec.push(value); return Ok(None); }
+                        vec.push(JSON::JsObject(Default::default()));
+                    }
+                    let slot = vec.get_mut(index).ok_or_else(|| PathError::IndexOutOfBounds(path.clone()))?;
+                    if last { return Ok(Some(std::mem::replace(slot, value))); }
+                    slot
+                }
+                _ => return Err(PathError::NotAContainer(path)),
+            };
+        }
+
+        //Empty pointer replaces the value itself
+        Ok(Some(std::mem::replace(current, value)))
+    }
+
+    /// Removes the value at a JSON Pointer and returns it, or None if there is no value at the pointer
+    ///
+    /// Later elements of an array shift down to fill the gap. Removing the value itself (the empty pointer) leaves `JsNull`.
+    pub fn remove_pointer(&mut self, pointer: &str) -> Option<JSON>
+    {
+        let mut tokens = pointer_tokens(pointer)?;
+        let Some(last) = tokens.pop() else { return Some(std::mem::replace(self, JSON::JsNull)); };
+
+        match follow_mut(self, &tokens)? {
+            JSON::JsObject(map) => map.remove(&last),
+            JSON::JsArray(vec) => {
+                let index = pointer_index(&last).filter(|index| *index < vec.len())?;
+                Some(vec.remove(index))
+            }
+            _ => None
+        }
+    }
+}
+
+
+/* (TESTS) */
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::json;
+
+    #[test]
+    fn test_parse()
+    {
+        let key = |k: &str| PathSegment::Key(k.to_string());
+
+        assert_eq!( Ok(JsonPath::root()), JsonPath::parse("") );
+        assert_eq!( vec![key("config"), key("servers"), PathSegment::Index(2), key("host")],
+                    JsonPath::parse("config.servers[2].host").unwrap().segments() );
+        assert_eq!( vec![PathSegment::Index(0), PathSegment::Index(1)], JsonPath::parse("[0][1]").unwrap().segments() );
+        assert_eq!( vec![key("a"), key("b.c"), key("say \"hi\"")], JsonPath::parse(r#"a["b.c"]["say \"hi\""]"#).unwrap().segments() );
+
+        assert!( JsonPath::parse("a..b").is_err() );
+        assert!( JsonPath::parse(".a").is_err() );
+        assert!( JsonPath::parse("a[x]").is_err() );
+        assert!( JsonPath::parse("a[1").is_err() );
+        assert!( JsonPath::parse("a[\"b]").is_err() );
+        assert!( JsonPath::parse("a]").is_err() );
+    }
+
+    #[test]
+    fn test_display()
+    {
+        for path in ["", "a", "config.servers[2].host", "[0][1].x", r#"a["b.c"][""].d"#, r#"["say \"hi\""]"#] {
+            assert_eq!( path, JsonPath::parse(path).unwrap().to_string() );
+        }
+    }
+
+    #[test]
+    fn test_get_path()
+    {
+        let doc = json(r#"{ "config" : { "servers" : [{ "host" : "a" }, { "host" : "b" }] } }"#);
+
+        assert_eq!( Some(&JSON::JsString("b".to_string())), doc.get_path("config.servers[1].host") );
+        assert_eq!( Some(&doc), doc.get_path("") );
+        assert_eq!( None, doc.get_path("config.servers[2].host") );
+        assert_eq!( None, doc.get_path("config.servers.host") );
+        assert_eq!( None, doc.get_path("config[") );
+    }
+
+    #[test]
+    fn test_set_path()
+    {
+        let mut doc = json(r#"{ "config" : { "servers" : [{ "host" : "a" }] } }"#);
+
+        assert_eq!( Ok(Some(JSON::JsString("a".to_string()))), doc.set_path("config.servers[0].host", JSON::JsNull) );
+        assert_eq!( Ok(None), doc.set_path("config.servers[1].host", JSON::JsBool(true)) );  //Appends a new object
+        assert_eq!( Ok(None), doc.set_path("config.ports[0]", JSON::JsBool(false)) );        //Creates a new array
+        assert_eq!( Some(&JSON::JsNull), doc.get_path("config.servers[0].host") );
+        assert_eq!( Some(&JSON::JsBool(true)), doc.get_path("config.servers[1].host") );
+        assert_eq!( Some(&JSON::JsBool(false)), doc.get_path("config.ports[0]") );
+
+        assert_eq!( Err(PathError::IndexOutOfBounds(JsonPath::parse("config.ports[5]").unwrap())),
+                    doc.set_path("config.ports[5]", JSON::JsNull) );
+        assert_eq!( Err(PathError::NotAContainer(JsonPath::parse("config.ports[0]").unwrap())),
+                    doc.set_path("config.ports[0].x", JSON::JsNull) );
+        assert_eq!( Err(PathError::NotAContainer(JsonPath::parse("config").unwrap())),
+                    doc.set_path("config[0]", JSON::JsNull) );
+
+        //Nothing is created when the path fails further on
+        let before = doc.clone();
+        assert_eq!( Err(PathError::IndexOutOfBounds(JsonPath::parse("config.x.y[3]").unwrap())),
+                    doc.set_path("config.x.y[3]", JSON::JsNull) );
+        assert_eq!( Err(PathError::IndexOutOfBounds(JsonPath::parse("config.servers[2][0].a[1]").unwrap())),
+                    doc.set_path("config.servers[2][0].a[1]", JSON::JsNull) );
+        assert_eq!( before, doc );
+
+        let mut empty = json("{}");
+        assert!( empty.set_path("x.y[3]", JSON::JsNull).is_err() );
+        assert_eq!( json("{}"), empty );
+        assert_eq!( Ok(None), empty.set_path("x.y[0][0].z", JSON::JsNull) );
+        assert_eq!( json(r#"{ "x" : { "y" : [[{ "z" : null }]] } }"#), empty );
+    }
+
+    #[test]
+    fn test_pointer()
+    {
+        let doc = json(r#"{ "servers" : [{ "host" : "a" }, { "host" : "b" }], "a/b" : 1, "m~n" : 2, "" : 3, "7" : 4 }"#);
+
+        assert_eq!( Some(&doc), doc.pointer("") );
+        assert_eq!( Some(&JSON::JsString("b".to_string())), doc.pointer("/servers/1/host") );
+        assert_eq!( Some(&json("1")), doc.pointer("/a~1b") );
+        assert_eq!( Some(&json("2")), doc.pointer("/m~0n") );
+        assert_eq!( Some(&json("3")), doc.pointer("/") );
+        assert_eq!( Some(&json("4")), doc.pointer("/7") );                  //Digits are a key within an object
+
+        assert_eq!( None, doc.pointer("servers") );                          //Must start with '/'
+        assert_eq!( None, doc.pointer("/servers/01") );                      //Leading zero
+        assert_eq!( None, doc.pointer("/servers/-") );
+        assert_eq!( None, doc.pointer("/servers/2") );
+        assert_eq!( None, doc.pointer("/servers/0/host/x") );
+    }
+
+    #[test]
+    fn test_pointer_mut()
+    {
+        let mut doc = json(r#"{ "servers" : [{ "host" : "a" }, { "host" : "b" }] }"#);
+
+        if let Some(JSON::JsString(host)) = doc.pointer_mut("/servers/0/host") {
+            host.push_str(".local");
+        }
+        assert_eq!( Some(&json(r#""a.local""#)), doc.pointer("/servers/0/host") );
+        assert_eq!( None, doc.pointer_mut("/servers/2") );
+    }
+
+    #[test]
+    fn test_set_pointer()
+    {
+        let mut doc = json(r#"{ "servers" : [{ "host" : "a" }] }"#);
+
+        assert_eq!( Ok(Some(json(r#""a""#))), doc.set_pointer("/servers/0/host", json(r#""b""#)) );
+        assert_eq!( Ok(None), doc.set_pointer("/servers/-", json(r#"{ "host" : "c" }"#)) );
+        assert_eq!( Ok(None), doc.set_pointer("/servers/2/host", json(r#""d""#)) );
+        assert_eq!( Ok(None), doc.set_pointer("/limits/max/a~1b", json("1")) );
+        assert_eq!( json(r#"{ "servers" : [{ "host" : "b" }, { "host" : "c" }, { "host" : "d" }], "limits" : { "max" : { "a/b" : 1 } } }"#), doc );
+
+        let path = |p: &str| JsonPath::parse(p).unwrap();
+        assert_eq!( Err(PathError::IndexOutOfBounds(path("servers[4]"))), doc.set_pointer("/servers/4", json("1")) );
+        assert_eq!( Err(PathError::NotAContainer(path("servers"))), doc.set_pointer("/servers/x", json("1")) );
+        assert_eq!( Err(PathError::NotAContainer(path("limits.max[\"a/b\"]"))), doc.set_pointer("/limits/max/a~1b/c", json("1")) );
+        assert!( matches!(doc.set_pointer("servers", json("1")), Err(PathError::Syntax(0, _))) );
+
+        assert_eq!( Ok(Some(doc.clone())), doc.set_pointer("", json("1")) );
+        assert_eq!( json("1"), doc );
+    }
+
+    #[test]
+    fn test_remove_pointer()
+    {
+        let mut doc = json(r#"{ "a" : [1, 2, 3], "b" : { "c" : true } }"#);
+
+        assert_eq!( Some(json("1")), doc.remove_pointer("/a/0") );
+        assert_eq!( Some(json("true")), doc.remove_pointer("/b/c") );
+        assert_eq!( None, doc.remove_pointer("/a/2") );
+        assert_eq!( None, doc.remove_pointer("/a/-") );
+        assert_eq!( None, doc.remove_pointer("/x/y") );
+        assert_eq!( None, doc.remove_pointer("a") );
+        assert_eq!( json(r#"{ "a" : [2, 3], "b" : {} }"#), doc );
+
+        assert_eq!( Some(doc.clone()), doc.remove_pointer("") );
+        assert_eq!( JSON::JsNull, doc );
+    }
+}
@@ -0,0 +1,233 @@
+//! Converting Java-style properties files and dotenv files (ie. "server.port=8080") to nested values
+//!
+//! Each key is a dotted path of object keys, so "server.host=localhost" and "server.port=8080" make one "server" object.
+
+/* (IMPORTS) */
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::combinator::{escaped_transform, Parser as _};
+use crate::lexer::match_escape;
+use crate::query::infer_scalar;
+use crate::{JsonPath, PathSegment, JSON};
+
+
+// Characters that separate a key from its value after it (or from the separator), and that are skipped at the start of a line
+const WHITESPACE: [char; 3] = [' ', '\t', '\u{c}'];
+
+/// Error returned when the properties of a file can not be made into one value, along with the number of the line of the property
+#[derive(Debug, Clone, PartialEq)]
+pub enum PropertiesError {
+    /// The key has an empty key in its path (ie. "server..port" or ".port")
+    InvalidKey(usize, String),
+    /// The key sets a value inside the value of another key, or a value that other keys are inside of (ie. "a=1" with "a.b=2")
+    Conflict(usize, String),
+}
+
+impl PropertiesError {
+
+    /// Returns the number of the line of the property that failed
+    pub fn line(&self) -> usize
+    {
+        match self {
+            PropertiesError::InvalidKey(line, _) | PropertiesError::Conflict(line, _) => *line,
+        }
+    }
+}
+
+impl fmt::Display for PropertiesError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+    {
+        match self {
+            PropertiesError::InvalidKey(line, key) => write!(f, "line {line}: key \"{key}\" has an empty key in its path"),
+            PropertiesError::Conflict(line, key) => write!(f, "line {line}: key \"{key}\" conflicts with the value of another key"),
+        }
+    }
+}
+
+impl std::error::Error for PropertiesError {}
+
+impl JSON {
+
+    /// Builds an object from the text of a properties or dotenv file, setting the dotted path of each key to its value.
+    ///
+    /// As in Java properties files, lines starting with '#' or '!' are comments, a key is separated from its value by '=', ':' or whitespace,
+    /// a line ending with a backslash continues on the next one, and a backslash escapes the character after it (`\n`, `\t` and `\uXXXX` as in json).
+    /// As in dotenv files, a key can start with "export " and a value can be quoted, with escapes in double quotes and none in single quotes.
+    ///
+    /// Unquoted values that are the literals true, false & null or numbers (as json writes them, so "007" is a string) are kept as such,
+    /// any other value is a string. A key given more than once is set to its last value.
+    ///
+    /// # Examples
+    /// ```
+    /// use Parser::{try_parse, JSON};
+    ///
+    /// let text = "# server\nserver.host = localhost\nserver.port = 8080\nexport DEBUG=false\nGREETING=\"hello\\tworld\"\n";
+    /// let value = JSON::from_properties(text).unwrap();
+    ///
+    /// let expected = r#"{ "server" : { "host" : "localhost", "port" : 8080 }, "DEBUG" : false, "GREETING" : "hello\tworld" }"#;
+    /// assert_eq!( try_parse(expected).unwrap(), value );
+    /// ```
+    ///
+    /// # Errors
+    /// Returns a `PropertiesError` if a key has an empty key in its path, or if a key's value would be inside another key's value (ie. "a=1" and "a.b=2")
+    pub fn from_properties(text: &str) -> Result<JSON, PropertiesError>
+    {
+        let mut root = JSON::JsObject(HashMap::new());
+
+        for (line, logical) in logical_lines(text) {
+            let entry = logical.trim_start_matches(WHITESPACE);
+            if entry.is_empty() || entry.starts_with(['#', '!']) { continue; }
+
+            let entry = match entry.strip_prefix("export") {
+                Some(rest) if rest.starts_with(WHITESPACE) => rest.trim_start_matches(WHITESPACE),
+                _ => entry,
+            };
+            let (rest, key) = escaped_transform(match_key_chars, '\\', match_property_escape).parse(entry).unwrap_or(("", Cow::Borrowed(entry)));
+
+            let names: Vec<&str> = key.split('.').collect();
+            if names.iter().any(|name| name.is_empty()) {
+                return Err(PropertiesError::InvalidKey(line, key.into_owned()));
+            }
+
+            let mut path = JsonPath::root();
+            for name in names {
+                path.push(PathSegment::Key(name.to_string()));
+            }
+
+            //Replacing an object would drop the keys inside of it, so it conflicts just as stepping into a value does
+            let replaces_object = matches!(root.get_json_path(&path), Some(JSON::JsObject(_)));
+            if replaces_object || root.set_json_path(&path, property_value(rest)).is_err() {
+                return Err(PropertiesError::Conflict(line, key.into_owned()));
+            }
+        }
+
+        Ok(root)
+    }
+}
+
+// Joins the lines that end with a backslash to the lines after them (without their leading whitespace), along with the number of the first line
+fn logical_lines(text: &str) -> Vec<(usize, String)>
+{
+    let mut lines = vec![];
+    let mut pending: Option<(usize, String)> = None;
+
+    for (i, line) in text.lines().enumerate() {
+        let (number, mut logical) = match pending.take() {
+            Some((number, logical)) => (number, logical + line.trim_start_matches(WHITESPACE)),
+            None => (i + 1, line.to_string()),
+        };
+
+        //An odd number of backslashes leaves the last one unescaped, but comments never continue
+        let comment = logical.trim_start_matches(WHITESPACE).starts_with(['#', '!']);
+        let backslashes = logical.bytes().rev().take_while(|b| *b == b'\\').count();
+
+        if !comment && backslashes % 2 == 1 {
+            logical.pop();
+            pending = Some((number, logical));
+        } else {
+            lines.push((number, logical));
+        }
+    }
+
+    lines.extend(pending);
+    lines
+}
+
+// Converts what follows the key of a property, skipping its separator. Quoted values are always strings
+fn property_value(rest: &str) -> JSON
+{
+    let rest = rest.trim_start_matches(WHITESPACE);
+    let value = rest.strip_prefix(['=', ':']).unwrap_or(rest).trim_start_matches(WHITESPACE);
+
+    let quoted = value.trim_end_matches(WHITESPACE);
+    if let Some(single) = quoted.strip_prefix('\'').and_then(|s| s.strip_suffix('\'')) {
+        return JSON::JsString(single.to_string());
+    }
+    if let Some(double) = quoted.strip_prefix('"').and_then(|s| s.strip_suffix('"')) {
+        return JSON::JsString(unescape(double).into_owned());
+    }
+
+    infer_scalar(&unescape(value))
+}
+
+// Replaces the escape sequences of a value by the characters they stand for
+fn unescape(text: &str) -> Cow<'_, str>
+{
+    match escaped_transform(match_value_chars, '\\', match_property_escape).parse(text) {
+        Ok((_, unescaped)) => unescaped,
+        Err(_) => Cow::Borrowed(text),  //Only a trailing backslash fails, which never ends a logical line
+    }
+}
+
+// Matches the characters of a key up to a backslash or the separator after it
+fn match_key_chars(input: &str) -> Result<(&str, &str), &str>
+{
+    let n = input.find(['\\', '=', ':', ' ', '\t', '\u{c}']).unwrap_or(input.len());
+    Ok( (&input[n..], &input[..n]) )
+}
+
+// Matches the characters of a value up to a backslash
+fn match_value_chars(input: &str) -> Result<(&str, &str), &str>
+{
+    let n = input.find('\\').unwrap_or(input.len());
+    Ok( (&input[n..], &input[..n]) )
+}
+
+// Matches what follows a backslash, the escapes of json or else any character, which stands for itself (ie. "\=" or "\ ")
+fn match_property_escape(input: &str) -> Result<(&str, char), &str>
+{
+    match_escape(input).or_else(|_| {
+        let ch = input.chars().next().ok_or(input)?;
+        Ok((&input[ch.len_utf8()..], ch))
+    })
+}
+
+
+/* (TESTS) */
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::json;
+
+    #[test]
+    fn test_from_properties()
+    {
+        let text = "\
+# comment
+! also a comment \\
+app.name=demo
+app.port : 8080
+app.ratio 0.5
+app.enabled=true
+app.zip=007
+app.list = one, \\
+           two
+key\\=with\\ escapes = caf\\u00e9\\n
+   indented=  spaced
+empty=
+bare
+";
+        let expected = json(r#"{ "app" : { "name" : "demo", "port" : 8080, "ratio" : 0.5, "enabled" : true, "zip" : "007", "list" : "one, two" },
+                                 "key=with escapes" : "café\n", "indented" : "spaced", "empty" : "", "bare" : "" }"#);
+        assert_eq!( Ok(expected), JSON::from_properties(text) );
+    }
+
+    #[test]
+    fn test_from_dotenv()
+    {
+        let text = "export PORT=8080\nNAME=\"a \\\"b\\\"\\tc\"\nRAW='a\\tb' \nQUOTED_NUMBER=\"1\"\nexport=1\nPORT=9090\r\n";
+        let expected = json(r#"{ "PORT" : 9090, "NAME" : "a \"b\"\tc", "RAW" : "a\\tb", "QUOTED_NUMBER" : "1", "export" : 1 }"#);
+        assert_eq!( Ok(expected), JSON::from_properties(text) );
+    }
+
+    #[test]
+    fn test_properties_errors()
+    {
+        assert_eq!( Err(PropertiesError::InvalidKey(2, "a..b".to_string())), JSON::from_properties("a=1\na..b=2") );
+        assert_eq!( Err(PropertiesError::Conflict(2, "a.b".to_string())), JSON::from_properties("a=1\na.b=2") );
+        assert_eq!( Err(PropertiesError::Conflict(2, "a".to_string())), JSON::from_properties("a.b=2\na=1") );
+        assert_eq!( 3, PropertiesError::Conflict(3, "a".to_string()).line() );
+    }
+}
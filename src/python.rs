@@ -0,0 +1,231 @@
+//! Bindings for Python through PyO3 (enabled with the `python` feature), as the `json_parser` extension module
+//!
+//! `loads` and `dumps` convert between json text and Python values as the standard `json` module does,
+//! while `parse` keeps the document on the Rust side as a `Json` handle that is only converted where it is queried.
+//! Invalid json raises a `ValueError` with the position and cause of the error.
+
+/* (IMPORTS) */
+use std::collections::HashMap;
+
+use pyo3::exceptions::{PyTypeError, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::{PyBool, PyDict, PyFloat, PyInt, PyList, PyString, PyTuple};
+
+use crate::{parse_lossy, try_parse_with, Number, Options, MAX_DEPTH, JSON};
+
+
+/// A parsed document
+#[pyclass(frozen, name = "Json", module = "json_parser")]
+pub struct Json {
+    value: JSON,
+}
+
+#[pymethods]
+impl Json {
+
+    /// Returns a handle to the value at a JSON Pointer (ie. "/servers/0/host"), or None if there is no such value
+    fn pointer(&self, pointer: &str) -> Option<Json>
+    {
+        self.value.pointer(pointer).map(|value| Json { value: value.clone() })
+    }
+
+    /// Returns the document as Python values (dicts, lists, strings, ints, floats, bools and None)
+    fn to_python<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyAny>>
+    {
+        to_python(py, &self.value)
+    }
+
+    /// Returns the document as json text, compact or indented by 2 spaces if pretty
+    #[pyo3(signature = (pretty = false))]
+    fn dumps(&self, pretty: bool) -> String
+    {
+        match pretty {
+            true => format!("{:#}", self.value.display_json()),
+            false => self.value.display_json().to_string(),
+        }
+    }
+
+    fn __str__(&self) -> String
+    {
+        self.value.display_json().to_string()
+    }
+
+    fn __repr__(&self) -> String
+    {
+        format!("Json({})", self.value.display_json())
+    }
+}
+
+// Parses text strictly (with comments if allowed), or leniently as parse_lossy does, making up a value wherever the text is broken
+fn parse_text(text: &str, lenient: bool, allow_comments: bool) -> PyResult<JSON>
+{
+    match lenient {
+        true => Ok(parse_lossy(text).0),
+        false => try_parse_with(text, &Options { allow_comments, ..Options::default() }).map_err(|e| PyValueError::new_err(e.to_string())),
+    }
+}
+
+/// Parses json text into Python values. With lenient, broken text never raises, the parts that can be read are kept
+/// (ie. unreadable array elements become None and unclosed arrays end with the text)
+#[pyfunction]
+#[pyo3(signature = (text, *, lenient = false, allow_comments = false))]
+fn loads<'py>(py: Python<'py>, text: &str, lenient: bool, allow_comments: bool) -> PyResult<Bound<'py, PyAny>>
+{
+    to_python(py, &parse_text(text, lenient, allow_comments)?)
+}
+
+/// Serializes Python values (or a `Json` handle) as json text, compact or indented by 2 spaces if pretty.
+/// Raises a `TypeError` for values that have no json form, and a `ValueError` for NaN and infinite floats.
+#[pyfunction]
+#[pyo3(signature = (value, *, pretty = false))]
+fn dumps(value: &Bound<'_, PyAny>, pretty: bool) -> PyResult<String>
+{
+    Ok(Json { value: from_python(value, 0)? }.dumps(pretty))
+}
+
+/// Parses json text into a `Json` handle, see `loads`
+#[pyfunction]
+#[pyo3(signature = (text, *, lenient = false, allow_comments = false))]
+fn parse(text: &str, lenient: bool, allow_comments: bool) -> PyResult<Json>
+{
+    Ok(Json { value: parse_text(text, lenient, allow_comments)? })
+}
+
+/// Fast json parsing, serializing and querying
+#[pymodule(name = "json_parser")]
+mod json_parser {
+    #[pymodule_export]
+    use super::{dumps, loads, parse, Json};
+}
+
+// Converts a value to Python values, the members of objects in order of key
+fn to_python<'py>(py: Python<'py>, value: &JSON) -> PyResult<Bound<'py, PyAny>>
+{
+    let object = match value {
+        JSON::JsNull => py.None().into_bound(py),
+        JSON::JsBool(b) => PyBool::new(py, *b).to_owned().into_any(),
+        JSON::JsNumber(n) => number_to_python(py, n)?,
+        JSON::JsString(s) => PyString::new(py, s).into_any(),
+        JSON::JsArray(vec) => {
+            let list = PyList::empty(py);
+            for element in vec {
+                list.append(to_python(py, element)?)?;
+            }
+            list.into_any()
+        }
+        JSON::JsObject(map) => {
+            let mut members: Vec<(&String, &JSON)> = map.iter().collect();
+            members.sort_by(|a, b| a.0.cmp(b.0));
+
+            let dict = PyDict::new(py);
+            for (key, member) in members {
+                dict.set_item(key, to_python(py, member)?)?;
+            }
+            dict.into_any()
+        }
+    };
+    Ok(object)
+}
+
+// Converts a number to an int if it is an integer (of any size, as Python ints are unbounded) and otherwise to a float
+fn number_to_python<'py>(py: Python<'py>, n: &Number) -> PyResult<Bound<'py, PyAny>>
+{
+    if let Some(i) = n.as_i64() { return Ok(i.into_pyobject(py)?.into_any()); }
+    if let Some(u) = n.as_u64() { return Ok(u.into_pyobject(py)?.into_any()); }
+
+    let literal = format!("{n:?}");
+    if !literal.contains(['.', 'e', 'E']) {
+        return py.get_type::<PyInt>().call1((literal,));   //Only integer literals kept by arbitrary_precision get here
+    }
+    Ok(PyFloat::new(py, n.as_f64()).into_any())
+}
+
+// Converts Python values to a value, refusing to go deeper than the parser would so self-referencing lists end
+fn from_python(object: &Bound<'_, PyAny>, depth: usize) -> PyResult<JSON>
+{
+    if depth > MAX_DEPTH {
+        return Err(PyValueError::new_err("arrays and objects are nested too deeply"));
+    }
+
+    if object.is_none() { return Ok(JSON::JsNull); }
+    if let Ok(handle) = object.cast::<Json>() { return Ok(handle.get().value.clone()); }
+
+    //bool is a subclass of int, so it is checked first
+    if let Ok(b) = object.cast::<PyBool>() { return Ok(JSON::JsBool(b.is_true())); }
+    if object.is_instance_of::<PyInt>() {
+        if let Ok(i) = object.extract::<i64>() { return Ok(JSON::JsNumber(Number::from(i))); }
+        if let Ok(u) = object.extract::<u64>() { return Ok(JSON::JsNumber(Number::from(u))); }
+
+        let literal = object.str()?.to_string();
+        return Number::from_literal(&literal).map(JSON::JsNumber).ok_or_else(|| PyValueError::new_err(format!("int {literal} is out of range")));
+    }
+    if let Ok(f) = object.cast::<PyFloat>() {
+        let f = f.value();
+        return Number::from_f64(f).map(JSON::JsNumber).ok_or_else(|| PyValueError::new_err(format!("float {f} is not allowed in json")));
+    }
+    if let Ok(s) = object.cast::<PyString>() { return Ok(JSON::JsString(s.to_str()?.to_string())); }
+
+    if let Ok(list) = object.cast::<PyList>() {
+        return list.iter().map(|element| from_python(&element, depth + 1)).collect::<PyResult<Vec<JSON>>>().map(JSON::JsArray);
+    }
+    if let Ok(tuple) = object.cast::<PyTuple>() {
+        return tuple.iter().map(|element| from_python(&element, depth + 1)).collect::<PyResult<Vec<JSON>>>().map(JSON::JsArray);
+    }
+    if let Ok(dict) = object.cast::<PyDict>() {
+        let mut map = HashMap::new();
+        for (key, member) in dict.iter() {
+            let Ok(key) = key.cast::<PyString>() else {
+                return Err(PyTypeError::new_err(format!("keys must be str, not {}", key.get_type().name()?)));
+            };
+            map.insert(key.to_str()?.to_string(), from_python(&member, depth + 1)?);
+        }
+        return Ok(JSON::JsObject(map));
+    }
+
+    Err(PyTypeError::new_err(format!("object of type {} is not json serializable", object.get_type().name()?)))
+}
+
+
+/* (TESTS) */
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::json;
+
+    #[test]
+    fn test_python_round_trip()
+    {
+        Python::initialize();
+        Python::attach(|py| {
+            let value = json(r#"{ "name" : "Ada", "tags" : ["x", 1, 2.5, -3, true, null], "nested" : { "empty" : [] } }"#);
+            let object = to_python(py, &value).unwrap();
+
+            assert_eq!( "{'name': 'Ada', 'nested': {'empty': []}, 'tags': ['x', 1, 2.5, -3, True, None]}", object.repr().unwrap().to_string() );
+            assert_eq!( value, from_python(&object, 0).unwrap() );
+
+            let tuple = (1, "a", (false,)).into_pyobject(py).unwrap();
+            assert_eq!( json(r#"[1, "a", [false]]"#), from_python(tuple.as_any(), 0).unwrap() );
+        });
+    }
+
+    #[test]
+    fn test_python_errors()
+    {
+        Python::initialize();
+        Python::attach(|py| {
+            let error = |object: &Bound<'_, PyAny>| from_python(object, 0).unwrap_err().to_string();
+
+            assert_eq!( "TypeError: keys must be str, not int", error(PyDict::from_sequence(&[(1, 2)].into_pyobject(py).unwrap()).unwrap().as_any()) );
+            assert_eq!( "ValueError: float NaN is not allowed in json", error(PyFloat::new(py, f64::NAN).as_any()) );
+            assert_eq!( "TypeError: object of type set is not json serializable", error(&py.eval(c"{1}", None, None).unwrap()) );
+
+            let cycle = PyList::empty(py);
+            cycle.append(&cycle).unwrap();
+            assert_eq!( "ValueError: arrays and objects are nested too deeply", error(cycle.as_any()) );
+
+            assert!( parse_text("[1, 2", false, false).is_err() );
+            assert_eq!( json("[1, 2]"), parse_text("[1, 2", true, false).unwrap() );
+            assert_eq!( json("[1]"), parse_text("[1 /* one */]", false, true).unwrap() );
+        });
+    }
+}
@@ -0,0 +1,253 @@
+//! Converting URL query strings (ie. "a=1&b[0]=x&c.d=true") to nested values and back
+//!
+//! The key of each parameter is a path, with object keys separated by dots or written in brackets (ie. "c.d" or "c[d]")
+//! and array indexes written in brackets (ie. "b[0]"). An empty index (ie. "b[]") appends to the array.
+
+/* (IMPORTS) */
+use std::collections::HashMap;
+use std::fmt::Write as _;
+
+use crate::{try_parse, JsonPath, PathError, PathSegment, JSON};
+
+
+impl JSON {
+
+    /// Builds a value from a URL query string (with or without its leading '?'), setting the path of each parameter's key to its value.
+    /// Parameters are percent-decoded ('+' is a space) before their keys are split into paths. Values that are the literals true, false & null
+    /// or numbers (as json writes them, so "007" is a string) are kept as such, any other value is a string. A key given more than once without an index
+    /// (ie. "tag=a&tag=b") collects its values into an array, and parameters with an empty key are skipped.
+    ///
+    /// # Examples
+    /// ```
+    /// use Parser::{try_parse, JSON};
+    ///
+    /// let value = JSON::from_query_string("a=1&b[0]=x&b[]=y%20z&c.d=true").unwrap();
+    /// assert_eq!( try_parse(r#"{ "a" : 1, "b" : ["x", "y z"], "c" : { "d" : true } }"#).unwrap(), value );
+    /// ```
+    ///
+    /// # Errors
+    /// Returns a `PathError` if a key is not a valid path (ie. "a[0"), if two keys need the same value to be different kinds
+    /// of container (ie. "a[0]" and "a.b"), or if the indexes of an array have gaps (ie. "a[0]" and "a[2]" without "a[1]")
+    pub fn from_query_string(query: &str) -> Result<JSON, PathError>
+    {
+        let query = query.strip_prefix('?').unwrap_or(query);
+        let mut root: Option<JSON> = None;
+
+        for parameter in query.split('&').filter(|parameter| !parameter.is_empty()) {
+            let (key, value) = parameter.split_once('=').unwrap_or((parameter, ""));
+            let key = decode(key);
+            if key.is_empty() { continue; }
+
+            let segments = key_segments(&key)?;
+
+            //The root is an array if the first key starts with an index, otherwise an object
+            let root = root.get_or_insert_with(|| match segments.first() {
+                Some(Some(PathSegment::Index(_)) | None) => JSON::JsArray(vec![]),
+                _ => JSON::JsObject(HashMap::new()),
+            });
+            set_parameter(root, &segments, infer_scalar(&decode(value)))?;
+        }
+
+        Ok(root.unwrap_or_else(|| JSON::JsObject(HashMap::new())))
+    }
+
+    /// Returns the URL query string (without a leading '?') of the leaf values of an array or object, the reverse of `from_query_string`.
+    /// Object keys are dotted (or bracketed if they contain a dot or bracket) and written in order, array indexes are bracketed,
+    /// and keys & values are percent-encoded.
+    ///
+    /// Empty arrays & objects have no parameters so they are left out, as is everything of a value that is not an array or object.
+    /// Strings that look like numbers or literals (ie. "1") and object keys that are empty or contain ']' are not preserved.
+    pub fn to_query_string(&self) -> String
+    {
+        let mut parameters = vec![];
+        append_parameters(self, "", &mut parameters);
+        parameters.join("&")
+    }
+}
+
+// Splits the key of a parameter into the segments of its path, an empty index (which appends to its array) is None
+fn key_segments(key: &str) -> Result<Vec<Option<PathSegment>>, PathError>
+{
+    let bytes = key.as_bytes();
+    let mut segments = vec![];
+    let mut pos = 0;
+    let error = |pos: usize, message: &str| Err(PathError::Syntax(pos, message.to_string()));
+
+    while pos < bytes.len() {
+        if bytes[pos] == b'[' {
+            let Some(len) = key[pos + 1..].find(']') else { return error(pos, "expected ']'"); };
+            let inner = &key[pos + 1..pos + 1 + len];
+
+            segments.push(match inner.parse::<usize>() {
+                _ if inner.is_empty() => None,
+                Ok(index) if inner.bytes().all(|b| b.is_ascii_digit()) => Some(PathSegment::Index(index)),
+                _ => Some(PathSegment::Key(inner.to_string())),
+            });
+            pos += len + 2;
+        } else {
+            //After the first segment, dotted keys must be preceded by a dot
+            if !segments.is_empty() {
+                if bytes[pos] != b'.' { return error(pos, "expected '.' or '['"); }
+                pos += 1;
+            }
+
+            let len = bytes[pos..].iter().take_while(|b| !matches!(b, b'.' | b'[' | b']')).count();
+            if len == 0 { return error(pos, "expected a key"); }
+
+            segments.push(Some(PathSegment::Key(key[pos..pos + len].to_string())));
+            pos += len;
+        }
+    }
+
+    Ok(segments)
+}
+
+// Sets the value of a parameter at the path of its key, resolving empty indexes to the end of their arrays
+fn set_parameter(root: &mut JSON, segments: &[Option<PathSegment>], value: JSON) -> Result<(), PathError>
+{
+    let mut path = JsonPath::root();
+    for segment in segments {
+        let segment = segment.clone().unwrap_or_else(|| match root.get_json_path(&path) {
+            Some(JSON::JsArray(vec)) => PathSegment::Index(vec.len()),
+            _ => PathSegment::Index(0),
+        });
+        path.push(segment);
+    }
+
+    //A key repeated without an index collects its values into an array
+    if let Some(PathSegment::Key(_)) = path.segments().last() {
+        match root.get_json_path(&path) {
+            Some(JSON::JsArray(vec)) => path.push(PathSegment::Index(vec.len())),
+            Some(previous @ (JSON::JsNull | JSON::JsBool(_) | JSON::JsNumber(_) | JSON::JsString(_))) => {
+                let values = JSON::JsArray(vec![previous.clone(), value]);
+                return root.set_json_path(&path, values).map(|_| ());
+            }
+            _ => {}
+        }
+    }
+
+    root.set_json_path(&path, value).map(|_| ())
+}
+
+// Converts a text value (ie. of a query parameter), the literals true, false & null and numbers written as json writes them
+// are kept as they are and anything else is a string (so "007" or "1e3" stays the way it was written)
+pub(crate) fn infer_scalar(text: &str) -> JSON
+{
+    match try_parse(text) {
+        Ok(value @ (JSON::JsNull | JSON::JsBool(_))) if value.display_json().to_string() == text => value,
+        Ok(value @ JSON::JsNumber(_)) if value.display_json().to_string() == text && !has_leading_zero(text) => value,
+        _ => JSON::JsString(text.to_string()),
+    }
+}
+
+// Tells whether the integer part of a number has a leading zero (ie. "007"), which json does not allow
+fn has_leading_zero(number: &str) -> bool
+{
+    let digits = number.trim_start_matches('-').as_bytes();
+    digits.len() > 1 && digits[0] == b'0' && digits[1].is_ascii_digit()
+}
+
+// Appends a parameter for every leaf value below a value with the given key, the members of objects in order of key
+fn append_parameters(value: &JSON, key: &str, parameters: &mut Vec<String>)
+{
+    match value {
+        JSON::JsArray(vec) => {
+            for (i, element) in vec.iter().enumerate() {
+                append_parameters(element, &format!("{key}[{i}]"), parameters);
+            }
+        }
+        JSON::JsObject(map) => {
+            let mut members: Vec<(&String, &JSON)> = map.iter().collect();
+            members.sort_by(|a, b| a.0.cmp(b.0));
+
+            for (name, member) in members {
+                let dotted = !name.is_empty() && !name.contains(['.', '[', ']']);
+                let member_key = match dotted {
+                    true if key.is_empty() => name.to_string(),
+                    true => format!("{key}.{name}"),
+                    false => format!("{key}[{name}]"),
+                };
+                append_parameters(member, &member_key, parameters);
+            }
+        }
+        _ if key.is_empty() => {}
+        JSON::JsString(s) => parameters.push(format!("{}={}", encode(key), encode(s))),
+        scalar => parameters.push(format!("{}={}", encode(key), scalar.display_json())),
+    }
+}
+
+// Percent-decodes a key or value of a query string, '+' is a space and a '%' that is not followed by 2 hex digits is kept
+fn decode(text: &str) -> String
+{
+    let bytes = text.as_bytes();
+    let hex = |i: usize| bytes.get(i).and_then(|b| char::from(*b).to_digit(16));
+
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match (bytes[i], hex(i + 1), hex(i + 2)) {
+            (b'%', Some(high), Some(low)) => { decoded.push((high << 4 | low) as u8); i += 3; }
+            (b'+', _, _) => { decoded.push(b' '); i += 1; }
+            (b, _, _) => { decoded.push(b); i += 1; }
+        }
+    }
+
+    String::from_utf8_lossy(&decoded).into_owned() //Escapes of bytes that are not utf-8 become U+FFFD
+}
+
+// Percent-encodes a key or value of a query string, every byte but the unreserved characters of RFC 3986 is escaped
+fn encode(text: &str) -> String
+{
+    let mut encoded = String::with_capacity(text.len());
+    for b in text.bytes() {
+        match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => encoded.push(char::from(b)),
+            _ => { let _ = write!(encoded, "%{b:02X}"); }
+        }
+    }
+    encoded
+}
+
+
+/* (TESTS) */
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::json;
+
+    #[test]
+    fn test_from_query_string()
+    {
+        let query = "?a=1&b[0]=x&b[1]=&c.d=true&c[e][]=null&c[e][]=1.5&f=007&g=%E2%9C%93+ok&h&&=skipped";
+        let expected = json(r#"{ "a" : 1, "b" : ["x", ""], "c" : { "d" : true, "e" : [null, 1.5] }, "f" : "007", "g" : "✓ ok", "h" : "" }"#);
+        assert_eq!( Ok(expected), JSON::from_query_string(query) );
+
+        //Repeated keys collect their values, and indexes can be written encoded
+        assert_eq!( Ok(json(r#"{ "tag" : ["a", "b", "c"] }"#)), JSON::from_query_string("tag=a&tag=b&tag=c") );
+        assert_eq!( Ok(json(r#"{ "b" : ["x"] }"#)), JSON::from_query_string("b%5B0%5D=x") );
+        assert_eq!( Ok(json(r#"[{ "a" : 1 }, 2]"#)), JSON::from_query_string("[0].a=1&[]=2") );
+        assert_eq!( Ok(json("{}")), JSON::from_query_string("") );
+    }
+
+    #[test]
+    fn test_query_string_errors()
+    {
+        assert_eq!( Err(PathError::Syntax(1, "expected ']'".to_string())), JSON::from_query_string("a[0=1") );
+        assert_eq!( Err(PathError::Syntax(2, "expected a key".to_string())), JSON::from_query_string("a..b=1") );
+        assert!( matches!(JSON::from_query_string("a[1]=x"), Err(PathError::IndexOutOfBounds(_))) );
+        assert!( matches!(JSON::from_query_string("a[0]=x&a.b=y"), Err(PathError::NotAContainer(_))) );
+    }
+
+    #[test]
+    fn test_to_query_string()
+    {
+        let value = json(r#"{ "a" : 1, "b" : ["x", "y z"], "c" : { "d" : true, "e.f" : null, "7" : "&" }, "g" : [], "h" : {} }"#);
+        assert_eq!( "a=1&b%5B0%5D=x&b%5B1%5D=y%20z&c.7=%26&c.d=true&c%5Be.f%5D=null", value.to_query_string() );
+
+        let value = json(r#"{ "a" : [{ "b" : -2 }, [false]], "c" : { "d e" : 1.5, "f" : "007" } }"#);
+        assert_eq!( Ok(value.clone()), JSON::from_query_string(&value.to_query_string()) );
+
+        assert_eq!( "%5B0%5D=1&%5B1%5D.a=2", json(r#"[1, { "a" : 2 }]"#).to_query_string() );
+        assert_eq!( "", json("3").to_query_string() );
+    }
+}
@@ -0,0 +1,491 @@
+//! Parsing from readers and files a buffer at a time, without reading the whole text into memory first
+//!
+//! `reader_tokens` yields the tokens of a document as they are read, so even a multi-GB file can be processed
+//! as a stream of events in constant memory. `parse_reader` and `parse_file` build a tree from the same tokens,
+//! which only needs memory for the tree itself.
+//!
+//! With the `gzip` and `zstd` features `decompress` wraps a reader of compressed input (ie. a `.json.gz` or `.json.zst` export) in a decoder,
+//! and `parse_file` decompresses files itself.
+
+/* (IMPORTS) */
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader};
+use std::path::Path;
+
+use crate::lexer::{match_digit_chars, Lexeme, LexemeKind};
+use crate::tokens::Structure;
+use crate::{Options, ParseError, ParseErrorKind, Token, JSON};
+
+
+/// Error returned when parsing from a reader
+#[derive(Debug)]
+pub enum ReadError {
+    /// Reading the input failed
+    Io(io::Error),
+    /// The input is not valid json
+    Parse(ParseError),
+}
+
+impl fmt::Display for ReadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+    {
+        match self {
+            ReadError::Io(e) => write!(f, "read failed: {e}"),
+            ReadError::Parse(e) => e.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for ReadError {}
+
+impl From<io::Error> for ReadError {
+    fn from(e: io::Error) -> ReadError
+    {
+        ReadError::Io(e)
+    }
+}
+
+impl From<ParseError> for ReadError {
+    fn from(e: ParseError) -> ReadError
+    {
+        ReadError::Parse(e)
+    }
+}
+
+// Position in the input, tracked as bytes are read since the text before it is gone
+#[derive(Debug, Clone, Copy)]
+struct Position {
+    offset: usize,
+    line: usize,
+    column: usize,
+}
+
+/// Iterator returned by `reader_tokens`
+///
+/// After the first error no more items are returned.
+#[derive(Debug)]
+pub struct ReaderTokens<R> {
+    reader: R,
+    comments: bool,
+    position: Position,
+    text: Vec<u8>,          //Text of the lexeme being read
+    structure: Structure,
+    failed: bool,
+}
+
+/// Returns an iterator over the tokens of a document read from a buffered reader, see `tokens`
+///
+/// Comments are skipped if the options allow them. There is no depth limit, as nesting is tracked without recursion.
+pub fn reader_tokens<R: BufRead>(reader: R, options: &Options) -> ReaderTokens<R>
+{
+    ReaderTokens {
+        reader,
+        comments: options.allow_comments,
+        position: Position { offset: 0, line: 1, column: 1 },
+        text: vec![],
+        structure: Structure::new(),
+        failed: false,
+    }
+}
+
+impl<R: BufRead> ReaderTokens<R> {
+
+    // Returns the next byte without consuming it, or None at the end of the input
+    fn peek(&mut self) -> io::Result<Option<u8>>
+    {
+        loop {
+            match self.reader.fill_buf() {
+                Ok(buf) => return Ok(buf.first().copied()),
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    // Consumes the next byte, which has been peeked
+    fn bump(&mut self, b: u8)
+    {
+        self.reader.consume(1);
+        self.position.offset += 1;
+        if b == b'\n' {
+            self.position.line += 1;
+            self.position.column = 1;
+        } else if b & 0xc0 != 0x80 {
+            self.position.column += 1;         //Continuation bytes are part of the same character
+        }
+    }
+
+    // Consumes the next byte into the text of the lexeme, returns None at the end of the input
+    fn take(&mut self) -> io::Result<Option<u8>>
+    {
+        let b = self.peek()?;
+        if let Some(b) = b {
+            self.bump(b);
+            self.text.push(b);
+        }
+        Ok(b)
+    }
+
+    fn error(&self, kind: ParseErrorKind, at: Position) -> ReadError
+    {
+        ReadError::Parse(ParseError::at(kind, at.offset, at.line, at.column))
+    }
+
+    // Skips whitespace and (if allowed) comments
+    fn skip_trivia(&mut self) -> Result<(), ReadError>
+    {
+        while let Some(b) = self.peek()? {
+            match b {
+                b' ' | b'\t' | b'\n' | b'\r' => self.bump(b),
+                b'/' if self.comments => {
+                    let start = self.position;
+                    self.bump(b);
+                    match self.peek()? {
+                        Some(b'/') => while let Some(b) = self.peek()? {
+                            if b == b'\n' || b == b'\r' { break; }
+                            self.bump(b);
+                        },
+                        Some(b'*') => {
+                            self.bump(b'*');
+                            let mut star = false;
+                            loop {
+                                let Some(b) = self.peek()? else { return Err(self.error(ParseErrorKind::Syntax, start)); };
+                                self.bump(b);
+                                if star && b == b'/' { break; }
+                                star = b == b'*';
+                            }
+                        }
+                        _ => return Err(self.error(ParseErrorKind::Syntax, start)),
+                    }
+                }
+                _ => break,
+            }
+        }
+        Ok(())
+    }
+
+    // Reads the text of the next lexeme, returning its kind and where it starts, or None at the end of the input
+    fn read_lexeme(&mut self) -> Result<Option<(LexemeKind, Position)>, ReadError>
+    {
+        self.skip_trivia()?;
+        self.text.clear();
+        let start = self.position;
+        let Some(first) = self.take()? else { return Ok(None); };
+
+        let kind = match first {
+            b'[' => LexemeKind::BeginArray,
+            b']' => LexemeKind::EndArray,
+            b'{' => LexemeKind::BeginObject,
+            b'}' => LexemeKind::EndObject,
+            b':' => LexemeKind::Colon,
+            b',' => LexemeKind::Comma,
+            b'"' => {
+                //Up to the closing quote, an escaped character can not close the string
+                loop {
+                    match self.take()? {
+                        None => return Err(self.error(ParseErrorKind::Syntax, start)),
+                        Some(b'\\') => { if self.take()?.is_none() { return Err(self.error(ParseErrorKind::Syntax, start)); } }
+                        Some(b'"') => break,
+                        Some(_) => {}
+                    }
+                }
+                LexemeKind::String
+            }
+            b'-' | b'0'..=b'9' => {
+                //Every character that can be part of a number, the literal is checked once it is complete
+                while let Some(b'0'..=b'9' | b'-' | b'+' | b'.' | b'e' | b'E') = self.peek()? {
+                    self.take()?;
+                }
+                LexemeKind::Number
+            }
+            b'a'..=b'z' => {
+                while let Some(b'a'..=b'z') = self.peek()? {
+                    self.take()?;
+                }
+                match &self.text[..] {
+                    b"null" => LexemeKind::Null,
+                    b"true" => LexemeKind::True,
+                    b"false" => LexemeKind::False,
+                    _ => return Err(self.error(ParseErrorKind::Syntax, start)),
+                }
+            }
+            _ => return Err(self.error(ParseErrorKind::Syntax, start)),
+        };
+
+        Ok(Some((kind, start)))
+    }
+
+    // Reads the next token along with where it starts
+    fn next_token(&mut self) -> Result<Option<(Token, Position)>, ReadError>
+    {
+        loop {
+            let Some((kind, start)) = self.read_lexeme()? else {
+                if self.structure.is_done() { return Ok(None); }
+                return Err(self.error(ParseErrorKind::Syntax, self.position));
+            };
+
+            //Strings and numbers are checked when they are made into tokens, as for a string input
+            let text = std::str::from_utf8(&self.text).map_err(|_| self.error(ParseErrorKind::Syntax, start))?;
+            if kind == LexemeKind::Number && match_digit_chars(text) != Ok(("", text)) {
+                return Err(self.error(ParseErrorKind::Syntax, start));
+            }
+            match self.structure.feed(&Lexeme { kind, text, start: start.offset }) {
+                Ok(Some(token)) => return Ok(Some((token, start))),
+                Ok(None) => continue,
+                Err(kind) => return Err(self.error(kind, start)),
+            }
+        }
+    }
+}
+
+impl<R: BufRead> Iterator for ReaderTokens<R> {
+    type Item = Result<Token, ReadError>;
+
+    fn next(&mut self) -> Option<Result<Token, ReadError>>
+    {
+        if self.failed { return None; }
+
+        let token = self.next_token();
+        self.failed = token.is_err();
+        token.map(|token| token.map(|(token, _)| token)).transpose()
+    }
+}
+
+
+/* (TREE BUILDING) */
+
+/// Parses a document containing a single json value from a buffered reader, see `try_parse_with`
+///
+/// The text is read a buffer at a time and only the tree is kept in memory.
+///
+/// # Errors
+/// Returns a `ReadError` if reading fails, or if the input is not valid json or is nested deeper than the options allow
+pub fn parse_reader<R: BufRead>(reader: R, options: &Options) -> Result<JSON, ReadError>
+{
+    let mut tokens = reader_tokens(reader, options);
+    let mut stack: Vec<(JSON, Option<String>)> = vec![];    //Open containers, with the key of the member being read
+
+    while let Some((token, start)) = tokens.next_token()? {
+        if let Some(kind) = options.interrupted() { return Err(tokens.error(kind, start)); }
+
+        let value = match token {
+            Token::Key(key) => {
+                if let Some((_, pending)) = stack.last_mut() { *pending = Some(key); }
+                continue;
+            }
+            Token::BeginArray | Token::BeginObject if stack.len() >= options.max_depth => {
+                return Err(tokens.error(ParseErrorKind::TooDeep, start));
+            }
+            Token::BeginArray => { stack.push((JSON::JsArray(vec![]), None)); continue; }
+            Token::BeginObject => { stack.push((JSON::JsObject(Default::default()), None)); continue; }
+            Token::EndArray | Token::EndObject => stack.pop().map(|(container, _)| container).expect("ends match a begin"),
+            Token::Null => JSON::JsNull,
+            Token::Bool(b) => JSON::JsBool(b),
+            Token::Number(n) => JSON::JsNumber(n),
+            Token::String(s) => JSON::JsString(s),
+        };
+
+        match stack.last_mut() {
+            Some((JSON::JsArray(vec), _)) => vec.push(value),
+            Some((JSON::JsObject(map), key)) => { map.insert(key.take().expect("values in objects follow a key"), value); }
+            _ => {
+                //The whole value has been read, but anything after it is still an error
+                tokens.next_token()?;
+                return Ok(value);
+            }
+        }
+    }
+
+    unreachable!("the tokens only end after a whole value")
+}
+
+/// Parses a file containing a single json value, reading it a buffer at a time (see `parse_reader`).
+/// Files compressed with gzip or zstd are decompressed as they are read if the `gzip` or `zstd` feature is enabled.
+///
+/// # Errors
+/// Returns a `ReadError` if the file can not be read (or decompressed), or if it is not valid json or is nested deeper than the options allow
+pub fn parse_file(path: impl AsRef<Path>, options: &Options) -> Result<JSON, ReadError>
+{
+    #[cfg(any(feature = "gzip", feature = "zstd"))]
+    return parse_reader(decompress(BufReader::new(File::open(path)?))?, options);
+
+    #[cfg(not(any(feature = "gzip", feature = "zstd")))]
+    return parse_reader(BufReader::new(File::open(path)?), options);
+}
+
+
+/* (DECOMPRESSION) */
+
+// The first bytes of every gzip member and zstd frame, neither of which can start a json document
+#[cfg(feature = "gzip")]
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+#[cfg(feature = "zstd")]
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xb5, 0x2f, 0xfd];
+
+/// Returns a reader of the decompressed input if the input starts as a gzip or zstd stream does, and of the input as it is otherwise,
+/// so compressed and plain input can be passed to `parse_reader`, `reader_tokens` or `ndjson` alike.
+/// Concatenated gzip members (as `cat a.gz b.gz` makes) are read as one stream.
+///
+/// Only the formats of the enabled features (`gzip` and `zstd`) are recognised. The start of the input is looked at without reading past
+/// the buffer of the reader, so a reader that is empty or buffers fewer bytes than the magic number of a format is taken as it is.
+///
+/// # Examples
+/// ```
+/// # #[cfg(feature = "gzip")] {
+/// use std::io::Write;
+/// use flate2::{write::GzEncoder, Compression};
+/// use Parser::{decompress, parse_reader, try_parse, Options};
+///
+/// let mut encoder = GzEncoder::new(vec![], Compression::default());
+/// encoder.write_all(br#"{ "rows" : [1, 2, 3] }"#).unwrap();
+/// let compressed = encoder.finish().unwrap();
+///
+/// let value = parse_reader(decompress(compressed.as_slice()).unwrap(), &Options::default()).unwrap();
+/// assert_eq!( try_parse(r#"{ "rows" : [1, 2, 3] }"#).unwrap(), value );
+/// # }
+/// ```
+///
+/// # Errors
+/// Returns an `io::Error` if reading the start of the input fails
+#[cfg(any(feature = "gzip", feature = "zstd"))]
+pub fn decompress<'a, R: BufRead + 'a>(mut reader: R) -> io::Result<Box<dyn BufRead + 'a>>
+{
+    let start = reader.fill_buf()?;
+
+    #[cfg(feature = "gzip")]
+    if start.starts_with(&GZIP_MAGIC) {
+        return Ok(Box::new(BufReader::new(flate2::bufread::MultiGzDecoder::new(reader))));
+    }
+    #[cfg(feature = "zstd")]
+    if start.starts_with(&ZSTD_MAGIC) {
+        return Ok(Box::new(BufReader::new(zstd::stream::read::Decoder::with_buffer(reader)?)));
+    }
+
+    Ok(Box::new(reader))
+}
+
+
+/* (TESTS) */
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{tokens, try_parse};
+
+    // Reader that returns at most one byte per read, so every lexeme straddles buffer boundaries
+    struct Trickle<'a>(&'a [u8]);
+
+    impl<'a> io::Read for Trickle<'a> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize>
+        {
+            let Some((first, rest)) = self.0.split_first() else { return Ok(0); };
+            buf[0] = *first;
+            self.0 = rest;
+            Ok(1)
+        }
+    }
+
+    fn parse(input: &str) -> Result<JSON, ReadError>
+    {
+        parse_reader(BufReader::with_capacity(1, Trickle(input.as_bytes())), &Options::default())
+    }
+
+    fn error(input: &str) -> (ParseErrorKind, usize, usize, usize)
+    {
+        match parse(input) {
+            Err(ReadError::Parse(e)) => (e.kind(), e.offset(), e.line(), e.column()),
+            other => panic!("expected a parse error, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_reader_tokens()
+    {
+        let input = r#" { "a" : [1, null, -2.5e3], "b\"c" : {}, "d" : "é\n\u0041" } "#;
+        let read: Vec<Token> = reader_tokens(input.as_bytes(), &Options::default()).map(|t| t.unwrap()).collect();
+        let expected: Vec<Token> = tokens(input).map(|t| t.unwrap()).collect();
+
+        assert_eq!( expected, read );
+    }
+
+    #[test]
+    fn test_parse_reader()
+    {
+        let input = "{ \"a\" : [1, true, \"x\"], \"é\" : { \"b\" : null } }";
+        assert_eq!( try_parse(input).unwrap(), parse(input).unwrap() );
+        assert_eq!( JSON::JsBool(false), parse(" false ").unwrap() );
+
+        let options = Options { allow_comments: true, ..Options::default() };
+        let commented = "// head\n[1, /* two */ 2]";
+        assert_eq!( try_parse("[1, 2]").unwrap(), parse_reader(commented.as_bytes(), &options).unwrap() );
+    }
+
+    #[test]
+    fn test_errors()
+    {
+        assert_eq!( (ParseErrorKind::Syntax, 7, 2, 4), error("[1,\n 2,]") );
+        assert_eq!( (ParseErrorKind::Syntax, 1, 1, 2), error("[\"é") );
+        assert_eq!( (ParseErrorKind::Syntax, 1, 1, 2), error("[nul]") );
+        assert_eq!( (ParseErrorKind::Syntax, 1, 1, 2), error("[1e]") );
+        assert_eq!( (ParseErrorKind::Syntax, 0, 1, 1), error("\"\\q\"") );
+        assert_eq!( (ParseErrorKind::Syntax, 3, 1, 4), error("[1 ") );
+        assert_eq!( (ParseErrorKind::TrailingCharacters, 4, 1, 5), error("[1] 2") );
+        assert_eq!( (ParseErrorKind::Syntax, 0, 1, 1), error("// x\n1") );
+
+        let deep = "[".repeat(200) + &"]".repeat(200);
+        assert_eq!( ParseErrorKind::TooDeep, error(&deep).0 );
+        assert!( parse_reader(deep.as_bytes(), &Options { max_depth: 200, ..Options::default() }).is_ok() );
+    }
+
+    #[test]
+    fn test_parse_file()
+    {
+        let path = std::env::temp_dir().join(format!("parse_file_{}.json", std::process::id()));
+        std::fs::write(&path, "[1, 2, 3]\n").unwrap();
+
+        assert_eq!( try_parse("[1, 2, 3]").unwrap(), parse_file(&path, &Options::default()).unwrap() );
+        std::fs::remove_file(&path).unwrap();
+
+        assert!( matches!(parse_file(&path, &Options::default()), Err(ReadError::Io(_))) );
+    }
+
+    #[cfg(feature = "gzip")]
+    #[test]
+    fn test_gzip()
+    {
+        use flate2::{write::GzEncoder, Compression};
+        use std::io::Write;
+
+        let gzip = |text: &str| {
+            let mut encoder = GzEncoder::new(vec![], Compression::default());
+            encoder.write_all(text.as_bytes()).unwrap();
+            encoder.finish().unwrap()
+        };
+
+        let mut members = gzip("{\"a\" : 1}\n");
+        members.extend(gzip("{\"a\" : 2}\n"));
+        let values: Vec<JSON> = crate::ndjson(decompress(members.as_slice()).unwrap()).map(Result::unwrap).collect();
+        assert_eq!( vec![try_parse(r#"{ "a" : 1 }"#).unwrap(), try_parse(r#"{ "a" : 2 }"#).unwrap()], values );
+
+        let path = std::env::temp_dir().join(format!("parse_file_{}.json.gz", std::process::id()));
+        std::fs::write(&path, gzip("[1, 2, 3]")).unwrap();
+        assert_eq!( try_parse("[1, 2, 3]").unwrap(), parse_file(&path, &Options::default()).unwrap() );
+        std::fs::remove_file(&path).unwrap();
+
+        //A truncated stream fails as reading does, and plain input is read as it is
+        let truncated = &gzip("[1, 2, 3]")[..12];
+        assert!( matches!(parse_reader(decompress(truncated).unwrap(), &Options::default()), Err(ReadError::Io(_))) );
+        assert_eq!( try_parse("[]").unwrap(), parse_reader(decompress("[]".as_bytes()).unwrap(), &Options::default()).unwrap() );
+    }
+
+    #[cfg(feature = "zstd")]
+    #[test]
+    fn test_zstd()
+    {
+        let compressed = zstd::encode_all(r#"{ "rows" : [true, null] }"#.as_bytes(), 3).unwrap();
+        let value = parse_reader(decompress(compressed.as_slice()).unwrap(), &Options::default()).unwrap();
+
+        assert_eq!( try_parse(r#"{ "rows" : [true, null] }"#).unwrap(), value );
+        assert!( matches!(parse_reader(decompress(&compressed[..8]).unwrap(), &Options::default()), Err(ReadError::Io(_))) );
+    }
+}
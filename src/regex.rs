@@ -0,0 +1,571 @@
+//! Small regular expression engine, matching in time linear in the input (there is no backtracking, so no pattern is catastrophic)
+//!
+//! Supports the subset of ECMA-262 syntax that json schemas use in practice:
+//! literals, `.`, character classes (`[a-z]`, `[^0-9]`, `\d`, `\w`, `\s` and their negations),
+//! anchors (`^`, `$`, `\b`), groups (`(...)`, `(?:...)`), alternation (`|`)
+//! and quantifiers (`*`, `+`, `?`, `{n}`, `{n,}`, `{n,m}`, lazy variants are accepted).
+//!
+//! Patterns are compiled to a program run as a Pike VM, which follows every way the pattern can match at once.
+
+/* (IMPORTS) */
+use std::fmt;
+
+
+// A single regex element
+#[derive(Debug, Clone)]
+enum Node {
+    Char(char),
+    Any,                                                // . (anything except a newline)
+    Class { ranges: Vec<(char, char)>, negated: bool }, // [...] (inclusive ranges)
+    Start,                                              // ^
+    End,                                                // $
+    WordBoundary(bool),                                 // \b (true) and \B (false)
+    Group(Vec<Vec<Node>>),                              // Alternatives, each a sequence of nodes
+    Repeat { node: Box<Node>, min: usize, max: Option<usize> },
+}
+
+// An instruction of a compiled regular expression
+#[derive(Debug, Clone)]
+enum Inst {
+    Char(char),
+    Any,
+    Class { ranges: Vec<(char, char)>, negated: bool },
+    Start,
+    End,
+    WordBoundary(bool),
+    Split(usize, usize),                                // Continue at both, preferring the first
+    Jump(usize),
+    Match,
+}
+
+// A compiled regular expression
+#[derive(Debug, Clone)]
+pub(crate) struct Regex {
+    program: Vec<Inst>,
+}
+
+/// Error returned when a regular expression is malformed or uses unsupported syntax
+#[derive(Debug, Clone, PartialEq)]
+pub struct RegexError {
+    message: String,
+}
+
+impl fmt::Display for RegexError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+    {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for RegexError {}
+
+impl From<String> for RegexError {
+    fn from(message: String) -> RegexError
+    {
+        RegexError { message }
+    }
+}
+
+// The threads of a step of a match, along with the step each instruction was last added in and room for following jumps
+struct Threads {
+    pcs: Vec<usize>,
+    seen: Vec<usize>,
+    stack: Vec<usize>,
+}
+
+// Predefined character classes (ie. \d, \w & \s)
+const DIGIT: &[(char, char)] = &[('0', '9')];
+const WORD: &[(char, char)] = &[('0', '9'), ('A', 'Z'), ('_', '_'), ('a', 'z')];
+const SPACE: &[(char, char)] = &[('\t', '\r'), (' ', ' '), ('\u{a0}', '\u{a0}'), ('\u{feff}', '\u{feff}')];
+
+// Patterns are untrusted (ie. from a schema), so groups may only be nested so deep and programs may only be so long
+const MAX_GROUP_DEPTH: usize = 128;
+const MAX_PROGRAM_LEN: usize = 100_000;
+
+impl Regex {
+
+    // Compiles a pattern, returns the problem if the pattern is malformed, unsupported or too large
+    pub(crate) fn new(pattern: &str) -> Result<Regex, RegexError>
+    {
+        let chars: Vec<char> = pattern.chars().collect();
+        let mut pos = 0;
+
+        let alternatives = parse_alternatives(&chars, &mut pos, 0)?;
+        if pos < chars.len() {
+            return Err(format!("unmatched ')' at position {pos}").into());
+        }
+
+        let mut program = vec![];
+        compile_alternatives(&alternatives, &mut program)?;
+        program.push(Inst::Match);
+
+        Ok(Regex { program })
+    }
+
+    // Returns true if the pattern matches anywhere within the input (json schema patterns are not implicitly anchored)
+    pub(crate) fn is_match(&self, input: &str) -> bool
+    {
+        self.run(input.char_indices(), input.len(), false).is_some()
+    }
+
+    // Returns the end offset of the first match (in backtracking order) at the start of chars and their offsets, where ^ matches.
+    // Chars are only read while a match could still become longer, along with the one after to check for $ and \b
+    #[cfg(feature = "regex")]
+    pub(crate) fn match_start(&self, chars: impl Iterator<Item = (usize, char)>, len: usize) -> Option<usize>
+    {
+        self.run(chars, len, true)
+    }
+
+    // Runs the program over chars and their offsets, with a thread for every way the pattern can match so far,
+    // which all advance one char at a time so matching takes time linear in the input whatever the pattern.
+    // Threads are kept in order of preference, so anchored at the start of the input this returns the end offset of the match
+    // a backtracking matcher would find first. Otherwise a thread starts at every char and the end of any match is returned.
+    fn run(&self, mut chars: impl Iterator<Item = (usize, char)>, len: usize, anchored: bool) -> Option<usize>
+    {
+        let mut pending: Vec<usize> = vec![];
+        let mut threads = Threads { pcs: vec![], seen: vec![usize::MAX; self.program.len()], stack: vec![] };
+        let mut matched = None;
+
+        let mut prev = None;
+        let mut next = chars.next();
+        for step in 0.. {
+            let (offset, ch) = match next {
+                Some((offset, ch)) => (offset, Some(ch)),
+                None => (len, None),
+            };
+
+            //Follow the jumps and assertions of every pending thread, and of a new one if a match may start here
+            threads.pcs.clear();
+            if matched.is_none() && (!anchored || step == 0) { pending.push(0); }
+            for pc in pending.drain(..) {
+                self.add_thread(&mut threads, pc, step, prev, ch);
+            }
+
+            for &pc in &threads.pcs {
+                let consumed = match (&self.program[pc], ch) {
+                    (Inst::Match, _) => {
+                        matched = Some(offset);
+                        if !anchored { return matched; }
+                        break; //Threads after this one are less preferred
+                    }
+                    (_, None) => false,
+                    (Inst::Char(c), Some(ch)) => *c == ch,
+                    (Inst::Any, Some(ch)) => ch != '\n',
+                    (Inst::Class { ranges, negated }, Some(ch)) => ranges.iter().any(|(lo, hi)| *lo <= ch && ch <= *hi) != *negated,
+                    _ => false,
+                };
+                if consumed { pending.push(pc + 1); }
+            }
+
+            if ch.is_none() || (pending.is_empty() && (anchored || matched.is_some())) { break; }
+            prev = ch;
+            next = chars.next();
+        }
+
+        matched
+    }
+
+    // Adds a thread at pc and every thread it leads to without reading a char, in order of preference, given the chars around the position.
+    // Each instruction is only added once a step, so repetitions of patterns that match nothing (ie. `(a*)*`) end.
+    fn add_thread(&self, threads: &mut Threads, pc: usize, step: usize, prev: Option<char>, next: Option<char>)
+    {
+        let Threads { pcs, seen, stack } = threads;
+
+        stack.push(pc);
+        while let Some(pc) = stack.pop() {
+            if seen[pc] == step { continue; }
+            seen[pc] = step;
+
+            match self.program[pc] {
+                Inst::Split(first, second) => { stack.push(second); stack.push(first); }
+                Inst::Jump(to) => stack.push(to),
+                Inst::Start => if prev.is_none() { stack.push(pc + 1); },
+                Inst::End => if next.is_none() { stack.push(pc + 1); },
+                Inst::WordBoundary(expected) => if (is_word(prev) != is_word(next)) == expected { stack.push(pc + 1); },
+                _ => pcs.push(pc),
+            }
+        }
+    }
+}
+
+
+/* (PATTERN PARSING) */
+
+// Parses alternatives separated by | until the end of the pattern or a closing parenthesis
+fn parse_alternatives(chars: &[char], pos: &mut usize, depth: usize) -> Result<Vec<Vec<Node>>, String>
+{
+    let mut alternatives = vec![parse_sequence(chars, pos, depth)?];
+
+    while chars.get(*pos) == Some(&'|') {
+        *pos += 1;
+        alternatives.push(parse_sequence(chars, pos, depth)?);
+    }
+
+    Ok(alternatives)
+}
+
+// Parses a sequence of quantified atoms
+fn parse_sequence(chars: &[char], pos: &mut usize, depth: usize) -> Result<Vec<Node>, String>
+{
+    let mut seq = vec![];
+
+    while let Some(&ch) = chars.get(*pos) {
+        if ch == '|' || ch == ')' { break; }
+
+        let atom = parse_atom(chars, pos, depth)?;
+        seq.push( parse_quantifier(chars, pos, atom)? );
+    }
+
+    Ok(seq)
+}
+
+// Parses a single atom (ie. a literal, class, group, anchor or escape) within depth groups
+fn parse_atom(chars: &[char], pos: &mut usize, depth: usize) -> Result<Node, String>
+{
+    let ch = chars[*pos];
+    *pos += 1;
+
+    match ch {
+        '.' => Ok(Node::Any),
+        '^' => Ok(Node::Start),
+        '$' => Ok(Node::End),
+        '[' => parse_class(chars, pos),
+        '\\' => parse_escape(chars, pos),
+        '*' | '+' | '?' => Err(format!("nothing to repeat at position {}", *pos - 1)),
+        '(' if depth >= MAX_GROUP_DEPTH => Err(format!("groups are nested more than {MAX_GROUP_DEPTH} deep")),
+        '(' => {
+            //Groups never capture, so (?:...) is the same as (...)
+            if chars.get(*pos) == Some(&'?') {
+                if chars.get(*pos + 1) != Some(&':') {
+                    return Err(format!("unsupported group syntax at position {}", *pos - 1));
+                }
+                *pos += 2;
+            }
+
+            let alternatives = parse_alternatives(chars, pos, depth + 1)?;
+            if chars.get(*pos) != Some(&')') {
+                return Err("missing ')'".to_string());
+            }
+            *pos += 1;
+
+            Ok(Node::Group(alternatives))
+        }
+        _ => Ok(Node::Char(ch)),
+    }
+}
+
+// Parses an escape sequence after the backslash
+fn parse_escape(chars: &[char], pos: &mut usize) -> Result<Node, String>
+{
+    let ch = *chars.get(*pos).ok_or("pattern ends with a backslash")?;
+    *pos += 1;
+
+    let class = |ranges: &[(char, char)], negated| Node::Class { ranges: ranges.to_vec(), negated };
+
+    match ch {
+        'd' => Ok(class(DIGIT, false)),
+        'D' => Ok(class(DIGIT, true)),
+        'w' => Ok(class(WORD, false)),
+        'W' => Ok(class(WORD, true)),
+        's' => Ok(class(SPACE, false)),
+        'S' => Ok(class(SPACE, true)),
+        'b' => Ok(Node::WordBoundary(true)),
+        'B' => Ok(Node::WordBoundary(false)),
+        _ => escaped_char(ch).map(Node::Char),
+    }
+}
+
+// Returns the char represented by an escape that stands for a single char (ie. \n or \.)
+fn escaped_char(ch: char) -> Result<char, String>
+{
+    match ch {
+        'n' => Ok('\n'),
+        't' => Ok('\t'),
+        'r' => Ok('\r'),
+        'f' => Ok('\u{c}'),
+        'v' => Ok('\u{b}'),
+        '0' => Ok('\0'),
+        _ if ch.is_ascii_alphanumeric() => Err(format!("unsupported escape \\{ch}")),
+        _ => Ok(ch), //Escaped punctuation is always a literal
+    }
+}
+
+// Parses a bracketed character class after the opening bracket
+fn parse_class(chars: &[char], pos: &mut usize) -> Result<Node, String>
+{
+    let negated = chars.get(*pos) == Some(&'^');
+    if negated { *pos += 1; }
+
+    let mut ranges = vec![];
+    let mut first = true;
+
+    loop {
+        let ch = *chars.get(*pos).ok_or("missing ']'")?;
+        *pos += 1;
+
+        if ch == ']' && !first { break; }
+        first = false;
+
+        //Escapes within a class are either a predefined class or a single char
+        let lo = if ch == '\\' {
+            let esc = *chars.get(*pos).ok_or("pattern ends with a backslash")?;
+            *pos += 1;
+
+            match esc {
+                'd' => { ranges.extend_from_slice(DIGIT); continue; }
+                'w' => { ranges.extend_from_slice(WORD); continue; }
+                's' => { ranges.extend_from_slice(SPACE); continue; }
+                'D' | 'W' | 'S' => return Err(format!("unsupported escape \\{esc} within a character class")),
+                'b' => '\u{8}',
+                _ => escaped_char(esc)?,
+            }
+        } else {
+            ch
+        };
+
+        //A dash between two chars is a range, otherwise it is a literal dash
+        let is_range = chars.get(*pos) == Some(&'-') && chars.get(*pos + 1).is_some_and(|c| *c != ']');
+        if !is_range {
+            ranges.push((lo, lo));
+            continue;
+        }
+
+        let mut hi = chars[*pos + 1];
+        *pos += 2;
+        if hi == '\\' {
+            hi = escaped_char( *chars.get(*pos).ok_or("pattern ends with a backslash")? )?;
+            *pos += 1;
+        }
+
+        if hi < lo {
+            return Err(format!("range out of order in character class ({lo}-{hi})"));
+        }
+        ranges.push((lo, hi));
+    }
+
+    Ok(Node::Class { ranges, negated })
+}
+
+// Parses an optional quantifier that follows an atom
+fn parse_quantifier(chars: &[char], pos: &mut usize, atom: Node) -> Result<Node, String>
+{
+    let (min, max) = match chars.get(*pos) {
+        Some('*') => { *pos += 1; (0, None) }
+        Some('+') => { *pos += 1; (1, None) }
+        Some('?') => { *pos += 1; (0, Some(1)) }
+        Some('{') => match parse_braces(chars, *pos + 1) {
+            Some((min, max, end)) => { *pos = end; (min, max) }
+            None => return Ok(atom), //Not a valid quantifier, so the brace is matched literally by the next atom
+        },
+        _ => return Ok(atom),
+    };
+
+    if matches!(atom, Node::Start | Node::End | Node::WordBoundary(_)) {
+        return Err(format!("nothing to repeat at position {}", *pos - 1));
+    }
+    if max.is_some_and(|m| m < min) {
+        return Err("numbers out of order in {} quantifier".to_string());
+    }
+    if max.unwrap_or(min) > MAX_PROGRAM_LEN {
+        return Err("number too large in {} quantifier".to_string());
+    }
+
+    //Lazy quantifiers match the same strings, only the choice of match differs
+    if chars.get(*pos) == Some(&'?') { *pos += 1; }
+
+    Ok(Node::Repeat { node: Box::new(atom), min, max })
+}
+
+// Parses the inside of a {n}, {n,} or {n,m} quantifier, returning (min, max, position after the closing brace)
+fn parse_braces(chars: &[char], start: usize) -> Option<(usize, Option<usize>, usize)>
+{
+    let number = |from: usize| {
+        let n = chars[from..].iter().take_while(|c| c.is_ascii_digit()).count();
+        let value = chars[from..from + n].iter().collect::<String>().parse::<usize>().ok();
+        (value, from + n)
+    };
+
+    let (min, pos) = number(start);
+    let min = min?;
+
+    match chars.get(pos)? {
+        '}' => Some((min, Some(min), pos + 1)),
+        ',' => {
+            let (max, pos) = number(pos + 1);
+            (chars.get(pos)? == &'}').then_some((min, max, pos + 1))
+        }
+        _ => None
+    }
+}
+
+
+/* (COMPILING) */
+
+// Appends the instructions of alternatives, tried in order
+fn compile_alternatives(alternatives: &[Vec<Node>], program: &mut Vec<Inst>) -> Result<(), String>
+{
+    let mut jumps = vec![];
+
+    for (i, seq) in alternatives.iter().enumerate() {
+        let last = i + 1 == alternatives.len();
+        let split = program.len();
+        if !last { program.push(Inst::Split(split + 1, 0)); }
+
+        for node in seq {
+            compile_node(node, program)?;
+        }
+
+        //Once an alternative matches skip the others, and if it does not try the next one
+        if !last {
+            jumps.push(program.len());
+            program.push(Inst::Jump(0));
+            program[split] = Inst::Split(split + 1, program.len());
+        }
+    }
+
+    let end = program.len();
+    for jump in jumps {
+        program[jump] = Inst::Jump(end);
+    }
+    Ok(())
+}
+
+// Appends the instructions of a node, repeated nodes are written out once for every repetition
+fn compile_node(node: &Node, program: &mut Vec<Inst>) -> Result<(), String>
+{
+    if program.len() > MAX_PROGRAM_LEN {
+        return Err("pattern is too large".to_string());
+    }
+
+    match node {
+        Node::Char(c) => program.push(Inst::Char(*c)),
+        Node::Any => program.push(Inst::Any),
+        Node::Class { ranges, negated } => program.push(Inst::Class { ranges: ranges.clone(), negated: *negated }),
+        Node::Start => program.push(Inst::Start),
+        Node::End => program.push(Inst::End),
+        Node::WordBoundary(expected) => program.push(Inst::WordBoundary(*expected)),
+        Node::Group(alternatives) => compile_alternatives(alternatives, program)?,
+        Node::Repeat { node, min, max } => {
+            //A node with no instructions (ie. an empty group) is nothing however often it is repeated
+            let start = program.len();
+            compile_node(node, program)?;
+            if program.len() == start { return Ok(()); }
+            program.truncate(start);
+
+            for _ in 0..*min {
+                compile_node(node, program)?;
+            }
+
+            //Repetitions are greedy, preferring another one to what follows
+            match max {
+                None => {
+                    let split = program.len();
+                    program.push(Inst::Split(split + 1, 0));
+                    compile_node(node, program)?;
+                    program.push(Inst::Jump(split));
+                    program[split] = Inst::Split(split + 1, program.len());
+                }
+                Some(max) => {
+                    let mut splits = vec![];
+                    for _ in *min..*max {
+                        splits.push(program.len());
+                        program.push(Inst::Split(0, 0));
+                        compile_node(node, program)?;
+                    }
+
+                    let end = program.len();
+                    for split in splits {
+                        program[split] = Inst::Split(split + 1, end);
+                    }
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+// Returns true if there is a char and it is a word char
+fn is_word(ch: Option<char>) -> bool
+{
+    ch.is_some_and(|c| WORD.iter().any(|(lo, hi)| *lo <= c && c <= *hi))
+}
+
+
+/* (TESTS) */
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn is_match(pattern: &str, input: &str) -> bool
+    {
+        Regex::new(pattern).unwrap().is_match(input)
+    }
+
+    #[test]
+    fn test_literals_and_anchors()
+    {
+        assert!( is_match("abc", "xxabcxx") );                  //Patterns are not anchored
+        assert!( !is_match("^abc", "xxabc") );
+        assert!( !is_match("abc$", "abcxx") );
+        assert!( is_match("^a.c$", "abc") );
+        assert!( is_match("^a\\.c$", "a.c") );
+        assert!( !is_match("^a\\.c$", "abc") );
+        assert!( is_match("\\bcat\\b", "the cat sat") );
+        assert!( !is_match("\\bcat\\b", "concatenate") );
+    }
+
+    #[test]
+    fn test_classes()
+    {
+        assert!( is_match("^[a-z]+$", "hello") );
+        assert!( !is_match("^[a-z]+$", "Hello") );
+        assert!( is_match("^[^0-9]+$", "abc") );
+        assert!( is_match("^\\d{3}-\\d{4}$", "555-1234") );
+        assert!( is_match("^[\\w.-]+@[\\w-]+\\.\\w+$", "first.last@example.com") );
+        assert!( is_match("^\\S+\\s\\S+$", "two words") );
+        assert!( is_match("^[a-]$", "-") );                     //Trailing dash is a literal
+    }
+
+    #[test]
+    fn test_groups_and_quantifiers()
+    {
+        assert!( is_match("^(ab)+$", "ababab") );
+        assert!( !is_match("^(ab)+$", "ababa") );
+        assert!( is_match("^(?:cat|dog)s?$", "dogs") );
+        assert!( is_match("^a{2,3}$", "aaa") );
+        assert!( !is_match("^a{2,3}$", "aaaa") );
+        assert!( is_match("^a{2,}$", "aaaaa") );
+        assert!( is_match("^a{,2}$", "a{,2}") );                //Not a valid quantifier, so matched literally
+        assert!( is_match("^(a*)*b$", "aaab") );
+        assert!( is_match("^.*?end$", "the end") );
+    }
+
+    #[test]
+    fn test_invalid_patterns()
+    {
+        assert!( Regex::new("(abc").is_err() );
+        assert!( Regex::new("abc)").is_err() );
+        assert!( Regex::new("[abc").is_err() );
+        assert!( Regex::new("*abc").is_err() );
+        assert!( Regex::new("a{3,1}").is_err() );
+        assert!( Regex::new("[z-a]").is_err() );
+        assert!( Regex::new("(?<name>a)").is_err() );
+        assert!( Regex::new("\\p{L}").is_err() );
+
+        //Patterns that would take too long to compile or too deep to parse
+        assert!( Regex::new(&"(".repeat(100_000)).is_err() );
+        assert!( Regex::new("a{1000000}").is_err() );
+        assert!( Regex::new("(a{1000}){1000}").is_err() );
+        assert!( Regex::new("((){100000}){100000}").is_ok() );
+    }
+
+    #[test]
+    fn test_linear_time()
+    {
+        //Matching never backtracks, so neither long inputs nor nested repetitions take more than a step per char
+        assert!( is_match("^[a-z]+$", &"a".repeat(200_000)) );
+        assert!( !is_match("^(a*)*$", &format!("{}!", "a".repeat(30))) );
+        assert!( !is_match("^(a|aa)+$", &format!("{}!", "a".repeat(10_000))) );
+        assert!( is_match("(x+x+)+y", &format!("{}y", "x".repeat(10_000))) );
+    }
+}
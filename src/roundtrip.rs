@@ -0,0 +1,335 @@
+//! Checking that documents survive being parsed and serialized again
+//!
+//! `verify_roundtrip` parses a document, serializes it with `display_json`, parses the output again and compares
+//! the results. Along the way the original text is compared token by token with the output, so differences that do not
+//! change the value (ie. reordered keys or different escapes) are reported as well as ones that do.
+
+/* (IMPORTS) */
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::lexer::{match_digit_chars, match_until_double_quote, unescape};
+use crate::try_parse_with;
+use crate::{JsonPath, Options, ParseError, PathSegment, JSON};
+
+
+/// The ways in which a serialized document can differ from its original text
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DivergenceKind {
+    /// A number literal has more precision than the number type can hold (ie. 0.10000000000000000001 became 0.1)
+    LostPrecision,
+    /// The members of an object are written in a different order (objects do not keep the order of their input)
+    ReorderedKeys,
+    /// A string or key is written with different escape sequences (ie. "\u0041" became "A")
+    NormalizedEscapes,
+    /// An object has the same key more than once, only the last of its members is kept
+    DuplicateKey,
+    /// The reparsed output is not the same value as the parsed input
+    ValueChanged,
+}
+
+/// A single difference found by `verify_roundtrip`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Divergence {
+    /// Path to the value where the difference was found
+    pub path: JsonPath,
+    pub kind: DivergenceKind,
+    /// Text of the value (or the list of keys, for ReorderedKeys) in the original input
+    pub original: String,
+    /// Text of the value (or the list of keys) in the serialized output
+    pub roundtripped: String,
+}
+
+/// Result of `verify_roundtrip`
+#[derive(Debug, Clone, PartialEq)]
+pub struct RoundtripReport {
+    /// The serialized document
+    pub output: String,
+    /// Every difference between the original text and the output, in document order
+    pub divergences: Vec<Divergence>,
+}
+
+impl RoundtripReport {
+
+    /// Returns true if the output represents exactly the same value as the input,
+    /// ie. the only differences are reordered keys and escapes
+    pub fn is_lossless(&self) -> bool
+    {
+        self.divergences.iter().all(|d| matches!(d.kind, DivergenceKind::ReorderedKeys | DivergenceKind::NormalizedEscapes))
+    }
+}
+
+impl fmt::Display for Divergence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+    {
+        write!(f, "{:?} at \"{}\": {} became {}", self.kind, self.path, self.original, self.roundtripped)
+    }
+}
+
+/// Parses the input, serializes it, parses the output again and reports every way in which the output differs from the input
+///
+/// # Errors
+/// Returns a `ParseError` if the input can not be parsed with the options
+///
+/// # Examples
+/// ```
+/// use Parser::{verify_roundtrip, DivergenceKind, Options};
+///
+/// let report = verify_roundtrip(r#"{ "id" : 12345678901234567890123 }"#, &Options::default()).unwrap();
+///
+/// # #[cfg(not(feature = "arbitrary_precision"))]
+/// assert_eq!( DivergenceKind::LostPrecision, report.divergences[0].kind );
+/// ```
+pub fn verify_roundtrip(input: &str, options: &Options) -> Result<RoundtripReport, ParseError>
+{
+    let value = try_parse_with(input, options)?;
+    let output = value.display_json().to_string();
+
+    let mut divergences = vec![];
+    compare_text(input.trim_start(), &mut JsonPath::root(), Some(&value), &mut divergences);
+
+    //The output was produced by the serializer so it should always parse, if not it is reported as a changed value
+    match try_parse_with(&output, options) {
+        Ok(reparsed) => compare_values(&value, &reparsed, &mut JsonPath::root(), &mut divergences),
+        Err(e) => divergences.push(Divergence {
+            path: JsonPath::root(),
+            kind: DivergenceKind::ValueChanged,
+            original: input.trim().to_string(),
+            roundtripped: format!("unparseable output ({e})"),
+        }),
+    }
+
+    Ok(RoundtripReport { output, divergences })
+}
+
+
+/* (TEXT COMPARISON) */
+
+// Escaped form a string is written with by display_json (without the quotes)
+fn escaped(s: &str) -> String
+{
+    let mut out = String::new();
+    crate::ser::write_escaped_str(&mut out, s);
+    out[1..out.len() - 1].to_string()
+}
+
+// Sign, significant digits & exponent of a number literal, so literals with the same decimal value give the same result
+// (ie. 1.50, 15e-1 and 0.15E1 all give (false, "15", -1))
+fn decimal_value(literal: &str) -> (bool, String, i64)
+{
+    let (negative, unsigned) = match literal.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, literal),
+    };
+    let (mantissa, exponent) = unsigned.split_once(['e', 'E']).unwrap_or((unsigned, "0"));
+    let (int, frac) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+
+    let exponent = exponent.trim_start_matches('+').parse::<i64>()
+                           .unwrap_or(if exponent.starts_with('-') { i64::MIN / 2 } else { i64::MAX / 2 });
+
+    let digits = format!("{int}{frac}");
+    let significant = digits.trim_start_matches('0');
+    let trimmed = significant.trim_end_matches('0');
+    let exponent = exponent - frac.len() as i64 + (significant.len() - trimmed.len()) as i64;
+
+    if trimmed.is_empty() { return (false, String::new(), 0); }     //Zero, including -0
+    (negative, trimmed.to_string(), exponent)
+}
+
+// Walks the original text of a value alongside the parsed value (None for members dropped as duplicate keys), recording
+// how the output of display_json will differ from it. The text has already been parsed, so it is known to be valid.
+// Returns the text following the value
+fn compare_text<'a>(text: &'a str, path: &mut JsonPath, value: Option<&JSON>, out: &mut Vec<Divergence>) -> &'a str
+{
+    let mut divergence = |path: &JsonPath, kind, original: &str, roundtripped: String| {
+        out.push(Divergence { path: path.clone(), kind, original: original.to_string(), roundtripped });
+    };
+
+    match text.as_bytes().first() {
+        Some(b'"') => {
+            let (rest, raw) = match_until_double_quote(&text[1..]).unwrap_or(("", ""));
+            if let Some(JSON::JsString(s)) = value {
+                if escaped(s) != raw {
+                    divergence(path, DivergenceKind::NormalizedEscapes, &format!("\"{raw}\""), format!("\"{}\"", escaped(s)));
+                }
+            }
+            rest.get(1..).unwrap_or("")
+        }
+        Some(b'-' | b'0'..=b'9') => {
+            let (rest, literal) = match_digit_chars(text).unwrap_or(("", ""));
+            if let Some(JSON::JsNumber(n)) = value {
+                let written = format!("{n:?}");
+                if decimal_value(literal) != decimal_value(&written) {
+                    divergence(path, DivergenceKind::LostPrecision, literal, written);
+                }
+            }
+            rest
+        }
+        Some(b'[') => {
+            let elements = match value { Some(JSON::JsArray(vec)) => vec.as_slice(), _ => &[] };
+            let mut rest = text[1..].trim_start();
+            let mut i = 0;
+
+            while !rest.starts_with(']') && !rest.is_empty() {
+                path.push(PathSegment::Index(i));
+                rest = compare_text(rest, path, elements.get(i), out).trim_start();
+                path.pop();
+
+                rest = rest.strip_prefix(',').unwrap_or(rest).trim_start();
+                i += 1;
+            }
+            rest.get(1..).unwrap_or("")
+        }
+        Some(b'{') => {
+            let members = match value { Some(JSON::JsObject(map)) => Some(map), _ => None };
+            let mut rest = text[1..].trim_start();
+
+            //Keys in their original order, along with the differences found within each member
+            let mut keys: Vec<(String, Vec<Divergence>)> = vec![];
+            let mut positions: HashMap<String, usize> = HashMap::new();
+
+            while rest.starts_with('"') {
+                let (after_key, raw) = match_until_double_quote(&rest[1..]).unwrap_or(("", ""));
+                let key = unescape(raw).unwrap_or_default();
+                rest = after_key.get(1..).unwrap_or("").trim_start();
+                rest = rest.strip_prefix(':').unwrap_or(rest).trim_start();
+
+                let mut member = vec![];
+                if escaped(&key) != raw {
+                    member.push(Divergence { path: path.clone(), kind: DivergenceKind::NormalizedEscapes,
+                                             original: format!("\"{raw}\""), roundtripped: format!("\"{}\"", escaped(&key)) });
+                }
+
+                path.push(PathSegment::Key(key.clone()));
+                rest = compare_text(rest, path, members.and_then(|m| m.get(&key)), &mut member).trim_start();
+
+                //Only the last member with a key is kept, so differences within earlier ones no longer matter
+                match positions.get(&key) {
+                    Some(&first) => {
+                        member.insert(0, Divergence { path: path.clone(), kind: DivergenceKind::DuplicateKey,
+                                                      original: "earlier member".to_string(), roundtripped: "dropped".to_string() });
+                        keys[first].1 = member;
+                    }
+                    None => {
+                        positions.insert(key.clone(), keys.len());
+                        keys.push((key, member));
+                    }
+                }
+                path.pop();
+
+                rest = rest.strip_prefix(',').unwrap_or(rest).trim_start();
+            }
+
+            //display_json writes keys in sorted order
+            let original: Vec<&str> = keys.iter().map(|(key, _)| key.as_str()).collect();
+            let mut written = original.clone();
+            written.sort();
+            if original != written {
+                divergence(path, DivergenceKind::ReorderedKeys, &format!("{original:?}"), format!("{written:?}"));
+            }
+
+            out.extend(keys.into_iter().flat_map(|(_, member)| member));
+            rest.get(1..).unwrap_or("")
+        }
+        //null, true or false (or the end of the text)
+        _ => text.trim_start_matches(|c: char| c.is_ascii_alphabetic())
+    }
+}
+
+// Records every place where the reparsed value differs from the parsed value
+fn compare_values(original: &JSON, reparsed: &JSON, path: &mut JsonPath, out: &mut Vec<Divergence>)
+{
+    match (original, reparsed) {
+        (JSON::JsArray(a), JSON::JsArray(b)) if a.len() == b.len() => {
+            for (i, (a, b)) in a.iter().zip(b).enumerate() {
+                path.push(PathSegment::Index(i));
+                compare_values(a, b, path, out);
+                path.pop();
+            }
+        }
+        (JSON::JsObject(a), JSON::JsObject(b)) if a.len() == b.len() && a.keys().all(|key| b.contains_key(key)) => {
+            for (key, a) in a {
+                path.push(PathSegment::Key(key.clone()));
+                compare_values(a, &b[key], path, out);
+                path.pop();
+            }
+        }
+        (a, b) if a.semantic_eq(b) => {}
+        (a, b) => out.push(Divergence {
+            path: path.clone(),
+            kind: DivergenceKind::ValueChanged,
+            original: a.display_json().to_string(),
+            roundtripped: b.display_json().to_string(),
+        }),
+    }
+}
+
+
+/* (TESTS) */
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generator::Generator;
+
+    fn kinds(input: &str) -> Vec<(String, DivergenceKind)>
+    {
+        verify_roundtrip(input, &Options::default()).unwrap()
+                                                    .divergences.into_iter()
+                                                    .map(|d| (d.path.to_string(), d.kind))
+                                                    .collect()
+    }
+
+    #[test]
+    fn test_decimal_value()
+    {
+        assert_eq!( decimal_value("1.50"), decimal_value("15e-1") );
+        assert_eq!( decimal_value("0.15E1"), decimal_value("1.5") );
+        assert_eq!( decimal_value("100"), decimal_value("1e2") );
+        assert_eq!( decimal_value("-0"), decimal_value("0.0") );
+        assert_ne!( decimal_value("-1"), decimal_value("1") );
+        assert_ne!( decimal_value("0.1"), decimal_value("0.10000000000000000001") );
+    }
+
+    #[test]
+    fn test_exact_roundtrip()
+    {
+        let report = verify_roundtrip(r#"{ "a" : [1, 2.5, "x\"y"], "b" : { "c" : null, "d" : true } }"#, &Options::default()).unwrap();
+
+        assert_eq!( Vec::<Divergence>::new(), report.divergences );
+        assert_eq!( r#"{"a":[1,2.5,"x\"y"],"b":{"c":null,"d":true}}"#, report.output );
+        assert!( report.is_lossless() );
+    }
+
+    #[test]
+    fn test_divergences()
+    {
+        assert_eq!( vec![("".to_string(), DivergenceKind::ReorderedKeys)], kinds(r#"{ "b" : 1, "a" : 2 }"#) );
+        assert_eq!( vec![("[0]".to_string(), DivergenceKind::NormalizedEscapes)], kinds(r#"["A\/"]"#) );
+        assert_eq!( vec![("".to_string(), DivergenceKind::NormalizedEscapes)], kinds(r#"{ "\u0061" : 1 }"#) );
+
+        let report = verify_roundtrip(r#"{ "a" : 1, "a" : 2 }"#, &Options::default()).unwrap();
+        assert_eq!( DivergenceKind::DuplicateKey, report.divergences[0].kind );
+        assert!( !report.is_lossless() );
+
+        #[cfg(not(feature = "arbitrary_precision"))]
+        {
+            let report = verify_roundtrip("[1.0, 0.10000000000000000001, 1e2]", &Options::default()).unwrap();
+            assert_eq!( 1, report.divergences.len() );
+            assert_eq!( ("[1]".to_string(), DivergenceKind::LostPrecision), (report.divergences[0].path.to_string(), report.divergences[0].kind) );
+            assert_eq!( "0.10000000000000000001 became 0.1", report.divergences[0].to_string().split(": ").nth(1).unwrap() );
+        }
+    }
+
+    #[test]
+    fn test_generated_roundtrip()
+    {
+        //Every generated value must be written and read back unchanged
+        for value in Generator::new(3).take(300) {
+            let output = value.display_json().to_string();
+            let report = verify_roundtrip(&output, &Options::default()).unwrap();
+
+            assert!( report.divergences.is_empty(), "{output}: {:?}", report.divergences );
+            assert!( crate::try_parse(&output).is_ok_and(|reparsed| reparsed.semantic_eq(&value)) );
+        }
+    }
+}
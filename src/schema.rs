@@ -0,0 +1,513 @@
+//! JSON Schema validation
+//!
+//! Supports a subset of draft 7: `type`, `properties`, `required`, `items`, `enum`, `const`,
+//! `minimum`, `maximum`, `exclusiveMinimum`, `exclusiveMaximum`, `minLength`, `maxLength`,
+//! `minItems`, `maxItems` and `pattern`, as well as the boolean schemas `true` and `false`.
+//! Any other keyword is ignored.
+//!
+//! Validation does not stop at the first problem, every violation is returned along with
+//! the JSON Pointer (ie. "/users/0/name") of the value that caused it.
+
+/* (IMPORTS) */
+use std::fmt;
+
+use crate::regex::Regex;
+use crate::{Number, JSON};
+
+
+/// A compiled json schema, which can be used to validate any number of documents
+#[derive(Debug, Clone)]
+pub struct Schema {
+    root: Node,
+}
+
+/// A single way in which a document does not conform to a schema
+#[derive(Debug, Clone, PartialEq)]
+pub struct Violation {
+    /// JSON Pointer to the offending value in the document ("" is the document itself)
+    pub path: String,
+    /// The schema keyword that was violated (ie. "required")
+    pub keyword: &'static str,
+    /// Description of the violation
+    pub message: String,
+}
+
+/// Error returned when a schema itself is malformed (ie. a "type" that is not a string)
+#[derive(Debug, Clone, PartialEq)]
+pub struct SchemaError {
+    /// JSON Pointer to the offending keyword in the schema
+    pub path: String,
+    /// Description of the problem
+    pub message: String,
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+    {
+        write!(f, "{}: {}", display_path(&self.path), self.message)
+    }
+}
+
+impl fmt::Display for SchemaError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+    {
+        write!(f, "invalid schema at {}: {}", display_path(&self.path), self.message)
+    }
+}
+
+impl std::error::Error for SchemaError {}
+
+// The empty pointer refers to the whole document, which is easier to read as "/"
+fn display_path(path: &str) -> &str
+{
+    if path.is_empty() { "/" } else { path }
+}
+
+// Appends a reference token to a JSON Pointer, escaping ~ and / as required by RFC 6901
+pub(crate) fn push_pointer(path: &str, token: &str) -> String
+{
+    format!("{path}/{}", token.replace('~', "~0").replace('/', "~1"))
+}
+
+
+/* (SCHEMA COMPILATION) */
+
+// The json types that can be named by the type keyword
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Type {
+    Null,
+    Boolean,
+    Object,
+    Array,
+    Number,
+    Integer,
+    String,
+}
+
+impl Type {
+
+    fn from_name(name: &str) -> Option<Type>
+    {
+        match name {
+            "null" => Some(Type::Null),
+            "boolean" => Some(Type::Boolean),
+            "object" => Some(Type::Object),
+            "array" => Some(Type::Array),
+            "number" => Some(Type::Number),
+            "integer" => Some(Type::Integer),
+            "string" => Some(Type::String),
+            _ => None
+        }
+    }
+
+    fn name(&self) -> &'static str
+    {
+        match self {
+            Type::Null => "null",
+            Type::Boolean => "boolean",
+            Type::Object => "object",
+            Type::Array => "array",
+            Type::Number => "number",
+            Type::Integer => "integer",
+            Type::String => "string",
+        }
+    }
+
+    // Returns true if the value is of this type (integers are numbers with no fractional part, ie. 1.0 is an integer)
+    fn matches(&self, value: &JSON) -> bool
+    {
+        match (self, value) {
+            (Type::Null, JSON::JsNull) => true,
+            (Type::Boolean, JSON::JsBool(_)) => true,
+            (Type::Object, JSON::JsObject(_)) => true,
+            (Type::Array, JSON::JsArray(_)) => true,
+            (Type::Number, JSON::JsNumber(_)) => true,
+            (Type::Integer, JSON::JsNumber(n)) => n.is_i64() || n.is_u64() || n.as_f64().fract() == 0.0,
+            (Type::String, JSON::JsString(_)) => true,
+            _ => false
+        }
+    }
+}
+
+// Returns the name of the type of a json value, as used by the type keyword
+fn type_name(value: &JSON) -> &'static str
+{
+    match value {
+        JSON::JsNull => "null",
+        JSON::JsBool(_) => "boolean",
+        JSON::JsNumber(_) => "number",
+        JSON::JsString(_) => "string",
+        JSON::JsArray(_) => "array",
+        JSON::JsObject(_) => "object",
+    }
+}
+
+// The items keyword is either a single schema for every element, or a schema for each position (ie. a tuple)
+#[derive(Debug, Clone)]
+enum Items {
+    All(Box<Node>),
+    Tuple(Vec<Node>),
+}
+
+// A compiled (sub)schema, each keyword is None / empty if it was not present
+#[derive(Debug, Clone, Default)]
+struct Node {
+    reject_all: bool, //The false schema
+    types: Option<Vec<Type>>,
+    properties: Vec<(String, Node)>,
+    required: Vec<String>,
+    items: Option<Items>,
+    enum_values: Option<Vec<JSON>>,
+    const_value: Option<JSON>,
+    minimum: Option<f64>,
+    maximum: Option<f64>,
+    exclusive_minimum: Option<f64>,
+    exclusive_maximum: Option<f64>,
+    min_length: Option<usize>,
+    max_length: Option<usize>,
+    min_items: Option<usize>,
+    max_items: Option<usize>,
+    pattern: Option<(String, Regex)>,
+}
+
+// Compiles a schema value found at the given path of the schema document
+fn compile(schema: &JSON, path: &str) -> Result<Node, SchemaError>
+{
+    let error = |keyword: &str, message: &str| SchemaError { path: push_pointer(path, keyword), message: message.to_string() };
+
+    let map = match schema {
+        JSON::JsBool(accept) => return Ok(Node { reject_all: !accept, ..Node::default() }),
+        JSON::JsObject(map) => map,
+        _ => return Err(SchemaError { path: path.to_string(), message: "a schema must be an object or a boolean".to_string() }),
+    };
+
+    // Helpers for keywords whose values must be a number or a non-negative integer
+    let number = |keyword: &str| -> Result<Option<f64>, SchemaError> {
+        match map.get(keyword) {
+            None => Ok(None),
+            Some(JSON::JsNumber(n)) => Ok(Some(n.as_f64())),
+            Some(_) => Err(error(keyword, "must be a number")),
+        }
+    };
+    //As in the spec, integral floats (ie. 2.0) are integers too
+    let count = |keyword: &str| -> Result<Option<usize>, SchemaError> {
+        let integer = |n: &Number| n.as_u64().or_else(|| {
+            let f = n.as_f64();
+            (f.fract() == 0.0 && (0.0..18446744073709551616.0).contains(&f)).then_some(f as u64)
+        });
+
+        match map.get(keyword) {
+            None => Ok(None),
+            Some(JSON::JsNumber(n)) => integer(n).and_then(|n| usize::try_from(n).ok())
+                                                 .map(Some)
+                                                 .ok_or_else(|| error(keyword, "must be a non-negative integer")),
+            Some(_) => Err(error(keyword, "must be a non-negative integer")),
+        }
+    };
+
+    let mut node = Node {
+        minimum: number("minimum")?,
+        maximum: number("maximum")?,
+        exclusive_minimum: number("exclusiveMinimum")?,
+        exclusive_maximum: number("exclusiveMaximum")?,
+        min_length: count("minLength")?,
+        max_length: count("maxLength")?,
+        min_items: count("minItems")?,
+        max_items: count("maxItems")?,
+        const_value: map.get("const").cloned(),
+        ..Node::default()
+    };
+
+    //Type is either a single type name or an array of type names
+    node.types = match map.get("type") {
+        None => None,
+        Some(JSON::JsString(name)) => Some(vec![ Type::from_name(name).ok_or_else(|| error("type", "unknown type name"))? ]),
+        Some(JSON::JsArray(names)) => Some( names.iter().map(|name| match name {
+                                                JSON::JsString(name) => Type::from_name(name),
+                                                _ => None,
+                                            })
+                                            .collect::<Option<Vec<Type>>>()
+                                            .ok_or_else(|| error("type", "must only contain type names"))? ),
+        Some(_) => return Err(error("type", "must be a string or an array of strings")),
+    };
+
+    if let Some(properties) = map.get("properties") {
+        let JSON::JsObject(properties) = properties else { return Err(error("properties", "must be an object")); };
+        let properties_path = push_pointer(path, "properties");
+
+        //Sorted so violations are reported in a consistent order
+        let mut names: Vec<&String> = properties.keys().collect();
+        names.sort();
+
+        for name in names {
+            let sub = compile(&properties[name], &push_pointer(&properties_path, name))?;
+            node.properties.push((name.clone(), sub));
+        }
+    }
+
+    if let Some(required) = map.get("required") {
+        node.required = match required {
+            JSON::JsArray(names) => names.iter().map(|name| match name {
+                                        JSON::JsString(name) => Some(name.clone()),
+                                        _ => None,
+                                    })
+                                    .collect::<Option<Vec<String>>>()
+                                    .ok_or_else(|| error("required", "must only contain strings"))?,
+            _ => return Err(error("required", "must be an array of strings")),
+        };
+    }
+
+    node.items = match map.get("items") {
+        None => None,
+        Some(JSON::JsArray(schemas)) => {
+            let items_path = push_pointer(path, "items");
+            let tuple = schemas.iter()
+                               .enumerate()
+                               .map(|(i, schema)| compile(schema, &push_pointer(&items_path, &i.to_string())))
+                               .collect::<Result<Vec<Node>, SchemaError>>()?;
+            Some(Items::Tuple(tuple))
+        }
+        Some(schema) => Some(Items::All( Box::new(compile(schema, &push_pointer(path, "items"))?) )),
+    };
+
+    node.enum_values = match map.get("enum") {
+        None => None,
+        Some(JSON::JsArray(values)) => Some(values.clone()),
+        Some(_) => return Err(error("enum", "must be an array")),
+    };
+
+    node.pattern = match map.get("pattern") {
+        None => None,
+        Some(JSON::JsString(pattern)) => {
+            let regex = Regex::new(pattern).map_err(|e| error("pattern", &format!("invalid regular expression, {e}")))?;
+            Some((pattern.clone(), regex))
+        }
+        Some(_) => return Err(error("pattern", "must be a string")),
+    };
+
+    Ok(node)
+}
+
+
+/* (VALIDATION) */
+
+impl Schema {
+
+    /// Compiles a schema from its json representation (ie. the output of `parse_json` on a schema file)
+    ///
+    /// # Errors
+    /// Returns a `SchemaError` if a supported keyword has a value of the wrong kind,
+    /// or if a pattern is not a valid (or supported) regular expression
+    pub fn new(schema: &JSON) -> Result<Schema, SchemaError>
+    {
+        Ok(Schema { root: compile(schema, "")? })
+    }
+
+    /// Validates a document against the schema, returning every violation found (an empty vec means the document is valid)
+    pub fn validate(&self, document: &JSON) -> Vec<Violation>
+    {
+        let mut violations = vec![];
+        validate_node(&self.root, document, "", &mut violations);
+        violations
+    }
+
+    /// Returns true if the document conforms to the schema
+    pub fn is_valid(&self, document: &JSON) -> bool
+    {
+        self.validate(document).is_empty()
+    }
+}
+
+// Validates a value against a compiled (sub)schema, adding any violations to the vec
+fn validate_node(node: &Node, value: &JSON, path: &str, violations: &mut Vec<Violation>)
+{
+    let mut violation = |keyword: &'static str, message: String| {
+        violations.push(Violation { path: path.to_string(), keyword, message });
+    };
+
+    if node.reject_all {
+        violation("false", "no value is allowed here".to_string());
+        return;
+    }
+
+    if let Some(types) = &node.types {
+        if !types.iter().any(|t| t.matches(value)) {
+            let names: Vec<&str> = types.iter().map(|t| t.name()).collect();
+            violation("type", format!("expected {} but found {}", names.join(" or "), type_name(value)));
+        }
+    }
+
+    if let Some(values) = &node.enum_values {
+        if !values.contains(value) {
+            violation("enum", "value is not one of the allowed values".to_string());
+        }
+    }
+
+    if let Some(expected) = &node.const_value {
+        if value != expected {
+            violation("const", "value is not equal to the constant".to_string());
+        }
+    }
+
+    match value {
+        JSON::JsNumber(n) => {
+            let n = n.as_f64();
+            if let Some(min) = node.minimum.filter(|min| n < *min) {
+                violation("minimum", format!("{n} is less than the minimum of {min}"));
+            }
+            if let Some(max) = node.maximum.filter(|max| n > *max) {
+                violation("maximum", format!("{n} is greater than the maximum of {max}"));
+            }
+            if let Some(min) = node.exclusive_minimum.filter(|min| n <= *min) {
+                violation("exclusiveMinimum", format!("{n} is not greater than {min}"));
+            }
+            if let Some(max) = node.exclusive_maximum.filter(|max| n >= *max) {
+                violation("exclusiveMaximum", format!("{n} is not less than {max}"));
+            }
+        }
+        JSON::JsString(s) => {
+            let len = s.chars().count(); //Length is in characters, not bytes
+            if let Some(min) = node.min_length.filter(|min| len < *min) {
+                violation("minLength", format!("string is shorter than {min} characters"));
+            }
+            if let Some(max) = node.max_length.filter(|max| len > *max) {
+                violation("maxLength", format!("string is longer than {max} characters"));
+            }
+            if let Some((pattern, regex)) = &node.pattern {
+                if !regex.is_match(s) {
+                    violation("pattern", format!("string does not match the pattern {pattern:?}"));
+                }
+            }
+        }
+        JSON::JsArray(elements) => {
+            if let Some(min) = node.min_items.filter(|min| elements.len() < *min) {
+                violation("minItems", format!("array has fewer than {min} items"));
+            }
+            if let Some(max) = node.max_items.filter(|max| elements.len() > *max) {
+                violation("maxItems", format!("array has more than {max} items"));
+            }
+
+            //Tuple schemas only apply to the elements at their position, any extra elements are not checked
+            let schemas: Box<dyn Iterator<Item = &Node>> = match &node.items {
+                None => Box::new(std::iter::empty()),
+                Some(Items::All(schema)) => Box::new(std::iter::repeat(&**schema)),
+                Some(Items::Tuple(schemas)) => Box::new(schemas.iter()),
+            };
+
+            for (i, (element, schema)) in elements.iter().zip(schemas).enumerate() {
+                validate_node(schema, element, &push_pointer(path, &i.to_string()), violations);
+            }
+        }
+        JSON::JsObject(map) => {
+            //Required names are checked in the order they were listed in the schema
+            for name in &node.required {
+                if !map.contains_key(name) {
+                    violation("required", format!("missing required property {name:?}"));
+                }
+            }
+
+            for (name, schema) in &node.properties {
+                if let Some(property) = map.get(name) {
+                    validate_node(schema, property, &push_pointer(path, name), violations);
+                }
+            }
+        }
+        JSON::JsNull | JSON::JsBool(_) => {}
+    }
+}
+
+
+/* (TESTS) */
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::json;
+
+    fn keywords(schema: &str, document: &str) -> Vec<(String, &'static str)>
+    {
+        Schema::new(&json(schema)).unwrap()
+            .validate(&json(document))
+            .into_iter()
+            .map(|v| (v.path, v.keyword))
+            .collect()
+    }
+
+    #[test]
+    fn test_type()
+    {
+        assert!( keywords(r#"{ "type" : "string" }"#, r#""abc""#).is_empty() );
+        assert!( keywords(r#"{ "type" : ["string", "null"] }"#, "null").is_empty() );
+        assert!( keywords(r#"{ "type" : "integer" }"#, "2.0").is_empty() );
+
+        assert_eq!( vec![("".to_string(), "type")], keywords(r#"{ "type" : "integer" }"#, "2.5") );
+        assert_eq!( vec![("".to_string(), "type")], keywords(r#"{ "type" : "object" }"#, "[1]") );
+    }
+
+    #[test]
+    fn test_boolean_schemas()
+    {
+        assert!( keywords("true", r#"{ "a" : 1 }"#).is_empty() );
+        assert_eq!( vec![("/a".to_string(), "false")], keywords(r#"{ "properties" : { "a" : false } }"#, r#"{ "a" : 1 }"#) );
+    }
+
+    #[test]
+    fn test_object_keywords()
+    {
+        let schema = r#"{ "type" : "object", "required" : ["name", "age"],
+                          "properties" : { "name" : { "type" : "string", "minLength" : 1 },
+                                           "age" : { "type" : "integer", "minimum" : 0, "maximum" : 150 } } }"#;
+
+        assert!( keywords(schema, r#"{ "name" : "Michael", "age" : 33 }"#).is_empty() );
+        assert_eq!( vec![("".to_string(), "required"), ("/name".to_string(), "minLength")],
+                    keywords(schema, r#"{ "name" : "" }"#) );
+        assert_eq!( vec![("/age".to_string(), "maximum")], keywords(schema, r#"{ "name" : "Old", "age" : 200 }"#) );
+    }
+
+    #[test]
+    fn test_array_keywords()
+    {
+        let schema = r#"{ "type" : "array", "maxItems" : 3, "items" : { "enum" : ["a", "b", 1] } }"#;
+
+        assert!( keywords(schema, r#"["a", 1, 1.0]"#).is_empty() );
+        assert_eq!( vec![("/1".to_string(), "enum")], keywords(schema, r#"["a", "c"]"#) );
+        assert_eq!( vec![("".to_string(), "maxItems")], keywords(schema, r#"[1, 1, 1, 1]"#) );
+
+        let tuple = r#"{ "items" : [{ "type" : "string" }, { "type" : "number" }] }"#;
+        assert!( keywords(tuple, r#"["a", 1, null]"#).is_empty() );
+        assert_eq!( vec![("/0".to_string(), "type")], keywords(tuple, r#"[1, 1]"#) );
+    }
+
+    #[test]
+    fn test_string_keywords()
+    {
+        let schema = r#"{ "items" : { "properties" : { "id/no" : { "pattern" : "^[A-Z]{2}[0-9]+$", "maxLength" : 5 } } } }"#;
+
+        assert!( keywords(schema, r#"[{ "id/no" : "AB12" }]"#).is_empty() );
+        assert_eq!( vec![("/0/id~1no".to_string(), "maxLength"), ("/0/id~1no".to_string(), "pattern")],
+                    keywords(schema, r#"[{ "id/no" : "ab1234" }]"#) );
+
+        //Integral floats are accepted as lengths
+        let lengths = r#"{ "minLength" : 2.0, "maxLength" : 3e0 }"#;
+        assert!( keywords(lengths, r#""abc""#).is_empty() );
+        assert_eq!( vec![("".to_string(), "minLength")], keywords(lengths, r#""a""#) );
+        assert_eq!( vec![("".to_string(), "maxLength")], keywords(lengths, r#""abcd""#) );
+
+        //Patterns match long strings without running out of stack
+        let long = format!("\"{}\"", "a".repeat(200_000));
+        assert!( keywords(r#"{ "pattern" : "^[a-z]+$" }"#, &long).is_empty() );
+        assert_eq!( vec![("".to_string(), "pattern")], keywords(r#"{ "pattern" : "^(a*)*$" }"#, r#""aaaaaaaaaaaaaaaaaaaaaaaaaaaaaa!""#) );
+    }
+
+    #[test]
+    fn test_invalid_schemas()
+    {
+        let error = |schema: &str| Schema::new(&json(schema)).unwrap_err().path;
+
+        assert_eq!( "", error("1") );
+        assert_eq!( "/type", error(r#"{ "type" : "text" }"#) );
+        assert_eq!( "/minLength", error(r#"{ "minLength" : -1 }"#) );
+        assert_eq!( "/minLength", error(r#"{ "minLength" : 2.5 }"#) );
+        assert_eq!( "/maxItems", error(r#"{ "maxItems" : "3" }"#) );
+        assert_eq!( "/properties/a/pattern", error(r#"{ "properties" : { "a" : { "pattern" : "(" } } }"#) );
+    }
+}
@@ -0,0 +1,242 @@
+//! Serializing json values back to text
+
+/* (IMPORTS) */
+use std::fmt;
+
+use crate::{Number, JSON};
+
+
+// Appends a string as a quoted json string literal, escaping quotes, backslashes and control characters
+pub(crate) fn write_escaped_str(out: &mut String, s: &str)
+{
+    out.push('"');
+    for ch in s.chars() {
+        match ch {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            '\u{8}' => out.push_str("\\b"),
+            '\u{c}' => out.push_str("\\f"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+// Returns the canonical spelling of a number, numbers that compare equal (ie. 1, 1.0 and 1e0) are spelled the same
+// Integers are written exactly, integral floats are written as integers, and other floats use the shortest round-trip form
+pub(crate) fn canonical_number(n: &Number) -> String
+{
+    if let Some(i) = n.as_i64() { return i.to_string(); }
+    if let Some(u) = n.as_u64() { return u.to_string(); }
+
+    let f = n.as_f64();
+
+    //Only literals kept by arbitrary_precision are too large for a float, and they can not be written as inf
+    #[cfg(feature = "arbitrary_precision")]
+    if !f.is_finite() { return canonical_literal(n.as_str()); }
+
+    if f.fract() == 0.0 && f.abs() < 1e38 {
+        return (f as i128).to_string(); //Also turns -0 into 0
+    }
+    format!("{f:?}")
+}
+
+// Returns a literal in scientific notation with one digit before the decimal point and no extra zeros (ie. -10.0E998 is -1e999)
+// Literals with an exponent too large for an i128 are returned as they are
+#[cfg(feature = "arbitrary_precision")]
+fn canonical_literal(literal: &str) -> String
+{
+    let (sign, unsigned) = match literal.strip_prefix('-') {
+        Some(rest) => ("-", rest),
+        None => ("", literal),
+    };
+    let (mantissa, exponent) = unsigned.split_once(['e', 'E']).unwrap_or((unsigned, "0"));
+    let (int_part, frac_part) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+    let Ok(exponent) = exponent.parse::<i128>() else { return literal.to_string(); };
+
+    //The value is digits * 10^(exponent - frac_part.len()), moved so the point is after the first significant digit
+    let digits = format!("{int_part}{frac_part}");
+    let significant = digits.trim_start_matches('0').trim_end_matches('0');
+    let trailing = digits.trim_end_matches('0').len();
+    let exponent = exponent - frac_part.len() as i128 + (digits.len() - trailing) as i128 + significant.len() as i128 - 1;
+
+    match significant.split_at_checked(1) {
+        Some((first, "")) => format!("{sign}{first}e{exponent}"),
+        Some((first, rest)) => format!("{sign}{first}.{rest}e{exponent}"),
+        None => "0".to_string(),
+    }
+}
+
+// Appends the canonical form of a value, see to_canonical_string
+fn write_canonical(out: &mut String, value: &JSON)
+{
+    match value {
+        JSON::JsNull => out.push_str("null"),
+        JSON::JsBool(b) => out.push_str(if *b { "true" } else { "false" }),
+        JSON::JsNumber(n) => out.push_str(&canonical_number(n)),
+        JSON::JsString(s) => write_escaped_str(out, s),
+        JSON::JsArray(vec) => {
+            out.push('[');
+            for (i, element) in vec.iter().enumerate() {
+                if i > 0 { out.push(','); }
+                write_canonical(out, element);
+            }
+            out.push(']');
+        }
+        JSON::JsObject(map) => {
+            let mut members: Vec<(&String, &JSON)> = map.iter().collect();
+            members.sort_by(|a, b| a.0.cmp(b.0));
+
+            out.push('{');
+            for (i, (key, member)) in members.into_iter().enumerate() {
+                if i > 0 { out.push(','); }
+                write_escaped_str(out, key);
+                out.push(':');
+                write_canonical(out, member);
+            }
+            out.push('}');
+        }
+    }
+}
+
+impl JSON {
+
+    /// Serializes the value in a canonical form: no whitespace, object keys sorted, minimal string escapes
+    /// and numbers spelled the same whenever they compare equal, so equal documents always produce the same text
+    pub fn to_canonical_string(&self) -> String
+    {
+        let mut out = String::new();
+        write_canonical(&mut out, self);
+        out
+    }
+}
+
+
+/* (DISPLAY) */
+
+/// Adapter returned by `JSON::display_json` that formats a value as json text
+///
+/// `{}` gives compact json on a single line and `{:#}` gives pretty printed json indented by 2 spaces.
+/// Object keys are sorted so the output does not depend on the order of the underlying map.
+pub struct DisplayJson<'a> {
+    value: &'a JSON,
+}
+
+impl JSON {
+
+    /// Returns an adapter that formats the value as json text rather than as Rust enum syntax,
+    /// ie. `format!("{:#}", value.display_json())` for readable logs and output files
+    pub fn display_json<'a>(&'a self) -> DisplayJson<'a>
+    {
+        DisplayJson { value: self }
+    }
+}
+
+impl<'a> fmt::Display for DisplayJson<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+    {
+        let mut out = String::new();
+        write_display(&mut out, self.value, f.alternate(), 0);
+        f.write_str(&out)
+    }
+}
+
+// Appends a newline followed by the indentation for a nesting level
+pub(crate) fn write_indent(out: &mut String, level: usize)
+{
+    out.push('\n');
+    for _ in 0..level {
+        out.push_str("  ");
+    }
+}
+
+// Appends a value as json text, one element or member per line if pretty
+// Numbers are written as they are held (ie. 1.0 stays 1.0), unlike the canonical form
+fn write_display(out: &mut String, value: &JSON, pretty: bool, level: usize)
+{
+    match value {
+        JSON::JsNull => out.push_str("null"),
+        JSON::JsBool(b) => out.push_str(if *b { "true" } else { "false" }),
+        JSON::JsNumber(n) => out.push_str(&format!("{n:?}")),      //Debug keeps exponents short (ie. 1e300)
+        JSON::JsString(s) => write_escaped_str(out, s),
+        JSON::JsArray(vec) => {
+            out.push('[');
+            for (i, element) in vec.iter().enumerate() {
+                if i > 0 { out.push(','); }
+                if pretty { write_indent(out, level + 1); }
+                write_display(out, element, pretty, level + 1);
+            }
+            if pretty && !vec.is_empty() { write_indent(out, level); }
+            out.push(']');
+        }
+        JSON::JsObject(map) => {
+            let mut members: Vec<(&String, &JSON)> = map.iter().collect();
+            members.sort_by(|a, b| a.0.cmp(b.0));
+
+            out.push('{');
+            for (i, (key, member)) in members.iter().enumerate() {
+                if i > 0 { out.push(','); }
+                if pretty { write_indent(out, level + 1); }
+                write_escaped_str(out, key);
+                out.push_str(if pretty { ": " } else { ":" });
+                write_display(out, member, pretty, level + 1);
+            }
+            if pretty && !members.is_empty() { write_indent(out, level); }
+            out.push('}');
+        }
+    }
+}
+
+
+/* (TESTS) */
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::json;
+
+    #[test]
+    fn test_write_escaped_str()
+    {
+        let mut out = String::new();
+        write_escaped_str(&mut out, "a\"b\\c\nd\u{1}é");
+
+        assert_eq!( r#""a\"b\\c\nd\u0001é""#, out );
+    }
+
+    #[test]
+    fn test_to_canonical_string()
+    {
+        let doc = json(r#"{ "b" : [1.0, 2.5, -0, 1e2, null], "a" : { "d" : true, "c" : "x" } }"#);
+
+        assert_eq!( r#"{"a":{"c":"x","d":true},"b":[1,2.5,0,100,null]}"#, doc.to_canonical_string() );
+        assert_eq!( "18446744073709551615", json("18446744073709551615").to_canonical_string() );
+        assert_eq!( "-9223372036854775808", json("-9223372036854775808").to_canonical_string() );
+        assert_eq!( "1e300", json("1e300").to_canonical_string() );
+    }
+
+    #[cfg(feature = "arbitrary_precision")]
+    #[test]
+    fn test_canonical_out_of_range()
+    {
+        //Numbers too large for a float are spelled from their literals, so they stay valid json and keep their values apart
+        let doc = json("[1e999, -10.0E998, 2e+999, 0.0150e1002]");
+        assert_eq!( "[1e999,-1e999,2e999,1.5e1000]", doc.to_canonical_string() );
+        assert_eq!( doc.clone(), json(&doc.to_canonical_string()) );
+        assert_ne!( json("1e999").content_hash(), json("2e999").content_hash() );
+    }
+
+    #[test]
+    fn test_display_json()
+    {
+        let doc = json(r#"{ "b" : [1, 2.5, {}], "a" : { "c" : "x y" }, "d" : [] }"#);
+
+        assert_eq!( r#"{"a":{"c":"x y"},"b":[1,2.5,{}],"d":[]}"#, doc.display_json().to_string() );
+        assert_eq!( "{\n  \"a\": {\n    \"c\": \"x y\"\n  },\n  \"b\": [\n    1,\n    2.5,\n    {}\n  ],\n  \"d\": []\n}",
+                    format!("{:#}", doc.display_json()) );
+        assert_eq!( "null", format!("{:#}", JSON::JsNull.display_json()) );
+    }
+}
@@ -0,0 +1,306 @@
+//! Immutable json documents with shared subtrees
+//!
+//! `ArcJson` mirrors `JSON` but keeps strings, arrays and objects behind an `Arc`, so cloning a document (or sending it to
+//! another thread) never copies it. Edits (`set`, `insert` and `remove`) are copy-on-write: they leave the document unchanged
+//! and return a new one that shares every subtree not on the edited path, so keeping many versions of a large document
+//! (ie. snapshots of configuration state) only costs the size of their differences.
+
+/* (IMPORTS) */
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::{JsonPath, Number, PathError, PathSegment, JSON};
+
+
+/// A json value whose strings, arrays and objects are reference counted and shared between clones
+#[derive(Debug, Clone, PartialEq)]
+pub enum ArcJson {
+    JsNull,
+    JsBool(bool),
+    JsNumber(Number),
+    JsString(Arc<str>),
+    JsArray(Arc<Vec<ArcJson>>),
+    JsObject(Arc<HashMap<Arc<str>, ArcJson>>),
+}
+
+impl From<JSON> for ArcJson {
+    fn from(value: JSON) -> ArcJson
+    {
+        match value {
+            JSON::JsNull => ArcJson::JsNull,
+            JSON::JsBool(b) => ArcJson::JsBool(b),
+            JSON::JsNumber(n) => ArcJson::JsNumber(n),
+            JSON::JsString(s) => ArcJson::JsString(s.into()),
+            JSON::JsArray(vec) => ArcJson::JsArray(Arc::new( vec.into_iter().map(ArcJson::from).collect() )),
+            JSON::JsObject(map) => ArcJson::JsObject(Arc::new( map.into_iter().map(|(k, v)| (k.into(), ArcJson::from(v))).collect() )),
+        }
+    }
+}
+
+impl From<&ArcJson> for JSON {
+    fn from(value: &ArcJson) -> JSON
+    {
+        value.to_json()
+    }
+}
+
+impl ArcJson {
+
+    /// Returns a deep copy of the document as a plain `JSON` value
+    pub fn to_json(&self) -> JSON
+    {
+        match self {
+            ArcJson::JsNull => JSON::JsNull,
+            ArcJson::JsBool(b) => JSON::JsBool(*b),
+            ArcJson::JsNumber(n) => JSON::JsNumber(n.clone()),
+            ArcJson::JsString(s) => JSON::JsString(s.to_string()),
+            ArcJson::JsArray(vec) => JSON::JsArray( vec.iter().map(ArcJson::to_json).collect() ),
+            ArcJson::JsObject(map) => JSON::JsObject( map.iter().map(|(k, v)| (k.to_string(), v.to_json())).collect() ),
+        }
+    }
+
+    /// Returns the value at a dotted path (ie. "config.servers[2].host"), see `JSON::get_path`
+    pub fn get_path(&self, path: &str) -> Option<&ArcJson>
+    {
+        self.get_json_path(&JsonPath::parse(path).ok()?)
+    }
+
+    /// Returns the value at a parsed path, or None if any segment of it does not exist
+    pub fn get_json_path(&self, path: &JsonPath) -> Option<&ArcJson>
+    {
+        path.segments().iter().try_fold(self, |value, segment| match (value, segment) {
+            (ArcJson::JsObject(map), PathSegment::Key(key)) => map.get(key.as_str()),
+            (ArcJson::JsArray(vec), PathSegment::Index(index)) => vec.get(*index),
+            _ => None
+        })
+    }
+
+    /// Returns a copy of the document with the value at a dotted path set, leaving this document unchanged
+    ///
+    /// Missing containers are created and an index equal to the length of an array appends to it, as in `JSON::set_path`.
+    ///
+    /// # Errors
+    /// Returns a `PathError` if the path is malformed, steps into a value that is not the right kind of container,
+    /// or uses an index past the end of an array
+    pub fn set(&self, path: &str, value: impl Into<ArcJson>) -> Result<ArcJson, PathError>
+    {
+        self.set_json_path(&JsonPath::parse(path)?, value)
+    }
+
+    /// Returns a copy of the document with the value at a parsed path set, see `set`
+    pub fn set_json_path(&self, path: &JsonPath, value: impl Into<ArcJson>) -> Result<ArcJson, PathError>
+    {
+        let value = value.into();
+        rebuild(self, path, path.segments().len(), &mut |_| Ok(value.clone()))
+    }
+
+    /// Returns a copy of the document with a value inserted at a dotted path, leaving this document unchanged
+    ///
+    /// If the path ends in an array index the value is inserted before the element at that index, shifting the rest along
+    /// (an index equal to the length of the array appends). If it ends in a key the member is added or replaced, as in `set`.
+    ///
+    /// # Errors
+    /// Returns a `PathError` as for `set`
+    pub fn insert(&self, path: &str, value: impl Into<ArcJson>) -> Result<ArcJson, PathError>
+    {
+        let path = JsonPath::parse(path)?;
+        let value = value.into();
+        let Some(last) = path.segments().last() else { return Ok(value) };
+
+        rebuild(self, &path, path.segments().len() - 1, &mut |parent| match (parent, last) {
+            (ArcJson::JsArray(vec), PathSegment::Index(index)) if *index <= vec.len() => {
+                let mut vec = Vec::clone(vec);
+                vec.insert(*index, value.clone());
+                Ok(ArcJson::JsArray(Arc::new(vec)))
+            }
+            (ArcJson::JsArray(_), PathSegment::Index(_)) => Err(PathError::IndexOutOfBounds(path.clone())),
+            (ArcJson::JsObject(map), PathSegment::Key(key)) => {
+                let mut map = HashMap::clone(map);
+                map.insert(key.as_str().into(), value.clone());
+                Ok(ArcJson::JsObject(Arc::new(map)))
+            }
+            _ => Err(PathError::NotAContainer(path.prefix(path.segments().len() - 1))),
+        })
+    }
+
+    /// Returns a copy of the document with the value at a dotted path removed, leaving this document unchanged.
+    /// Later elements of an array shift down, removing the root gives `JsNull`, and a path that does not exist
+    /// gives a copy that shares everything with this document.
+    ///
+    /// # Errors
+    /// Returns a `PathError` if the path is malformed
+    pub fn remove(&self, path: &str) -> Result<ArcJson, PathError>
+    {
+        let path = JsonPath::parse(path)?;
+        if self.get_json_path(&path).is_none() { return Ok(self.clone()); }
+        let Some(last) = path.segments().last() else { return Ok(ArcJson::JsNull) };
+
+        //The value exists, so its parent is a container of the right kind
+        rebuild(self, &path, path.segments().len() - 1, &mut |parent| match (parent, last) {
+            (ArcJson::JsArray(vec), PathSegment::Index(index)) => {
+                let mut vec = Vec::clone(vec);
+                vec.remove(*index);
+                Ok(ArcJson::JsArray(Arc::new(vec)))
+            }
+            (ArcJson::JsObject(map), PathSegment::Key(key)) => {
+                let mut map = HashMap::clone(map);
+                map.remove(key.as_str());
+                Ok(ArcJson::JsObject(Arc::new(map)))
+            }
+            _ => Ok(parent.clone()),
+        })
+    }
+
+    /// Returns true if both values are the same allocation (or the same scalar), ie. a subtree shared by two documents
+    pub fn ptr_eq(&self, other: &ArcJson) -> bool
+    {
+        match (self, other) {
+            (ArcJson::JsString(a), ArcJson::JsString(b)) => Arc::ptr_eq(a, b),
+            (ArcJson::JsArray(a), ArcJson::JsArray(b)) => Arc::ptr_eq(a, b),
+            (ArcJson::JsObject(a), ArcJson::JsObject(b)) => Arc::ptr_eq(a, b),
+            (a, b) => a == b,
+        }
+    }
+}
+
+// Copies the containers along the first n segments of the path, replacing the value found after them with the result of
+// the edit and sharing everything else. Missing containers are created, shaped by the segment that steps into them
+fn rebuild(node: &ArcJson, path: &JsonPath, n: usize, edit: &mut dyn FnMut(&ArcJson) -> Result<ArcJson, PathError>) -> Result<ArcJson, PathError>
+{
+    rebuild_from(node, path, 0, n, edit)
+}
+
+// Recursive helper for rebuild, i is the segment to step into next
+fn rebuild_from(node: &ArcJson, path: &JsonPath, i: usize, n: usize, edit: &mut dyn FnMut(&ArcJson) -> Result<ArcJson, PathError>) -> Result<ArcJson, PathError>
+{
+    if i == n { return edit(node); }
+    let segment = &path.segments()[i];
+
+    //Placeholder for a missing value, shaped by the segment that will step into it
+    let empty = match path.segments().get(i + 1) {
+        Some(PathSegment::Index(_)) => ArcJson::JsArray(Arc::default()),
+        _ => ArcJson::JsObject(Arc::default()),
+    };
+
+    match (node, segment) {
+        (ArcJson::JsObject(map), PathSegment::Key(key)) => {
+            let child = rebuild_from(map.get(key.as_str()).unwrap_or(&empty), path, i + 1, n, edit)?;
+            let mut map = HashMap::clone(map);
+            map.insert(key.as_str().into(), child);
+            Ok(ArcJson::JsObject(Arc::new(map)))
+        }
+        (ArcJson::JsArray(vec), PathSegment::Index(index)) => {
+            let child = match vec.get(*index) {
+                Some(child) => rebuild_from(child, path, i + 1, n, edit)?,
+                None if *index == vec.len() => rebuild_from(&empty, path, i + 1, n, edit)?,
+                None => return Err(PathError::IndexOutOfBounds(path.prefix(i + 1))),
+            };
+            let mut vec = Vec::clone(vec);
+            if *index == vec.len() { vec.push(child); } else { vec[*index] = child; }
+            Ok(ArcJson::JsArray(Arc::new(vec)))
+        }
+        _ => Err(PathError::NotAContainer(path.prefix(i))),
+    }
+}
+
+
+/* (TESTS) */
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::json;
+
+    #[test]
+    fn test_conversion()
+    {
+        let doc = json(r#"{ "servers" : [{ "host" : "a", "port" : 80 }], "debug" : false, "name" : null }"#);
+        let shared = ArcJson::from(doc.clone());
+
+        assert_eq!( doc, shared.to_json() );
+        assert_eq!( Some(&ArcJson::JsString("a".into())), shared.get_path("servers[0].host") );
+    }
+
+    #[test]
+    fn test_structural_sharing()
+    {
+        let original = ArcJson::from(json(r#"{ "a" : { "big" : [1, 2, 3] }, "b" : { "x" : 1 } }"#));
+        let updated = original.set("b.x", json("2")).unwrap();
+
+        assert_eq!( json(r#"{ "a" : { "big" : [1, 2, 3] }, "b" : { "x" : 2 } }"#), updated.to_json() );
+        assert_eq!( Some(&ArcJson::from(json("1"))), original.get_path("b.x") );              //Original is unchanged
+
+        //The untouched subtree is shared, the updated one is not
+        assert!( original.get_path("a").unwrap().ptr_eq(updated.get_path("a").unwrap()) );
+        assert!( !original.get_path("b").unwrap().ptr_eq(updated.get_path("b").unwrap()) );
+    }
+
+    #[test]
+    fn test_set()
+    {
+        let doc = ArcJson::from(json(r#"{ "list" : [1] }"#));
+
+        assert_eq!( json(r#"{ "list" : [1, 2] }"#), doc.set("list[1]", json("2")).unwrap().to_json() );
+        assert_eq!( json(r#"{ "list" : [1], "new" : { "deep" : [true] } }"#), doc.set("new.deep[0]", json("true")).unwrap().to_json() );
+        assert_eq!( json("7"), doc.set("", json("7")).unwrap().to_json() );
+
+        assert_eq!( Err(PathError::IndexOutOfBounds(JsonPath::parse("list[5]").unwrap())), doc.set("list[5]", json("2")) );
+        assert_eq!( Err(PathError::NotAContainer(JsonPath::parse("list[0]").unwrap())), doc.set("list[0].x", json("2")) );
+    }
+
+    #[test]
+    fn test_insert()
+    {
+        let doc = ArcJson::from(json(r#"{ "list" : [1, 3] }"#));
+
+        assert_eq!( json(r#"{ "list" : [1, 2, 3] }"#), doc.insert("list[1]", json("2")).unwrap().to_json() );
+        assert_eq!( json(r#"{ "list" : [1, 3, 4] }"#), doc.insert("list[2]", json("4")).unwrap().to_json() );
+        assert_eq!( json(r#"{ "list" : [1, 3], "k" : "v" }"#), doc.insert("k", json(r#""v""#)).unwrap().to_json() );
+        assert_eq!( json(r#"{ "list" : [1, 3], "new" : [0] }"#), doc.insert("new[0]", json("0")).unwrap().to_json() );
+
+        assert_eq!( Err(PathError::IndexOutOfBounds(JsonPath::parse("list[5]").unwrap())), doc.insert("list[5]", json("2")) );
+        assert_eq!( Err(PathError::NotAContainer(JsonPath::parse("list").unwrap())), doc.insert("list.x", json("2")) );
+    }
+
+    #[test]
+    fn test_remove()
+    {
+        let doc = ArcJson::from(json(r#"{ "list" : [1, 2, 3], "keep" : { "x" : 1 }, "drop" : true }"#));
+
+        assert_eq!( json(r#"{ "list" : [1, 3], "keep" : { "x" : 1 }, "drop" : true }"#), doc.remove("list[1]").unwrap().to_json() );
+        assert_eq!( json(r#"{ "list" : [1, 2, 3], "keep" : { "x" : 1 } }"#), doc.remove("drop").unwrap().to_json() );
+        assert_eq!( ArcJson::JsNull, doc.remove("").unwrap() );
+
+        //Removing something that does not exist shares the whole document
+        assert!( doc.remove("keep.y").unwrap().ptr_eq(&doc) );
+        assert!( doc.remove("list[3]").unwrap().ptr_eq(&doc) );
+        assert!( doc.remove("list[").is_err() );
+    }
+
+    #[test]
+    fn test_snapshots()
+    {
+        let v1 = ArcJson::from(json(r#"{ "limits" : { "cpu" : 1 }, "users" : ["a"] }"#));
+        let v2 = v1.set("limits.cpu", json("2")).unwrap();
+        let v3 = v2.insert("users[0]", json(r#""z""#)).unwrap().remove("limits").unwrap();
+
+        assert_eq!( json(r#"{ "limits" : { "cpu" : 1 }, "users" : ["a"] }"#), v1.to_json() );
+        assert_eq!( json(r#"{ "limits" : { "cpu" : 2 }, "users" : ["a"] }"#), v2.to_json() );
+        assert_eq!( json(r#"{ "users" : ["z", "a"] }"#), v3.to_json() );
+        assert!( v1.get_path("users").unwrap().ptr_eq(v2.get_path("users").unwrap()) );
+    }
+
+    #[test]
+    fn test_send_between_threads()
+    {
+        let doc = ArcJson::from(json(r#"{ "a" : [1, 2, 3] }"#));
+
+        let handles: Vec<_> = (0..4).map(|i| {
+            let doc = doc.clone();
+            std::thread::spawn(move || doc.set("a[0]", json(&i.to_string())).unwrap())
+        }).collect();
+
+        for (i, handle) in handles.into_iter().enumerate() {
+            assert_eq!( Some(&ArcJson::from(json(&i.to_string()))), handle.join().unwrap().get_path("a[0]") );
+        }
+    }
+}
@@ -0,0 +1,202 @@
+//! Substituting variables into the placeholders of a document (ie. `"http://${host}:${port}/"`)
+//!
+//! A placeholder is `${name}` anywhere in a string value, object keys are left as they are. `$$` is an escaped `$`,
+//! so `$${name}` is the text `${name}`, and a `$` that is not followed by `{` or `$` is kept.
+
+/* (IMPORTS) */
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::env::env_value;
+use crate::{JsonPath, PathSegment, JSON};
+
+
+/// How a string that is nothing but one placeholder (ie. `"${port}"`) is replaced
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WholePlaceholder {
+    /// By the text of the variable, as any other placeholder is (the string "8080")
+    Text,
+    /// By the value of the variable itself, which can be any json value (the number 8080)
+    Value,
+}
+
+/// Error returned when a placeholder can not be replaced, along with the path of the string it is in
+#[derive(Debug, Clone, PartialEq)]
+pub enum TemplateError {
+    /// The variable of a placeholder has no value
+    Unresolved(JsonPath, String),
+    /// A placeholder has no closing brace (ie. "${name")
+    Unclosed(JsonPath),
+}
+
+impl fmt::Display for TemplateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+    {
+        match self {
+            TemplateError::Unresolved(path, name) => write!(f, "variable \"{name}\" has no value (in \"{path}\")"),
+            TemplateError::Unclosed(path) => write!(f, "placeholder has no closing '}}' (in \"{path}\")"),
+        }
+    }
+}
+
+impl std::error::Error for TemplateError {}
+
+impl JSON {
+
+    /// Returns a copy of the value with the placeholders of its strings replaced by the text of variables from a map.
+    /// The text of a string variable is the string, any other value is written as compact json.
+    ///
+    /// # Examples
+    /// ```
+    /// use std::collections::HashMap;
+    /// use Parser::{try_parse, WholePlaceholder, JSON};
+    ///
+    /// let template = try_parse(r#"{ "url" : "http://${host}:${port}/", "port" : "${port}", "price" : "$$5" }"#).unwrap();
+    /// let vars = HashMap::from([("host".to_string(), JSON::JsString("localhost".to_string())), ("port".to_string(), try_parse("8080").unwrap())]);
+    ///
+    /// let value = template.substitute(&vars, WholePlaceholder::Value).unwrap();
+    /// assert_eq!( try_parse(r#"{ "url" : "http://localhost:8080/", "port" : 8080, "price" : "$5" }"#).unwrap(), value );
+    /// ```
+    ///
+    /// # Errors
+    /// Returns a `TemplateError` if a variable is not in the map or a placeholder is not closed
+    pub fn substitute(&self, vars: &HashMap<String, JSON>, whole: WholePlaceholder) -> Result<JSON, TemplateError>
+    {
+        self.substitute_with(|name| vars.get(name).cloned(), whole)
+    }
+
+    /// Returns a copy of the value with the placeholders of its strings replaced by environment variables, see `substitute`.
+    /// Whole placeholders take the value of a variable as `merge_env_vars` does (ie. "8080" is a number and "[1, 2]" an array).
+    ///
+    /// # Errors
+    /// Returns a `TemplateError` if a variable is not set (or is not unicode) or a placeholder is not closed
+    pub fn substitute_env(&self, whole: WholePlaceholder) -> Result<JSON, TemplateError>
+    {
+        self.substitute_with(|name| std::env::var(name).ok().map(|text| match whole {
+            WholePlaceholder::Text => JSON::JsString(text),
+            WholePlaceholder::Value => env_value(&text),
+        }), whole)
+    }
+
+    /// Returns a copy of the value with the placeholders of its strings replaced by the variables a function returns for their names
+    /// (None if a variable has no value), see `substitute`
+    ///
+    /// # Errors
+    /// Returns a `TemplateError` if a variable has no value or a placeholder is not closed
+    pub fn substitute_with<F>(&self, lookup: F, whole: WholePlaceholder) -> Result<JSON, TemplateError>
+    where
+        F: Fn(&str) -> Option<JSON>
+    {
+        substitute_value(self, &mut JsonPath::root(), &lookup, whole)
+    }
+}
+
+// Substitutes the strings of a value at a path (which is left as it was given)
+fn substitute_value<F>(value: &JSON, path: &mut JsonPath, lookup: &F, whole: WholePlaceholder) -> Result<JSON, TemplateError>
+where
+    F: Fn(&str) -> Option<JSON>
+{
+    let substituted = match value {
+        JSON::JsString(s) => substitute_string(s, path, lookup, whole)?,
+        JSON::JsArray(vec) => {
+            let mut elements = Vec::with_capacity(vec.len());
+            for (i, element) in vec.iter().enumerate() {
+                path.push(PathSegment::Index(i));
+                elements.push(substitute_value(element, path, lookup, whole)?);
+                path.pop();
+            }
+            JSON::JsArray(elements)
+        }
+        JSON::JsObject(map) => {
+            let mut members = HashMap::with_capacity(map.len());
+            for (key, member) in map {
+                path.push(PathSegment::Key(key.clone()));
+                members.insert(key.clone(), substitute_value(member, path, lookup, whole)?);
+                path.pop();
+            }
+            JSON::JsObject(members)
+        }
+        scalar => scalar.clone(),
+    };
+    Ok(substituted)
+}
+
+// Replaces the placeholders of a string, or the whole string by the value of its only placeholder
+fn substitute_string<F>(s: &str, path: &JsonPath, lookup: &F, whole: WholePlaceholder) -> Result<JSON, TemplateError>
+where
+    F: Fn(&str) -> Option<JSON>
+{
+    let resolve = |name: &str| lookup(name).ok_or_else(|| TemplateError::Unresolved(path.clone(), name.to_string()));
+
+    if whole == WholePlaceholder::Value {
+        if let Some(name) = s.strip_prefix("${").and_then(|rest| rest.strip_suffix('}')).filter(|name| !name.contains('}')) {
+            return resolve(name);
+        }
+    }
+
+    let mut text = String::with_capacity(s.len());
+    let mut rest = s;
+
+    while let Some(dollar) = rest.find('$') {
+        text.push_str(&rest[..dollar]);
+        let after = &rest[dollar + 1..];
+
+        rest = if let Some(after) = after.strip_prefix('$') {
+            text.push('$');
+            after
+        } else if let Some(after) = after.strip_prefix('{') {
+            let Some(end) = after.find('}') else { return Err(TemplateError::Unclosed(path.clone())); };
+            match resolve(&after[..end])? {
+                JSON::JsString(value) => text.push_str(&value),
+                value => text.push_str(&value.display_json().to_string()),
+            }
+            &after[end + 1..]
+        } else {
+            text.push('$');
+            after
+        };
+    }
+
+    text.push_str(rest);
+    Ok(JSON::JsString(text))
+}
+
+
+/* (TESTS) */
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::json;
+
+    fn vars() -> HashMap<String, JSON>
+    {
+        HashMap::from([
+            ("host".to_string(), json(r#""db.internal""#)),
+            ("port".to_string(), json("5432")),
+            ("tags".to_string(), json(r#"["a", "b"]"#)),
+        ])
+    }
+
+    #[test]
+    fn test_substitute()
+    {
+        let template = json(r#"{ "${host}" : "${host}:${port}", "list" : ["${tags}", "$${port} costs $$5, $ or $x", 7], "port" : "${port}" }"#);
+
+        let expected = json(r#"{ "${host}" : "db.internal:5432", "list" : ["[\"a\",\"b\"]", "${port} costs $5, $ or $x", 7], "port" : "5432" }"#);
+        assert_eq!( Ok(expected), template.substitute(&vars(), WholePlaceholder::Text) );
+
+        let expected = json(r#"{ "${host}" : "db.internal:5432", "list" : [["a", "b"], "${port} costs $5, $ or $x", 7], "port" : 5432 }"#);
+        assert_eq!( Ok(expected), template.substitute(&vars(), WholePlaceholder::Value) );
+    }
+
+    #[test]
+    fn test_substitute_errors()
+    {
+        let template = json(r#"{ "a" : ["ok", "${missing}"] }"#);
+        assert_eq!( Err(TemplateError::Unresolved(JsonPath::parse("a[1]").unwrap(), "missing".to_string())),
+                    template.substitute(&vars(), WholePlaceholder::Value) );
+
+        let template = json(r#"{ "a" : "${host" }"#);
+        assert_eq!( Err(TemplateError::Unclosed(JsonPath::parse("a").unwrap())), template.substitute(&vars(), WholePlaceholder::Text) );
+    }
+}
@@ -0,0 +1,254 @@
+//! Pull parser that yields the tokens of a json document one at a time
+//!
+//! `tokens` checks the structure of the document as it goes (ie. that keys are followed by colons and that brackets match),
+//! so a consumer can decode just the parts it needs and skip the rest without building a tree.
+//! Nesting is tracked with an explicit stack rather than recursion, so there is no depth limit.
+
+/* (IMPORTS) */
+use crate::lexer::{lex, Lexeme, LexemeKind, Lexer};
+use crate::{Number, ParseError, ParseErrorKind};
+
+
+/// A single token of a json document
+#[derive(Debug, Clone, PartialEq)]
+pub enum Token {
+    BeginArray,
+    EndArray,
+    BeginObject,
+    EndObject,
+    /// An object key, with its escapes replaced
+    Key(String),
+    Null,
+    Bool(bool),
+    Number(Number),
+    /// A string value, with its escapes replaced
+    String(String),
+}
+
+// What the document allows next
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Expect {
+    Value,
+    ValueOrEnd,     //First element of an array, or its end
+    CommaOrEnd,
+    KeyOrEnd,       //First key of an object, or its end
+    Key,
+    Colon,
+    Done,
+}
+
+/// Iterator returned by `tokens`
+#[derive(Debug, Clone)]
+pub struct Tokens<'a> {
+    input: &'a str,
+    lexer: Lexer<'a>,
+    structure: Structure,
+    failed: bool,
+}
+
+/// Returns an iterator over the tokens of a document containing a single json value (surrounded by optional whitespace)
+///
+/// After the first error no more items are returned.
+///
+/// # Examples
+/// ```
+/// use Parser::{tokens, Token};
+///
+/// let keys: Vec<String> = tokens(r#"{ "a" : 1, "b" : [true] }"#)
+///     .filter_map(|token| match token { Ok(Token::Key(key)) => Some(key), _ => None })
+///     .collect();
+///
+/// assert_eq!( vec!["a", "b"], keys );
+/// ```
+pub fn tokens<'a>(input: &'a str) -> Tokens<'a>
+{
+    Tokens { input, lexer: lex(input), structure: Structure::new(), failed: false }
+}
+
+impl<'a> Tokens<'a> {
+
+    // Stops the iterator with an error at an offset
+    fn error(&mut self, kind: ParseErrorKind, offset: usize) -> Option<Result<Token, ParseError>>
+    {
+        self.failed = true;
+        Some(Err(ParseError::new(kind, self.input, offset)))
+    }
+}
+
+impl<'a> Iterator for Tokens<'a> {
+    type Item = Result<Token, ParseError>;
+
+    fn next(&mut self) -> Option<Result<Token, ParseError>>
+    {
+        if self.failed { return None; }
+
+        loop {
+            let offset = self.lexer.offset();
+
+            let lexeme = match self.lexer.next() {
+                Some(Ok(lexeme)) if lexeme.kind == LexemeKind::Whitespace => continue,
+                Some(Ok(lexeme)) => lexeme,
+                Some(Err(e)) => { self.failed = true; return Some(Err(e)); }
+                None if self.structure.is_done() => return None,
+                None => return self.error(ParseErrorKind::Syntax, offset),
+            };
+
+            match self.structure.feed(&lexeme) {
+                Ok(Some(token)) => return Some(Ok(token)),
+                Ok(None) => continue,
+                Err(kind) => return self.error(kind, offset),
+            }
+        }
+    }
+}
+
+
+/* (STRUCTURE) */
+
+// Checks the structure of a document one lexeme at a time, shared by the pull parsers over strings and readers
+#[derive(Debug, Clone)]
+pub(crate) struct Structure {
+    stack: Vec<bool>,   //Open containers, true for objects
+    expect: Expect,
+}
+
+impl Structure {
+
+    pub(crate) fn new() -> Structure
+    {
+        Structure { stack: vec![], expect: Expect::Value }
+    }
+
+    // Returns true once a whole value has been read
+    pub(crate) fn is_done(&self) -> bool
+    {
+        self.expect == Expect::Done
+    }
+
+    // Takes the next lexeme that is not whitespace (or a comment), returning the token it makes (None for commas and colons)
+    // or the kind of error if the lexeme is out of place
+    pub(crate) fn feed(&mut self, lexeme: &Lexeme) -> Result<Option<Token>, ParseErrorKind>
+    {
+        let token = match self.expect {
+            Expect::Done => return Err(ParseErrorKind::TrailingCharacters),
+            Expect::Value => self.value(lexeme),
+            Expect::ValueOrEnd => self.end(lexeme).or_else(|| self.value(lexeme)),
+            Expect::CommaOrEnd => {
+                if lexeme.kind == LexemeKind::Comma {
+                    self.expect = if self.stack.last() == Some(&true) { Expect::Key } else { Expect::Value };
+                    return Ok(None);
+                }
+                self.end(lexeme)
+            }
+            Expect::KeyOrEnd | Expect::Key => {
+                let end = if self.expect == Expect::KeyOrEnd { self.end(lexeme) } else { None };
+                end.or_else(|| {
+                    let key = lexeme.string_value()?;
+                    self.expect = Expect::Colon;
+                    Some(Token::Key(key))
+                })
+            }
+            Expect::Colon => {
+                if lexeme.kind == LexemeKind::Colon {
+                    self.expect = Expect::Value;
+                    return Ok(None);
+                }
+                None
+            }
+        };
+
+        token.map(Some).ok_or(ParseErrorKind::Syntax)
+    }
+
+    // What follows a complete value, which depends on the container it is in
+    fn after_value(&self) -> Expect
+    {
+        if self.stack.is_empty() { Expect::Done } else { Expect::CommaOrEnd }
+    }
+
+    // Converts a lexeme where a value is expected into a token, or None if it can not start a value
+    fn value(&mut self, lexeme: &Lexeme) -> Option<Token>
+    {
+        let token = match lexeme.kind {
+            LexemeKind::BeginArray => { self.stack.push(false); self.expect = Expect::ValueOrEnd; return Some(Token::BeginArray); }
+            LexemeKind::BeginObject => { self.stack.push(true); self.expect = Expect::KeyOrEnd; return Some(Token::BeginObject); }
+            LexemeKind::Null => Token::Null,
+            LexemeKind::True => Token::Bool(true),
+            LexemeKind::False => Token::Bool(false),
+            LexemeKind::Number => Token::Number(Number::from_literal(lexeme.text)?),
+            LexemeKind::String => Token::String(lexeme.string_value()?),
+            _ => return None
+        };
+
+        self.expect = self.after_value();
+        Some(token)
+    }
+
+    // Converts a lexeme that closes the innermost container into a token, or None if it does not close it
+    fn end(&mut self, lexeme: &Lexeme) -> Option<Token>
+    {
+        let token = match (lexeme.kind, self.stack.last()?) {
+            (LexemeKind::EndObject, true) => Token::EndObject,
+            (LexemeKind::EndArray, false) => Token::EndArray,
+            _ => return None
+        };
+
+        self.stack.pop();
+        self.expect = self.after_value();
+        Some(token)
+    }
+}
+
+
+/* (TESTS) */
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn all(input: &str) -> Result<Vec<Token>, ParseError>
+    {
+        tokens(input).collect()
+    }
+
+    #[test]
+    fn test_tokens()
+    {
+        use Token::*;
+
+        let expected = vec![ BeginObject, Key("a".to_string()), BeginArray, Number(1u64.into()), Null, Bool(false), EndArray,
+                             Key("b\"c".to_string()), BeginObject, EndObject, Key("d".to_string()), String("é\n".to_string()), EndObject ];
+
+        assert_eq!( Ok(expected), all(r#" { "a" : [1, null, false], "b\"c" : {}, "d" : "é\n" } "#) );
+        assert_eq!( Ok(vec![BeginArray, EndArray]), all("[]") );
+        assert_eq!( Ok(vec![Number((-5i64).into())]), all("-5") );
+    }
+
+    #[test]
+    fn test_errors()
+    {
+        let error = |input: &str| all(input).map_err(|e| (e.kind(), e.offset())).unwrap_err();
+
+        assert_eq!( (ParseErrorKind::Syntax, 3), error("[1 2]") );
+        assert_eq!( (ParseErrorKind::Syntax, 4), error("[1, ]") );
+        assert_eq!( (ParseErrorKind::Syntax, 6), error(r#"{ "a" 1 }"#) );
+        assert_eq!( (ParseErrorKind::Syntax, 1), error(r#"["\q"]"#) );
+        assert_eq!( (ParseErrorKind::Syntax, 2), error("{ 1 : 2 }") );
+        assert_eq!( (ParseErrorKind::Syntax, 1), error("[}") );
+        assert_eq!( (ParseErrorKind::Syntax, 4), error("[[1]") );
+        assert_eq!( (ParseErrorKind::TrailingCharacters, 3), error("[] []") );
+        assert_eq!( (ParseErrorKind::Syntax, 0), error("") );
+
+        //Tokens before the error are still yielded, and nothing after it
+        let mut iter = tokens("[nul]");
+        assert_eq!( Some(Ok(Token::BeginArray)), iter.next() );
+        assert!( iter.next().is_some_and(|t| t.is_err()) );
+        assert_eq!( None, iter.next() );
+    }
+
+    #[test]
+    fn test_no_depth_limit()
+    {
+        let deep = "[".repeat(10_000) + &"]".repeat(10_000);
+        assert_eq!( Ok(20_000), tokens(&deep).collect::<Result<Vec<_>, _>>().map(|t| t.len()) );
+    }
+}
@@ -0,0 +1,573 @@
+//! Operations that prune or transform a json value
+
+/* (IMPORTS) */
+use std::collections::{HashMap, HashSet};
+
+use crate::{JsonPath, NormalizedJson, PathError, PathSegment, JSON};
+
+
+/* (RETAIN & FILTER) */
+
+impl JSON {
+
+    /// Keeps only the members of an object for which the predicate returns true, does nothing if the value is not an object
+    pub fn retain_entries<F>(&mut self, mut f: F)
+    where
+        F: FnMut(&str, &JSON) -> bool
+    {
+        if let JSON::JsObject(map) = self {
+            map.retain(|key, value| f(key, value));
+        }
+    }
+
+    /// Keeps only the elements of an array for which the predicate returns true, does nothing if the value is not an array
+    pub fn retain_elements<F>(&mut self, f: F)
+    where
+        F: FnMut(&JSON) -> bool
+    {
+        if let JSON::JsArray(vec) = self {
+            vec.retain(f);
+        }
+    }
+
+    /// Returns a copy of the value with only the object members for which the predicate returns true,
+    /// values that are not objects are returned unchanged
+    pub fn filtered_entries<F>(&self, mut f: F) -> JSON
+    where
+        F: FnMut(&str, &JSON) -> bool
+    {
+        match self {
+            JSON::JsObject(map) => JSON::JsObject( map.iter()
+                                                      .filter(|(key, value)| f(key, value))
+                                                      .map(|(key, value)| (key.clone(), value.clone()))
+                                                      .collect() ),
+            _ => self.clone()
+        }
+    }
+
+    /// Returns a copy of the value with only the array elements for which the predicate returns true,
+    /// values that are not arrays are returned unchanged
+    pub fn filtered_elements<F>(&self, mut f: F) -> JSON
+    where
+        F: FnMut(&JSON) -> bool
+    {
+        match self {
+            JSON::JsArray(vec) => JSON::JsArray( vec.iter().filter(|value| f(value)).cloned().collect() ),
+            _ => self.clone()
+        }
+    }
+}
+
+
+/* (ARRAY UTILITIES) */
+
+impl JSON {
+
+    /// Removes duplicate elements from an array, keeping the first occurrence of each,
+    /// does nothing if the value is not an array
+    ///
+    /// Unlike `Vec::dedup` duplicates do not have to be next to each other. Elements are compared as in `semantic_eq`.
+    pub fn dedup(&mut self)
+    {
+        let mut seen = HashSet::new();
+        self.retain_elements(|value| seen.insert(key_of(value)));
+    }
+
+    /// Removes elements of an array that have the same value at the path (see `get_path`) as an earlier element,
+    /// does nothing if the value is not an array
+    ///
+    /// Elements that have no value at the path are always kept.
+    pub fn unique_by(&mut self, path: &str)
+    {
+        let mut seen = HashSet::new();
+        self.retain_elements(|value| match value.get_path(path) {
+            Some(key) => seen.insert(key_of(key)),
+            None => true
+        });
+    }
+
+    /// Groups the elements of an array by their value at the path (see `get_path`), returning an object that maps
+    /// each distinct value to an array of the elements that have it, in their original order
+    ///
+    /// String values are used as keys directly, other values are keyed by their canonical form (ie. `1`, `true` or `null`).
+    /// Elements that have no value at the path are left out, and a value that is not an array gives an empty object.
+    pub fn group_by(&self, path: &str) -> JSON
+    {
+        let mut groups: HashMap<String, JSON> = HashMap::new();
+
+        if let JSON::JsArray(vec) = self {
+            for element in vec {
+                let key = match element.get_path(path) {
+                    Some(JSON::JsString(s)) => s.clone(),
+                    Some(other) => other.to_canonical_string(),
+                    None => continue
+                };
+
+                if let JSON::JsArray(group) = groups.entry(key).or_insert(JSON::JsArray(vec![])) {
+                    group.push(element.clone());
+                }
+            }
+        }
+
+        JSON::JsObject(groups)
+    }
+}
+
+// Hashable form of a value, so semantically equal values are only counted once
+fn key_of(value: &JSON) -> NormalizedJson
+{
+    NormalizedJson::new(value.clone())
+}
+
+
+/* (DEEP TRANSFORMATION) */
+
+impl JSON {
+
+    /// Returns a transformed copy of the value, calling the function on every value in the tree along with its path
+    ///
+    /// The tree is walked bottom up, so the function receives each array or object with its elements already transformed.
+    /// Returning None removes the value from its parent (a removed root becomes `JsNull`).
+    /// Paths always refer to positions in the original value, so array indexes do not shift when preceding elements are removed.
+    pub fn map_values<F>(&self, mut f: F) -> JSON
+    where
+        F: FnMut(&JsonPath, JSON) -> Option<JSON>
+    {
+        map_value(self, &JsonPath::root(), &mut f).unwrap_or(JSON::JsNull)
+    }
+
+    /// Returns a copy of the value with every object key replaced by the result of the function,
+    /// which is called with the path of the object that the key belongs to
+    ///
+    /// If two keys of the same object map to the same new key, only one of the members is kept.
+    pub fn map_keys<F>(&self, mut f: F) -> JSON
+    where
+        F: FnMut(&JsonPath, &str) -> String
+    {
+        map_key(self, &JsonPath::root(), &mut f)
+    }
+}
+
+// Recursive helper for map_values
+fn map_value<F>(value: &JSON, path: &JsonPath, f: &mut F) -> Option<JSON>
+where
+    F: FnMut(&JsonPath, JSON) -> Option<JSON>
+{
+    let mapped = match value {
+        JSON::JsArray(vec) => JSON::JsArray( vec.iter()
+                                                .enumerate()
+                                                .filter_map(|(i, element)| map_value(element, &path.index(i), f))
+                                                .collect() ),
+        JSON::JsObject(map) => JSON::JsObject( map.iter()
+                                                  .filter_map(|(key, member)| {
+                                                      map_value(member, &path.key(key), f).map(|m| (key.clone(), m))
+                                                  })
+                                                  .collect() ),
+        _ => value.clone()
+    };
+
+    f(path, mapped)
+}
+
+// Recursive helper for map_keys
+fn map_key<F>(value: &JSON, path: &JsonPath, f: &mut F) -> JSON
+where
+    F: FnMut(&JsonPath, &str) -> String
+{
+    match value {
+        JSON::JsArray(vec) => JSON::JsArray( vec.iter()
+                                                .enumerate()
+                                                .map(|(i, element)| map_key(element, &path.index(i), f))
+                                                .collect() ),
+        JSON::JsObject(map) => JSON::JsObject( map.iter()
+                                                  .map(|(key, member)| (f(path, key), map_key(member, &path.key(key), f)))
+                                                  .collect() ),
+        _ => value.clone()
+    }
+}
+
+
+/* (MERGE) */
+
+/// How `JSON::merge` combines an array with an array at the same place in the overlay
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArrayMerge {
+    /// The overlay's array replaces the base's
+    Replace,
+    /// The overlay's elements are appended to the base's
+    Concat,
+}
+
+impl JSON {
+
+    /// Deep merges an overlay into the value (ie. a configuration file layered over the defaults)
+    ///
+    /// Objects are merged member by member, arrays are combined as given by `arrays`,
+    /// and any other value of the overlay (including null) replaces the value it is merged into.
+    pub fn merge(&mut self, overlay: JSON, arrays: ArrayMerge)
+    {
+        match (self, overlay) {
+            (JSON::JsObject(map), JSON::JsObject(overlay)) => {
+                for (key, member) in overlay {
+                    match map.get_mut(&key) {
+                        Some(value) => value.merge(member, arrays),
+                        None => { map.insert(key, member); }
+                    }
+                }
+            }
+            (JSON::JsArray(vec), JSON::JsArray(overlay)) if arrays == ArrayMerge::Concat => vec.extend(overlay),
+            (value, overlay) => *value = overlay,
+        }
+    }
+}
+
+
+/* (PROJECTION) */
+
+// A step of a selected path, `[*]` steps into every element of an array
+// Redaction patterns can also have `*` for any member or element and `**` for any number of steps (including none)
+#[derive(Debug, Clone, PartialEq)]
+enum Step {
+    Key(String),
+    Index(usize),
+    All,
+    Any,
+    Descend,
+}
+
+// Parses a path as in `JsonPath::parse`, with `[*]` allowed in place of any index
+fn parse_steps(path: &str) -> Result<Vec<Step>, PathError>
+{
+    let mut steps = vec![];
+    let mut offset = 0;
+
+    for (i, piece) in path.split("[*]").enumerate() {
+        if i > 0 { steps.push(Step::All); }
+
+        //A key after a wildcard is written with its dot (ie. "items[*].id")
+        let dotted = i > 0 && piece.starts_with('.');
+        let piece_offset = offset + usize::from(dotted);
+        let parsed = JsonPath::parse(if dotted { &piece[1..] } else { piece }).map_err(|e| match e {
+            PathError::Syntax(at, message) => PathError::Syntax(piece_offset + at, message),
+            e => e,
+        })?;
+        if dotted && parsed.is_root() { return Err(PathError::Syntax(piece_offset, "expected a key".to_string())); }
+
+        steps.extend(parsed.segments().iter().map(|segment| match segment {
+            PathSegment::Key(key) => Step::Key(key.clone()),
+            PathSegment::Index(index) => Step::Index(*index),
+        }));
+        offset += piece.len() + "[*]".len();
+    }
+    Ok(steps)
+}
+
+impl JSON {
+
+    /// Returns a reduced copy of the value with only the values at the paths (ie. `["a", "b.c", "items[*].id"]`),
+    /// in the syntax of `JsonPath::parse` with `[*]` for every element of an array
+    ///
+    /// Objects keep only the selected members and arrays only the selected elements, in their original order.
+    /// Paths that do not exist are left out, if nothing is selected the result is an empty value of the same kind.
+    ///
+    /// # Errors
+    /// Returns a `PathError::Syntax` if a path is malformed
+    pub fn select(&self, paths: &[&str]) -> Result<JSON, PathError>
+    {
+        let steps = paths.iter().map(|path| parse_steps(path)).collect::<Result<Vec<Vec<Step>>, PathError>>()?;
+        let steps: Vec<&[Step]> = steps.iter().map(Vec::as_slice).collect();
+
+        Ok(select(self, &steps).unwrap_or_else(|| match self {
+            JSON::JsObject(_) => JSON::JsObject(HashMap::new()),
+            JSON::JsArray(_) => JSON::JsArray(vec![]),
+            _ => JSON::JsNull
+        }))
+    }
+}
+
+// Recursive helper for select, returns None if none of the paths lead to a value
+fn select(value: &JSON, paths: &[&[Step]]) -> Option<JSON>
+{
+    if paths.iter().any(|steps| steps.is_empty()) { return Some(value.clone()); }
+
+    match value {
+        JSON::JsObject(map) => {
+            let mut selected = HashMap::new();
+            for (key, member) in map {
+                let rests: Vec<&[Step]> = paths.iter().filter(|steps| steps[0] == Step::Key(key.clone())).map(|steps| &steps[1..]).collect();
+                if rests.is_empty() { continue; }
+                if let Some(member) = select(member, &rests) { selected.insert(key.clone(), member); }
+            }
+            (!selected.is_empty()).then_some(JSON::JsObject(selected))
+        }
+        JSON::JsArray(vec) => {
+            let mut selected = vec![];
+            for (i, element) in vec.iter().enumerate() {
+                let rests: Vec<&[Step]> = paths.iter().filter(|steps| matches!(steps[0], Step::All) || steps[0] == Step::Index(i)).map(|steps| &steps[1..]).collect();
+                if rests.is_empty() { continue; }
+                selected.extend(select(element, &rests));
+            }
+            (!selected.is_empty()).then_some(JSON::JsArray(selected))
+        }
+        _ => None
+    }
+}
+
+
+/* (REDACTION) */
+
+// Parses a redaction pattern, a path as in `select` that may start with `$` and have `*` and `**` in place of keys
+fn parse_pattern(pattern: &str) -> Result<Vec<Step>, PathError>
+{
+    let start = if pattern.starts_with("$.") { 2 } else if pattern.starts_with('$') { 1 } else { 0 };
+
+    let steps = parse_steps(&pattern[start..]).map_err(|e| match e {
+        PathError::Syntax(at, message) => PathError::Syntax(start + at, message),
+        e => e,
+    })?;
+
+    Ok(steps.into_iter().map(|step| match step {
+        Step::Key(key) if key == "*" => Step::Any,
+        Step::Key(key) if key == "**" => Step::Descend,
+        step => step,
+    }).collect())
+}
+
+impl JSON {
+
+    /// Replaces the values at any of the path patterns with the string "[REDACTED]" (ie. for logging payloads), returning how many
+    /// values were replaced, see `redact_with`
+    ///
+    /// # Examples
+    /// ```
+    /// use Parser::try_parse;
+    ///
+    /// let mut payload = try_parse(r#"{ "users" : [{ "name" : "ann", "ssn" : "123" }], "auth" : { "password" : "hunter2" } }"#).unwrap();
+    /// payload.redact(&["**.password", "$.users[*].ssn"]).unwrap();
+    ///
+    /// let expected = r#"{ "users" : [{ "name" : "ann", "ssn" : "[REDACTED]" }], "auth" : { "password" : "[REDACTED]" } }"#;
+    /// assert_eq!( try_parse(expected).unwrap(), payload );
+    /// ```
+    ///
+    /// # Errors
+    /// Returns a `PathError::Syntax` if a pattern is malformed, in which case nothing is replaced
+    pub fn redact(&mut self, patterns: &[&str]) -> Result<usize, PathError>
+    {
+        self.redact_with(patterns, &JSON::JsString("[REDACTED]".to_string()))
+    }
+
+    /// Replaces the values at any of the path patterns with a mask, returning how many values were replaced.
+    /// Patterns are paths as in `select` (optionally starting with `$` or `$.`), where a `*` key matches any member or element
+    /// and a `**` key matches any number of steps, so `**.password` is every member named password at any depth.
+    /// Matching arrays & objects are masked as a whole, and paths that do not exist are skipped.
+    ///
+    /// # Errors
+    /// Returns a `PathError::Syntax` if a pattern is malformed, in which case nothing is replaced
+    pub fn redact_with(&mut self, patterns: &[&str], mask: &JSON) -> Result<usize, PathError>
+    {
+        let steps = patterns.iter().map(|pattern| parse_pattern(pattern)).collect::<Result<Vec<Vec<Step>>, PathError>>()?;
+        let steps: Vec<&[Step]> = steps.iter().map(Vec::as_slice).collect();
+
+        Ok(redact(self, &steps, mask))
+    }
+}
+
+// Recursive helper for redact_with, returns the number of values that were masked
+fn redact(value: &mut JSON, patterns: &[&[Step]], mask: &JSON) -> usize
+{
+    //A leading ** can match no steps at all, so the rest of its pattern is also tried here
+    let mut expanded = vec![];
+    let mut pending = patterns.to_vec();
+    while let Some(steps) = pending.pop() {
+        if let [Step::Descend, rest @ ..] = steps { pending.push(rest); }
+        expanded.push(steps);
+    }
+
+    if expanded.iter().any(|steps| steps.is_empty()) {
+        *value = mask.clone();
+        return 1;
+    }
+
+    // Returns the rest of the patterns to try on a member or element, given whether a step leads to it
+    let rests = |leads: &dyn Fn(&Step) -> bool| -> Vec<&[Step]> {
+        expanded.iter().filter_map(|steps| match &steps[0] {
+            Step::Descend => Some(*steps),
+            step if leads(step) => Some(&steps[1..]),
+            _ => None,
+        }).collect()
+    };
+
+    let mut masked = 0;
+    match value {
+        JSON::JsObject(map) => {
+            for (key, member) in map.iter_mut() {
+                let rests = rests(&|step| matches!(step, Step::Any) || *step == Step::Key(key.clone()));
+                if !rests.is_empty() { masked += redact(member, &rests, mask); }
+            }
+        }
+        JSON::JsArray(vec) => {
+            for (i, element) in vec.iter_mut().enumerate() {
+                let rests = rests(&|step| matches!(step, Step::All | Step::Any) || *step == Step::Index(i));
+                if !rests.is_empty() { masked += redact(element, &rests, mask); }
+            }
+        }
+        _ => {}
+    }
+    masked
+}
+
+
+/* (TESTS) */
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::json;
+
+    #[test]
+    fn test_retain_entries()
+    {
+        let mut doc = json(r#"{ "name" : "Michael", "password" : "hunter2", "age" : null }"#);
+        doc.retain_entries(|key, value| key != "password" && *value != JSON::JsNull);
+
+        assert_eq!( json(r#"{ "name" : "Michael" }"#), doc );
+    }
+
+    #[test]
+    fn test_retain_elements()
+    {
+        let mut doc = json("[1, null, 2, null]");
+        doc.retain_elements(|value| *value != JSON::JsNull);
+
+        assert_eq!( json("[1, 2]"), doc );
+    }
+
+    #[test]
+    fn test_filtered()
+    {
+        let obj = json(r#"{ "a" : 1, "b" : 2 }"#);
+        let arr = json("[1, 2, 3]");
+
+        assert_eq!( json(r#"{ "b" : 2 }"#), obj.filtered_entries(|key, _| key == "b") );
+        assert_eq!( json("[3]"), arr.filtered_elements(|value| value.as_i64() > Some(2)) );
+        assert_eq!( json("[1, 2, 3]"), arr );                                      //Original is unchanged
+        assert_eq!( JSON::JsNull, JSON::JsNull.filtered_elements(|_| false) );      //Not an array, so unchanged
+    }
+
+    #[test]
+    fn test_dedup()
+    {
+        let mut doc = json(r#"[1, "a", 1.0, { "x" : 1, "y" : 2 }, "a", { "y" : 2, "x" : 1 }, 2]"#);
+        doc.dedup();
+
+        assert_eq!( json(r#"[1, "a", { "x" : 1, "y" : 2 }, 2]"#), doc );
+    }
+
+    #[test]
+    fn test_unique_by()
+    {
+        let mut doc = json(r#"[{ "id" : 1, "v" : "a" }, { "v" : "x" }, { "id" : 2 }, { "id" : 1, "v" : "b" }, { "v" : "y" }]"#);
+        doc.unique_by("id");
+
+        assert_eq!( json(r#"[{ "id" : 1, "v" : "a" }, { "v" : "x" }, { "id" : 2 }, { "v" : "y" }]"#), doc );
+    }
+
+    #[test]
+    fn test_group_by()
+    {
+        let doc = json(r#"[{ "city" : "London", "n" : 1 }, { "city" : "Paris", "n" : 2 }, { "n" : 3 }, { "city" : "London", "n" : 4 }, { "city" : 5 }]"#);
+        let expected = json(r#"{ "London" : [{ "city" : "London", "n" : 1 }, { "city" : "London", "n" : 4 }],
+                                 "Paris" : [{ "city" : "Paris", "n" : 2 }],
+                                 "5" : [{ "city" : 5 }] }"#);
+
+        assert_eq!( expected, doc.group_by("city") );
+        assert_eq!( json("{}"), JSON::JsNull.group_by("city") );
+    }
+
+    #[test]
+    fn test_map_values()
+    {
+        let doc = json(r#"{ "a" : null, "b" : [1.4, null, 2.6], "c" : { "d" : null } }"#);
+
+        //Strip nulls & round numbers
+        let mapped = doc.map_values(|_, value| match value {
+            JSON::JsNull => None,
+            JSON::JsNumber(n) => crate::Number::from_f64(n.as_f64().round()).map(JSON::JsNumber),
+            _ => Some(value)
+        });
+        assert_eq!( json(r#"{ "b" : [1, 3], "c" : {} }"#), mapped );
+
+        //Paths refer to the original positions
+        let mut paths = vec![];
+        json("[0, [1]]").map_values(|path, value| { paths.push(path.to_string()); Some(value) });
+        assert_eq!( vec!["[0]", "[1][0]", "[1]", ""], paths );
+
+        assert_eq!( JSON::JsNull, doc.map_values(|_, _| None) );
+    }
+
+    #[test]
+    fn test_map_keys()
+    {
+        let doc = json(r#"{ "Name" : "Michael", "Address" : { "City" : "London" }, "Tags" : [{ "Key" : 1 }] }"#);
+        let lower = doc.map_keys(|_, key| key.to_lowercase());
+
+        assert_eq!( json(r#"{ "name" : "Michael", "address" : { "city" : "London" }, "tags" : [{ "key" : 1 }] }"#), lower );
+
+        //Only rename keys within the address object
+        let renamed = doc.map_keys(|path, key| if path.to_string() == "Address" { key.to_uppercase() } else { key.to_string() });
+        assert_eq!( Some(&JSON::JsString("London".to_string())), renamed.get_path("Address.CITY") );
+    }
+
+    #[test]
+    fn test_merge()
+    {
+        let mut config = json(r#"{ "name" : "app", "server" : { "host" : "localhost", "port" : 80 }, "plugins" : ["a"] }"#);
+        config.merge(json(r#"{ "server" : { "port" : 8080, "tls" : true }, "plugins" : ["b"], "name" : null }"#), ArrayMerge::Replace);
+
+        assert_eq!( json(r#"{ "name" : null, "server" : { "host" : "localhost", "port" : 8080, "tls" : true }, "plugins" : ["b"] }"#), config );
+
+        config.merge(json(r#"{ "plugins" : ["c"], "server" : 1 }"#), ArrayMerge::Concat);
+        assert_eq!( json(r#"{ "name" : null, "server" : 1, "plugins" : ["b", "c"] }"#), config );
+    }
+
+    #[test]
+    fn test_select()
+    {
+        let doc = json(r#"{ "a" : 1, "b" : { "c" : 2, "d" : 3 }, "items" : [{ "id" : 1, "x" : 0 }, { "x" : 0 }, { "id" : 3, "tags" : ["t", "u"] }] }"#);
+
+        assert_eq!( json(r#"{ "a" : 1, "b" : { "c" : 2 }, "items" : [{ "id" : 1 }, { "id" : 3 }] }"#), doc.select(&["a", "b.c", "items[*].id"]).unwrap() );
+        assert_eq!( json(r#"{ "items" : [{ "id" : 1 }, { "tags" : ["u"] }] }"#), doc.select(&["items[0].id", "items[*].tags[1]", "missing"]).unwrap() );
+        assert_eq!( json("{}"), doc.select(&["a.b"]).unwrap() );
+        assert_eq!( json("[[1], [3]]"), json("[[1, 2], [3]]").select(&["[*][0]"]).unwrap() );
+
+        assert_eq!( PathError::Syntax(9, "expected a key".to_string()), doc.select(&["items[*].."]).unwrap_err() );
+    }
+
+    #[test]
+    fn test_redact()
+    {
+        let doc = json(r#"{ "password" : "p0", "users" : [{ "ssn" : "1", "auth" : { "password" : "p1", "token" : "t" } }, { "name" : "bo" }],
+                            "config" : { "keys" : ["k1", "k2"], "db" : { "password" : "p2" } } }"#);
+
+        let mut redacted = doc.clone();
+        let mask = JSON::JsString("***".to_string());
+        assert_eq!( Ok(6), redacted.redact_with(&["**.password", "$.users[*].ssn", "users[0].auth.*", "$.config.keys[1]", "missing.path"], &mask) );
+
+        let expected = json(r#"{ "password" : "***", "users" : [{ "ssn" : "***", "auth" : { "password" : "***", "token" : "***" } }, { "name" : "bo" }],
+                                 "config" : { "keys" : ["k1", "***"], "db" : { "password" : "***" } } }"#);
+        assert_eq!( expected, redacted );
+
+        //Containers are masked whole, and ** also matches the root
+        let mut redacted = doc.clone();
+        redacted.redact(&["config", "users.*"]).unwrap();
+        assert_eq!( json(r#"["[REDACTED]", "[REDACTED]"]"#), redacted.get_path("users").unwrap().clone() );
+        assert_eq!( Some(&JSON::JsString("[REDACTED]".to_string())), redacted.get_path("config") );
+
+        let mut redacted = doc.clone();
+        assert_eq!( Ok(1), redacted.redact_with(&["**"], &JSON::JsNull) );
+        assert_eq!( JSON::JsNull, redacted );
+
+        let mut redacted = doc.clone();
+        assert_eq!( Err(PathError::Syntax(5, "expected ']'".to_string())), redacted.redact(&["$.a[0"]) );
+        assert_eq!( doc, redacted );
+    }
+}
@@ -0,0 +1,243 @@
+//! Visitor based traversal of a json value
+//!
+//! Implement the callbacks of `Visitor` that an analysis needs (they all default to doing nothing)
+//! and call `walk`, which visits every value in the tree in depth first order along with its path.
+
+/* (IMPORTS) */
+use std::collections::HashMap;
+
+use crate::{JsonPath, Number, PathSegment, JSON};
+
+
+/// Callbacks for each kind of json value, called by `walk`
+#[allow(unused_variables)]
+pub trait Visitor {
+
+    /// Called for every null
+    fn visit_null(&mut self, path: &JsonPath) {}
+
+    /// Called for every bool
+    fn visit_bool(&mut self, path: &JsonPath, value: bool) {}
+
+    /// Called for every number
+    fn visit_number(&mut self, path: &JsonPath, value: &Number) {}
+
+    /// Called for every string
+    fn visit_string(&mut self, path: &JsonPath, value: &str) {}
+
+    /// Called before the elements of an array are visited, return false to skip the elements (exit_array is still called)
+    fn enter_array(&mut self, path: &JsonPath, array: &[JSON]) -> bool { true }
+
+    /// Called after the elements of an array have been visited
+    fn exit_array(&mut self, path: &JsonPath, array: &[JSON]) {}
+
+    /// Called before the members of an object are visited, return false to skip the members (exit_object is still called)
+    fn enter_object(&mut self, path: &JsonPath, object: &HashMap<String, JSON>) -> bool { true }
+
+    /// Called after the members of an object have been visited
+    fn exit_object(&mut self, path: &JsonPath, object: &HashMap<String, JSON>) {}
+}
+
+/// Walks every value of the tree in depth first order, calling the matching callbacks of the visitor.
+/// Array elements are visited in order, object members are visited in the (unspecified) order of the object.
+pub fn walk<V: Visitor>(value: &JSON, visitor: &mut V)
+{
+    walk_value(value, &mut JsonPath::root(), visitor);
+}
+
+// Recursive helper for walk, the path is extended before visiting a child and restored afterwards
+fn walk_value<V: Visitor>(value: &JSON, path: &mut JsonPath, visitor: &mut V)
+{
+    match value {
+        JSON::JsNull => visitor.visit_null(path),
+        JSON::JsBool(b) => visitor.visit_bool(path, *b),
+        JSON::JsNumber(n) => visitor.visit_number(path, n),
+        JSON::JsString(s) => visitor.visit_string(path, s),
+        JSON::JsArray(vec) => {
+            if visitor.enter_array(path, vec) {
+                for (i, element) in vec.iter().enumerate() {
+                    path.push(PathSegment::Index(i));
+                    walk_value(element, path, visitor);
+                    path.pop();
+                }
+            }
+            visitor.exit_array(path, vec);
+        }
+        JSON::JsObject(map) => {
+            if visitor.enter_object(path, map) {
+                for (key, member) in map {
+                    path.push(PathSegment::Key(key.clone()));
+                    walk_value(member, path, visitor);
+                    path.pop();
+                }
+            }
+            visitor.exit_object(path, map);
+        }
+    }
+}
+
+
+/* (FOLD) */
+
+impl JSON {
+
+    /// Visits every value of the tree (including this one) along with its path, accumulating a result
+    ///
+    /// Values are visited depth first with each array or object visited before its elements,
+    /// so `fold` sees the same values in the same order as `walk`.
+    pub fn fold<B, F>(&self, init: B, mut f: F) -> B
+    where
+        F: FnMut(B, &JsonPath, &JSON) -> B
+    {
+        fold_value(self, &mut JsonPath::root(), init, &mut f)
+    }
+}
+
+// Recursive helper for fold
+fn fold_value<B, F>(value: &JSON, path: &mut JsonPath, init: B, f: &mut F) -> B
+where
+    F: FnMut(B, &JsonPath, &JSON) -> B
+{
+    let mut acc = f(init, path, value);
+
+    match value {
+        JSON::JsArray(vec) => {
+            for (i, element) in vec.iter().enumerate() {
+                path.push(PathSegment::Index(i));
+                acc = fold_value(element, path, acc, f);
+                path.pop();
+            }
+        }
+        JSON::JsObject(map) => {
+            for (key, member) in map {
+                path.push(PathSegment::Key(key.clone()));
+                acc = fold_value(member, path, acc, f);
+                path.pop();
+            }
+        }
+        _ => {}
+    }
+
+    acc
+}
+
+
+/* (SEARCH) */
+
+impl JSON {
+
+    /// Returns every value in the tree (including this one) for which the predicate returns true, along with its path,
+    /// in the same order that `walk` visits them
+    pub fn find_all<F>(&self, mut predicate: F) -> Vec<(JsonPath, &JSON)>
+    where
+        F: FnMut(&JsonPath, &JSON) -> bool
+    {
+        let mut found = vec![];
+        find_values(self, &mut JsonPath::root(), &mut predicate, &mut found);
+        found
+    }
+}
+
+// Recursive helper for find_all
+fn find_values<'a, F>(value: &'a JSON, path: &mut JsonPath, predicate: &mut F, found: &mut Vec<(JsonPath, &'a JSON)>)
+where
+    F: FnMut(&JsonPath, &JSON) -> bool
+{
+    if predicate(path, value) {
+        found.push((path.clone(), value));
+    }
+
+    match value {
+        JSON::JsArray(vec) => {
+            for (i, element) in vec.iter().enumerate() {
+                path.push(PathSegment::Index(i));
+                find_values(element, path, predicate, found);
+                path.pop();
+            }
+        }
+        JSON::JsObject(map) => {
+            for (key, member) in map {
+                path.push(PathSegment::Key(key.clone()));
+                find_values(member, path, predicate, found);
+                path.pop();
+            }
+        }
+        _ => {}
+    }
+}
+
+
+/* (TESTS) */
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_json;
+
+    // Records every callback as a line of text
+    #[derive(Default)]
+    struct Recorder {
+        events: Vec<String>,
+    }
+
+    impl Visitor for Recorder {
+        fn visit_null(&mut self, path: &JsonPath) { self.events.push(format!("null {path}")); }
+        fn visit_bool(&mut self, path: &JsonPath, value: bool) { self.events.push(format!("bool {path} {value}")); }
+        fn visit_number(&mut self, path: &JsonPath, value: &Number) { self.events.push(format!("number {path} {value}")); }
+        fn visit_string(&mut self, path: &JsonPath, value: &str) { self.events.push(format!("string {path} {value}")); }
+        fn enter_array(&mut self, path: &JsonPath, _: &[JSON]) -> bool { self.events.push(format!("enter {path}")); !path.is_root() }
+        fn exit_array(&mut self, path: &JsonPath, _: &[JSON]) { self.events.push(format!("exit {path}")); }
+    }
+
+    #[test]
+    fn test_walk()
+    {
+        let (_, doc) = parse_json(r#"{ "a" : [null, true, 1, "x"] }"#).unwrap();
+        let mut recorder = Recorder::default();
+        walk(&doc, &mut recorder);
+
+        assert_eq!( vec!["enter a", "null a[0]", "bool a[1] true", "number a[2] 1", "string a[3] x", "exit a"], recorder.events );
+    }
+
+    #[test]
+    fn test_walk_skip()
+    {
+        let (_, doc) = parse_json("[[1], 2]").unwrap();
+        let mut recorder = Recorder::default();
+        walk(&doc, &mut recorder); //Recorder skips the elements of the root array
+
+        assert_eq!( vec!["enter ", "exit "], recorder.events );
+    }
+
+    #[test]
+    fn test_fold()
+    {
+        let (_, doc) = parse_json(r#"{ "orders" : [{ "amount" : 10.5 }, { "amount" : 2, "items" : [{ "amount" : 1 }] }] }"#).unwrap();
+
+        //Sum of every amount field, anywhere in the document
+        let total = doc.fold(0.0, |acc, path, value| match (path.segments().last(), value) {
+            (Some(PathSegment::Key(key)), JSON::JsNumber(n)) if key == "amount" => acc + n.as_f64(),
+            _ => acc
+        });
+        assert_eq!( 13.5, total );
+
+        let paths = parse_json("[1, [2]]").unwrap().1.fold(vec![], |mut acc, path, _| { acc.push(path.to_string()); acc });
+        assert_eq!( vec!["", "[0]", "[1]", "[1][0]"], paths );
+    }
+
+    #[test]
+    fn test_find_all()
+    {
+        let (_, doc) = parse_json(r#"{ "config" : { "api_key" : "sk_live_123", "hosts" : ["a", "sk_test_456"] }, "n" : 1 }"#).unwrap();
+
+        //Every string that looks like a secret key
+        let mut secrets: Vec<(String, &JSON)> = doc.find_all(|_, value| matches!(value, JSON::JsString(s) if s.starts_with("sk_")))
+                                                   .into_iter()
+                                                   .map(|(path, value)| (path.to_string(), value))
+                                                   .collect();
+        secrets.sort_by(|a, b| a.0.cmp(&b.0));
+
+        assert_eq!( vec![("config.api_key".to_string(), &JSON::JsString("sk_live_123".to_string())),
+                         ("config.hosts[1]".to_string(), &JSON::JsString("sk_test_456".to_string()))], secrets );
+        assert!( doc.find_all(|_, value| *value == JSON::JsNull).is_empty() );
+    }
+}
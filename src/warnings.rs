@@ -0,0 +1,271 @@
+//! Recoverable problems found in input that still parses, collected by `parse_with_warnings`
+//!
+//! Duplicate keys, unpaired surrogates, numbers that can not be held exactly and a leading byte order mark are all
+//! accepted by the parser, so a strict consumer looks at the warnings to surface them instead of failing.
+
+/* (IMPORTS) */
+use std::collections::HashSet;
+use std::fmt;
+
+use crate::error::{line_column, write_path};
+use crate::lexer::{lex_with, LexemeKind};
+use crate::{try_parse_with, JsonPath, Options, ParseError, JSON};
+
+
+const BOM: char = '\u{feff}';
+
+/// The kind of recoverable problem
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WarningKind {
+    /// An object has the key more than once, only the last member with it is kept
+    DuplicateKey,
+    /// A string escapes half of a surrogate pair (ie. `"\ud800"`), it was replaced with U+FFFD
+    LoneSurrogate,
+    /// A number can not be held exactly, so it was rounded to the nearest f64
+    PrecisionLoss,
+    /// The input starts with a byte order mark, which was skipped
+    ByteOrderMark,
+}
+
+/// A recoverable problem found by `parse_with_warnings`, with its position in the input
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Warning {
+    kind: WarningKind,
+    offset: usize,
+    line: usize,
+    column: usize,
+    path: JsonPath,
+}
+
+impl Warning {
+
+    // Creates a warning at a byte offset into the input
+    fn new(kind: WarningKind, input: &str, offset: usize, path: JsonPath) -> Warning
+    {
+        let (line, column) = line_column(input, offset);
+        Warning { kind, offset, line, column, path }
+    }
+
+    /// Returns the kind of problem
+    pub fn kind(&self) -> WarningKind
+    {
+        self.kind
+    }
+
+    /// Returns the byte offset into the input of the lexeme with the problem
+    pub fn offset(&self) -> usize
+    {
+        self.offset
+    }
+
+    /// Returns the line (starting at 1) of the lexeme with the problem
+    pub fn line(&self) -> usize
+    {
+        self.line
+    }
+
+    /// Returns the column (in characters, starting at 1) of the lexeme with the problem
+    pub fn column(&self) -> usize
+    {
+        self.column
+    }
+
+    /// Returns the path of the value with the problem, for a duplicate key that of the member which replaced the earlier one
+    pub fn path(&self) -> &JsonPath
+    {
+        &self.path
+    }
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+    {
+        let message = match self.kind {
+            WarningKind::DuplicateKey => "duplicate key, only the last value is kept",
+            WarningKind::LoneSurrogate => "unpaired surrogate replaced with U+FFFD",
+            WarningKind::PrecisionLoss => "number rounded to the nearest f64",
+            WarningKind::ByteOrderMark => "byte order mark skipped",
+        };
+        write!(f, "{message} at line {} column {}", self.line, self.column)?;
+        write_path(f, &self.path)
+    }
+}
+
+/// Parses a string containing exactly one json value as in `try_parse_with`, also returning the recoverable problems
+/// in it in the order they appear. A leading byte order mark is skipped (with a warning) rather than being an error.
+///
+/// # Examples
+/// ```
+/// use Parser::{parse_with_warnings, Options, WarningKind};
+///
+/// let (_, warnings) = parse_with_warnings(r#"{ "a" : 1, "a" : 2 }"#, &Options::default()).unwrap();
+/// assert_eq!( WarningKind::DuplicateKey, warnings[0].kind() );
+/// assert_eq!( "duplicate key, only the last value is kept at line 1 column 12 (in $.a)", warnings[0].to_string() );
+/// ```
+///
+/// # Errors
+/// Returns a `ParseError` if the input is not a single valid json value, positioned in the input as given
+pub fn parse_with_warnings(input: &str, options: &Options) -> Result<(JSON, Vec<Warning>), ParseError>
+{
+    let skipped = if input.starts_with(BOM) { BOM.len_utf8() } else { 0 };
+    let text = &input[skipped..];
+
+    let value = try_parse_with(text, options).map_err(|e| {
+        ParseError::new(e.kind(), input, e.offset() + skipped).with_path(e.path().clone())
+    })?;
+
+    let mut warnings = vec![];
+    if skipped > 0 { warnings.push(Warning::new(WarningKind::ByteOrderMark, input, 0, JsonPath::root())); }
+
+    //Index of each open array, and key of each open object (None while the key of the next member is expected)
+    enum Open {
+        Array(usize),
+        Object(Option<String>, HashSet<String>),
+    }
+    let mut stack: Vec<Open> = vec![];
+    let path = |stack: &[Open]| stack.iter().fold(JsonPath::root(), |path, open| match open {
+        Open::Array(i) => path.index(*i),
+        Open::Object(Some(key), _) => path.key(key),
+        Open::Object(None, _) => path,
+    });
+
+    for lexeme in lex_with(text, options).map_while(Result::ok) {
+        let offset = lexeme.start + skipped;
+        let mut warn = |kind, stack: &[Open]| warnings.push(Warning::new(kind, input, offset, path(stack)));
+
+        match lexeme.kind {
+            LexemeKind::BeginArray => stack.push(Open::Array(0)),
+            LexemeKind::BeginObject => stack.push(Open::Object(None, HashSet::new())),
+            LexemeKind::EndArray | LexemeKind::EndObject => { stack.pop(); }
+            LexemeKind::Comma => match stack.last_mut() {
+                Some(Open::Array(i)) => *i += 1,
+                Some(Open::Object(key, _)) => *key = None,
+                None => {}
+            },
+            LexemeKind::String => {
+                if !options.raw_strings && has_lone_surrogate(lexeme.text) { warn(WarningKind::LoneSurrogate, &stack); }
+
+                if let Some(Open::Object(key @ None, seen)) = stack.last_mut() {
+                    let name = lexeme.string_value().unwrap_or_default();
+                    let duplicate = !seen.insert(name.clone());
+                    *key = Some(name);
+                    if duplicate { warn(WarningKind::DuplicateKey, &stack); }
+                }
+            }
+            LexemeKind::Number if loses_precision(lexeme.text) => warn(WarningKind::PrecisionLoss, &stack),
+            _ => {}
+        }
+    }
+
+    Ok((value, warnings))
+}
+
+// Tells whether a string literal escapes a high surrogate that is not followed by an escaped low surrogate, or a lone low one
+fn has_lone_surrogate(literal: &str) -> bool
+{
+    let unit = |s: &str| s.strip_prefix("\\u").and_then(|hex| hex.get(..4)).and_then(|hex| u32::from_str_radix(hex, 16).ok());
+
+    let mut rest = literal;
+    while let Some(i) = rest.find('\\') {
+        rest = &rest[i..];
+        match unit(rest) {
+            Some(0xd800..=0xdbff) => match unit(&rest[6..]) {
+                Some(0xdc00..=0xdfff) => rest = &rest[12..],
+                _ => return true,
+            },
+            Some(0xdc00..=0xdfff) => return true,
+            Some(_) => rest = &rest[6..],
+            None => rest = &rest[2..], //Every other escape is a backslash and one ascii character
+        }
+    }
+    false
+}
+
+// Tells whether a number literal changes value when it is converted, which never happens when the literal is kept
+fn loses_precision(literal: &str) -> bool
+{
+    if cfg!(feature = "arbitrary_precision") { return false; }
+
+    let is_integer = !literal.contains(['.', 'e', 'E']);
+    if is_integer && (literal.parse::<u64>().is_ok() || literal.parse::<i64>().is_ok()) { return false; }
+
+    //Compare the significant digits & exponent of the literal with those of the shortest form of the f64
+    match literal.parse::<f64>() {
+        Ok(f) if f.is_finite() => significand(literal) != significand(&format!("{f:e}")),
+        _ => true,
+    }
+}
+
+// Splits a number literal into its significant digits and the power of ten they are multiplied by (ie. "1.50e2" is ("15", 1))
+fn significand(literal: &str) -> (String, i64)
+{
+    let literal = literal.trim_start_matches('-');
+    let (mantissa, exponent) = literal.split_once(['e', 'E']).unwrap_or((literal, "0"));
+    let (int, frac) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+
+    let digits = format!("{int}{frac}");
+    let digits = digits.trim_start_matches('0');
+    let trimmed = digits.trim_end_matches('0');
+    if trimmed.is_empty() { return (String::new(), 0); }
+
+    //Exponents too large for an i64 are rejected by the f64 conversion before this is reached
+    let exponent = exponent.trim_start_matches('+').parse::<i64>().unwrap_or(0);
+    let power = exponent - frac.len() as i64 + (digits.len() - trimmed.len()) as i64;
+    (trimmed.to_string(), power)
+}
+
+
+/* (TESTS) */
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{try_parse, ParseErrorKind};
+
+    fn warnings(input: &str) -> Vec<(WarningKind, usize, String)>
+    {
+        let (_, warnings) = parse_with_warnings(input, &Options::default()).unwrap();
+        warnings.iter().map(|w| (w.kind(), w.offset(), w.path().to_string())).collect()
+    }
+
+    #[test]
+    fn test_parse_with_warnings()
+    {
+        let input = r#"{ "a" : [1, { "b" : 1, "b" : "\ud800" }], "a" : "\ud83d\ude00\udc00" }"#;
+        let (value, _) = parse_with_warnings(input, &Options::default()).unwrap();
+        assert_eq!( try_parse(input).unwrap(), value );
+
+        assert_eq!( vec![
+            (WarningKind::DuplicateKey, 23, "a[1].b".to_string()),
+            (WarningKind::LoneSurrogate, 29, "a[1].b".to_string()),
+            (WarningKind::DuplicateKey, 42, "a".to_string()),
+            (WarningKind::LoneSurrogate, 48, "a".to_string()),
+        ], warnings(input) );
+
+        assert_eq!( Vec::<(WarningKind, usize, String)>::new(), warnings(r#"[{ "a" : 1 }, { "a" : "\\ud800\ud800\udfff" }]"#) );
+        let raw = Options { raw_strings: true, ..Options::default() };
+        assert!( parse_with_warnings(r#""\udc00""#, &raw).unwrap().1.is_empty() );
+    }
+
+    #[test]
+    fn test_byte_order_mark()
+    {
+        assert_eq!( vec![(WarningKind::ByteOrderMark, 0, String::new())], warnings("\u{feff}[1]") );
+
+        //Errors are positioned in the input as given
+        let error = parse_with_warnings("\u{feff}[1,]", &Options::default()).unwrap_err();
+        assert_eq!( (ParseErrorKind::Syntax, 6), (error.kind(), error.offset()) );
+    }
+
+    #[test]
+    #[cfg(not(feature = "arbitrary_precision"))]
+    fn test_precision_loss()
+    {
+        let numbers = ["1", "-0", "0.1", "1.5e300", "100e-2", "18446744073709551615", "-9223372036854775808", "0.30000000000000004"];
+        assert!( numbers.iter().all(|n| !loses_precision(n)) );
+
+        let numbers = ["18446744073709551616", "-9223372036854775809", "0.123456789012345678", "9007199254740993.0"];
+        assert!( numbers.iter().all(|n| loses_precision(n)) );
+
+        assert_eq!( vec![(WarningKind::PrecisionLoss, 4, "[1]".to_string())], warnings("[1, 12345678901234567890123]") );
+    }
+}
@@ -0,0 +1,97 @@
+//! Bindings for JavaScript through wasm-bindgen (enabled with the `wasm` feature), ie. for a browser-based json inspector
+//!
+//! A parsed document stays on the Rust side as a `Json` handle, so values are only converted to text when they are shown.
+//! Functions that fail throw the message of their error as a string.
+
+/* (IMPORTS) */
+use wasm_bindgen::prelude::*;
+
+use crate::schema::Schema;
+use crate::{try_parse, JSON};
+
+
+/// A parsed document
+#[wasm_bindgen]
+pub struct Json {
+    value: JSON,
+}
+
+/// Parses json text, throwing the position and cause of the error if it is not a single json value
+#[wasm_bindgen]
+pub fn parse(text: &str) -> Result<Json, String>
+{
+    try_parse(text).map(|value| Json { value }).map_err(|e| e.to_string())
+}
+
+#[wasm_bindgen]
+impl Json {
+
+    /// Returns the document as json text, compact or indented by 2 spaces if pretty
+    pub fn stringify(&self, pretty: bool) -> String
+    {
+        match pretty {
+            true => format!("{:#}", self.value.display_json()),
+            false => self.value.display_json().to_string(),
+        }
+    }
+
+    /// Returns a copy of the value at a JSON Pointer (ie. "/servers/0/host"), or undefined if there is no such value
+    pub fn pointer(&self, pointer: &str) -> Option<Json>
+    {
+        self.value.pointer(pointer).map(|value| Json { value: value.clone() })
+    }
+
+    /// Returns the kind of the value: "null", "boolean", "number", "string", "array" or "object"
+    pub fn kind(&self) -> String
+    {
+        let kind = match self.value {
+            JSON::JsNull => "null",
+            JSON::JsBool(_) => "boolean",
+            JSON::JsNumber(_) => "number",
+            JSON::JsString(_) => "string",
+            JSON::JsArray(_) => "array",
+            JSON::JsObject(_) => "object",
+        };
+        kind.to_string()
+    }
+
+    /// Validates the document against a JSON Schema, returning a message for every violation (none if the document is valid).
+    /// Throws if the schema itself is malformed.
+    pub fn validate(&self, schema: &Json) -> Result<Vec<String>, String>
+    {
+        let schema = Schema::new(&schema.value).map_err(|e| e.to_string())?;
+        Ok(schema.validate(&self.value).iter().map(ToString::to_string).collect())
+    }
+}
+
+
+/* (TESTS) */
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wasm_bindings()
+    {
+        let doc = parse(r#"{ "servers" : [{ "host" : "a" }, { "host" : "b", "port" : "80" }] }"#).unwrap();
+        let server = doc.pointer("/servers/1").unwrap();
+
+        assert_eq!( r#"{"host":"b","port":"80"}"#, server.stringify(false) );
+        assert_eq!( "{\n  \"host\": \"a\"\n}", doc.pointer("/servers/0").unwrap().stringify(true) );
+        assert_eq!( "object", server.kind() );
+        assert!( doc.pointer("/servers/2").is_none() );
+
+        let schema = parse(r#"{ "properties" : { "port" : { "type" : "integer" } } }"#).unwrap();
+        assert_eq!( Ok(vec!["/port: expected integer but found string".to_string()]), server.validate(&schema) );
+        assert_eq!( Ok(vec![]), doc.validate(&schema) );
+    }
+
+    #[test]
+    fn test_wasm_errors()
+    {
+        assert!( parse("[1, 2").is_err() );
+
+        let schema = parse(r#"{ "type" : 1 }"#).unwrap();
+        assert!( parse("1").unwrap().validate(&schema).is_err() );
+    }
+}
@@ -0,0 +1,402 @@
+//! Push-style json writer for output too big or too dynamic to build as a tree first
+//!
+//! `JsonWriter` writes each token straight to an `io::Write` as it is pushed, placing commas and colons itself.
+//! The structure is checked at runtime, so pushing something in the wrong place (ie. a value where an object key
+//! is expected, or `end_array` inside an object) returns an error instead of producing invalid json.
+//! `JsonWriter::concatenated` writes a stream of top level values instead (ie. NDJSON), separating them itself.
+
+/* (IMPORTS) */
+use std::fmt;
+use std::io;
+
+use crate::ser::write_escaped_str;
+use crate::{Number, JSON};
+
+
+/// Error returned by `JsonWriter`
+#[derive(Debug)]
+pub enum WriterError {
+    /// The underlying writer failed
+    Io(io::Error),
+    /// The token can not be written at this point of the document, along with a description of what was expected
+    Misplaced(&'static str),
+    /// A float was NaN or infinite, which json can not represent
+    NonFiniteNumber,
+}
+
+impl fmt::Display for WriterError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+    {
+        match self {
+            WriterError::Io(e) => write!(f, "write failed: {e}"),
+            WriterError::Misplaced(expected) => write!(f, "misplaced token, expected {expected}"),
+            WriterError::NonFiniteNumber => f.write_str("NaN and infinite numbers can not be written as json"),
+        }
+    }
+}
+
+impl std::error::Error for WriterError {}
+
+impl From<io::Error> for WriterError {
+    fn from(e: io::Error) -> WriterError
+    {
+        WriterError::Io(e)
+    }
+}
+
+/// How `JsonWriter::concatenated` separates the top level values it writes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Separator {
+    /// Each value is followed by a newline, as in newline delimited json (NDJSON)
+    Newline,
+    /// Each value is preceded by a record separator (U+001E) and followed by a newline, as in json text sequences (RFC 7464)
+    RecordSeparator,
+    /// Values follow one another directly, with a space only between two that would otherwise run together (ie. numbers),
+    /// as `parse_many` reads them
+    None,
+}
+
+// An array or object that has been begun but not ended
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Frame {
+    Array { empty: bool },
+    Object { empty: bool, has_key: bool },
+}
+
+/// Writer that pushes json tokens to an `io::Write`, checking that they form a single valid json value
+///
+/// ```
+/// use Parser::JsonWriter;
+///
+/// let mut writer = JsonWriter::new(Vec::new());
+/// writer.begin_object().unwrap();
+/// writer.write_key("ids").unwrap();
+/// writer.begin_array().unwrap();
+/// for id in 1..=3 {
+///     writer.write_u64(id).unwrap();
+/// }
+/// writer.end_array().unwrap();
+/// writer.end_object().unwrap();
+///
+/// assert_eq!( br#"{"ids":[1,2,3]}"#.to_vec(), writer.finish().unwrap() );
+/// ```
+pub struct JsonWriter<W: io::Write> {
+    out: W,
+    stack: Vec<Frame>,
+    done: bool,                     //Whether the top level value is complete
+    separator: Option<Separator>,   //How top level values are separated, if more than one can be written
+    last: Option<u8>,               //The last byte written
+}
+
+impl<W: io::Write> JsonWriter<W> {
+
+    /// Creates a writer that writes a single json value to out
+    pub fn new(out: W) -> JsonWriter<W>
+    {
+        JsonWriter { out, stack: vec![], done: false, separator: None, last: None }
+    }
+
+    /// Creates a writer that writes any number of json values to out one after another, each separated from the next as given
+    ///
+    /// ```
+    /// use Parser::{try_parse, JsonWriter, Separator};
+    ///
+    /// let mut writer = JsonWriter::concatenated(Vec::new(), Separator::Newline);
+    /// for line in [r#"{ "id" : 1 }"#, "[true]"] {
+    ///     writer.write_value(&try_parse(line).unwrap()).unwrap();
+    /// }
+    ///
+    /// assert_eq!( b"{\"id\":1}\n[true]\n".to_vec(), writer.finish().unwrap() );
+    /// ```
+    pub fn concatenated(out: W, separator: Separator) -> JsonWriter<W>
+    {
+        JsonWriter { separator: Some(separator), ..JsonWriter::new(out) }
+    }
+
+    /// Begins an array, which must be closed with `end_array`
+    pub fn begin_array(&mut self) -> Result<(), WriterError>
+    {
+        self.before_value(b'[')?;
+        self.out.write_all(b"[")?;
+        self.stack.push(Frame::Array { empty: true });
+        Ok(())
+    }
+
+    /// Ends the innermost array
+    pub fn end_array(&mut self) -> Result<(), WriterError>
+    {
+        match self.stack.last() {
+            Some(Frame::Array { .. }) => self.end(b"]"),
+            Some(Frame::Object { .. }) => Err(WriterError::Misplaced("end_object")),
+            None => Err(WriterError::Misplaced("a value")),
+        }
+    }
+
+    /// Begins an object, which must be closed with `end_object`. Each member is written as `write_key` followed by a value.
+    pub fn begin_object(&mut self) -> Result<(), WriterError>
+    {
+        self.before_value(b'{')?;
+        self.out.write_all(b"{")?;
+        self.stack.push(Frame::Object { empty: true, has_key: false });
+        Ok(())
+    }
+
+    /// Ends the innermost object
+    pub fn end_object(&mut self) -> Result<(), WriterError>
+    {
+        match self.stack.last() {
+            Some(Frame::Object { has_key: false, .. }) => self.end(b"}"),
+            Some(Frame::Object { has_key: true, .. }) => Err(WriterError::Misplaced("a value for the key")),
+            Some(Frame::Array { .. }) => Err(WriterError::Misplaced("end_array")),
+            None => Err(WriterError::Misplaced("a value")),
+        }
+    }
+
+    /// Writes the key of the next member of the innermost object
+    pub fn write_key(&mut self, key: &str) -> Result<(), WriterError>
+    {
+        let Some(Frame::Object { empty, has_key: false }) = self.stack.last().copied() else {
+            return Err(WriterError::Misplaced(if self.stack.is_empty() { "a value" } else { "a value, not a key" }));
+        };
+
+        let mut text = String::new();
+        if !empty { text.push(','); }
+        write_escaped_str(&mut text, key);
+        text.push(':');
+        self.out.write_all(text.as_bytes())?;
+
+        self.stack.pop();
+        self.stack.push(Frame::Object { empty: false, has_key: true });
+        Ok(())
+    }
+
+    /// Writes a string value
+    pub fn write_str(&mut self, s: &str) -> Result<(), WriterError>
+    {
+        let mut text = String::new();
+        write_escaped_str(&mut text, s);
+        self.write_raw(&text)
+    }
+
+    /// Writes a null value
+    pub fn write_null(&mut self) -> Result<(), WriterError>
+    {
+        self.write_raw("null")
+    }
+
+    /// Writes a bool value
+    pub fn write_bool(&mut self, b: bool) -> Result<(), WriterError>
+    {
+        self.write_raw(if b { "true" } else { "false" })
+    }
+
+    /// Writes a number value
+    pub fn write_number(&mut self, n: &Number) -> Result<(), WriterError>
+    {
+        self.write_raw(&format!("{n:?}"))
+    }
+
+    /// Writes an integer value
+    pub fn write_i64(&mut self, i: i64) -> Result<(), WriterError>
+    {
+        self.write_raw(&i.to_string())
+    }
+
+    /// Writes a non-negative integer value
+    pub fn write_u64(&mut self, u: u64) -> Result<(), WriterError>
+    {
+        self.write_raw(&u.to_string())
+    }
+
+    /// Writes a float value, NaN and infinities can not be represented in json so they are an error (and nothing is written)
+    pub fn write_f64(&mut self, f: f64) -> Result<(), WriterError>
+    {
+        match Number::from_f64(f) {
+            Some(n) => self.write_number(&n),
+            None => Err(WriterError::NonFiniteNumber),
+        }
+    }
+
+    /// Writes a whole json value, ie. a subtree that is already in memory
+    pub fn write_value(&mut self, value: &JSON) -> Result<(), WriterError>
+    {
+        self.write_raw(&value.display_json().to_string())
+    }
+
+    /// Returns the underlying writer once a complete value (or for concatenated values, any number of them) has been written and flushed
+    ///
+    /// # Errors
+    /// Returns `WriterError::Misplaced` if no value has been written or an array or object has not been ended
+    pub fn finish(mut self) -> Result<W, WriterError>
+    {
+        let complete = self.done || (self.separator.is_some() && self.stack.is_empty());
+        if !complete {
+            return Err(WriterError::Misplaced(match self.stack.last() {
+                Some(Frame::Array { .. }) => "end_array",
+                Some(Frame::Object { .. }) => "end_object",
+                None => "a value",
+            }));
+        }
+        self.out.flush()?;
+        Ok(self.out)
+    }
+
+    // Writes a complete value (ie. a scalar) wherever one is allowed
+    fn write_raw(&mut self, text: &str) -> Result<(), WriterError>
+    {
+        self.before_value(text.as_bytes()[0])?;
+        self.out.write_all(text.as_bytes())?;
+        self.last = text.bytes().last();
+        self.after_value()
+    }
+
+    // Checks that a value starting with the given byte can be written next & writes the comma that separates it from the previous
+    // array element, or what separates it from the previous top level value
+    fn before_value(&mut self, first: u8) -> Result<(), WriterError>
+    {
+        //Numbers and literals run together, nothing else does (ie. "1" then "2", but not "1" then "[2]")
+        let runs_together = |b: u8| b.is_ascii_alphanumeric() || b == b'-';
+
+        match self.stack.last().copied() {
+            None => match self.separator {
+                None if self.done => Err(WriterError::Misplaced("the end of the document")),
+                Some(Separator::RecordSeparator) => Ok(self.out.write_all(b"\x1e")?),
+                Some(Separator::None) if self.done && self.last.is_some_and(runs_together) && runs_together(first) => Ok(self.out.write_all(b" ")?),
+                _ => Ok(()),
+            },
+            Some(Frame::Array { empty }) => {
+                if !empty { self.out.write_all(b",")?; }
+                Ok(())
+            }
+            Some(Frame::Object { has_key: true, .. }) => Ok(()),
+            Some(Frame::Object { has_key: false, .. }) => Err(WriterError::Misplaced("a key")),
+        }
+    }
+
+    // Records that a value has been completed within the innermost array or object (or as the whole document),
+    // writing the newline that follows a top level value if they are separated by one
+    fn after_value(&mut self) -> Result<(), WriterError>
+    {
+        match self.stack.last_mut() {
+            None => {
+                self.done = true;
+                if let Some(Separator::Newline | Separator::RecordSeparator) = self.separator { self.out.write_all(b"\n")?; }
+            }
+            Some(Frame::Array { empty }) => *empty = false,
+            Some(Frame::Object { has_key, .. }) => *has_key = false,
+        }
+        Ok(())
+    }
+
+    // Closes the innermost array or object, which is then a completed value of its parent
+    fn end(&mut self, closing: &[u8]) -> Result<(), WriterError>
+    {
+        self.out.write_all(closing)?;
+        self.last = closing.last().copied();
+        self.stack.pop();
+        self.after_value()
+    }
+}
+
+
+/* (TESTS) */
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::try_parse;
+
+    fn output(writer: JsonWriter<Vec<u8>>) -> String
+    {
+        String::from_utf8(writer.finish().unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_nested()
+    {
+        let mut w = JsonWriter::new(vec![]);
+        w.begin_array().unwrap();
+        w.write_null().unwrap();
+        w.begin_object().unwrap();
+        w.end_object().unwrap();
+        w.begin_object().unwrap();
+        w.write_key("a\"b").unwrap();
+        w.write_f64(1.5).unwrap();
+        w.write_key("c").unwrap();
+        w.begin_array().unwrap();
+        w.end_array().unwrap();
+        w.write_key("d").unwrap();
+        w.write_value(&try_parse(r#"{ "e" : [true] }"#).unwrap()).unwrap();
+        w.end_object().unwrap();
+        w.write_str("x\ny").unwrap();
+        w.write_i64(-3).unwrap();
+        w.end_array().unwrap();
+
+        let text = output(w);
+        assert_eq!( r#"[null,{},{"a\"b":1.5,"c":[],"d":{"e":[true]}},"x\ny",-3]"#, text );
+        assert!( try_parse(&text).is_ok() );
+    }
+
+    #[test]
+    fn test_scalar_document()
+    {
+        let mut w = JsonWriter::new(vec![]);
+        w.write_bool(true).unwrap();
+
+        assert!( matches!(w.write_null(), Err(WriterError::Misplaced("the end of the document"))) );
+        assert_eq!( "true", output(w) );
+    }
+
+    #[test]
+    fn test_misplaced()
+    {
+        let mut w = JsonWriter::new(vec![]);
+        assert!( matches!(w.write_key("a"), Err(WriterError::Misplaced(_))) );
+        assert!( matches!(w.end_array(), Err(WriterError::Misplaced(_))) );
+
+        w.begin_object().unwrap();
+        assert!( matches!(w.write_null(), Err(WriterError::Misplaced("a key"))) );
+        assert!( matches!(w.end_array(), Err(WriterError::Misplaced("end_object"))) );
+        w.write_key("a").unwrap();
+        assert!( matches!(w.write_key("b"), Err(WriterError::Misplaced(_))) );
+        assert!( matches!(w.end_object(), Err(WriterError::Misplaced("a value for the key"))) );
+        assert!( matches!(w.write_f64(f64::NAN), Err(WriterError::NonFiniteNumber)) );
+        w.write_u64(1).unwrap();
+        w.end_object().unwrap();
+        assert_eq!( r#"{"a":1}"#, output(w) );
+
+        let mut unfinished = JsonWriter::new(vec![]);
+        unfinished.begin_array().unwrap();
+        assert!( matches!(unfinished.finish(), Err(WriterError::Misplaced("end_array"))) );
+        assert!( matches!(JsonWriter::new(vec![]).finish(), Err(WriterError::Misplaced("a value"))) );
+    }
+
+    #[test]
+    fn test_concatenated()
+    {
+        let values = ["1", "-2", "true", "[3]", r#""a""#, "null", "{}", "4"].map(|v| try_parse(v).unwrap());
+        let write = |separator| {
+            let mut w = JsonWriter::concatenated(vec![], separator);
+            values.iter().for_each(|v| w.write_value(v).unwrap());
+            output(w)
+        };
+
+        assert_eq!( "1\n-2\ntrue\n[3]\n\"a\"\nnull\n{}\n4\n", write(Separator::Newline) );
+        assert_eq!( "\u{1e}1\n\u{1e}-2\n\u{1e}true\n\u{1e}[3]\n\u{1e}\"a\"\n\u{1e}null\n\u{1e}{}\n\u{1e}4\n", write(Separator::RecordSeparator) );
+        assert_eq!( r#"1 -2 true[3]"a"null{}4"#, write(Separator::None) );                     //Spaced only where they would run together
+
+        let text = write(Separator::None);
+        assert_eq!( values.to_vec(), crate::parse_many(&text).collect::<Result<Vec<_>, _>>().unwrap() );
+
+        //Values are written token by token as usual, and any number of them (even none) can be written
+        let mut w = JsonWriter::concatenated(vec![], Separator::Newline);
+        w.begin_array().unwrap();
+        w.end_array().unwrap();
+        w.write_null().unwrap();
+        assert_eq!( "[]\nnull\n", output(w) );
+        assert_eq!( "", output(JsonWriter::concatenated(vec![], Separator::None)) );
+
+        let mut unfinished = JsonWriter::concatenated(vec![], Separator::Newline);
+        unfinished.begin_object().unwrap();
+        assert!( matches!(unfinished.finish(), Err(WriterError::Misplaced("end_object"))) );
+    }
+}
@@ -0,0 +1,488 @@
+//! Converting simple XML documents to json values and back (enabled with the `xml` feature)
+//!
+//! The root element becomes an object with a single member named after it. Within an element:
+//! + Attributes are members named with a leading '@' (ie. `<a id="1"/>` is `{ "a" : { "@id" : "1" } }`), their values are always strings
+//! + Child elements are members named after them, and children that share a name are collected into an array in document order
+//! + Text is the member "#text", or the whole value of an element that has no attributes or children (`<a>hi</a>` is `{ "a" : "hi" }`)
+//! + An element with nothing in it is null
+//!
+//! Comments, processing instructions and the document type declaration are skipped, CDATA sections are text, and namespace prefixes are part of the name.
+//! The order of differently named children and text between children are not kept, and text that is only whitespace is dropped from elements that have children.
+
+/* (IMPORTS) */
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::error::line_column;
+use crate::JSON;
+
+
+// Elements nested deeper than this fail instead of overflowing the stack, the same limit as the json parser
+const MAX_DEPTH: usize = 128;
+
+/// Error returned by `JSON::from_xml`, with the position of the problem in the input
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct XmlError {
+    message: String,
+    offset: usize,
+    line: usize,
+    column: usize,
+}
+
+impl XmlError {
+
+    /// Returns what is wrong with the input (ie. "expected '>'")
+    pub fn message(&self) -> &str
+    {
+        &self.message
+    }
+
+    /// Returns the byte offset of the problem in the input
+    pub fn offset(&self) -> usize
+    {
+        self.offset
+    }
+
+    /// Returns the line of the problem, starting at 1
+    pub fn line(&self) -> usize
+    {
+        self.line
+    }
+
+    /// Returns the column of the problem in characters, starting at 1
+    pub fn column(&self) -> usize
+    {
+        self.column
+    }
+}
+
+impl fmt::Display for XmlError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+    {
+        write!(f, "{} at line {} column {}", self.message, self.line, self.column)
+    }
+}
+
+impl std::error::Error for XmlError {}
+
+impl JSON {
+
+    /// Parses an XML document into a value, see the `xml` module for how elements, attributes and text are mapped
+    ///
+    /// # Examples
+    /// ```
+    /// use Parser::{try_parse, JSON};
+    ///
+    /// let value = JSON::from_xml(r#"<order id="7"><item>tea</item><item>cake</item><note/></order>"#).unwrap();
+    /// assert_eq!( try_parse(r#"{ "order" : { "@id" : "7", "item" : ["tea", "cake"], "note" : null } }"#).unwrap(), value );
+    /// ```
+    ///
+    /// # Errors
+    /// Returns an `XmlError` if the input is not well-formed XML with a single root element, or uses an entity other than
+    /// the predefined ones and character references (ie. `&lt;` or `&#233;`)
+    pub fn from_xml(input: &str) -> Result<JSON, XmlError>
+    {
+        let mut reader = XmlReader { input, pos: 0 };
+        if input.starts_with('\u{feff}') { reader.pos = '\u{feff}'.len_utf8(); }
+
+        reader.skip_misc()?;
+        if !reader.rest().starts_with('<') { return Err(reader.error("expected an element")); }
+
+        let (name, value) = reader.element(0)?;
+
+        reader.skip_misc()?;
+        if !reader.rest().is_empty() { return Err(reader.error("unexpected characters after the root element")); }
+
+        Ok(JSON::JsObject(HashMap::from([(name.to_string(), value)])))
+    }
+
+    /// Returns the XML document of a value that is an object with a single member (the root element), the reverse of `from_xml`.
+    /// Members are written in order of key, with the elements of an array written as siblings that share the array's name.
+    ///
+    /// Returns None if the value is not an object with one member, if a name is not a valid XML name, or if an attribute or "#text"
+    /// is not a string, number or bool.
+    pub fn to_xml(&self) -> Option<String>
+    {
+        let JSON::JsObject(map) = self else { return None; };
+        if map.len() != 1 { return None; }
+
+        let mut out = String::new();
+        for (name, value) in map {
+            write_element(&mut out, name, value)?;
+        }
+        Some(out)
+    }
+}
+
+// The input of a document and how far into it has been read
+struct XmlReader<'a> {
+    input: &'a str,
+    pos: usize,
+}
+
+impl<'a> XmlReader<'a> {
+
+    fn rest(&self) -> &'a str
+    {
+        &self.input[self.pos..]
+    }
+
+    fn error(&self, message: &str) -> XmlError
+    {
+        let (line, column) = line_column(self.input, self.pos);
+        XmlError { message: message.to_string(), offset: self.pos, line, column }
+    }
+
+    // Consumes a str if the rest of the input starts with it
+    fn eat(&mut self, s: &str) -> bool
+    {
+        let found = self.rest().starts_with(s);
+        if found { self.pos += s.len(); }
+        found
+    }
+
+    fn expect(&mut self, s: &str) -> Result<(), XmlError>
+    {
+        match self.eat(s) {
+            true => Ok(()),
+            false => Err(self.error(&format!("expected '{s}'"))),
+        }
+    }
+
+    fn skip_whitespace(&mut self)
+    {
+        let rest = self.rest();
+        self.pos += rest.len() - rest.trim_start_matches([' ', '\t', '\r', '\n']).len();
+    }
+
+    // Consumes the input up to and including the end of a construct (ie. "-->"), returning what was before it
+    fn until(&mut self, end: &str) -> Result<&'a str, XmlError>
+    {
+        let rest = self.rest();
+        let Some(n) = rest.find(end) else { return Err(self.error(&format!("expected '{end}'"))); };
+        self.pos += n + end.len();
+        Ok(&rest[..n])
+    }
+
+    // Skips whitespace, comments, processing instructions and the document type declaration before and after the root element
+    fn skip_misc(&mut self) -> Result<(), XmlError>
+    {
+        loop {
+            self.skip_whitespace();
+
+            if self.eat("<!--") {
+                self.until("-->")?;
+            } else if self.eat("<?") {
+                self.until("?>")?;
+            } else if self.eat("<!DOCTYPE") {
+                //The internal subset in brackets can hold '>' of its own declarations
+                let rest = self.rest();
+                if rest.find('[').is_some_and(|open| rest.find('>').is_none_or(|close| open < close)) {
+                    self.until("]")?;
+                }
+                self.until(">")?;
+            } else {
+                return Ok(());
+            }
+        }
+    }
+
+    fn name(&mut self) -> Result<&'a str, XmlError>
+    {
+        let rest = self.rest();
+        let len = rest.find(|ch: char| !is_name_char(ch)).unwrap_or(rest.len());
+
+        match rest[..len].chars().next() {
+            Some(first) if is_name_start(first) => { self.pos += len; Ok(&rest[..len]) }
+            _ => Err(self.error("expected a name")),
+        }
+    }
+
+    // Reads an element starting at its '<', returning its name and value
+    fn element(&mut self, depth: usize) -> Result<(&'a str, JSON), XmlError>
+    {
+        if depth == MAX_DEPTH { return Err(self.error("elements are nested too deeply")); }
+        self.expect("<")?;
+        let name = self.name()?;
+
+        let mut attributes: Vec<(&str, String)> = vec![];
+        let mut children: Vec<(&str, JSON)> = vec![];
+        let mut texts: Vec<String> = vec![];
+
+        loop {
+            self.skip_whitespace();
+            if self.eat("/>") { return Ok((name, element_value(attributes, children, texts))); }
+            if self.eat(">") { break; }
+
+            let start = self.pos;
+            let attribute = self.name()?;
+            self.skip_whitespace();
+            self.expect("=")?;
+            self.skip_whitespace();
+
+            let quote = if self.eat("\"") { "\"" } else { self.expect("'")?; "'" };
+            let value_start = self.pos;
+            let raw = self.until(quote)?;
+            if raw.contains('<') { return Err(self.error("attribute values can not contain '<'")); }
+
+            if attributes.iter().any(|(other, _)| *other == attribute) {
+                self.pos = start;
+                return Err(self.error("duplicate attribute"));
+            }
+            attributes.push((attribute, self.decode(raw, value_start)?));
+        }
+
+        loop {
+            if self.eat("</") {
+                let start = self.pos;
+                if self.name()? != name {
+                    self.pos = start;
+                    return Err(self.error(&format!("expected '</{name}>'")));
+                }
+                self.skip_whitespace();
+                self.expect(">")?;
+                break;
+            } else if self.eat("<!--") {
+                self.until("-->")?;
+            } else if self.eat("<![CDATA[") {
+                texts.push(self.until("]]>")?.to_string());
+            } else if self.eat("<?") {
+                self.until("?>")?;
+            } else if self.rest().starts_with('<') {
+                children.push(self.element(depth + 1)?);
+            } else {
+                let rest = self.rest();
+                let Some(n) = rest.find('<') else { return Err(self.error(&format!("expected '</{name}>'"))); };
+                let text = self.decode(&rest[..n], self.pos)?;
+                self.pos += n;
+                texts.push(text);
+            }
+        }
+
+        Ok((name, element_value(attributes, children, texts)))
+    }
+
+    // Replaces the entity and character references of text or an attribute value, which starts at an offset into the input
+    fn decode(&self, raw: &str, start: usize) -> Result<String, XmlError>
+    {
+        let mut decoded = String::with_capacity(raw.len());
+        let mut rest = raw;
+
+        while let Some(amp) = rest.find('&') {
+            decoded.push_str(&rest[..amp]);
+            let reference = &rest[amp + 1..];
+
+            let ch = reference.find(';').and_then(|semi| {
+                let ch = match &reference[..semi] {
+                    "lt" => '<',
+                    "gt" => '>',
+                    "amp" => '&',
+                    "quot" => '"',
+                    "apos" => '\'',
+                    number => {
+                        let code = match number.strip_prefix("#x") {
+                            Some(hex) => u32::from_str_radix(hex, 16).ok(),
+                            None => number.strip_prefix('#')?.parse().ok(),
+                        };
+                        char::from_u32(code?)?
+                    }
+                };
+                Some((ch, semi))
+            });
+
+            let Some((ch, semi)) = ch else {
+                let offset = start + (raw.len() - rest.len()) + amp;
+                let (line, column) = line_column(self.input, offset);
+                return Err(XmlError { message: "unknown entity reference".to_string(), offset, line, column });
+            };
+            decoded.push(ch);
+            rest = &reference[semi + 1..];
+        }
+
+        decoded.push_str(rest);
+        Ok(decoded)
+    }
+}
+
+// Maps the attributes, children and text of an element to its value
+fn element_value(attributes: Vec<(&str, String)>, children: Vec<(&str, JSON)>, texts: Vec<String>) -> JSON
+{
+    if attributes.is_empty() && children.is_empty() {
+        return match texts.is_empty() {
+            true => JSON::JsNull,
+            false => JSON::JsString(texts.concat()),
+        };
+    }
+
+    let mut map: HashMap<String, JSON> = attributes.into_iter()
+        .map(|(name, value)| (format!("@{name}"), JSON::JsString(value)))
+        .collect();
+
+    for (name, child) in children {
+        match map.get_mut(name) {
+            Some(JSON::JsArray(vec)) => vec.push(child),
+            Some(previous) => { let first = std::mem::replace(previous, JSON::JsNull); *previous = JSON::JsArray(vec![first, child]); }
+            None => { map.insert(name.to_string(), child); }
+        }
+    }
+
+    let text: String = texts.into_iter().filter(|text| !text.trim().is_empty()).collect();
+    if !text.is_empty() { map.insert("#text".to_string(), JSON::JsString(text)); }
+
+    JSON::JsObject(map)
+}
+
+// Writes the element (or the sibling elements of an array) of a value
+fn write_element(out: &mut String, name: &str, value: &JSON) -> Option<()>
+{
+    if !is_name(name) { return None; }
+
+    match value {
+        JSON::JsNull => out.push_str(&format!("<{name}/>")),
+        JSON::JsArray(vec) => {
+            for element in vec {
+                write_element(out, name, element)?;
+            }
+        }
+        JSON::JsObject(map) => {
+            let mut members: Vec<(&String, &JSON)> = map.iter().collect();
+            members.sort_by(|a, b| a.0.cmp(b.0));
+
+            out.push_str(&format!("<{name}"));
+            for (key, value) in &members {
+                if let Some(attribute) = key.strip_prefix('@') {
+                    if !is_name(attribute) { return None; }
+                    out.push_str(&format!(" {attribute}=\"{}\"", escape(&scalar_text(value)?, true)));
+                }
+            }
+
+            let text = map.get("#text").map(scalar_text);
+            let children: Vec<_> = members.into_iter().filter(|(key, _)| !key.starts_with('@') && *key != "#text").collect();
+
+            if text.is_none() && children.is_empty() {
+                out.push_str("/>");
+                return Some(());
+            }
+
+            out.push('>');
+            if let Some(text) = text { out.push_str(&escape(&text?, false)); }
+            for (key, child) in children {
+                write_element(out, key, child)?;
+            }
+            out.push_str(&format!("</{name}>"));
+        }
+        scalar => out.push_str(&format!("<{name}>{}</{name}>", escape(&scalar_text(scalar)?, false))),
+    }
+    Some(())
+}
+
+// Returns the text of a string, number or bool
+fn scalar_text(value: &JSON) -> Option<Cow<'_, str>>
+{
+    match value {
+        JSON::JsString(s) => Some(Cow::Borrowed(s)),
+        JSON::JsBool(_) | JSON::JsNumber(_) => Some(Cow::Owned(value.display_json().to_string())),
+        _ => None,
+    }
+}
+
+// Escapes the characters of text that would be read as markup, and the quotes of an attribute value
+fn escape(text: &str, attribute: bool) -> Cow<'_, str>
+{
+    if !text.contains(['<', '>', '&', '"']) { return Cow::Borrowed(text); }
+
+    let mut escaped = String::with_capacity(text.len());
+    for ch in text.chars() {
+        match ch {
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '&' => escaped.push_str("&amp;"),
+            '"' if attribute => escaped.push_str("&quot;"),
+            ch => escaped.push(ch),
+        }
+    }
+    Cow::Owned(escaped)
+}
+
+fn is_name_start(ch: char) -> bool
+{
+    ch.is_alphabetic() || ch == '_' || ch == ':'
+}
+
+fn is_name_char(ch: char) -> bool
+{
+    ch.is_alphanumeric() || matches!(ch, '_' | ':' | '-' | '.')
+}
+
+fn is_name(name: &str) -> bool
+{
+    name.chars().next().is_some_and(is_name_start) && name.chars().all(is_name_char)
+}
+
+
+/* (TESTS) */
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::json;
+
+    #[test]
+    fn test_from_xml()
+    {
+        let xml = r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE feed [ <!ENTITY x "y"> ]>
+<!-- a feed -->
+<soap:Envelope xmlns:soap="http://www.w3.org/2003/05/soap-envelope">
+  <soap:Body>
+    <price currency='EUR'>1.50</price>
+    <tag>a &amp; b</tag>
+    <tag><![CDATA[<raw>]]></tag>
+    <tag/>
+    <note lang="en">caf&#233; &#x2713;<b>bold</b></note>
+    <empty></empty>
+  </soap:Body>
+</soap:Envelope>
+"#;
+        let expected = json(r##"{ "soap:Envelope" : { "@xmlns:soap" : "http://www.w3.org/2003/05/soap-envelope", "soap:Body" : {
+            "price" : { "@currency" : "EUR", "#text" : "1.50" },
+            "tag" : ["a & b", "<raw>", null],
+            "note" : { "@lang" : "en", "#text" : "café ✓", "b" : "bold" },
+            "empty" : null
+        } } }"##);
+        assert_eq!( Ok(expected), JSON::from_xml(xml) );
+
+        assert_eq!( Ok(json(r#"{ "a" : "  " }"#)), JSON::from_xml("<a>  </a>") );
+    }
+
+    #[test]
+    fn test_xml_errors()
+    {
+        let error = |input: &str| JSON::from_xml(input).map_err(|e| (e.message().to_string(), e.line(), e.column()));
+
+        assert_eq!( Err(("expected '</b>'".to_string(), 1, 9)), error("<a><b></a>") );
+        assert_eq!( Err(("expected '</a>'".to_string(), 1, 4)), error("<a>x") );
+        assert_eq!( Err(("unknown entity reference".to_string(), 2, 3)), error("<a>\nx &nbsp;</a>") );
+        assert_eq!( Err(("duplicate attribute".to_string(), 1, 10)), error("<a x='1' x='2'/>") );
+        assert_eq!( Err(("unexpected characters after the root element".to_string(), 1, 5)), error("<a/><b/>") );
+        assert_eq!( Err(("expected an element".to_string(), 1, 1)), error("text") );
+        assert_eq!( Err(("elements are nested too deeply".to_string(), 1, 1 + 3 * MAX_DEPTH)), error(&"<a>".repeat(MAX_DEPTH + 1)) );
+    }
+
+    #[test]
+    fn test_to_xml()
+    {
+        let value = json(r##"{ "feed" : { "@id" : 7, "entry" : [{ "@n" : "a\"b", "#text" : "x < y" }, "plain", null], "ok" : true } }"##);
+        let xml = value.to_xml().unwrap();
+        assert_eq!( r#"<feed id="7"><entry n="a&quot;b">x &lt; y</entry><entry>plain</entry><entry/><ok>true</ok></feed>"#, xml );
+
+        //Values read from XML are written back the same
+        let value = JSON::from_xml(r#"<a x="1"><b>2</b><b><c/></b>text</a>"#).unwrap();
+        assert_eq!( Ok(value.clone()), JSON::from_xml(&value.to_xml().unwrap()) );
+
+        assert_eq!( None, json(r#"{ "a" : 1, "b" : 2 }"#).to_xml() );
+        assert_eq!( None, json(r#"{ "1a" : 1 }"#).to_xml() );
+        assert_eq!( None, json(r#"{ "a" : { "@x" : [1] } }"#).to_xml() );
+        assert_eq!( None, json("[]").to_xml() );
+    }
+}
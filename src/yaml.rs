@@ -0,0 +1,130 @@
+//! Writing values as YAML block documents (ie. for config files and readable diffs)
+//!
+//! Only writing is supported. Strings are left unquoted only when every YAML parser reads them back as the same string,
+//! anything else (ie. "yes", "1.0" or "a: b") is written in double quotes, with the same escapes as json.
+
+/* (IMPORTS) */
+use crate::ser::{write_escaped_str, write_indent};
+use crate::JSON;
+
+
+impl JSON {
+
+    /// Returns the value as a YAML document, objects as one "key: value" line per member (in order of key) and arrays as one "- " line per element.
+    /// Empty arrays and objects are written as `[]` and `{}`.
+    ///
+    /// # Examples
+    /// ```
+    /// use Parser::try_parse;
+    ///
+    /// let value = try_parse(r#"{ "name" : "web", "ports" : [80, 443], "env" : { "DEBUG" : "yes" }, "tags" : [] }"#).unwrap();
+    /// assert_eq!( "env:\n  DEBUG: \"yes\"\nname: web\nports:\n  - 80\n  - 443\ntags: []\n", value.to_yaml() );
+    /// ```
+    pub fn to_yaml(&self) -> String
+    {
+        let mut out = String::new();
+        write_yaml(&mut out, self, 0);
+        out.push('\n');
+        out
+    }
+}
+
+// Tells whether an array or object is written as lines of its own rather than inline
+fn is_block(value: &JSON) -> bool
+{
+    match value {
+        JSON::JsArray(vec) => !vec.is_empty(),
+        JSON::JsObject(map) => !map.is_empty(),
+        _ => false,
+    }
+}
+
+// Appends a value starting at the current position, with the lines after the first indented to a level
+fn write_yaml(out: &mut String, value: &JSON, level: usize)
+{
+    match value {
+        JSON::JsArray(vec) if !vec.is_empty() => {
+            for (i, element) in vec.iter().enumerate() {
+                if i > 0 { write_indent(out, level); }
+                out.push_str("- ");
+                write_yaml(out, element, level + 1);   //The contents of a block element line up after its "- "
+            }
+        }
+        JSON::JsObject(map) if !map.is_empty() => {
+            let mut members: Vec<(&String, &JSON)> = map.iter().collect();
+            members.sort_by(|a, b| a.0.cmp(b.0));
+
+            for (i, (key, member)) in members.into_iter().enumerate() {
+                if i > 0 { write_indent(out, level); }
+                write_string(out, key);
+                out.push(':');
+
+                if is_block(member) {
+                    write_indent(out, level + 1);
+                    write_yaml(out, member, level + 1);
+                } else {
+                    out.push(' ');
+                    write_yaml(out, member, level + 1);
+                }
+            }
+        }
+        JSON::JsString(s) => write_string(out, s),
+        JSON::JsNumber(n) => out.push_str(&yaml_number(&format!("{n:?}"))),
+        other => out.push_str(&other.display_json().to_string()),
+    }
+}
+
+fn write_string(out: &mut String, s: &str)
+{
+    match is_plain(s) {
+        true => out.push_str(s),
+        false => write_escaped_str(out, s),
+    }
+}
+
+// Tells whether a string can be written without quotes, which is when it can not be read as a bool, null, number or YAML syntax
+fn is_plain(s: &str) -> bool
+{
+    let is_keyword = ["true", "false", "null", "yes", "no", "on", "off", "y", "n"].iter().any(|word| s.eq_ignore_ascii_case(word));
+
+    s.starts_with(|ch: char| ch.is_alphabetic() || ch == '_' || ch == '/') && !s.ends_with(' ') && !is_keyword
+        && s.chars().all(|ch| ch.is_alphanumeric() || matches!(ch, ' ' | '_' | '-' | '.' | '/' | '@'))
+}
+
+// Rewrites the exponent of a number in the form older (YAML 1.1) parsers read as a float, with a decimal point and a sign (ie. 1e300 as 1.0e+300)
+fn yaml_number(literal: &str) -> String
+{
+    let Some((mantissa, exponent)) = literal.split_once(['e', 'E']) else { return literal.to_string(); };
+
+    let mantissa = if mantissa.contains('.') { mantissa.to_string() } else { format!("{mantissa}.0") };
+    let exponent = if exponent.starts_with(['-', '+']) { exponent.to_string() } else { format!("+{exponent}") };
+    format!("{mantissa}e{exponent}")
+}
+
+
+/* (TESTS) */
+#[cfg(test)]
+mod tests {
+    use crate::json;
+
+    #[test]
+    fn test_to_yaml()
+    {
+        let value = json(r#"{ "list" : [1, [2, 3], { "a" : null, "b" : [true] }, [], "x"], "nested" : { "deep" : { "k" : 1.5 } } }"#);
+        let expected = "list:\n  - 1\n  - - 2\n    - 3\n  - a: null\n    b:\n      - true\n  - []\n  - x\nnested:\n  deep:\n    k: 1.5\n";
+
+        assert_eq!( expected, value.to_yaml() );
+        assert_eq!( "[]\n", json("[]").to_yaml() );
+        assert_eq!( "1.0e+300\n", json("1e300").to_yaml() );
+    }
+
+    #[test]
+    fn test_strings()
+    {
+        let value = json(r##"["plain text", "No", "1.0", "", "a: b", "- x", "two\nlines", " padded", "user@example.com", "#hash", "ünïcode"]"##);
+        let expected = "- plain text\n- \"No\"\n- \"1.0\"\n- \"\"\n- \"a: b\"\n- \"- x\"\n- \"two\\nlines\"\n- \" padded\"\n- user@example.com\n- \"#hash\"\n- ünïcode\n";
+
+        assert_eq!( expected, value.to_yaml() );
+        assert_eq!( "\"key: x\": 1\n", json(r#"{ "key: x" : 1 }"#).to_yaml() );
+    }
+}